@@ -58,7 +58,7 @@ for problem in problems:
         print(f"Standard error when generating linear config for {problem}:\n{stderr}")
         continue
 
-    _, _, config = stdout.splitlines()
+    *_, config = stdout.splitlines()
     with open(config, "r", encoding="utf-8") as f:
         data = json.load(f)
 
@@ -90,7 +90,7 @@ for problem in problems:
         print(f"Standard error when generating non-linear config for {problem}:\n{stderr}")
         continue
 
-    _, _, config = stdout.splitlines()
+    *_, config = stdout.splitlines()
     with open(config, "r", encoding="utf-8") as f:
         data = json.load(f)
 
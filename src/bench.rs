@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+use colored::Colorize;
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ExpectedValue;
+
+/// Fields of the run JSON written by `Logger::finalize` that the benchmark relies on.
+#[derive(Deserialize)]
+struct _RunResult {
+    elapsed: f64,
+    evaluated_moves: usize,
+    peak_memory_kb: Option<u64>,
+    solution: _RunSolution,
+    iteration_ms: Option<Vec<f64>>,
+}
+
+#[derive(Deserialize)]
+struct _RunSolution {
+    working_time: f64,
+}
+
+/// Aggregated statistics of all runs on a single instance.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BenchStatistics {
+    runs: usize,
+    iterations: usize,
+    mean_iteration_ms: f64,
+    median_iteration_ms: f64,
+    p95_iteration_ms: f64,
+    moves_per_second: f64,
+    peak_memory_kb: Option<u64>,
+    mean_working_time: f64,
+}
+
+fn _mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Nearest-rank percentile of `sorted`, with `p` in `[0, 1]`.
+fn _percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Run the algorithm once on `problem`, returning its run JSON. The iteration log is disabled, as the run reports the
+/// duration of its iterations in the run JSON instead.
+fn _run_once(problem: &str, iterations: usize, outputs: &Path, args: &[String]) -> Result<_RunResult, Box<dyn Error>> {
+    let mut command = Command::new(env::current_exe()?);
+    command
+        .arg("run")
+        .arg(problem)
+        .arg("--fix-iteration")
+        .arg(iterations.to_string())
+        .arg("--outputs")
+        .arg(outputs)
+        .arg("--record-iteration-times");
+
+    // A flag given twice is rejected by the command line parser
+    if !args.iter().any(|arg| arg == "--disable-logging") {
+        command.arg("--disable-logging");
+    }
+
+    let output = command.args(args).output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Run on {problem} exited with {}:\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    // The first path printed by `Logger::finalize` is the run JSON
+    let stdout = String::from_utf8(output.stdout)?;
+    let path = ExpectedValue::cast(stdout.lines().next().map(PathBuf::from))?;
+    Ok(serde_json::from_str(&fs::read_to_string(&path)?)?)
+}
+
+/// Relative change of `current` against `baseline`, right-aligned to `width` (before coloring, since escape codes
+/// would otherwise count towards the padding).
+fn _compare(current: f64, baseline: f64, lower_is_better: bool, width: usize) -> String {
+    let change = (current - baseline) / baseline * 100.0;
+    let text = format!("{:>width$}", format!("{change:+.1}%"));
+    if change.abs() < 2.0 {
+        text.normal().to_string()
+    } else if (change < 0.0) == lower_is_better {
+        text.green().to_string()
+    } else {
+        text.red().to_string()
+    }
+}
+
+/// Run the algorithm `repeat` times with a fixed iteration budget on each of `problems` (forwarding `args` to every
+/// run) and report timing statistics, optionally comparing them against a baseline file written by an earlier
+/// benchmark.
+pub fn bench(
+    problems: &[String],
+    repeat: usize,
+    iterations: usize,
+    baseline: Option<&str>,
+    save_baseline: Option<&str>,
+    args: &[String],
+) -> Result<(), Box<dyn Error>> {
+    if args.iter().any(|arg| arg == "--record-iteration-times") {
+        return Err("--record-iteration-times is already passed to every run of a benchmark".into());
+    }
+
+    let baseline = match baseline {
+        Some(path) => serde_json::from_str::<BTreeMap<String, BenchStatistics>>(&fs::read_to_string(path)?)?,
+        None => BTreeMap::new(),
+    };
+
+    let id = rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(char::from)
+        .collect::<String>();
+    let outputs = env::temp_dir().join(format!("min-timespan-delivery-bench-{id}"));
+
+    let mut results = BTreeMap::new();
+    for problem in problems {
+        let mut iteration_ms = vec![];
+        let mut moves_per_second = vec![];
+        let mut working_time = vec![];
+        let mut peak_memory_kb = None;
+        for run in 0..repeat {
+            eprint!("Benchmarking {problem} ({}/{repeat})     \r", run + 1);
+            let result = _run_once(problem, iterations, &outputs, args)?;
+
+            iteration_ms.extend(ExpectedValue::cast(result.iteration_ms)?);
+            moves_per_second.push(result.evaluated_moves as f64 / result.elapsed);
+            working_time.push(result.solution.working_time);
+            peak_memory_kb = peak_memory_kb.max(result.peak_memory_kb);
+        }
+        eprintln!();
+
+        if iteration_ms.is_empty() {
+            return Err(format!("Runs on {problem} took less than 2 iterations").into());
+        }
+
+        iteration_ms.sort_by(f64::total_cmp);
+        results.insert(
+            problem.clone(),
+            BenchStatistics {
+                runs: repeat,
+                iterations,
+                mean_iteration_ms: _mean(&iteration_ms),
+                median_iteration_ms: _percentile(&iteration_ms, 0.5),
+                p95_iteration_ms: _percentile(&iteration_ms, 0.95),
+                moves_per_second: _mean(&moves_per_second),
+                peak_memory_kb,
+                mean_working_time: _mean(&working_time),
+            },
+        );
+    }
+
+    let _ = fs::remove_dir_all(&outputs);
+
+    println!(
+        "{:<40} {:>12} {:>12} {:>12} {:>14} {:>12} {:>14}",
+        "Instance", "Mean (ms)", "Median (ms)", "P95 (ms)", "Moves/s", "Memory (kB)", "Working time"
+    );
+    for (problem, stats) in &results {
+        println!(
+            "{:<40} {:>12.3} {:>12.3} {:>12.3} {:>14.0} {:>12} {:>14.2}",
+            problem,
+            stats.mean_iteration_ms,
+            stats.median_iteration_ms,
+            stats.p95_iteration_ms,
+            stats.moves_per_second,
+            stats.peak_memory_kb.map_or_else(|| "-".to_string(), |m| m.to_string()),
+            stats.mean_working_time,
+        );
+
+        if let Some(base) = baseline.get(problem) {
+            println!(
+                "{:<40} {} {} {} {} {} {}",
+                "  vs. baseline",
+                _compare(stats.mean_iteration_ms, base.mean_iteration_ms, true, 12),
+                _compare(stats.median_iteration_ms, base.median_iteration_ms, true, 12),
+                _compare(stats.p95_iteration_ms, base.p95_iteration_ms, true, 12),
+                _compare(stats.moves_per_second, base.moves_per_second, false, 14),
+                match (stats.peak_memory_kb, base.peak_memory_kb) {
+                    (Some(current), Some(base)) => _compare(current as f64, base as f64, true, 12),
+                    _ => format!("{:>12}", "-"),
+                },
+                _compare(stats.mean_working_time, base.mean_working_time, true, 14),
+            );
+        }
+    }
+
+    if let Some(path) = save_baseline {
+        fs::write(path, serde_json::to_string_pretty(&results)?)?;
+        eprintln!("Saved baseline to {path}");
+    }
+
+    Ok(())
+}
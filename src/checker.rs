@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::config;
+use crate::neighborhoods::Neighborhood;
+use crate::routes::{DroneRoute, Route, TruckRoute};
+use crate::seeding;
+
+/// Number of random routes generated per route type when running the consistency checks.
+const SAMPLES: usize = 200;
+
+/// Maximum number of customers in a randomly generated route.
+const MAX_ROUTE_LENGTH: usize = 8;
+
+/// Relative tolerance of the values derived incrementally against those computed from scratch.
+const _TOLERANCE: f64 = 1e-9;
+
+/// Whether the value `derived` incrementally matches `expected`, computed from scratch, up to [`_TOLERANCE`].
+fn _close(derived: f64, expected: f64) -> bool {
+    (derived - expected).abs() <= _TOLERANCE * expected.abs().max(1.0)
+}
+
+const INTRA_NEIGHBORHOODS: [Neighborhood; 7] = [
+    Neighborhood::Move(1, 0),
+    Neighborhood::Move(1, 1),
+    Neighborhood::Move(2, 0),
+    Neighborhood::Move(2, 1),
+    Neighborhood::Move(2, 2),
+    Neighborhood::TwoOpt,
+    Neighborhood::OrOpt,
+];
+
+/// [`INTRA_NEIGHBORHOODS`] along with the other Move (k, l) neighborhoods of `--neighborhoods`.
+fn _route_neighborhoods() -> Vec<Neighborhood> {
+    let mut result = INTRA_NEIGHBORHOODS.to_vec();
+    for &neighborhood in &config::current().neighborhoods {
+        if let Neighborhood::Move(..) = neighborhood
+            && !result.contains(&neighborhood)
+        {
+            result.push(neighborhood);
+        }
+    }
+
+    result
+}
+
+fn _multiset(customers: &[usize]) -> BTreeMap<usize, usize> {
+    let mut result = BTreeMap::new();
+    for &c in customers.iter().filter(|&&c| c != 0) {
+        *result.entry(c).or_insert(0) += 1;
+    }
+
+    result
+}
+
+fn _merge(mut first: BTreeMap<usize, usize>, second: BTreeMap<usize, usize>) -> BTreeMap<usize, usize> {
+    for (c, count) in second {
+        *first.entry(c).or_insert(0) += count;
+    }
+
+    first
+}
+
+/// Assert that `route` starts and ends at the depot, visits the depot nowhere else and carries the same cached
+/// values as a route freshly constructed from its customer list (up to [`_TOLERANCE`] for a derived waiting time
+/// violation, see [`Route::_derived`]).
+fn _check_route<R>(route: &R, context: &str)
+where
+    R: Route + fmt::Debug,
+{
+    let customers = &route.data().customers;
+    if customers.first() != Some(&0) || customers.last() != Some(&0) {
+        panic!("{context}: route {route:?} does not start and end at the depot");
+    }
+
+    if customers.len() < 3 || customers[1..customers.len() - 1].contains(&0) {
+        panic!("{context}: route {route:?} has an invalid interior");
+    }
+
+    let fresh = R::new(customers.clone());
+    let cached = [
+        route.working_time(),
+        route.capacity_violation(),
+        route.waiting_time_violation(),
+    ];
+    let expected = [
+        fresh.working_time(),
+        fresh.capacity_violation(),
+        fresh.waiting_time_violation(),
+    ];
+    let matches = if route._derived() {
+        cached[..2] == expected[..2] && _close(cached[2], expected[2])
+    } else {
+        cached == expected
+    };
+    if !matches {
+        panic!("{context}: route {route:?} has cached values {cached:?}, recomputation gives {expected:?}");
+    }
+}
+
+fn _random_route<R>(rng: &mut impl Rng) -> Option<Vec<usize>>
+where
+    R: Route,
+{
+    let mut candidates = (1..config::current().customers_count + 1)
+        .filter(|&c| R::_servable(c))
+        .collect::<Vec<usize>>();
+    if candidates.is_empty() {
+        return None;
+    }
+
+    candidates.shuffle(rng);
+    let length = rng.random_range(1..MAX_ROUTE_LENGTH.min(candidates.len()) + 1);
+
+    let mut customers = vec![0];
+    customers.extend_from_slice(&candidates[..length]);
+    customers.push(0);
+    Some(customers)
+}
+
+fn _check_single<R>(rng: &mut impl Rng)
+where
+    R: Route + fmt::Debug,
+{
+    let Some(customers) = _random_route::<R>(rng) else {
+        return;
+    };
+
+    let route = R::new(customers.clone());
+    _check_route(route.as_ref(), "new");
+
+    let original = _multiset(&customers);
+
+    // `push` followed by `pop` must be an identity
+    if let Some(extra) = (1..config::current().customers_count + 1).find(|c| !customers.contains(c) && R::_servable(*c))
+    {
+        let pushed = route.push(extra);
+        _check_route(pushed.as_ref(), "push");
+
+        let mut expected = original.clone();
+        expected.insert(extra, 1);
+        if _multiset(&pushed.data().customers) != expected {
+            panic!("push: {route:?} + {extra} produced {pushed:?}");
+        }
+
+        let popped = pushed.pop();
+        _check_route(popped.as_ref(), "pop");
+        if popped.data().customers != customers {
+            panic!("pop: {pushed:?} produced {popped:?}, expected {customers:?}");
+        }
+    }
+
+    for neighborhood in _route_neighborhoods() {
+        for (new_route, tabu) in route.intra_route(neighborhood) {
+            let context = format!("intra_route {neighborhood}");
+            _check_route(new_route.as_ref(), &context);
+
+            if _multiset(&new_route.data().customers) != original {
+                panic!("{context}: {route:?} produced {new_route:?}");
+            }
+
+            if tabu.iter().any(|c| !original.contains_key(c)) {
+                panic!("{context}: {route:?} produced tabu attribute {tabu:?}");
+            }
+
+            // The waiting time violation derived from the prefix aggregates of the original route must match
+            let new_customers = &new_route.data().customers;
+            let start = (0..customers.len())
+                .find(|&i| customers[i] != new_customers[i])
+                .unwrap_or(1);
+            let end = (0..customers.len())
+                .rfind(|&i| customers[i] != new_customers[i])
+                .map_or(start, |i| i + 1);
+            let derived = config::with_current(|config| {
+                config.quantize(route.waiting_time_violation_with(
+                    config,
+                    start,
+                    end,
+                    &new_customers[start..end],
+                    new_route.working_time(),
+                ))
+            });
+            let expected = R::new(new_customers.clone()).waiting_time_violation();
+            if !_close(derived, expected) {
+                panic!(
+                    "{context}: {route:?} -> {new_route:?} has a waiting time violation of {expected}, derived {derived}"
+                );
+            }
+        }
+    }
+
+    fn _check_extract<R, T>(route: &Arc<R>, original: &BTreeMap<usize, usize>)
+    where
+        R: Route + fmt::Debug,
+        T: Route + fmt::Debug,
+    {
+        for neighborhood in [Neighborhood::Move(1, 0), Neighborhood::Move(2, 0)] {
+            for (remaining, extracted, tabu) in route.inter_route_extract::<T>(neighborhood) {
+                let context = format!("inter_route_extract {neighborhood}");
+                _check_route(remaining.as_ref(), &context);
+                _check_route(extracted.as_ref(), &context);
+
+                let combined = _merge(
+                    _multiset(&remaining.data().customers),
+                    _multiset(&extracted.data().customers),
+                );
+                if &combined != original {
+                    panic!("{context}: {route:?} was split into {remaining:?} and {extracted:?}");
+                }
+
+                if _multiset(&tabu) != _multiset(&extracted.data().customers) {
+                    panic!("{context}: tabu attribute {tabu:?} does not match extracted route {extracted:?}");
+                }
+
+                if extracted.data().customers.iter().any(|&c| !T::_servable(c)) {
+                    panic!("{context}: extracted route {extracted:?} contains unservable customers");
+                }
+            }
+        }
+    }
+
+    _check_extract::<R, TruckRoute>(&route, &original);
+    _check_extract::<R, DroneRoute>(&route, &original);
+}
+
+fn _check_pair<RI, RJ>(rng: &mut impl Rng)
+where
+    RI: Route + fmt::Debug,
+    RJ: Route + fmt::Debug,
+{
+    let (Some(customers_i), Some(mut customers_j)) = (_random_route::<RI>(rng), _random_route::<RJ>(rng)) else {
+        return;
+    };
+
+    // Routes of the same solution never share customers
+    customers_j.retain(|c| *c == 0 || !customers_i.contains(c));
+    if customers_j.len() < 3 {
+        return;
+    }
+
+    let route_i = RI::new(customers_i);
+    let route_j = RJ::new(customers_j);
+    let original = _merge(
+        _multiset(&route_i.data().customers),
+        _multiset(&route_j.data().customers),
+    );
+
+    for neighborhood in _route_neighborhoods() {
+        let context = format!("inter_route {neighborhood}");
+        for (new_i, new_j, _) in route_i.inter_route(route_j.clone(), neighborhood) {
+            let mut combined = BTreeMap::new();
+            if let Some(ref new_i) = new_i {
+                _check_route(new_i.as_ref(), &context);
+                combined = _merge(combined, _multiset(&new_i.data().customers));
+            }
+            if let Some(ref new_j) = new_j {
+                _check_route(new_j.as_ref(), &context);
+                combined = _merge(combined, _multiset(&new_j.data().customers));
+            }
+
+            if combined != original {
+                panic!("{context}: {route_i:?} and {route_j:?} produced {new_i:?} and {new_j:?}");
+            }
+        }
+    }
+}
+
+/// Run randomized consistency checks of the route construction primitives against the current config.
+///
+/// Every route obtained from `push`, `pop`, `intra_route`, `inter_route` and `inter_route_extract` must keep the
+/// depot at both ends, preserve the multiset of customers and carry the same values as a route built from scratch
+/// (exactly, but for the derived waiting time violations), and the waiting time violations derived for the
+/// intra-route moves must match. Panics with a description of the first violation found.
+pub fn check_routes() {
+    let mut rng = seeding::substream(seeding::CHECKS);
+    for _ in 0..SAMPLES {
+        _check_single::<TruckRoute>(&mut rng);
+        _check_single::<DroneRoute>(&mut rng);
+
+        _check_pair::<TruckRoute, TruckRoute>(&mut rng);
+        _check_pair::<TruckRoute, DroneRoute>(&mut rng);
+        _check_pair::<DroneRoute, TruckRoute>(&mut rng);
+        _check_pair::<DroneRoute, DroneRoute>(&mut rng);
+    }
+
+    eprintln!("Route consistency checks passed ({SAMPLES} samples per route type)");
+}
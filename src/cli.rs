@@ -1,8 +1,13 @@
 use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
 
 use clap::{Parser, Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
 
+use crate::config::{Distance, DistanceMatrix};
+use crate::neighborhoods::Neighborhood;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize, Serialize)]
 pub enum EnergyModel {
     #[serde(rename = "linear")]
@@ -61,6 +66,10 @@ pub enum Strategy {
     Vns,
     #[serde(rename = "adaptive")]
     Adaptive,
+    /// Weight neighborhoods separately for truck-bound and drone-bound solutions, rewarding moves that reduce the
+    /// working time of the side defining the makespan
+    #[serde(rename = "bottleneck")]
+    Bottleneck,
 }
 
 impl fmt::Display for Strategy {
@@ -73,6 +82,141 @@ impl fmt::Display for Strategy {
                 Self::Cyclic => "cyclic",
                 Self::Vns => "vns",
                 Self::Adaptive => "adaptive",
+                Self::Bottleneck => "bottleneck",
+            }
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize, Serialize)]
+pub enum Algorithm {
+    /// Tabu search
+    #[default]
+    #[serde(rename = "tabu")]
+    Tabu,
+    /// Simulated annealing
+    #[serde(rename = "sa")]
+    Sa,
+    /// Iterated local search
+    #[serde(rename = "ils")]
+    Ils,
+    /// Tabu search restarting from offspring of the elite set
+    #[serde(rename = "memetic")]
+    Memetic,
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Tabu => "tabu",
+                Self::Sa => "sa",
+                Self::Ils => "ils",
+                Self::Memetic => "memetic",
+            }
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize, Serialize)]
+pub enum InitMethod {
+    /// Grow routes of each cluster of customers from a priority queue of vehicle working times
+    #[default]
+    #[serde(rename = "heap")]
+    Heap,
+    /// Merge truck routes by Clarke-Wright savings, then greedily move chains of dronable customers to drones
+    #[serde(rename = "savings")]
+    Savings,
+    /// Split a nearest neighbor giant tour into optimal truck routes, then greedily move chains of dronable customers
+    /// to drones
+    #[serde(rename = "split")]
+    Split,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize, Serialize)]
+pub enum MoveOrder {
+    /// Evaluate candidate moves in the order they are generated
+    #[default]
+    #[serde(rename = "natural")]
+    Natural,
+    /// Evaluate candidate moves that shorten the modified routes the most first
+    #[serde(rename = "arc-length")]
+    ArcLength,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize, Serialize)]
+pub enum RouteFormat {
+    /// The customer list of the route, e.g. `[0, 17, 42, 0]`
+    #[default]
+    #[serde(rename = "list")]
+    List,
+    /// The customers joined by arrows, followed by the working time and load of the route, e.g.
+    /// `0 → 17 → 42 → 0 (t=1834s, load=12.5kg)`
+    #[serde(rename = "arrows")]
+    Arrows,
+}
+
+impl fmt::Display for RouteFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::List => "list",
+                Self::Arrows => "arrows",
+            }
+        )
+    }
+}
+
+impl fmt::Display for MoveOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Natural => "natural",
+                Self::ArcLength => "arc-length",
+            }
+        )
+    }
+}
+
+impl fmt::Display for InitMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Heap => "heap",
+                Self::Savings => "savings",
+                Self::Split => "split",
+            }
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize, Serialize)]
+pub enum RiskMeasure {
+    /// Average capacity violation over the demand scenarios
+    #[default]
+    #[serde(rename = "expected")]
+    Expected,
+    /// Average capacity violation over the worst (1 - [--cvar-alpha]) fraction of the demand scenarios
+    #[serde(rename = "cvar")]
+    Cvar,
+}
+
+impl fmt::Display for RiskMeasure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Expected => "expected",
+                Self::Cvar => "cvar",
             }
         )
     }
@@ -100,19 +244,56 @@ impl fmt::Display for DistanceType {
 }
 
 impl DistanceType {
-    pub fn matrix(&self, x: &[f64], y: &[f64]) -> Vec<Vec<f64>> {
+    pub fn matrix(&self, x: &[f64], y: &[f64]) -> DistanceMatrix {
         let n = x.len();
         assert_eq!(n, y.len());
 
-        let mut matrix = vec![vec![0.0; n]; n];
-        for i in 0..n {
-            for j in 0..n {
-                let dx = x[i] - x[j];
-                let dy = y[i] - y[j];
-                matrix[i][j] = match self {
-                    Self::Manhattan => dx.abs() + dy.abs(),
-                    Self::Euclidean => (dx * dx + dy * dy).sqrt(),
-                };
+        // Fill the matrix row by row with the distance type resolved outside of the inner loop: the branch-free
+        // zipped iterators below are auto-vectorized, which matters for instances with thousands of customers.
+        let mut matrix = vec![0.0; n * n];
+        for (row, (&xi, &yi)) in matrix.chunks_exact_mut(n.max(1)).zip(x.iter().zip(y)) {
+            let cells = row.iter_mut().zip(x.iter().zip(y));
+            match self {
+                Self::Manhattan => {
+                    for (cell, (&xj, &yj)) in cells {
+                        *cell = ((xi - xj).abs() + (yi - yj).abs()) as Distance;
+                    }
+                }
+                Self::Euclidean => {
+                    for (cell, (&xj, &yj)) in cells {
+                        let dx = xi - xj;
+                        let dy = yi - yj;
+                        *cell = (dx * dx + dy * dy).sqrt() as Distance;
+                    }
+                }
+            }
+        }
+
+        DistanceMatrix::new(n, matrix)
+    }
+
+    /// Like [`Self::matrix`], except that arcs between two customers tagged with the same distance type in `tags` use
+    /// that type instead. An empty `tags` means that no customer is tagged.
+    pub fn tagged_matrix(&self, x: &[f64], y: &[f64], tags: &[Option<Self>]) -> DistanceMatrix {
+        let mut matrix = self.matrix(x, y);
+        if tags.is_empty() {
+            return matrix;
+        }
+
+        assert_eq!(tags.len(), x.len());
+        for tag in Self::value_variants() {
+            let members = (0..tags.len()).filter(|&i| tags[i] == Some(*tag)).collect::<Vec<_>>();
+            if tag == self || members.is_empty() {
+                continue;
+            }
+
+            let x = members.iter().map(|&i| x[i]).collect::<Vec<_>>();
+            let y = members.iter().map(|&i| y[i]).collect::<Vec<_>>();
+            let local = tag.matrix(&x, &y);
+            for (a, &i) in members.iter().enumerate() {
+                for (b, &j) in members.iter().enumerate() {
+                    matrix.set(i, j, local.get(a, b));
+                }
             }
         }
 
@@ -120,6 +301,153 @@ impl DistanceType {
     }
 }
 
+/// An axis-aligned rectangle, parsed from `xmin,ymin,xmax,ymax`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Region {
+    pub x_min: f64,
+    pub y_min: f64,
+    pub x_max: f64,
+    pub y_max: f64,
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<f64>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<f64>, _>>()?;
+        match values[..] {
+            [x_min, y_min, x_max, y_max] => Ok(Self {
+                x_min,
+                y_min,
+                x_max,
+                y_max,
+            }),
+            _ => Err(format!("Expected 4 comma-separated values, got {}", values.len())),
+        }
+    }
+}
+
+/// Penalty coefficients of the energy, capacity, waiting time and fixed time violations, parsed from `e,c,w,f`.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PenaltyCoefficients(pub [f64; 4]);
+
+impl Default for PenaltyCoefficients {
+    fn default() -> Self {
+        Self([1.0; 4])
+    }
+}
+
+impl FromStr for PenaltyCoefficients {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<f64>().map_err(|e| e.to_string()))
+            .collect::<Result<Vec<f64>, _>>()?;
+        <[f64; 4]>::try_from(values)
+            .map(Self)
+            .map_err(|values| format!("Expected 4 comma-separated values, got {}", values.len()))
+    }
+}
+
+impl fmt::Display for PenaltyCoefficients {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [e, c, w, t] = self.0;
+        write!(f, "{e},{c},{w},{t}")
+    }
+}
+
+impl Region {
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        (self.x_min..=self.x_max).contains(&x) && (self.y_min..=self.y_max).contains(&y)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize, Serialize)]
+pub enum InstanceFormat {
+    /// The native text format of the `problems/data` instances
+    #[serde(rename = "native")]
+    Native,
+    /// Solomon VRPTW text format
+    #[serde(rename = "solomon")]
+    Solomon,
+    /// CVRPLIB (TSPLIB-style) `.vrp` format
+    #[serde(rename = "cvrplib")]
+    Cvrplib,
+    /// JSON instance format
+    #[serde(rename = "json")]
+    Json,
+}
+
+impl fmt::Display for InstanceFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Native => "native",
+                Self::Solomon => "solomon",
+                Self::Cvrplib => "cvrplib",
+                Self::Json => "json",
+            }
+        )
+    }
+}
+
+impl InstanceFormat {
+    /// Guess the format of an instance file from its extension, falling back to sniffing `content` for `.txt` files
+    /// (which may be either native or Solomon instances).
+    pub fn detect(path: &Path, content: Option<&str>) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::Json,
+            Some("vrp") => Self::Cvrplib,
+            _ => match content {
+                Some(content) if content.contains("VEHICLE") && content.contains("CUSTOMER") => Self::Solomon,
+                _ => Self::Native,
+            },
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Deserialize, Serialize)]
+pub enum DronableRule {
+    /// Keep the dronability of the input (customers of formats without one are dronable)
+    #[serde(rename = "keep")]
+    Keep,
+    /// Mark all customers as dronable
+    #[serde(rename = "all")]
+    All,
+    /// Mark no customer as dronable
+    #[serde(rename = "none")]
+    None,
+    /// Mark customers with demand at most [--dronable-threshold] as dronable
+    #[serde(rename = "demand")]
+    Demand,
+    /// Mark customers within [--dronable-threshold] (Euclidean) of the depot as dronable
+    #[serde(rename = "distance")]
+    Distance,
+}
+
+impl fmt::Display for DronableRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Keep => "keep",
+                Self::All => "all",
+                Self::None => "none",
+                Self::Demand => "demand",
+                Self::Distance => "distance",
+            }
+        )
+    }
+}
+
 #[derive(Debug, Parser)]
 #[command(
     long_about = "The min-timespan parallel technician-and-drone scheduling in door-to-door sampling service system",
@@ -145,7 +473,7 @@ pub enum Commands {
 
     /// Run the algorithm
     Run {
-        /// Path to the coordinate file
+        /// Path to the instance file, in any format supported by `convert` (detected from the file extension)
         problem: String,
 
         /// Path to truck config file
@@ -185,6 +513,16 @@ pub enum Commands {
         #[arg(long, default_value_t = 0)]
         ejection_chain_iterations: usize,
 
+        /// After the search, reassign the drone routes to the drones to minimize the latest drone completion time
+        /// exactly (by branch and bound)
+        #[arg(long)]
+        refine_drone_schedule: bool,
+
+        /// After the search, split drone routes into single-customer sorties whenever the resulting sorties can be
+        /// assigned to the drones with an earlier latest drone completion time
+        #[arg(long)]
+        match_drone_sorties: bool,
+
         /// The destroy rate during destroy-and-repair procedure when the elite set is popped,
         /// but before ejection-chain is executed (set to 0 to disable destroy-and-repair)
         #[arg(long, default_value_t = 0.1)]
@@ -202,6 +540,12 @@ pub enum Commands {
         #[arg(long, default_value_t = DistanceType::Euclidean)]
         truck_distance: DistanceType,
 
+        /// Path to a JSON list of `{"region": "xmin,ymin,xmax,ymax", "distance": "manhattan" | "euclidean"}` objects.
+        /// Customers (and the depot) inside a region are tagged with its distance type (the first matching region
+        /// wins), and truck arcs between two customers with the same tag use it instead of --truck-distance.
+        #[arg(long)]
+        truck_distance_regions: Option<String>,
+
         /// Distance type to use for drones.
         #[arg(long, default_value_t = DistanceType::Euclidean)]
         drone_distance: DistanceType,
@@ -214,18 +558,190 @@ pub enum Commands {
         #[arg(long)]
         drones_count: Option<usize>,
 
+        /// Path to a file listing the (1-based, whitespace or comma separated) indices of the customers to solve for.
+        /// Otherwise, solve for all customers of the instance.
+        #[arg(long)]
+        customers: Option<String>,
+
+        /// Only solve for the customers inside the rectangle `xmin,ymin,xmax,ymax`.
+        #[arg(long, allow_hyphen_values = true)]
+        region: Option<Region>,
+
+        /// Path to a JSON file of assignment constraints: a list of `{"customer": <index>, "vehicle": "truck" | "drone"}`
+        /// objects, with an optional `"index"` to require a specific vehicle of that type.
+        #[arg(long)]
+        locks: Option<String>,
+
+        /// Path to a JSON file of arc constraints: `{"required": [[a, b], ...], "forbidden": [[a, b], ...]}`, meaning
+        /// that customer `a` must (not) be immediately followed by customer `b`.
+        #[arg(long)]
+        arcs: Option<String>,
+
+        /// Path to a JSON file of zones: `{"zones": {<name>: [<index>, ...], ...}, "vehicles": [...]}`, where each
+        /// vehicle entry `{"vehicle": "truck" | "drone", "allowed": [<name>, ...]}` lists the zones that vehicles of
+        /// this type may enter, with an optional `"index"` to restrict a specific vehicle instead.
+        #[arg(long)]
+        zones: Option<String>,
+
         /// The waiting time limit for each customer (in seconds).
         #[arg(long, default_value_t = 3600.0)]
         waiting_time_limit: f64,
 
+        /// Limit of the total vehicle cost: the fixed cost of each vehicle serving at least one route plus the
+        /// variable cost of the distance traveled by trucks and the energy consumed by drones. Exceeding it is
+        /// penalized as a violation. No limit by default
+        #[arg(long)]
+        budget: Option<f64>,
+
+        /// Fixed cost of each truck serving at least one route, see --budget
+        #[arg(long, default_value_t = 0.0)]
+        truck_fixed_cost: f64,
+
+        /// Fixed cost of each drone serving at least one route, see --budget
+        #[arg(long, default_value_t = 0.0)]
+        drone_fixed_cost: f64,
+
+        /// Cost per kilometer traveled by trucks, see --budget
+        #[arg(long, default_value_t = 0.0)]
+        truck_cost_per_km: f64,
+
+        /// Cost per kilojoule of energy consumed by drones, see --budget
+        #[arg(long, default_value_t = 0.0)]
+        drone_cost_per_kj: f64,
+
+        /// Violations up to this value are considered zero when deciding feasibility, absorbing floating-point
+        /// residuals of the route evaluations
+        #[arg(long, default_value_t = 1e-9)]
+        feasibility_tolerance: f64,
+
+        /// Round every route value (working time and violations) and solution cost to this number of decimals, so
+        /// that last-bit differences between platform math libraries do not propagate into the search and results
+        /// can be compared bit for bit against golden outputs
+        #[arg(long)]
+        fixed_point: Option<u8>,
+
+        /// Number of demand scenarios sampled from the demand distributions of the instance (`demand_std` and
+        /// `demand_scenarios` of JSON instances). Capacity violations are then measured over these scenarios with
+        /// --risk-measure instead of on the nominal demands. 0 disables scenarios.
+        #[arg(long, default_value_t = 0)]
+        demand_samples: usize,
+
+        /// Risk measure aggregating the capacity violations of a route over the demand scenarios.
+        #[arg(long, default_value_t = RiskMeasure::Expected)]
+        risk_measure: RiskMeasure,
+
+        /// Confidence level of the CVaR risk measure, in [0, 1).
+        #[arg(long, default_value_t = 0.9)]
+        cvar_alpha: f64,
+
+        /// Seed of the demand scenarios [default: derived from --seed, or random]
+        #[arg(long)]
+        scenario_seed: Option<u64>,
+
+        /// Master seed of the random choices of the search [default: random]. The initial construction, the search
+        /// and each cluster of --decompose draw from separate substreams of it, so that seeded runs are reproducible
+        /// even when clusters are solved in parallel.
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Handover time of a drone at each customer, between landing and the next takeoff (in seconds). Customers
+        /// with a `drone_service_time` in a JSON instance use that value instead.
+        #[arg(long, default_value_t = 0.0)]
+        drone_service_time: f64,
+
+        /// Path to a JSON square matrix of drone cruise altitudes (in meters) over the depot and all customers of the
+        /// instance file, where entry `[a][b]` applies to the arc from `a` to `b`. Takeoff and landing times and
+        /// energy of each arc are scaled by its altitude relative to the cruise altitude of the drone config.
+        #[arg(long)]
+        altitudes: Option<String>,
+
+        /// Multiplier applied to the battery energy of the drone config, e.g. 0.8 to account for the capacity lost in
+        /// cold weather (ignored by the endurance and unlimited models).
+        #[arg(long, default_value_t = 1.0)]
+        battery_derating: f64,
+
+        /// Construction method of the initial solution.
+        #[arg(long, default_value_t = InitMethod::Heap)]
+        init: InitMethod,
+
+        /// Path to a solution JSON (as written by a previous run, e.g. `<problem>-<id>-solution.json`) to start the
+        /// search from instead of constructing one with `--init`. Its routes are checked against the loaded instance
+        /// and fleet, and evaluated again under the current config.
+        #[arg(long)]
+        initial_solution: Option<String>,
+
+        /// Split the customers into this many clusters (as the initial heuristic does), solve each of them with its
+        /// share of the vehicles in a separate run, then merge the results into the initial solution of the search,
+        /// whose inter-route moves improve it across clusters. Other options are forwarded to the runs.
+        #[arg(long)]
+        decompose: Option<usize>,
+
+        /// Solve the clusters of --decompose in parallel
+        #[arg(long)]
+        decompose_parallel: bool,
+
+        /// Instead of minimizing the makespan with the given fleet, find the smallest fleet reaching a feasible
+        /// solution with a makespan of at most this many seconds: bisect the number of trucks (with all drones), then
+        /// the number of drones, running a full search for each fleet size tried
+        #[arg(long)]
+        target_makespan: Option<f64>,
+
+        /// Order in which the candidate moves of each neighborhood are evaluated. Ties between equally good moves are
+        /// broken in favor of the first one.
+        #[arg(long, default_value_t = MoveOrder::Natural)]
+        move_order: MoveOrder,
+
+        /// Comma-separated neighborhoods searched by the local search, in order: `move-K-L` exchanges a segment of K
+        /// consecutive customers with a segment of L consecutive customers (or relocates it when L is 0) for any K >= 1
+        /// and L, e.g. `move-3-0` or `move-3-2`, along with `2-opt`, `or-opt`, `route-relocate`, `split-merge` and
+        /// `drone-rebalance`. The neighborhoods not in the default list are only searched when listed here
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "move-1-0,move-1-1,move-2-0,move-2-1,move-2-2,2-opt"
+        )]
+        neighborhoods: Vec<Neighborhood>,
+
         /// Tabu search neighborhood selection strategy.
         #[arg(long, default_value_t = Strategy::Adaptive)]
         strategy: Strategy,
 
+        /// Local search algorithm driving the neighborhoods: `tabu` moves to the best non-tabu neighbor, `sa` (simulated
+        /// annealing) draws a random neighbor and accepts it if it is no worse, or with probability `exp(-delta / T)`,
+        /// `ils` (iterated local search) kicks the current solution and descends to a local optimum, keeping it if it
+        /// is better, and `memetic` runs the tabu search but restarts from the crossover of two elite solutions
+        /// (which requires `--max-elite-size` of at least 2).
+        #[arg(long, default_value_t = Algorithm::Tabu)]
+        algorithm: Algorithm,
+
+        /// Initial temperature `T` of simulated annealing, in units of the solution cost. The temperature is reset to
+        /// it whenever the search restarts from the elite set.
+        #[arg(long, default_value_t = 100.0)]
+        initial_temperature: f64,
+
+        /// Factor by which the temperature of simulated annealing is multiplied after each iteration.
+        #[arg(long, default_value_t = 0.995)]
+        cooling_rate: f64,
+
+        /// Number of double-bridge moves on truck routes, and of relocations of drone routes to another drone, applied
+        /// by each kick of iterated local search.
+        #[arg(long, default_value_t = 1)]
+        kick_strength: usize,
+
         /// Fix the number of iterations and disable elite set extraction. Otherwise, run until the elite set is exhausted.
         #[arg(long)]
         fix_iteration: Option<usize>,
 
+        /// Stop the search (and skip the remaining post-optimization) once this many seconds of wall-clock time have
+        /// passed since the start of the run
+        #[arg(long)]
+        time_limit: Option<f64>,
+
+        /// Stop the search as soon as a feasible solution with a working time of at most this value is found, e.g. for
+        /// time-to-target experiments
+        #[arg(long)]
+        target_cost: Option<f64>,
+
         /// The number of non-improved iterations before resetting the current solution = [--reset-after-factor] * [Base]
         #[arg(long, default_value_t = 125.0)]
         reset_after_factor: f64,
@@ -234,24 +750,132 @@ pub enum Commands {
         #[arg(long, default_value_t = 0)]
         max_elite_size: usize,
 
+        /// When restarting, walk from the current solution towards the elite solution drawn by guided Move (1, 0) and
+        /// Move (1, 1) steps, and restart from the best solution met on the way instead of destroying and repairing
+        /// the elite solution.
+        #[arg(long)]
+        path_relinking: bool,
+
+        /// Number of iterations after each restart during which moves are restricted to the customers perturbed by the
+        /// restart (those whose vehicle or neighbors changed), or 0 to never restrict them
+        #[arg(long, default_value_t = 0)]
+        focus_iterations: usize,
+
+        /// Keep don't-look bits per neighborhood and customer with the tabu search: the customers of the bottleneck
+        /// vehicle are marked when a neighborhood finds no improving move, and unmarked when their route changes. Moves
+        /// relocating only marked customers are skipped
+        #[arg(long)]
+        dont_look_bits: bool,
+
+        /// Keep route-level don't-look bits per neighborhood with the tabu search: when a neighborhood finds no
+        /// improving move, the moves between the routes it searched from and the other routes are skipped (before being
+        /// generated) as long as neither route changes
+        #[arg(long)]
+        dont_look_routes: bool,
+
+        /// Relocate customers with Move (1, 0) and Move (2, 0), and insert them when repairing, only at the position of
+        /// each route adding the least distance (looked up in a table cached per route), instead of trying every
+        /// position
+        #[arg(long)]
+        insertion_table: bool,
+
+        /// Evaluate the inter-route 2-opt moves between routes of the same vehicle type in constant time each, from
+        /// aggregates of the tails of the routes, and only build the best one: the one exceeding the capacities the
+        /// least, then leaving the shorter longest route
+        #[arg(long)]
+        two_opt_best_only: bool,
+
+        /// Number of vehicles with the longest working times whose routes the neighborhoods move customers from,
+        /// instead of only the decisive vehicle. See also --decisive-epsilon
+        #[arg(long, default_value_t = 1)]
+        decisive_top_k: usize,
+
+        /// Also search from every vehicle whose working time is within this value of the longest one (in seconds),
+        /// in addition to the --decisive-top-k most loaded vehicles
+        #[arg(long, default_value_t = 0.0)]
+        decisive_epsilon: f64,
+
+        /// Granular neighborhoods: only evaluate the inter-route moves creating at least one arc between a customer
+        /// and one of its N nearest customers (by truck distance), or the depot. All moves are evaluated by default
+        #[arg(long)]
+        granularity: Option<usize>,
+
+        /// Biased randomized move selection: take the admissible candidate of rank R by cost instead of the best one,
+        /// where R follows a geometric distribution with this success probability in (0, 1] (1 always takes the best
+        /// one). Candidates meeting the aspiration criterion are always taken
+        #[arg(long)]
+        selection_bias: Option<f64>,
+
+        /// Evaluate the candidate moves of each pair of routes (and the intra-route moves of each route) in parallel on
+        /// a rayon thread pool. The move taken is the same as with a sequential evaluation, see also RAYON_NUM_THREADS
+        #[arg(long)]
+        parallel_evaluation: bool,
+
+        /// Number of tabu searches run in parallel, each on a thread of its own and from a different initial solution.
+        /// They periodically exchange their best solutions through a shared elite pool, and the best solution overall
+        /// is returned
+        #[arg(long, default_value_t = 1)]
+        threads: usize,
+
+        /// Number of iterations between two exchanges of best solutions between the searches of --threads
+        #[arg(long, default_value_t = 100)]
+        exchange_interval: usize,
+
+        /// Derive [--tabu-size-factor], [--reset-after-factor] and [--max-elite-size] from the instance (number of
+        /// customers, fraction of dronable customers and number of vehicles), overriding the given values
+        #[arg(long)]
+        auto_tune_light: bool,
+
         /// Exponent value E attached to the cost function:
         ///
         /// Cost(S) = [working time] * (1 + [weighted penalty values]).powf(E)
         #[arg(long, default_value_t = 0.5)]
         penalty_exponent: f64,
 
+        /// Initial penalty coefficients of the energy, capacity, waiting time and fixed time violations, e.g. the
+        /// `penalty_coefficients` reported by a previous run on a similar instance.
+        #[arg(long, default_value_t = PenaltyCoefficients::default())]
+        penalty_init: PenaltyCoefficients,
+
+        /// How much of the penalty coefficients learned since the start is kept when the search restarts from an
+        /// elite solution, as an exponent of their ratio to --penalty-init: 1 keeps them as is, 0 resets them to
+        /// --penalty-init and values in between dampen them.
+        #[arg(long, default_value_t = 1.0)]
+        penalty_retention: f64,
+
         /// Allow one route per truck only (this route can still serve multiple customers)
         #[arg(long)]
         single_truck_route: bool,
 
+        /// Maximum number of routes per truck
+        #[arg(long)]
+        max_routes_per_truck: Option<usize>,
+
+        /// Maximum number of customers per truck route
+        #[arg(long)]
+        max_customers_per_truck_route: Option<usize>,
+
         /// Allow one customer per drone route only (each drone can still perform multiple routes)
         #[arg(long)]
         single_drone_route: bool,
 
+        /// Minimum number of customers per drone route. Moves never leave a drone route with fewer customers (but may
+        /// empty it), routes built by the initial heuristics may still be shorter
+        #[arg(long, default_value_t = 1)]
+        min_customers_per_drone_route: usize,
+
+        /// Maximum number of customers per drone route
+        #[arg(long)]
+        max_customers_per_drone_route: Option<usize>,
+
         /// The verbose mode
         #[arg(short, long)]
         verbose: bool,
 
+        /// How routes are printed in the verbose output
+        #[arg(long, default_value_t = RouteFormat::List)]
+        route_format: RouteFormat,
+
         /// The directory to store results
         #[arg(long, default_value_t = String::from("outputs/"))]
         outputs: String,
@@ -260,6 +884,54 @@ pub enum Commands {
         #[arg(long)]
         disable_logging: bool,
 
+        /// Maximum size (in bytes) of the iteration log. When exceeded, the log is moved to `<problem>-<id>.1.csv`
+        /// (replacing the previous one) and restarted, while the iterations improving on the best feasible solution
+        /// are also kept in `<problem>-<id>-improvements.csv`
+        #[arg(long)]
+        log_max_size: Option<u64>,
+
+        /// Keep only the last N iterations of the log in memory instead of writing every iteration, and write them
+        /// to the iteration log when the run finishes or panics
+        #[arg(long)]
+        log_ring: Option<usize>,
+
+        /// Record every accepted move (iteration, neighborhood, customers involved and cost delta) to a compact binary
+        /// trace file, e.g. to train a move selection policy offline
+        #[arg(long)]
+        trace_moves: bool,
+
+        /// Report the duration of every iteration but the first (in milliseconds, excluding the time spent writing
+        /// the iteration log) as `iteration_ms` in the run JSON, as `bench` does
+        #[arg(long)]
+        record_iteration_times: bool,
+
+        /// Write every Nth new best solution found by the search to `<outputs>/<problem>-<id>/snapshots/iter<K>.json`,
+        /// where K is the iteration it was found at, in the format of the output solution JSON
+        #[arg(long)]
+        snapshot_every: Option<usize>,
+
+        /// Directory to watch for candidate solutions (in the output solution JSON format) computed by another
+        /// process during the run. New or modified files are verified and merged into the elite set.
+        #[arg(long)]
+        inject: Option<String>,
+
+        /// Number of iterations between two scans of the --inject directory
+        #[arg(long, default_value_t = 100)]
+        inject_interval: usize,
+
+        /// Control file polled (at most once per second) for commands written by an operator during the run, one per
+        /// line: `stop` ends the search gracefully, `checkpoint [path]` saves the best solution found so far, `report`
+        /// prints the progress of the search and `set time-limit <seconds|none>` replaces --time-limit. The file is
+        /// read again whenever it is modified; its content at the start of the run is ignored
+        #[arg(long)]
+        control: Option<String>,
+
+        /// Path to a JSON pool of routes from previous runs on the same instance. When restarting from the elite set,
+        /// pool routes whose customers were all destroyed are rebuilt as a whole before repairing the others. The
+        /// routes of the best solution are added to the pool (created if needed) at the end of the run.
+        #[arg(long)]
+        route_pool: Option<String>,
+
         /// Do not run the algorithm, only generate the config file
         #[arg(long)]
         dry_run: bool,
@@ -267,5 +939,166 @@ pub enum Commands {
         /// Extra data to store in the output JSON
         #[arg(long, default_value_t = String::new())]
         extra: String,
+
+        /// Run internal consistency checks on randomly generated routes before searching (slow, for debugging)
+        #[arg(long)]
+        paranoid: bool,
+
+        /// Before writing the outputs, rebuild the final solution from its customer lists and panic if any of its
+        /// values differs from the one reported, e.g. because of a stale cached route value
+        #[arg(long)]
+        verify_final: bool,
+
+        /// Directory to cache the parsed instance and distance matrices in, reused by later runs on the same
+        /// instance with the same instance-related options
+        #[arg(long)]
+        cache_dir: Option<String>,
+    },
+
+    /// Benchmark the algorithm with a fixed iteration budget
+    Bench {
+        /// Paths to the coordinate files
+        #[arg(required = true)]
+        problems: Vec<String>,
+
+        /// Number of runs per instance
+        #[arg(long, default_value_t = 5)]
+        repeat: usize,
+
+        /// Number of iterations of each run
+        #[arg(long, default_value_t = 500)]
+        iterations: usize,
+
+        /// Path to a baseline file to compare against
+        #[arg(long)]
+        baseline: Option<String>,
+
+        /// Path to store the statistics of this benchmark as a baseline
+        #[arg(long)]
+        save_baseline: Option<String>,
+
+        /// Extra arguments forwarded to each run, e.g. `bench problem.txt -- --strategy cyclic`
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
+    /// Convert an instance file to another format
+    Convert {
+        /// Path to the input instance file
+        input: String,
+
+        /// Path to the output instance file
+        output: String,
+
+        /// Format of the input file [default: detected from the file]
+        #[arg(long)]
+        from: Option<InstanceFormat>,
+
+        /// Format of the output file [default: detected from the file extension]
+        #[arg(long)]
+        to: Option<InstanceFormat>,
+
+        /// Number of trucks, overriding the one from the input file
+        #[arg(long)]
+        trucks_count: Option<usize>,
+
+        /// Number of drones, overriding the one from the input file
+        #[arg(long)]
+        drones_count: Option<usize>,
+
+        /// Vehicle capacity, overriding the one from the input file (required by the Solomon and CVRPLIB formats)
+        #[arg(long)]
+        capacity: Option<f64>,
+
+        /// Rule deciding which customers are dronable
+        #[arg(long, default_value_t = DronableRule::Keep)]
+        dronable: DronableRule,
+
+        /// Threshold of the demand and distance dronability rules
+        #[arg(long, required_if_eq_any([("dronable", "demand"), ("dronable", "distance")]))]
+        dronable_threshold: Option<f64>,
+    },
+
+    /// Compute descriptive statistics and a rough difficulty score of instance files
+    ProfileInstance {
+        /// Paths to the instance files
+        #[arg(required = true)]
+        problems: Vec<String>,
+
+        /// Format of the instance files [default: detected from each file]
+        #[arg(long)]
+        from: Option<InstanceFormat>,
+
+        /// Path to truck config file, whose capacity is used when the instance does not specify one
+        #[arg(long, default_value_t = String::from("problems/config_parameter/truck_config.json"))]
+        truck_cfg: String,
+
+        /// Path to store the statistics as JSON
+        #[arg(long)]
+        output: Option<String>,
+    },
+
+    /// Solve perturbed variants of an instance and report how much their solutions differ from that of the instance
+    Perturb {
+        /// Path to the base instance file
+        problem: String,
+
+        /// Format of the instance file [default: detected from the file]
+        #[arg(long)]
+        from: Option<InstanceFormat>,
+
+        /// Number of perturbed variants
+        #[arg(long, default_value_t = 10)]
+        variants: usize,
+
+        /// Maximum relative change of each demand, in percent
+        #[arg(long, default_value_t = 10.0)]
+        demand: f64,
+
+        /// Number of customers added to each variant, copying random customers at random positions
+        #[arg(long, default_value_t = 2)]
+        add: usize,
+
+        /// Number of random customers removed from each variant
+        #[arg(long, default_value_t = 2)]
+        remove: usize,
+
+        /// Maximum displacement of each coordinate, in percent of the extent of the instance along its axis
+        #[arg(long, default_value_t = 1.0)]
+        jitter: f64,
+
+        /// Number of iterations of each run
+        #[arg(long, default_value_t = 500)]
+        iterations: usize,
+
+        /// Seed of the perturbations [default: random]
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Path to store the metrics as JSON
+        #[arg(long)]
+        output: Option<String>,
+
+        /// Extra arguments forwarded to each run, e.g. `perturb problem.txt -- --strategy cyclic`
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
+    /// Cluster the customers of an instance as done by the initial heuristic, to inspect the spatial decomposition
+    Cluster {
+        /// Path to the instance file
+        problem: String,
+
+        /// Format of the instance file [default: detected from the file]
+        #[arg(long)]
+        from: Option<InstanceFormat>,
+
+        /// Number of clusters [default: the number of trucks of the instance, or its number of drones without trucks]
+        #[arg(short, long)]
+        clusters: Option<usize>,
+
+        /// Path to store the clusters as JSON
+        #[arg(long)]
+        output: Option<String>,
     },
 }
@@ -1,17 +1,29 @@
 use std::cmp::min;
 use std::collections::HashMap;
+use std::error::Error;
 use std::f64::consts;
+use std::fs;
+use std::path::Path;
 
-use crate::config::CONFIG;
+use serde::Serialize;
+
+use crate::cli::InstanceFormat;
+use crate::config;
+use crate::instance::Instance;
 
 pub fn clusterize(customers: &mut [usize], k: usize) -> Vec<Vec<usize>> {
+    let config = config::current();
+    sweep(&config.x, &config.y, customers, k)
+}
+
+/// Split `customers` into `k` clusters of equal angular width around the depot (index 0 of `x` and `y`), starting
+/// after the widest angular gap between consecutive customers.
+pub fn sweep(x: &[f64], y: &[f64], customers: &mut [usize], k: usize) -> Vec<Vec<usize>> {
     let mut clusters = vec![vec![]; k];
     if customers.is_empty() {
         return clusters;
     }
 
-    let x = &CONFIG.x;
-    let y = &CONFIG.y;
     let mut angles = HashMap::<usize, f64>::new();
     for &customer in customers.iter() {
         let mut angle = (y[customer] - y[0]).atan2(x[customer] - x[0]);
@@ -50,3 +62,85 @@ pub fn clusterize(customers: &mut [usize], k: usize) -> Vec<Vec<usize>> {
 
     clusters
 }
+
+#[derive(Serialize)]
+struct _Cluster {
+    /// Customers of the cluster, numbered from 1 in the order of the instance file.
+    customers: Vec<usize>,
+    demand: f64,
+    dronable: usize,
+}
+
+/// Cluster the customers of `problem` into `clusters` clusters as done by the initial heuristic, print a summary
+/// table and optionally store the clusters as JSON to `output`.
+pub fn cluster_instance(
+    problem: &str,
+    from: Option<InstanceFormat>,
+    clusters: Option<usize>,
+    output: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let data = fs::read_to_string(problem)?;
+    let format = from.unwrap_or_else(|| InstanceFormat::detect(Path::new(problem), Some(&data)));
+    let instance = Instance::parse(&data, format)?;
+
+    let k = match clusters {
+        Some(k) => k,
+        None => match (instance.trucks_count, instance.drones_count) {
+            (Some(0), Some(drones)) => drones,
+            (Some(trucks), _) => trucks,
+            _ => return Err("The instance does not specify its vehicles, --clusters is required".into()),
+        },
+    };
+    if k == 0 {
+        return Err("At least one cluster is required".into());
+    }
+
+    let x = [instance.depot.x]
+        .into_iter()
+        .chain(instance.customers.iter().map(|c| c.x))
+        .collect::<Vec<_>>();
+    let y = [instance.depot.y]
+        .into_iter()
+        .chain(instance.customers.iter().map(|c| c.y))
+        .collect::<Vec<_>>();
+    let mut index = Vec::from_iter(1..instance.customers.len() + 1);
+
+    let clusters = sweep(&x, &y, &mut index, k)
+        .into_iter()
+        .map(|mut customers| {
+            customers.sort_unstable();
+            let demand = customers.iter().map(|&c| instance.customers[c - 1].demand).sum();
+            let dronable = customers
+                .iter()
+                .filter(|&&c| instance.customers[c - 1].dronable)
+                .count();
+            _Cluster {
+                customers,
+                demand,
+                dronable,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    println!(
+        "{:>7} {:>9} {:>9} {:>12}  Members",
+        "Cluster", "Customers", "Dronable", "Demand"
+    );
+    for (i, cluster) in clusters.iter().enumerate() {
+        println!(
+            "{:>7} {:>9} {:>9} {:>12.2}  {:?}",
+            i,
+            cluster.customers.len(),
+            cluster.dronable,
+            cluster.demand,
+            cluster.customers,
+        );
+    }
+
+    if let Some(path) = output {
+        fs::write(path, serde_json::to_string_pretty(&clusters)?)?;
+        eprintln!("Saved clusters to {path}");
+    }
+
+    Ok(())
+}
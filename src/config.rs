@@ -1,12 +1,240 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::f64::consts;
-use std::fs;
-use std::sync::LazyLock;
-
-use clap::Parser;
-use regex::{Regex, RegexBuilder};
+use std::fs::{self, File};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::{fmt, slice};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
 
-use crate::cli;
+use crate::instance::Instance;
+use crate::neighborhoods::Neighborhood;
+use crate::{cli, seeding};
+
+/// Storage type of the distance matrices. Building with the `f32` feature halves their memory footprint (which
+/// dominates for instances with 1000+ customers) at the cost of ~7 significant digits per distance; route values are
+/// still accumulated in `f64`.
+#[cfg(feature = "f32")]
+pub type Distance = f32;
+
+/// Storage type of the distance matrices. Building with the `f32` feature halves their memory footprint (which
+/// dominates for instances with 1000+ customers) at the cost of ~7 significant digits per distance; route values are
+/// still accumulated in `f64`.
+#[cfg(not(feature = "f32"))]
+pub type Distance = f64;
+
+/// Name of [`Distance`], reported in the output JSON so results computed with reduced precision can be told apart.
+pub const DISTANCE_PRECISION: &str = if cfg!(feature = "f32") { "f32" } else { "f64" };
+
+/// Square matrix of pairwise distances, stored contiguously in row-major order.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DistanceMatrix {
+    _size: usize,
+    _data: Vec<Distance>,
+}
+
+impl DistanceMatrix {
+    pub fn new(size: usize, data: Vec<Distance>) -> Self {
+        assert_eq!(data.len(), size * size);
+        Self {
+            _size: size,
+            _data: data,
+        }
+    }
+
+    #[allow(clippy::useless_conversion)] // `Distance` may already be `f64`
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        f64::from(self._data[i * self._size + j])
+    }
+
+    pub fn set(&mut self, i: usize, j: usize, value: f64) {
+        self._data[i * self._size + j] = value as Distance;
+    }
+
+    /// The `k` nearest customers (excluding the depot) of each customer and of the depot, sorted by index.
+    pub fn nearest_neighbors(&self, k: usize) -> Vec<Vec<usize>> {
+        (0..self._size)
+            .map(|i| {
+                let mut neighbors = (1..self._size).filter(|&j| j != i).collect::<Vec<_>>();
+                neighbors.sort_by(|&a, &b| self.get(i, a).total_cmp(&self.get(i, b)));
+                neighbors.truncate(k);
+                neighbors.sort_unstable();
+                neighbors
+            })
+            .collect()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum VehicleKind {
+    #[serde(rename = "truck")]
+    Truck,
+    #[serde(rename = "drone")]
+    Drone,
+}
+
+impl fmt::Display for VehicleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Truck => "truck",
+                Self::Drone => "drone",
+            }
+        )
+    }
+}
+
+/// Requirement that a customer is served by a given type of vehicle, or by a specific vehicle of that type.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+pub struct CustomerLock {
+    pub vehicle: VehicleKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+}
+
+impl CustomerLock {
+    pub fn allows(&self, kind: VehicleKind, vehicle: usize) -> bool {
+        self.vehicle == kind && self.index.is_none_or(|index| index == vehicle)
+    }
+}
+
+/// Identifies the instance a solution was computed for, see [`Config::fingerprint`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct InstanceFingerprint {
+    pub customers_count: usize,
+    /// Hash of the coordinates of the depot and the customers.
+    pub coordinates_hash: u64,
+}
+
+impl fmt::Display for InstanceFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} customers, coordinates hash {:016x}",
+            self.customers_count, self.coordinates_hash
+        )
+    }
+}
+
+/// Constraints on which customer immediately follows another one within a route.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ArcConstraints {
+    _next: Vec<Option<usize>>,
+    _prev: Vec<Option<usize>>,
+    _forbidden: Vec<Vec<usize>>,
+}
+
+impl ArcConstraints {
+    pub const fn is_empty(&self) -> bool {
+        self._next.is_empty()
+    }
+
+    /// Whether `customer` takes part in any constraint.
+    pub fn constrained(&self, customer: usize) -> bool {
+        !self.is_empty()
+            && (self._next[customer].is_some()
+                || self._prev[customer].is_some()
+                || !self._forbidden[customer].is_empty()
+                || self._forbidden.iter().any(|f| f.contains(&customer)))
+    }
+
+    /// Whether the route visiting `customers` (including the depot at both ends) satisfies all constraints.
+    pub fn respected(&self, customers: &[usize]) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        for i in 1..customers.len() - 1 {
+            let c = customers[i];
+            if self._next[c].is_some_and(|next| next != customers[i + 1])
+                || self._prev[c].is_some_and(|prev| prev != customers[i - 1])
+                || self._forbidden[c].contains(&customers[i + 1])
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Whether inserting the chain of customers from `first` to `last` between the consecutive customers `a` and `b` of
+    /// a route satisfying all constraints keeps them satisfied.
+    pub fn insertable(&self, a: usize, first: usize, last: usize, b: usize) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        !(a != 0 && (self._next[a].is_some() || self._forbidden[a].contains(&first)))
+            && !(b != 0 && self._prev[b].is_some())
+            && self._prev[first].is_none_or(|prev| prev == a)
+            && self._next[last].is_none_or(|next| next == b)
+            && !self._forbidden[last].contains(&b)
+    }
+
+    /// The chain of customers linked to `customer` by required arcs, in visiting order.
+    pub fn chain(&self, mut customer: usize) -> Vec<usize> {
+        if self.is_empty() {
+            return vec![customer];
+        }
+
+        while let Some(prev) = self._prev[customer] {
+            customer = prev;
+        }
+
+        let mut chain = vec![customer];
+        while let Some(next) = self._next[customer] {
+            customer = next;
+            chain.push(customer);
+        }
+
+        chain
+    }
+}
+
+/// Zones of the customers and the zones each vehicle may enter.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ZoneConstraints {
+    _zones: Vec<Option<usize>>,
+    _trucks: Vec<Vec<bool>>,
+    _drones: Vec<Vec<bool>>,
+}
+
+impl ZoneConstraints {
+    pub const fn is_empty(&self) -> bool {
+        self._zones.is_empty()
+    }
+
+    /// Whether the `vehicle`-th vehicle of type `kind` may serve `customer`.
+    pub fn allows(&self, kind: VehicleKind, vehicle: usize, customer: usize) -> bool {
+        let Some(zone) = self._zones.get(customer).copied().flatten() else {
+            return true;
+        };
+
+        match kind {
+            VehicleKind::Truck => self._trucks[vehicle][zone],
+            VehicleKind::Drone => self._drones[vehicle][zone],
+        }
+    }
+
+    /// Whether any vehicle of type `kind` may serve `customer`.
+    pub fn servable(&self, kind: VehicleKind, customer: usize) -> bool {
+        let Some(zone) = self._zones.get(customer).copied().flatten() else {
+            return true;
+        };
+
+        match kind {
+            VehicleKind::Truck => self._trucks.iter().any(|allowed| allowed[zone]),
+            VehicleKind::Drone => self._drones.iter().any(|allowed| allowed[zone]),
+        }
+    }
+}
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TruckConfig {
@@ -31,6 +259,11 @@ pub struct LinearJSON {
     #[serde(rename = "cruiseAlt [m]")]
     altitude: f64,
 
+    /// Relative increase of the takeoff and landing durations per kg of payload, as heavier climbs and descents are
+    /// slower (0 when absent, i.e. durations independent of the payload).
+    #[serde(rename = "verticalSlowdown [1/kg]", default)]
+    vertical_slowdown: f64,
+
     #[serde(rename = "capacity [kg]")]
     capacity: f64,
 
@@ -61,6 +294,11 @@ pub struct NonLinearJSON {
     #[serde(rename = "cruiseAlt [m]")]
     altitude: f64,
 
+    /// Relative increase of the takeoff and landing durations per kg of payload, as heavier climbs and descents are
+    /// slower (0 when absent, i.e. durations independent of the payload).
+    #[serde(rename = "verticalSlowdown [1/kg]", default)]
+    vertical_slowdown: f64,
+
     #[serde(rename = "capacity [kg]")]
     capacity: f64,
 
@@ -114,6 +352,8 @@ pub enum DroneConfig {
         _data: LinearJSON,
         _takeoff_time: f64,
         _landing_time: f64,
+        #[serde(default = "_default_battery_derating")]
+        _battery_derating: f64,
     },
     NonLinear {
         _data: NonLinearJSON,
@@ -130,17 +370,33 @@ pub enum DroneConfig {
         _hori_c5: f64,
         _takeoff_time: f64,
         _landing_time: f64,
+        #[serde(default = "_default_battery_derating")]
+        _battery_derating: f64,
     },
     Endurance {
         _data: EnduranceJSON,
     },
 }
 
+const fn _default_battery_derating() -> f64 {
+    1.0
+}
+
 impl DroneConfig {
     const W: f64 = 1.5;
     const G: f64 = 9.8;
 
-    fn new(path: &String, config: cli::EnergyModel, speed_type: cli::ConfigType, range_type: cli::ConfigType) -> Self {
+    fn new(
+        path: &String,
+        config: cli::EnergyModel,
+        speed_type: cli::ConfigType,
+        range_type: cli::ConfigType,
+        battery_derating: f64,
+    ) -> Self {
+        assert!(
+            battery_derating > 0.0,
+            "Battery derating factor must be positive, got {battery_derating}"
+        );
         match config {
             cli::EnergyModel::Linear => {
                 let data = serde_json::from_str::<Vec<LinearJSON>>(&fs::read_to_string(path).unwrap()).unwrap();
@@ -153,6 +409,7 @@ impl DroneConfig {
                             _data: config,
                             _takeoff_time,
                             _landing_time,
+                            _battery_derating: battery_derating,
                         };
                     }
                 }
@@ -201,6 +458,7 @@ impl DroneConfig {
                             _hori_c5,
                             _takeoff_time,
                             _landing_time,
+                            _battery_derating: battery_derating,
                         };
                     }
                 }
@@ -238,10 +496,19 @@ impl DroneConfig {
         }
     }
 
+    /// Usable battery energy, i.e. the battery capacity of the drone config scaled by the derating factor.
     pub fn battery(&self) -> f64 {
         match self {
-            Self::Linear { _data, .. } => _data.battery,
-            Self::NonLinear { _data, .. } => _data.battery,
+            Self::Linear {
+                _data,
+                _battery_derating,
+                ..
+            } => _data.battery * _battery_derating,
+            Self::NonLinear {
+                _data,
+                _battery_derating,
+                ..
+            } => _data.battery * _battery_derating,
             Self::Endurance { .. } => 1.0,
         }
     }
@@ -312,16 +579,42 @@ impl DroneConfig {
         }
     }
 
-    pub fn takeoff_time(&self) -> f64 {
+    /// Cruise altitude of the drone (0 if takeoffs and landings are not modelled).
+    pub fn altitude(&self) -> f64 {
+        match self {
+            Self::Linear { _data, .. } => _data.altitude,
+            Self::NonLinear { _data, .. } => _data.altitude,
+            Self::Endurance { .. } => 0.0,
+        }
+    }
+
+    /// Factor by which carrying `weight` kg of payload lengthens takeoffs and landings.
+    pub fn vertical_factor(&self, weight: f64) -> f64 {
+        let slowdown = match self {
+            Self::Linear { _data, .. } => _data.vertical_slowdown,
+            Self::NonLinear { _data, .. } => _data.vertical_slowdown,
+            Self::Endurance { .. } => 0.0,
+        };
+
+        slowdown.mul_add(weight, 1.0)
+    }
+
+    /// Duration of a takeoff carrying `weight` kg of payload.
+    pub fn takeoff_time(&self, weight: f64) -> f64 {
         match self {
-            Self::Linear { _takeoff_time, .. } | Self::NonLinear { _takeoff_time, .. } => *_takeoff_time,
+            Self::Linear { _takeoff_time, .. } | Self::NonLinear { _takeoff_time, .. } => {
+                _takeoff_time * self.vertical_factor(weight)
+            }
             Self::Endurance { .. } => 0.0,
         }
     }
 
-    pub fn landing_time(&self) -> f64 {
+    /// Duration of a landing carrying `weight` kg of payload.
+    pub fn landing_time(&self, weight: f64) -> f64 {
         match self {
-            Self::Linear { _landing_time, .. } | Self::NonLinear { _landing_time, .. } => *_landing_time,
+            Self::Linear { _landing_time, .. } | Self::NonLinear { _landing_time, .. } => {
+                _landing_time * self.vertical_factor(weight)
+            }
             Self::Endurance { .. } => 0.0,
         }
     }
@@ -338,6 +631,14 @@ impl DroneConfig {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SerializedConfig {
     customers_count: usize,
+    #[serde(default)]
+    customer_ids: Vec<usize>,
+    #[serde(default)]
+    locks: Vec<Option<CustomerLock>>,
+    #[serde(default)]
+    arcs: ArcConstraints,
+    #[serde(default)]
+    zones: ZoneConstraints,
     trucks_count: usize,
     drones_count: usize,
 
@@ -345,8 +646,16 @@ pub struct SerializedConfig {
     y: Vec<f64>,
     demands: Vec<f64>,
     dronable: Vec<bool>,
+    #[serde(default)]
+    drone_service_times: Vec<f64>,
+    #[serde(default)]
+    priorities: Vec<f64>,
+    #[serde(default)]
+    altitudes: Option<DistanceMatrix>,
 
     truck_distance: cli::DistanceType,
+    #[serde(default)]
+    truck_distance_tags: Vec<Option<cli::DistanceType>>,
     drone_distance: cli::DistanceType,
 
     truck: TruckConfig,
@@ -360,39 +669,230 @@ pub struct SerializedConfig {
     adaptive_segments: usize,
     adaptive_fixed_segments: bool,
     ejection_chain_iterations: usize,
+    #[serde(default)]
+    refine_drone_schedule: bool,
+    #[serde(default)]
+    match_drone_sorties: bool,
     destroy_rate: f64,
     speed_type: cli::ConfigType,
     range_type: cli::ConfigType,
     waiting_time_limit: f64,
+    #[serde(default)]
+    budget: Option<f64>,
+    #[serde(default)]
+    truck_fixed_cost: f64,
+    #[serde(default)]
+    drone_fixed_cost: f64,
+    #[serde(default)]
+    truck_cost_per_km: f64,
+    #[serde(default)]
+    drone_cost_per_kj: f64,
+    #[serde(default)]
+    feasibility_tolerance: f64,
+    #[serde(default)]
+    fixed_point: Option<u8>,
+    #[serde(default)]
+    demand_scenarios: Vec<Vec<f64>>,
+    #[serde(default)]
+    risk_measure: cli::RiskMeasure,
+    #[serde(default = "_default_cvar_alpha")]
+    cvar_alpha: f64,
+    #[serde(default)]
+    scenario_seed: Option<u64>,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    init: cli::InitMethod,
+    #[serde(default)]
+    initial_solution: Option<String>,
+    #[serde(default)]
+    decompose: Option<usize>,
+    #[serde(default)]
+    decompose_parallel: bool,
+    #[serde(default)]
+    target_makespan: Option<f64>,
+    #[serde(default)]
+    move_order: cli::MoveOrder,
+    #[serde(default = "_default_neighborhoods")]
+    neighborhoods: Vec<Neighborhood>,
     strategy: cli::Strategy,
+    #[serde(default)]
+    algorithm: cli::Algorithm,
+    #[serde(default = "_default_initial_temperature")]
+    initial_temperature: f64,
+    #[serde(default = "_default_cooling_rate")]
+    cooling_rate: f64,
+    #[serde(default = "_default_kick_strength")]
+    kick_strength: usize,
     fix_iteration: Option<usize>,
+    #[serde(default)]
+    time_limit: Option<f64>,
+    #[serde(default)]
+    target_cost: Option<f64>,
     reset_after_factor: f64,
     max_elite_size: usize,
+    #[serde(default)]
+    path_relinking: bool,
+    #[serde(default)]
+    focus_iterations: usize,
+    #[serde(default)]
+    dont_look_bits: bool,
+    #[serde(default)]
+    dont_look_routes: bool,
+    #[serde(default)]
+    insertion_table: bool,
+    #[serde(default)]
+    two_opt_best_only: bool,
+    #[serde(default = "_default_decisive_top_k")]
+    decisive_top_k: usize,
+    #[serde(default)]
+    decisive_epsilon: f64,
+    #[serde(default)]
+    granularity: Option<usize>,
+    #[serde(default)]
+    selection_bias: Option<f64>,
+    #[serde(default)]
+    parallel_evaluation: bool,
+    #[serde(default = "_default_threads")]
+    threads: usize,
+    #[serde(default = "_default_exchange_interval")]
+    exchange_interval: usize,
+    #[serde(default)]
+    auto_tune_light: bool,
     penalty_exponent: f64,
+    #[serde(default)]
+    penalty_init: cli::PenaltyCoefficients,
+    #[serde(default = "_default_penalty_retention")]
+    penalty_retention: f64,
     single_truck_route: bool,
+    #[serde(default)]
+    max_routes_per_truck: Option<usize>,
+    #[serde(default)]
+    max_customers_per_truck_route: Option<usize>,
     single_drone_route: bool,
+    #[serde(default = "_default_min_customers_per_route")]
+    min_customers_per_drone_route: usize,
+    #[serde(default)]
+    max_customers_per_drone_route: Option<usize>,
     verbose: bool,
+    #[serde(default)]
+    route_format: cli::RouteFormat,
     outputs: String,
     disable_logging: bool,
+    #[serde(default)]
+    log_max_size: Option<u64>,
+    #[serde(default)]
+    log_ring: Option<usize>,
+    #[serde(default)]
+    trace_moves: bool,
+    #[serde(default)]
+    record_iteration_times: bool,
+    #[serde(default)]
+    snapshot_every: Option<usize>,
+    #[serde(default)]
+    inject: Option<String>,
+    #[serde(default = "_default_inject_interval")]
+    inject_interval: usize,
+    #[serde(default)]
+    control: Option<String>,
+    #[serde(default)]
+    route_pool: Option<String>,
     dry_run: bool,
     extra: String,
+    paranoid: bool,
+    #[serde(default)]
+    verify_final: bool,
+    cache_dir: Option<String>,
+}
+
+const fn _default_cvar_alpha() -> f64 {
+    0.9
+}
+
+const fn _default_initial_temperature() -> f64 {
+    100.0
+}
+
+const fn _default_cooling_rate() -> f64 {
+    0.995
+}
+
+const fn _default_kick_strength() -> usize {
+    1
+}
+
+const fn _default_decisive_top_k() -> usize {
+    1
+}
+
+fn _default_neighborhoods() -> Vec<Neighborhood> {
+    vec![
+        Neighborhood::Move(1, 0),
+        Neighborhood::Move(1, 1),
+        Neighborhood::Move(2, 0),
+        Neighborhood::Move(2, 1),
+        Neighborhood::Move(2, 2),
+        Neighborhood::TwoOpt,
+    ]
 }
 
+const fn _default_penalty_retention() -> f64 {
+    1.0
+}
+
+const fn _default_min_customers_per_route() -> usize {
+    1
+}
+
+const fn _default_inject_interval() -> usize {
+    100
+}
+
+const fn _default_threads() -> usize {
+    1
+}
+
+const fn _default_exchange_interval() -> usize {
+    100
+}
+
+/// Instance data and search parameters of a run. The instance data (customers, constraints and distance matrices) is
+/// held in `Arc`s, so that the clones of a config changing some of its parameters share it.
 #[derive(Clone, Debug)]
 pub struct Config {
     pub customers_count: usize,
+    /// Index of each customer (and the depot at index 0) in the instance file, which differs from its index here
+    /// when solving for a subset of the customers.
+    pub customer_ids: Arc<[usize]>,
+    /// Assignment constraint of each customer, or empty if there are none.
+    pub locks: Arc<[Option<CustomerLock>]>,
+    /// Required and forbidden successions of customers.
+    pub arcs: Arc<ArcConstraints>,
+    /// Zones of the customers and the zones each vehicle may enter.
+    pub zones: Arc<ZoneConstraints>,
     pub trucks_count: usize,
     pub drones_count: usize,
 
-    pub x: Vec<f64>,
-    pub y: Vec<f64>,
-    pub demands: Vec<f64>,
-    pub dronable: Vec<bool>,
+    pub x: Arc<[f64]>,
+    pub y: Arc<[f64]>,
+    pub demands: Arc<[f64]>,
+    pub dronable: Arc<[bool]>,
+    /// Handover time of a drone at each customer (0 at the depot).
+    pub drone_service_times: Arc<[f64]>,
+    /// Weight of the waiting time violation of each customer (1 unless the instance gives a priority).
+    pub priorities: Arc<[f64]>,
+    /// Cruise altitude of each drone arc, or empty to use the altitude of the drone config everywhere.
+    pub altitudes: Option<Arc<DistanceMatrix>>,
 
     pub truck_distance: cli::DistanceType,
+    /// Distance type overriding [`Self::truck_distance`] between customers with the same tag, or empty if no customer
+    /// is tagged.
+    pub truck_distance_tags: Arc<[Option<cli::DistanceType>]>,
     pub drone_distance: cli::DistanceType,
-    pub truck_distances: Vec<Vec<f64>>,
-    pub drone_distances: Vec<Vec<f64>>,
+    pub truck_distances: Arc<DistanceMatrix>,
+    pub drone_distances: Arc<DistanceMatrix>,
+    /// Candidate lists of the granular neighborhoods, see [`Self::candidate_arc`]. Empty without `--granularity`.
+    pub nearest_neighbors: Arc<[Vec<usize>]>,
 
     pub truck: TruckConfig,
     pub drone: DroneConfig,
@@ -405,41 +905,303 @@ pub struct Config {
     pub adaptive_segments: usize,
     pub adaptive_fixed_segments: bool,
     pub ejection_chain_iterations: usize,
+    pub refine_drone_schedule: bool,
+    pub match_drone_sorties: bool,
     pub destroy_rate: f64,
     pub speed_type: cli::ConfigType,
     pub range_type: cli::ConfigType,
     pub waiting_time_limit: f64,
+    /// Limit of the total vehicle cost, see [`crate::evaluate::EvaluationReport::vehicle_cost`].
+    pub budget: Option<f64>,
+    pub truck_fixed_cost: f64,
+    pub drone_fixed_cost: f64,
+    pub truck_cost_per_km: f64,
+    pub drone_cost_per_kj: f64,
+    pub feasibility_tolerance: f64,
+    pub fixed_point: Option<u8>,
+    /// Sampled demands of each customer (0 at the depot) per scenario, or empty to use [`Self::demands`] only.
+    pub demand_scenarios: Arc<[Vec<f64>]>,
+    pub risk_measure: cli::RiskMeasure,
+    pub cvar_alpha: f64,
+    pub scenario_seed: Option<u64>,
+    /// Master seed of the random number streams of [`crate::seeding`].
+    pub seed: Option<u64>,
+    pub init: cli::InitMethod,
+    pub initial_solution: Option<String>,
+    pub decompose: Option<usize>,
+    pub decompose_parallel: bool,
+    pub target_makespan: Option<f64>,
+    pub move_order: cli::MoveOrder,
+    pub neighborhoods: Vec<Neighborhood>,
     pub strategy: cli::Strategy,
+    pub algorithm: cli::Algorithm,
+    pub initial_temperature: f64,
+    pub cooling_rate: f64,
+    pub kick_strength: usize,
     pub fix_iteration: Option<usize>,
+    pub time_limit: Option<f64>,
+    pub target_cost: Option<f64>,
     pub reset_after_factor: f64,
     pub max_elite_size: usize,
+    pub path_relinking: bool,
+    pub focus_iterations: usize,
+    pub dont_look_bits: bool,
+    pub dont_look_routes: bool,
+    pub insertion_table: bool,
+    pub two_opt_best_only: bool,
+    pub decisive_top_k: usize,
+    pub decisive_epsilon: f64,
+    pub granularity: Option<usize>,
+    pub selection_bias: Option<f64>,
+    pub parallel_evaluation: bool,
+    pub threads: usize,
+    pub exchange_interval: usize,
+    pub auto_tune_light: bool,
     pub penalty_exponent: f64,
+    pub penalty_init: cli::PenaltyCoefficients,
+    pub penalty_retention: f64,
     pub single_truck_route: bool,
+    pub max_routes_per_truck: Option<usize>,
+    pub max_customers_per_truck_route: Option<usize>,
     pub single_drone_route: bool,
+    pub min_customers_per_drone_route: usize,
+    pub max_customers_per_drone_route: Option<usize>,
     pub verbose: bool,
+    pub route_format: cli::RouteFormat,
     pub outputs: String,
     pub disable_logging: bool,
+    pub log_max_size: Option<u64>,
+    pub log_ring: Option<usize>,
+    pub trace_moves: bool,
+    pub record_iteration_times: bool,
+    pub snapshot_every: Option<usize>,
+    pub inject: Option<String>,
+    pub inject_interval: usize,
+    pub control: Option<String>,
+    pub route_pool: Option<String>,
     pub dry_run: bool,
     pub extra: String,
+    pub paranoid: bool,
+    pub verify_final: bool,
+    pub cache_dir: Option<String>,
+}
+
+impl Config {
+    /// Read a config saved by a previous run (its `-config.json` output), without going through the command line.
+    pub fn from_json(data: &str) -> serde_json::Result<Self> {
+        serde_json::from_str::<SerializedConfig>(data).map(Self::from)
+    }
+
+    /// This config with its instance-derived part (customers, vehicle counts and distance matrices) replaced by
+    /// `instance`, whose vehicle counts default to those of this config. Everything tied to the customers of the
+    /// previous instance (customer subset, distance regions, altitude profile, locks, arcs, zones and demand
+    /// scenarios) is dropped, and drone service times default to 0.
+    pub fn with_instance(self, instance: &Instance) -> Self {
+        let options = _InstanceOptions {
+            problem: &self.problem,
+            truck_cfg: "",
+            drone_cfg: "",
+            config: self.config,
+            speed_type: self.speed_type,
+            range_type: self.range_type,
+            // The drone config is already derated, this only keys the cache
+            battery_derating: 1.0,
+            truck_distance: self.truck_distance,
+            truck_distance_regions: None,
+            drone_distance: self.drone_distance,
+            trucks_count: Some(instance.trucks_count.unwrap_or(self.trucks_count)),
+            drones_count: Some(instance.drones_count.unwrap_or(self.drones_count)),
+            customers: None,
+            region: None,
+            altitudes: None,
+        };
+        let data = _InstanceData::from_instance(instance.clone(), &options, &self.truck, &self.drone);
+        _check_servable(
+            &data.customer_ids,
+            &data.dronable,
+            &[],
+            &ZoneConstraints::default(),
+            data.trucks_count,
+            data.drones_count,
+        );
+
+        Self {
+            customers_count: data.customers_count,
+            customer_ids: data.customer_ids.into(),
+            locks: Arc::default(),
+            arcs: Arc::default(),
+            zones: Arc::default(),
+            trucks_count: data.trucks_count,
+            drones_count: data.drones_count,
+            x: data.x.into(),
+            y: data.y.into(),
+            demands: data.demands.into(),
+            dronable: data.dronable.into(),
+            drone_service_times: data.drone_service_times.into_iter().map(|t| t.unwrap_or(0.0)).collect(),
+            priorities: data.priorities.into(),
+            altitudes: None,
+            truck_distance_tags: Arc::default(),
+            nearest_neighbors: self
+                .granularity
+                .map_or_else(Arc::default, |k| data.truck_distances.nearest_neighbors(k).into()),
+            truck_distances: data.truck_distances.into(),
+            drone_distances: data.drone_distances.into(),
+            demand_scenarios: Arc::default(),
+            ..self
+        }
+    }
+
+    /// Assignment constraint of `customer`, if any.
+    pub fn lock(&self, customer: usize) -> Option<CustomerLock> {
+        self.locks.get(customer).copied().flatten()
+    }
+
+    /// Whether the `vehicle`-th vehicle of type `kind` may serve `customer`, according to both its lock and its zone.
+    pub fn allows(&self, kind: VehicleKind, vehicle: usize, customer: usize) -> bool {
+        self.lock(customer).is_none_or(|l| l.allows(kind, vehicle)) && self.zones.allows(kind, vehicle, customer)
+    }
+
+    /// Factor scaling the takeoff and landing of `drone` on the arc from `i` to `j`, i.e. the ratio of the altitude
+    /// of this arc to the cruise altitude of the drone config.
+    pub fn altitude_scale(&self, drone: &DroneConfig, i: usize, j: usize) -> f64 {
+        self.altitudes.as_ref().map_or(1.0, |a| a.get(i, j) / drone.altitude())
+    }
+
+    /// Round `value` to `--fixed-point` decimals, if enabled.
+    pub fn quantize(&self, value: f64) -> f64 {
+        match self.fixed_point {
+            Some(decimals) => {
+                let scale = 10f64.powi(i32::from(decimals));
+                (value * scale).round() / scale
+            }
+            None => value,
+        }
+    }
+
+    /// Capacity violation of a route serving `customers` with a total nominal demand of `weight` by a vehicle of
+    /// capacity `capacity`. With demand scenarios, the violations of the route in each scenario are aggregated by
+    /// [`Self::risk_measure`] instead.
+    pub fn capacity_violation(&self, customers: &[usize], weight: f64, capacity: f64) -> f64 {
+        if self.demand_scenarios.is_empty() {
+            return (weight - capacity).max(0.0);
+        }
+
+        let mut violations = self
+            .demand_scenarios
+            .iter()
+            .map(|demands| (customers.iter().map(|&c| demands[c]).sum::<f64>() - capacity).max(0.0))
+            .collect::<Vec<_>>();
+        let count = violations.len() as f64;
+        match self.risk_measure {
+            cli::RiskMeasure::Expected => violations.iter().sum::<f64>() / count,
+            cli::RiskMeasure::Cvar => {
+                // Average over the worst (1 - alpha) fraction of the scenarios, weighting the scenario at the
+                // boundary by the fraction of it inside the tail
+                violations.sort_by(|a, b| b.total_cmp(a));
+                let tail = (1.0 - self.cvar_alpha) * count;
+                let whole = tail.floor() as usize;
+                let mut total = violations[..whole].iter().sum::<f64>();
+                if let Some(&boundary) = violations.get(whole) {
+                    total += boundary * (tail - whole as f64);
+                }
+
+                total / tail
+            }
+        }
+    }
+
+    /// Fingerprint of the instance of this config, stored in solution files so that evaluating a solution against the
+    /// config of another instance is refused.
+    pub fn fingerprint(&self) -> InstanceFingerprint {
+        let mut hasher = DefaultHasher::new();
+        for (x, y) in self.x.iter().zip(self.y.iter()) {
+            x.to_bits().hash(&mut hasher);
+            y.to_bits().hash(&mut hasher);
+        }
+
+        InstanceFingerprint {
+            customers_count: self.customers_count,
+            coordinates_hash: hasher.finish(),
+        }
+    }
+
+    /// Parameters of each truck class. Routes cache their values per class, so all trucks of a class share them.
+    pub fn truck_classes(&self) -> &[TruckConfig] {
+        slice::from_ref(&self.truck)
+    }
+
+    /// Parameters of each drone class. Routes cache their values per class, so all drones of a class share them.
+    pub fn drone_classes(&self) -> &[DroneConfig] {
+        slice::from_ref(&self.drone)
+    }
+
+    /// Class of the truck at index `truck`, see [`Self::truck_classes`].
+    pub const fn truck_class(&self, _truck: usize) -> usize {
+        0
+    }
+
+    /// Class of the drone at index `drone`, see [`Self::drone_classes`].
+    pub const fn drone_class(&self, _drone: usize) -> usize {
+        0
+    }
+
+    /// Whether the arc between `i` and `j` is in the candidate lists of the granular neighborhoods: either end is the
+    /// depot or one of the `--granularity` nearest customers of the other. Always true without `--granularity`.
+    pub fn candidate_arc(&self, i: usize, j: usize) -> bool {
+        self.granularity.is_none()
+            || i == 0
+            || j == 0
+            || self.nearest_neighbors[i].binary_search(&j).is_ok()
+            || self.nearest_neighbors[j].binary_search(&i).is_ok()
+    }
 }
 
 impl From<SerializedConfig> for Config {
     fn from(config: SerializedConfig) -> Self {
-        let truck_distances = config.truck_distance.matrix(&config.x, &config.y);
+        let truck_distances = config
+            .truck_distance
+            .tagged_matrix(&config.x, &config.y, &config.truck_distance_tags);
         let drone_distances = config.drone_distance.matrix(&config.x, &config.y);
+        let nearest_neighbors = config
+            .granularity
+            .map_or_else(Vec::new, |k| truck_distances.nearest_neighbors(k));
 
         Self {
+            customer_ids: if config.customer_ids.is_empty() {
+                // Configs serialized before customer subsets were supported
+                (0..config.customers_count + 1).collect()
+            } else {
+                config.customer_ids.into()
+            },
+            drone_service_times: if config.drone_service_times.is_empty() {
+                // Configs serialized before drone service times were supported
+                vec![0.0; config.customers_count + 1].into()
+            } else {
+                config.drone_service_times.into()
+            },
+            priorities: if config.priorities.is_empty() {
+                // Configs serialized before customer priorities were supported
+                vec![1.0; config.customers_count + 1].into()
+            } else {
+                config.priorities.into()
+            },
+            altitudes: config.altitudes.map(Arc::new),
+            arcs: config.arcs.into(),
+            zones: config.zones.into(),
+            locks: config.locks.into(),
             customers_count: config.customers_count,
             trucks_count: config.trucks_count,
             drones_count: config.drones_count,
-            x: config.x,
-            y: config.y,
-            demands: config.demands,
-            dronable: config.dronable,
+            x: config.x.into(),
+            y: config.y.into(),
+            demands: config.demands.into(),
+            dronable: config.dronable.into(),
             truck_distance: config.truck_distance,
+            truck_distance_tags: config.truck_distance_tags.into(),
             drone_distance: config.drone_distance,
-            truck_distances,
-            drone_distances,
+            truck_distances: truck_distances.into(),
+            drone_distances: drone_distances.into(),
+            nearest_neighbors: nearest_neighbors.into(),
             truck: config.truck,
             drone: config.drone,
             problem: config.problem,
@@ -450,22 +1212,82 @@ impl From<SerializedConfig> for Config {
             adaptive_segments: config.adaptive_segments,
             adaptive_fixed_segments: config.adaptive_fixed_segments,
             ejection_chain_iterations: config.ejection_chain_iterations,
+            refine_drone_schedule: config.refine_drone_schedule,
+            match_drone_sorties: config.match_drone_sorties,
             destroy_rate: config.destroy_rate,
             speed_type: config.speed_type,
             range_type: config.range_type,
             waiting_time_limit: config.waiting_time_limit,
+            budget: config.budget,
+            truck_fixed_cost: config.truck_fixed_cost,
+            drone_fixed_cost: config.drone_fixed_cost,
+            truck_cost_per_km: config.truck_cost_per_km,
+            drone_cost_per_kj: config.drone_cost_per_kj,
+            feasibility_tolerance: config.feasibility_tolerance,
+            fixed_point: config.fixed_point,
+            demand_scenarios: config.demand_scenarios.into(),
+            risk_measure: config.risk_measure,
+            cvar_alpha: config.cvar_alpha,
+            scenario_seed: config.scenario_seed,
+            seed: config.seed,
+            init: config.init,
+            initial_solution: config.initial_solution,
+            decompose: config.decompose,
+            decompose_parallel: config.decompose_parallel,
+            target_makespan: config.target_makespan,
+            move_order: config.move_order,
+            neighborhoods: config.neighborhoods,
             strategy: config.strategy,
+            algorithm: config.algorithm,
+            initial_temperature: config.initial_temperature,
+            cooling_rate: config.cooling_rate,
+            kick_strength: config.kick_strength,
             fix_iteration: config.fix_iteration,
+            time_limit: config.time_limit,
+            target_cost: config.target_cost,
             reset_after_factor: config.reset_after_factor,
             max_elite_size: config.max_elite_size,
+            path_relinking: config.path_relinking,
+            focus_iterations: config.focus_iterations,
+            dont_look_bits: config.dont_look_bits,
+            dont_look_routes: config.dont_look_routes,
+            insertion_table: config.insertion_table,
+            two_opt_best_only: config.two_opt_best_only,
+            decisive_top_k: config.decisive_top_k,
+            decisive_epsilon: config.decisive_epsilon,
+            granularity: config.granularity,
+            selection_bias: config.selection_bias,
+            parallel_evaluation: config.parallel_evaluation,
+            threads: config.threads,
+            exchange_interval: config.exchange_interval,
+            auto_tune_light: config.auto_tune_light,
             penalty_exponent: config.penalty_exponent,
+            penalty_init: config.penalty_init,
+            penalty_retention: config.penalty_retention,
             single_truck_route: config.single_truck_route,
+            max_routes_per_truck: config.max_routes_per_truck,
+            max_customers_per_truck_route: config.max_customers_per_truck_route,
             single_drone_route: config.single_drone_route,
+            min_customers_per_drone_route: config.min_customers_per_drone_route,
+            max_customers_per_drone_route: config.max_customers_per_drone_route,
             verbose: config.verbose,
+            route_format: config.route_format,
             outputs: config.outputs,
             disable_logging: config.disable_logging,
+            log_max_size: config.log_max_size,
+            log_ring: config.log_ring,
+            trace_moves: config.trace_moves,
+            record_iteration_times: config.record_iteration_times,
+            snapshot_every: config.snapshot_every,
+            inject: config.inject,
+            inject_interval: config.inject_interval,
+            control: config.control,
+            route_pool: config.route_pool,
             dry_run: config.dry_run,
             extra: config.extra,
+            paranoid: config.paranoid,
+            verify_final: config.verify_final,
+            cache_dir: config.cache_dir,
         }
     }
 }
@@ -473,14 +1295,22 @@ impl From<SerializedConfig> for Config {
 impl From<Config> for SerializedConfig {
     fn from(config: Config) -> Self {
         Self {
+            customer_ids: config.customer_ids.to_vec(),
+            drone_service_times: config.drone_service_times.to_vec(),
+            priorities: config.priorities.to_vec(),
+            altitudes: config.altitudes.map(Arc::unwrap_or_clone),
+            arcs: Arc::unwrap_or_clone(config.arcs),
+            zones: Arc::unwrap_or_clone(config.zones),
+            locks: config.locks.to_vec(),
             customers_count: config.customers_count,
             trucks_count: config.trucks_count,
             drones_count: config.drones_count,
-            x: config.x,
-            y: config.y,
-            demands: config.demands,
-            dronable: config.dronable,
+            x: config.x.to_vec(),
+            y: config.y.to_vec(),
+            demands: config.demands.to_vec(),
+            dronable: config.dronable.to_vec(),
             truck_distance: config.truck_distance,
+            truck_distance_tags: config.truck_distance_tags.to_vec(),
             drone_distance: config.drone_distance,
             truck: config.truck,
             drone: config.drone,
@@ -492,161 +1322,829 @@ impl From<Config> for SerializedConfig {
             adaptive_segments: config.adaptive_segments,
             adaptive_fixed_segments: config.adaptive_fixed_segments,
             ejection_chain_iterations: config.ejection_chain_iterations,
+            refine_drone_schedule: config.refine_drone_schedule,
+            match_drone_sorties: config.match_drone_sorties,
             destroy_rate: config.destroy_rate,
             speed_type: config.speed_type,
             range_type: config.range_type,
             waiting_time_limit: config.waiting_time_limit,
+            budget: config.budget,
+            truck_fixed_cost: config.truck_fixed_cost,
+            drone_fixed_cost: config.drone_fixed_cost,
+            truck_cost_per_km: config.truck_cost_per_km,
+            drone_cost_per_kj: config.drone_cost_per_kj,
+            feasibility_tolerance: config.feasibility_tolerance,
+            fixed_point: config.fixed_point,
+            demand_scenarios: config.demand_scenarios.to_vec(),
+            risk_measure: config.risk_measure,
+            cvar_alpha: config.cvar_alpha,
+            scenario_seed: config.scenario_seed,
+            seed: config.seed,
+            init: config.init,
+            initial_solution: config.initial_solution,
+            decompose: config.decompose,
+            decompose_parallel: config.decompose_parallel,
+            target_makespan: config.target_makespan,
+            move_order: config.move_order,
+            neighborhoods: config.neighborhoods,
             strategy: config.strategy,
+            algorithm: config.algorithm,
+            initial_temperature: config.initial_temperature,
+            cooling_rate: config.cooling_rate,
+            kick_strength: config.kick_strength,
             fix_iteration: config.fix_iteration,
+            time_limit: config.time_limit,
+            target_cost: config.target_cost,
             reset_after_factor: config.reset_after_factor,
             max_elite_size: config.max_elite_size,
+            path_relinking: config.path_relinking,
+            focus_iterations: config.focus_iterations,
+            dont_look_bits: config.dont_look_bits,
+            dont_look_routes: config.dont_look_routes,
+            insertion_table: config.insertion_table,
+            two_opt_best_only: config.two_opt_best_only,
+            decisive_top_k: config.decisive_top_k,
+            decisive_epsilon: config.decisive_epsilon,
+            granularity: config.granularity,
+            selection_bias: config.selection_bias,
+            parallel_evaluation: config.parallel_evaluation,
+            threads: config.threads,
+            exchange_interval: config.exchange_interval,
+            auto_tune_light: config.auto_tune_light,
             penalty_exponent: config.penalty_exponent,
+            penalty_init: config.penalty_init,
+            penalty_retention: config.penalty_retention,
             single_truck_route: config.single_truck_route,
+            max_routes_per_truck: config.max_routes_per_truck,
+            max_customers_per_truck_route: config.max_customers_per_truck_route,
             single_drone_route: config.single_drone_route,
+            min_customers_per_drone_route: config.min_customers_per_drone_route,
+            max_customers_per_drone_route: config.max_customers_per_drone_route,
             verbose: config.verbose,
+            route_format: config.route_format,
             outputs: config.outputs,
             disable_logging: config.disable_logging,
+            log_max_size: config.log_max_size,
+            log_ring: config.log_ring,
+            trace_moves: config.trace_moves,
+            record_iteration_times: config.record_iteration_times,
+            snapshot_every: config.snapshot_every,
+            inject: config.inject,
+            inject_interval: config.inject_interval,
+            control: config.control,
+            route_pool: config.route_pool,
             dry_run: config.dry_run,
             extra: config.extra,
+            paranoid: config.paranoid,
+            verify_final: config.verify_final,
+            cache_dir: config.cache_dir,
         }
     }
 }
 
-pub static CONFIG: LazyLock<Config> = LazyLock::new(|| {
-    let arguments = cli::Arguments::parse();
-    eprintln!("Received {arguments:?}");
-    match arguments.command {
-        cli::Commands::Evaluate { config, .. } => {
-            let data = fs::read_to_string(config).unwrap();
-            let deserialized = serde_json::from_str::<SerializedConfig>(&data).unwrap();
-            Config::from(deserialized)
-        }
-        cli::Commands::Run {
-            problem,
-            truck_cfg,
-            drone_cfg,
-            config,
-            tabu_size_factor,
-            adaptive_iterations,
-            adaptive_fixed_iterations,
-            adaptive_segments,
-            adaptive_fixed_segments,
-            ejection_chain_iterations,
-            destroy_rate,
-            speed_type,
-            range_type,
-            truck_distance,
-            drone_distance,
+/// Options that determine the instance-derived part of [`Config`].
+struct _InstanceOptions<'a> {
+    problem: &'a str,
+    truck_cfg: &'a str,
+    drone_cfg: &'a str,
+    config: cli::EnergyModel,
+    speed_type: cli::ConfigType,
+    range_type: cli::ConfigType,
+    battery_derating: f64,
+    truck_distance: cli::DistanceType,
+    truck_distance_regions: Option<&'a str>,
+    drone_distance: cli::DistanceType,
+    trucks_count: Option<usize>,
+    drones_count: Option<usize>,
+    customers: Option<&'a str>,
+    region: Option<cli::Region>,
+    altitudes: Option<&'a str>,
+}
+
+/// Part of [`Config`] parsed and derived from the instance file, which is expensive to construct for large instances.
+#[derive(Deserialize, Serialize)]
+struct _InstanceData {
+    customers_count: usize,
+    customer_ids: Vec<usize>,
+    trucks_count: usize,
+    drones_count: usize,
+    x: Vec<f64>,
+    y: Vec<f64>,
+    demands: Vec<f64>,
+    dronable: Vec<bool>,
+    drone_service_times: Vec<Option<f64>>,
+    priorities: Vec<f64>,
+    demand_distributions: Vec<Option<_DemandDistribution>>,
+    truck_distance_tags: Vec<Option<cli::DistanceType>>,
+    truck_distances: DistanceMatrix,
+    drone_distances: DistanceMatrix,
+    altitudes: Option<DistanceMatrix>,
+}
+
+impl _InstanceData {
+    fn load(options: &_InstanceOptions, truck: &TruckConfig, drone: &DroneConfig) -> Self {
+        let data = fs::read_to_string(options.problem).unwrap();
+        let format = cli::InstanceFormat::detect(Path::new(options.problem), Some(&data));
+        Self::from_instance(Instance::parse(&data, format).unwrap(), options, truck, drone)
+    }
+
+    fn from_instance(instance: Instance, options: &_InstanceOptions, truck: &TruckConfig, drone: &DroneConfig) -> Self {
+        if instance
+            .customers
+            .iter()
+            .any(|c| c.service_time.is_some() || c.time_window.is_some())
+        {
+            eprintln!(
+                "Warning: service times and time windows of {} are ignored",
+                options.problem
+            );
+        }
+
+        let selected = options.customers.map(|path| {
+            fs::read_to_string(path)
+                .unwrap()
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|s| !s.is_empty())
+                .map(|s| {
+                    let id = s.parse::<usize>().unwrap();
+                    assert!(
+                        (1..instance.customers.len() + 1).contains(&id),
+                        "Customer {id} does not exist in {}",
+                        options.problem
+                    );
+                    id
+                })
+                .collect::<BTreeSet<usize>>()
+        });
+
+        let mut customer_ids = vec![0];
+        let mut x = vec![instance.depot.x];
+        let mut y = vec![instance.depot.y];
+        let mut demands = vec![0.0];
+        let mut dronable = vec![true];
+        let mut drone_service_times = vec![Some(0.0)];
+        let mut priorities = vec![1.0];
+        let mut demand_distributions = vec![None];
+        for (id, c) in (1..).zip(instance.customers) {
+            if selected.as_ref().is_some_and(|s| !s.contains(&id))
+                || options.region.is_some_and(|r| !r.contains(c.x, c.y))
+            {
+                continue;
+            }
+
+            customer_ids.push(id);
+            x.push(c.x);
+            y.push(c.y);
+            demands.push(c.demand);
+            dronable.push(c.dronable);
+            drone_service_times.push(c.drone_service_time);
+            priorities.push(c.priority.map_or(1.0, |priority| {
+                assert!(priority >= 0.0, "Negative priority {priority} of customer {id}");
+                priority
+            }));
+            demand_distributions.push(match (c.demand_scenarios, c.demand_std) {
+                (Some(scenarios), _) => {
+                    assert!(
+                        !scenarios.is_empty() && scenarios.iter().all(|&d| d >= 0.0),
+                        "Demand scenarios of customer {id} must be a non-empty list of non-negative values"
+                    );
+                    Some(_DemandDistribution::Discrete(scenarios))
+                }
+                (None, Some(std)) => {
+                    assert!(std >= 0.0, "Negative demand standard deviation {std} of customer {id}");
+                    Some(_DemandDistribution::Normal(std))
+                }
+                (None, None) => None,
+            });
+        }
+
+        let customers_count = customer_ids.len() - 1;
+        if options.customers.is_some() || options.region.is_some() {
+            eprintln!("Solving for {customers_count} customers of {}", options.problem);
+        }
+
+        let truck_distance_tags: Vec<_> = options
+            .truck_distance_regions
+            .map(|path| {
+                let regions = _load_distance_regions(path);
+                (0..x.len())
+                    .map(|i| {
+                        regions
+                            .iter()
+                            .find(|(r, _)| r.contains(x[i], y[i]))
+                            .map(|&(_, distance)| distance)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let truck_distances = options.truck_distance.tagged_matrix(&x, &y, &truck_distance_tags);
+        let drone_distances = options.drone_distance.matrix(&x, &y);
+        let altitudes = options
+            .altitudes
+            .map(|path| _load_altitudes(path, &customer_ids, drone));
+        let scale = |i: usize, j: usize| altitudes.as_ref().map_or(1.0, |a| a.get(i, j) / drone.altitude());
+
+        // Takeoff and landing durations without payload, the loaded ones on the way back are scaled by
+        // `vertical_factor`
+        let takeoff = drone.takeoff_time(0.0);
+        let takeoff_from_depot = drone.takeoff_power(0.0);
+
+        let landing = drone.landing_time(0.0);
+        let landing_from_depot = drone.landing_power(0.0);
+
+        let cruise_from_depot = drone.cruise_power(0.0);
+        for i in 1..customers_count + 1 {
+            let (outbound, inbound) = (scale(0, i), scale(i, 0));
+            dronable[i] = dronable[i]
+                && demands[i] <= drone.capacity()
+                && takeoff + drone.cruise_time(drone_distances.get(0, i) + drone_distances.get(i, 0)) + landing
+                    <= drone.fixed_time()
+                && (drone.landing_power(demands[i]) * drone.vertical_factor(demands[i]))
+                    .mul_add(inbound, landing_from_depot * outbound)
+                    .mul_add(
+                        landing,
+                        drone.cruise_power(demands[i]).mul_add(
+                            drone.cruise_time(drone_distances.get(i, 0)),
+                            (drone.takeoff_power(demands[i]) * drone.vertical_factor(demands[i]))
+                                .mul_add(inbound, takeoff_from_depot * outbound)
+                                .mul_add(
+                                    takeoff,
+                                    cruise_from_depot * drone.cruise_time(drone_distances.get(0, i)),
+                                ),
+                        ),
+                    )
+                    <= drone.battery();
+        }
+
+        let (trucks_count, drones_count) = _vehicle_counts(
+            options.problem,
+            &demands,
+            &dronable,
+            truck,
+            options.trucks_count.or(instance.trucks_count),
+            options.drones_count.or(instance.drones_count),
+        );
+
+        Self {
+            customers_count,
+            customer_ids,
             trucks_count,
             drones_count,
-            waiting_time_limit,
-            strategy,
-            fix_iteration,
-            reset_after_factor,
-            max_elite_size,
-            penalty_exponent,
-            single_truck_route,
-            single_drone_route,
-            verbose,
-            outputs,
-            disable_logging,
-            dry_run,
-            extra,
-        } => {
-            let trucks_count_regex = Regex::new(r"trucks_count (\d+)").unwrap();
-            let drones_count_regex = Regex::new(r"drones_count (\d+)").unwrap();
-            let depot_regex = Regex::new(r"depot (-?[\d\.]+)\s+(-?[\d\.]+)").unwrap();
-            let customers_regex = RegexBuilder::new(r"^\s*(-?[\d\.]+)\s+(-?[\d\.]+)\s+(0|1)\s+([\d\.]+)\s*$")
-                .multi_line(true)
-                .build()
-                .unwrap();
-
-            let data = fs::read_to_string(&problem).unwrap();
-
-            let trucks_count = trucks_count
-                .or_else(|| {
-                    trucks_count_regex
-                        .captures(&data)
-                        .and_then(|caps| caps.get(1))
-                        .and_then(|m| m.as_str().parse::<usize>().ok())
-                })
-                .expect("Missing trucks count");
-            let drones_count = drones_count
-                .or_else(|| {
-                    drones_count_regex
-                        .captures(&data)
-                        .and_then(|caps| caps.get(1))
-                        .and_then(|m| m.as_str().parse::<usize>().ok())
-                })
-                .expect("Missing drones count");
-
-            let depot = depot_regex
-                .captures(&data)
-                .and_then(|caps| {
-                    let x = caps.get(1)?.as_str().parse::<f64>().ok()?;
-                    let y = caps.get(2)?.as_str().parse::<f64>().ok()?;
-                    Some((x, y))
+            x,
+            y,
+            demands,
+            dronable,
+            drone_service_times,
+            priorities,
+            demand_distributions,
+            truck_distance_tags,
+            truck_distances,
+            drone_distances,
+            altitudes,
+        }
+    }
+
+    /// Key of the cache file: the instance-related options and the contents of the input files (so that editing any
+    /// of them invalidates the cache), stored in the file itself and compared on load.
+    fn _cache_key(options: &_InstanceOptions) -> Vec<u8> {
+        let mut key = format!(
+            "{} {DISTANCE_PRECISION} {} {} {} {} {} {} {:?} {:?} {:?}",
+            env!("CARGO_PKG_VERSION"),
+            options.config,
+            options.speed_type,
+            options.range_type,
+            options.battery_derating,
+            options.truck_distance,
+            options.drone_distance,
+            options.trucks_count,
+            options.drones_count,
+            options.region,
+        )
+        .into_bytes();
+
+        let files = [
+            Some(options.problem),
+            Some(options.truck_cfg),
+            Some(options.drone_cfg),
+            options.customers,
+            options.altitudes,
+            options.truck_distance_regions,
+        ];
+        for contents in files.map(|path| path.and_then(|path| fs::read(path).ok())) {
+            // Length-prefixed, so that the boundaries between the files are part of the key
+            match contents {
+                Some(contents) => {
+                    key.push(1);
+                    key.extend_from_slice(&(contents.len() as u64).to_le_bytes());
+                    key.extend_from_slice(&contents);
+                }
+                None => key.push(0),
+            }
+        }
+
+        key
+    }
+
+    /// Path of the cache file of `key`, named after its 64-bit FNV-1a hash: unlike the hashers of the standard library,
+    /// it does not change between toolchains, so that the cache survives an upgrade of the compiler.
+    fn _cache_path(cache_dir: &Path, problem: &str, key: &[u8]) -> PathBuf {
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        for &byte in key {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+
+        let stem = Path::new(problem)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        cache_dir.join(format!("{stem}-{hash:016x}.bin"))
+    }
+
+    fn load_cached(
+        cache_dir: &Option<String>,
+        options: &_InstanceOptions,
+        truck: &TruckConfig,
+        drone: &DroneConfig,
+    ) -> Self {
+        let Some(cache_dir) = cache_dir else {
+            return Self::load(options, truck, drone);
+        };
+
+        let key = Self::_cache_key(options);
+        let path = Self::_cache_path(Path::new(cache_dir), options.problem, &key);
+        if let Ok(file) = File::open(&path) {
+            match bincode::deserialize_from::<_, (Vec<u8>, Self)>(BufReader::new(file)) {
+                Ok((cached_key, data)) if cached_key == key => {
+                    eprintln!("Loaded cached instance data from {}", path.display());
+                    return data;
+                }
+                Ok(_) => eprintln!(
+                    "Ignoring cache file {} of other options or input files with the same hash",
+                    path.display()
+                ),
+                Err(e) => eprintln!("Ignoring invalid cache file {}: {e}", path.display()),
+            }
+        }
+
+        let data = Self::load(options, truck, drone);
+        let store = || -> bincode::Result<()> {
+            fs::create_dir_all(cache_dir)?;
+            bincode::serialize_into(BufWriter::new(File::create(&path)?), &(&key, &data))
+        };
+        match store() {
+            Ok(()) => eprintln!("Cached instance data to {}", path.display()),
+            Err(e) => eprintln!("Unable to cache instance data to {}: {e}", path.display()),
+        }
+
+        data
+    }
+}
+
+/// Numbers of trucks and drones, deriving those that are not given from the customers (`demands` and `dronable`
+/// include the depot at index 0) and printing how.
+///
+/// Without a number of trucks, there is one truck per truckload of the demand of the customers that cannot be served
+/// by drones. Without a number of drones, there are `2 * f` drones per truck, where `f` is the fraction of dronable
+/// customers (i.e. one drone per truck when half of the customers are dronable), and at most one per dronable customer.
+fn _vehicle_counts(
+    problem: &str,
+    demands: &[f64],
+    dronable: &[bool],
+    truck: &TruckConfig,
+    trucks_count: Option<usize>,
+    drones_count: Option<usize>,
+) -> (usize, usize) {
+    let customers_count = demands.len() - 1;
+    let dronable_count = dronable.iter().skip(1).filter(|&&d| d).count();
+
+    let trucks_count = trucks_count.unwrap_or_else(|| {
+        let demand = (1..demands.len())
+            .filter(|&c| !dronable[c])
+            .map(|c| demands[c])
+            .sum::<f64>();
+        let count = ((demand / truck.capacity).ceil() as usize).max(1);
+        eprintln!(
+            "{problem} does not specify the number of trucks, using {count} for a demand of {demand:.2} that only \
+             trucks can serve (capacity {:.2})",
+            truck.capacity
+        );
+        count
+    });
+    let drones_count = drones_count.unwrap_or_else(|| {
+        let fraction = dronable_count as f64 / customers_count.max(1) as f64;
+        let count = if dronable_count == 0 {
+            0
+        } else {
+            ((2.0 * fraction * trucks_count.max(1) as f64).round() as usize).clamp(1, dronable_count)
+        };
+        eprintln!(
+            "{problem} does not specify the number of drones, using {count} for {trucks_count} trucks and {:.1}% of \
+             dronable customers",
+            100.0 * fraction
+        );
+        count
+    });
+
+    (trucks_count, drones_count)
+}
+
+/// Distribution of the demand of a customer around its nominal demand.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+enum _DemandDistribution {
+    /// Normal distribution with the given standard deviation, truncated at 0
+    Normal(f64),
+    /// Equally likely demands
+    Discrete(Vec<f64>),
+}
+
+/// Sample `samples` demand scenarios, drawing the demand of each customer independently from its distribution (or
+/// keeping its nominal demand if it has none).
+fn _sample_demand_scenarios(
+    demands: &[f64],
+    distributions: &[Option<_DemandDistribution>],
+    samples: usize,
+    seed: Option<u64>,
+) -> Vec<Vec<f64>> {
+    let mut rng = seed.map_or_else(|| StdRng::from_rng(&mut rand::rng()), StdRng::seed_from_u64);
+    (0..samples)
+        .map(|_| {
+            demands
+                .iter()
+                .zip(distributions)
+                .map(|(&demand, distribution)| match distribution {
+                    Some(_DemandDistribution::Normal(std)) => {
+                        // Box-Muller transform
+                        let u = 1.0 - rng.random::<f64>();
+                        let v = rng.random::<f64>();
+                        let z = (-2.0 * u.ln()).sqrt() * (2.0 * consts::PI * v).cos();
+                        std.mul_add(z, demand).max(0.0)
+                    }
+                    Some(_DemandDistribution::Discrete(scenarios)) => scenarios[rng.random_range(0..scenarios.len())],
+                    None => demand,
                 })
-                .expect("Missing depot coordinates");
-
-            let mut customers_count = 0;
-            let mut x = vec![depot.0];
-            let mut y = vec![depot.1];
-            let mut demands = vec![0.0];
-            let mut dronable = vec![true];
-            for c in customers_regex.captures_iter(&data) {
-                customers_count += 1;
-
-                let (_, [_x, _y, _dronable, _demand]) = c.extract::<4>();
-                x.push(_x.parse::<f64>().unwrap());
-                y.push(_y.parse::<f64>().unwrap());
-                dronable.push(matches!(_dronable, "1"));
-                demands.push(_demand.parse::<f64>().unwrap());
+                .collect()
+        })
+        .collect()
+}
+
+/// Entry of a locks file, identifying the customer by its index in the instance file.
+#[derive(Deserialize)]
+struct _LockEntry {
+    customer: usize,
+    #[serde(flatten)]
+    lock: CustomerLock,
+}
+
+/// Read the assignment constraints of `path` (a JSON list of `{"customer", "vehicle", "index"}` objects) for the
+/// customers being solved for.
+fn _load_locks(
+    path: &str,
+    customer_ids: &[usize],
+    dronable: &[bool],
+    trucks_count: usize,
+    drones_count: usize,
+) -> Vec<Option<CustomerLock>> {
+    let entries = serde_json::from_str::<Vec<_LockEntry>>(&fs::read_to_string(path).unwrap()).unwrap();
+
+    let mut locks = vec![None; customer_ids.len()];
+    for entry in entries {
+        // Customers outside of the solved subset are irrelevant
+        let Some(customer) = customer_ids.iter().skip(1).position(|&id| id == entry.customer) else {
+            continue;
+        };
+        let customer = customer + 1;
+
+        let count = match entry.lock.vehicle {
+            VehicleKind::Truck => trucks_count,
+            VehicleKind::Drone => drones_count,
+        };
+        if count == 0 || entry.lock.index.is_some_and(|index| index >= count) {
+            panic!(
+                "Customer {} is locked to a non-existent {}",
+                entry.customer, entry.lock.vehicle
+            );
+        }
+        if entry.lock.vehicle == VehicleKind::Drone && !dronable[customer] {
+            panic!(
+                "Customer {} is locked to a drone but cannot be served by drones",
+                entry.customer
+            );
+        }
+
+        locks[customer] = Some(entry.lock);
+    }
+
+    locks
+}
+
+#[derive(Deserialize)]
+struct _ArcsFile {
+    #[serde(default)]
+    required: Vec<(usize, usize)>,
+    #[serde(default)]
+    forbidden: Vec<(usize, usize)>,
+}
+
+/// Read the arc constraints of `path` (a JSON object with `required` and `forbidden` lists of `[a, b]` pairs, meaning
+/// that customer `a` must or must not be immediately followed by customer `b`) for the customers being solved for.
+fn _load_arcs(path: &str, customer_ids: &[usize]) -> ArcConstraints {
+    let file = serde_json::from_str::<_ArcsFile>(&fs::read_to_string(path).unwrap()).unwrap();
+
+    // Map customers in the instance file to the customers being solved for
+    let map = |id: usize| customer_ids.iter().skip(1).position(|&c| c == id).map(|c| c + 1);
+
+    let size = customer_ids.len();
+    let mut arcs = ArcConstraints {
+        _next: vec![None; size],
+        _prev: vec![None; size],
+        _forbidden: vec![vec![]; size],
+    };
+
+    for (a, b) in file.required {
+        let (Some(ma), Some(mb)) = (map(a), map(b)) else {
+            if map(a).is_some() || map(b).is_some() {
+                panic!("Required arc ({a}, {b}) links a customer outside of the solved subset");
             }
 
-            let truck_distances = truck_distance.matrix(&x, &y);
-            let drone_distances = drone_distance.matrix(&x, &y);
+            continue;
+        };
 
-            let truck = serde_json::from_str::<TruckConfig>(&fs::read_to_string(truck_cfg).unwrap()).unwrap();
-            let drone = DroneConfig::new(&drone_cfg, config, speed_type, range_type);
+        if arcs._next[ma].is_some() || arcs._prev[mb].is_some() {
+            panic!("Required arc ({a}, {b}) conflicts with another required arc");
+        }
 
-            let takeoff = drone.takeoff_time();
-            let takeoff_from_depot = drone.takeoff_power(0.0);
+        let mut c = mb;
+        while let Some(next) = arcs._next[c] {
+            if next == ma {
+                panic!("Required arcs form a cycle through customer {a}");
+            }
 
-            let landing = drone.landing_time();
-            let landing_from_depot = drone.landing_power(0.0);
+            c = next;
+        }
 
-            let cruise_from_depot = drone.cruise_power(0.0);
-            for i in 1..customers_count + 1 {
-                dronable[i] = dronable[i]
-                    && demands[i] <= drone.capacity()
-                    && takeoff + drone.cruise_time(drone_distances[0][i] + drone_distances[i][0]) + landing
-                        <= drone.fixed_time()
-                    && (landing_from_depot + drone.landing_power(demands[i])).mul_add(
-                        landing,
-                        drone.cruise_power(demands[i]).mul_add(
-                            drone.cruise_time(drone_distances[i][0]),
-                            (takeoff_from_depot + drone.takeoff_power(demands[i]))
-                                .mul_add(takeoff, cruise_from_depot * drone.cruise_time(drone_distances[0][i])),
-                        ),
-                    ) <= drone.battery();
+        arcs._next[ma] = Some(mb);
+        arcs._prev[mb] = Some(ma);
+    }
+
+    for (a, b) in file.forbidden {
+        if let (Some(ma), Some(mb)) = (map(a), map(b)) {
+            if arcs._next[ma] == Some(mb) {
+                panic!("Arc ({a}, {b}) is both required and forbidden");
             }
 
-            Config {
-                customers_count,
-                trucks_count,
-                drones_count,
-                x,
-                y,
-                demands,
-                dronable,
-                truck_distance,
-                drone_distance,
-                truck_distances,
-                drone_distances,
-                truck,
-                drone,
+            arcs._forbidden[ma].push(mb);
+        }
+    }
+
+    arcs
+}
+
+#[derive(Deserialize)]
+struct _DistanceRegionEntry {
+    region: String,
+    distance: cli::DistanceType,
+}
+
+/// Read the distance regions of `path` (a JSON list of `{"region": "xmin,ymin,xmax,ymax", "distance": <type>}`
+/// objects), in order of precedence.
+fn _load_distance_regions(path: &str) -> Vec<(cli::Region, cli::DistanceType)> {
+    serde_json::from_str::<Vec<_DistanceRegionEntry>>(&fs::read_to_string(path).unwrap())
+        .unwrap()
+        .into_iter()
+        .map(|e| {
+            let region = e
+                .region
+                .parse::<cli::Region>()
+                .unwrap_or_else(|err| panic!("Invalid region {:?} in {path}: {err}", e.region));
+            (region, e.distance)
+        })
+        .collect()
+}
+
+/// Read the altitude profile of `path` (a JSON square matrix over the depot and all customers of the instance file,
+/// whose entry `[a][b]` is the cruise altitude of the drone arc from `a` to `b`) for the customers being solved for.
+fn _load_altitudes(path: &str, customer_ids: &[usize], drone: &DroneConfig) -> DistanceMatrix {
+    assert!(
+        drone.altitude() > 0.0,
+        "Altitude profiles require a drone config with a cruise altitude"
+    );
+
+    let matrix = serde_json::from_str::<Vec<Vec<f64>>>(&fs::read_to_string(path).unwrap()).unwrap();
+    let size = matrix.len();
+    if let Some(&id) = customer_ids.iter().find(|&&id| id >= size) {
+        panic!("Altitude profile {path} has no row for customer {id}");
+    }
+
+    let mut data = Vec::with_capacity(customer_ids.len() * customer_ids.len());
+    for &a in customer_ids {
+        assert_eq!(matrix[a].len(), size, "Altitude profile {path} is not a square matrix");
+        for &b in customer_ids {
+            let altitude = matrix[a][b];
+            assert!(altitude >= 0.0, "Negative altitude {altitude} of arc ({a}, {b})");
+
+            data.push(altitude as Distance);
+        }
+    }
+
+    DistanceMatrix::new(customer_ids.len(), data)
+}
+
+#[derive(Deserialize)]
+struct _ZonesFile {
+    zones: BTreeMap<String, Vec<usize>>,
+    #[serde(default)]
+    vehicles: Vec<_ZoneEntry>,
+}
+
+/// Zones a vehicle may enter, applying to all vehicles of the type unless `index` is given.
+#[derive(Deserialize)]
+struct _ZoneEntry {
+    vehicle: VehicleKind,
+    #[serde(default)]
+    index: Option<usize>,
+    allowed: Vec<String>,
+}
+
+/// Read the zones of `path` (a JSON object mapping zone names to the customers inside them, and a list of the zones
+/// each vehicle may enter) for the customers being solved for.
+fn _load_zones(path: &str, customer_ids: &[usize], trucks_count: usize, drones_count: usize) -> ZoneConstraints {
+    let file = serde_json::from_str::<_ZonesFile>(&fs::read_to_string(path).unwrap()).unwrap();
+    let names = file.zones.keys().collect::<Vec<_>>();
+
+    let mut zones = vec![None; customer_ids.len()];
+    for (zone, (name, customers)) in file.zones.iter().enumerate() {
+        for &id in customers {
+            // Customers outside of the solved subset are irrelevant
+            let Some(customer) = customer_ids.iter().skip(1).position(|&c| c == id) else {
+                continue;
+            };
+
+            if let Some(other) = zones[customer + 1].replace(zone) {
+                panic!("Customer {id} belongs to both zones {:?} and {name:?}", names[other]);
+            }
+        }
+    }
+
+    // Vehicles without an entry may enter every zone, and an entry for a specific vehicle overrides the one for its
+    // type
+    let mut trucks = vec![vec![true; names.len()]; trucks_count];
+    let mut drones = vec![vec![true; names.len()]; drones_count];
+    let mut entries = file.vehicles.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.index.is_some());
+    for entry in entries {
+        let allowed = names
+            .iter()
+            .map(|&name| entry.allowed.contains(name))
+            .collect::<Vec<_>>();
+        if let Some(unknown) = entry.allowed.iter().find(|name| !file.zones.contains_key(*name)) {
+            panic!("Unknown zone {unknown:?}");
+        }
+
+        let vehicles = match entry.vehicle {
+            VehicleKind::Truck => &mut trucks,
+            VehicleKind::Drone => &mut drones,
+        };
+        match entry.index {
+            Some(index) => match vehicles.get_mut(index) {
+                Some(vehicle) => *vehicle = allowed,
+                None => panic!("Zones are given for a non-existent {} {index}", entry.vehicle),
+            },
+            None => vehicles.fill(allowed),
+        }
+    }
+
+    ZoneConstraints {
+        _zones: zones,
+        _trucks: trucks,
+        _drones: drones,
+    }
+}
+
+/// Ensure that every customer can be served by at least one vehicle under the given constraints, listing all
+/// customers that cannot.
+fn _check_servable(
+    customer_ids: &[usize],
+    dronable: &[bool],
+    locks: &[Option<CustomerLock>],
+    zones: &ZoneConstraints,
+    trucks_count: usize,
+    drones_count: usize,
+) {
+    let mut unservable = vec![];
+    let mut drone_only = vec![];
+    for customer in 1..customer_ids.len() {
+        let lock = locks.get(customer).copied().flatten();
+        let allowed =
+            |kind, vehicle| lock.is_none_or(|l| l.allows(kind, vehicle)) && zones.allows(kind, vehicle, customer);
+
+        let by_truck = (0..trucks_count).any(|truck| allowed(VehicleKind::Truck, truck));
+        let by_drone = dronable[customer] && (0..drones_count).any(|drone| allowed(VehicleKind::Drone, drone));
+        if !by_truck && !by_drone {
+            unservable.push(customer_ids[customer]);
+            if drones_count == 0 && dronable[customer] {
+                drone_only.push(customer_ids[customer]);
+            }
+        }
+    }
+
+    if !unservable.is_empty() {
+        if drone_only.is_empty() {
+            panic!("No vehicle can serve customers {unservable:?}");
+        }
+
+        panic!(
+            "No vehicle can serve customers {unservable:?}, of which {drone_only:?} can only be served by drones but there \
+             are none"
+        );
+    }
+}
+
+/// Built-in rules deriving the tabu size factor, the reset after factor and the maximum elite set size from the
+/// instance, returned in this order.
+fn _auto_tune_light(
+    customers_count: usize,
+    dronable: &[bool],
+    trucks_count: usize,
+    drones_count: usize,
+) -> (f64, f64, usize) {
+    let dronable_count = dronable.iter().skip(1).filter(|&&d| d).count();
+    let dronable_fraction = if drones_count == 0 || customers_count == 0 {
+        0.0
+    } else {
+        dronable_count as f64 / customers_count as f64
+    };
+
+    // Same base as the search hyperparameters, counting drones only if they have customers to serve
+    let vehicles = trucks_count + if dronable_count > 0 { drones_count } else { 0 };
+    let base = customers_count as f64 / vehicles.max(1) as f64;
+
+    // Keep the tabu tenure around the square root of the number of customers
+    let tabu_size_factor = ((customers_count as f64).sqrt() / base.max(1.0)).clamp(0.25, 1.5);
+
+    // Assigning customers to drones opens up more plateaus, so wait longer before resetting
+    let reset_after_factor = 50.0f64.mul_add(dronable_fraction, 100.0);
+
+    // Larger instances benefit from more restarts
+    let max_elite_size = (customers_count / 10).clamp(3, 10);
+
+    (tabu_size_factor, reset_after_factor, max_elite_size)
+}
+
+thread_local! {
+    /// Config of the innermost [`scope`] entered on this thread.
+    static _CURRENT: RefCell<Option<Arc<Config>>> = const { RefCell::new(None) };
+}
+
+/// The config the search reads, i.e. that of the innermost [`scope`] entered on the current thread.
+///
+/// # Panics
+/// If the current thread is not inside a [`scope`].
+pub fn current() -> Arc<Config> {
+    _CURRENT.with_borrow(|config| Arc::clone(config.as_ref().expect("No config in scope, see `config::scope`")))
+}
+
+/// Call `f` with the [`current`] config borrowed rather than cloned, for the paths evaluating every candidate move.
+/// `f` must not enter a [`scope`] of its own.
+///
+/// # Panics
+/// If the current thread is not inside a [`scope`].
+pub fn with_current<R>(f: impl FnOnce(&Config) -> R) -> R {
+    _CURRENT.with_borrow(|config| f(config.as_ref().expect("No config in scope, see `config::scope`")))
+}
+
+/// Run `f` with `config` as the [`current`] config of this thread, restoring the previous one afterwards (even if `f`
+/// panics). Threads spawned by `f` do not inherit it and must enter a scope of their own, e.g. with a clone of the
+/// [`current`] one.
+pub fn scope<R>(config: Arc<Config>, f: impl FnOnce() -> R) -> R {
+    struct _Restore(Option<Arc<Config>>);
+
+    impl Drop for _Restore {
+        fn drop(&mut self) {
+            _CURRENT.set(self.0.take());
+        }
+    }
+
+    let _restore = _Restore(_CURRENT.replace(Some(config)));
+    f()
+}
+
+impl Config {
+    /// Build the config of `command` from the command line arguments: read the config saved by a previous run for
+    /// `evaluate`, or load the problem for `run`.
+    ///
+    /// # Panics
+    /// If `command` does not have a config.
+    pub fn from_command(command: cli::Commands) -> Self {
+        match command {
+            cli::Commands::Evaluate { config, .. } => {
+                let data = fs::read_to_string(config).unwrap();
+                Self::from_json(&data).unwrap()
+            }
+            cli::Commands::Run {
                 problem,
+                truck_cfg,
+                drone_cfg,
                 config,
                 tabu_size_factor,
                 adaptive_iterations,
@@ -654,23 +2152,327 @@ pub static CONFIG: LazyLock<Config> = LazyLock::new(|| {
                 adaptive_segments,
                 adaptive_fixed_segments,
                 ejection_chain_iterations,
+                refine_drone_schedule,
+                match_drone_sorties,
                 destroy_rate,
                 speed_type,
                 range_type,
+                truck_distance,
+                truck_distance_regions,
+                drone_distance,
+                trucks_count,
+                drones_count,
+                customers,
+                region,
+                locks,
+                arcs,
+                zones,
                 waiting_time_limit,
+                budget,
+                truck_fixed_cost,
+                drone_fixed_cost,
+                truck_cost_per_km,
+                drone_cost_per_kj,
+                feasibility_tolerance,
+                fixed_point,
+                demand_samples,
+                risk_measure,
+                cvar_alpha,
+                scenario_seed,
+                seed,
+                drone_service_time,
+                altitudes,
+                battery_derating,
+                init,
+                initial_solution,
+                decompose,
+                decompose_parallel,
+                target_makespan,
+                move_order,
+                neighborhoods,
                 strategy,
+                algorithm,
+                initial_temperature,
+                cooling_rate,
+                kick_strength,
                 fix_iteration,
+                time_limit,
+                target_cost,
                 reset_after_factor,
                 max_elite_size,
+                path_relinking,
+                focus_iterations,
+                dont_look_bits,
+                dont_look_routes,
+                insertion_table,
+                two_opt_best_only,
+                decisive_top_k,
+                decisive_epsilon,
+                granularity,
+                selection_bias,
+                parallel_evaluation,
+                threads,
+                exchange_interval,
+                auto_tune_light,
                 penalty_exponent,
+                penalty_init,
+                penalty_retention,
                 single_truck_route,
+                max_routes_per_truck,
+                max_customers_per_truck_route,
                 single_drone_route,
+                min_customers_per_drone_route,
+                max_customers_per_drone_route,
                 verbose,
+                route_format,
                 outputs,
                 disable_logging,
+                log_max_size,
+                log_ring,
+                trace_moves,
+                record_iteration_times,
+                snapshot_every,
+                inject,
+                inject_interval,
+                control,
+                route_pool,
                 dry_run,
                 extra,
+                paranoid,
+                verify_final,
+                cache_dir,
+            } => {
+                let truck = serde_json::from_str::<TruckConfig>(&fs::read_to_string(&truck_cfg).unwrap()).unwrap();
+                let drone = DroneConfig::new(&drone_cfg, config, speed_type, range_type, battery_derating);
+
+                let _InstanceData {
+                    customers_count,
+                    customer_ids,
+                    trucks_count,
+                    drones_count,
+                    x,
+                    y,
+                    demands,
+                    dronable,
+                    drone_service_times,
+                    priorities,
+                    demand_distributions,
+                    truck_distance_tags,
+                    truck_distances,
+                    drone_distances,
+                    altitudes,
+                } = _InstanceData::load_cached(
+                    &cache_dir,
+                    &_InstanceOptions {
+                        problem: &problem,
+                        truck_cfg: &truck_cfg,
+                        drone_cfg: &drone_cfg,
+                        config,
+                        speed_type,
+                        range_type,
+                        battery_derating,
+                        truck_distance,
+                        truck_distance_regions: truck_distance_regions.as_deref(),
+                        drone_distance,
+                        trucks_count,
+                        drones_count,
+                        customers: customers.as_deref(),
+                        region,
+                        altitudes: altitudes.as_deref(),
+                    },
+                    &truck,
+                    &drone,
+                );
+                let locks = locks
+                    .map(|path| _load_locks(&path, &customer_ids, &dronable, trucks_count, drones_count))
+                    .unwrap_or_default();
+                let arcs = arcs.map(|path| _load_arcs(&path, &customer_ids)).unwrap_or_default();
+                let zones = zones
+                    .map(|path| _load_zones(&path, &customer_ids, trucks_count, drones_count))
+                    .unwrap_or_default();
+                _check_servable(&customer_ids, &dronable, &locks, &zones, trucks_count, drones_count);
+                let (tabu_size_factor, reset_after_factor, max_elite_size) = if auto_tune_light {
+                    let tuned = _auto_tune_light(customers_count, &dronable, trucks_count, drones_count);
+                    eprintln!(
+                        "Auto-tuned tabu_size_factor = {:.3}, reset_after_factor = {:.1}, max_elite_size = {}",
+                        tuned.0, tuned.1, tuned.2
+                    );
+                    tuned
+                } else {
+                    (tabu_size_factor, reset_after_factor, max_elite_size)
+                };
+                let drone_service_times = drone_service_times
+                    .into_iter()
+                    .map(|t| t.unwrap_or(drone_service_time))
+                    .collect();
+                assert!(
+                    budget.is_none_or(|budget| budget > 0.0),
+                    "Budget must be positive, got {budget:?}"
+                );
+                assert!(
+                    initial_temperature > 0.0,
+                    "Initial temperature must be positive, got {initial_temperature}"
+                );
+                assert!(
+                    cooling_rate > 0.0 && cooling_rate <= 1.0,
+                    "Cooling rate must be in (0, 1], got {cooling_rate}"
+                );
+                assert!(
+                    (0.0..1.0).contains(&cvar_alpha),
+                    "CVaR confidence level must be in [0, 1), got {cvar_alpha}"
+                );
+                assert!(
+                    decisive_top_k > 0,
+                    "Number of decisive vehicles must be positive, got {decisive_top_k}"
+                );
+                assert!(
+                    decisive_epsilon >= 0.0,
+                    "Decisive epsilon must be non-negative, got {decisive_epsilon}"
+                );
+                if demand_samples > 0 && demand_distributions.iter().all(Option::is_none) {
+                    eprintln!("Warning: {problem} has no demand distributions, all demand scenarios are identical");
+                }
+                let demand_scenarios = _sample_demand_scenarios(
+                    &demands,
+                    &demand_distributions,
+                    demand_samples,
+                    scenario_seed.or_else(|| seed.map(|seed| seeding::substream_seed(seed, seeding::SCENARIOS))),
+                );
+                assert!(
+                    granularity.is_none_or(|k| k > 0),
+                    "Granularity must be positive, got {granularity:?}"
+                );
+                assert!(
+                    selection_bias.is_none_or(|bias| bias > 0.0 && bias <= 1.0),
+                    "Selection bias must be in (0, 1], got {selection_bias:?}"
+                );
+                assert!(threads > 0, "Number of threads must be positive, got {threads}");
+                assert!(
+                    exchange_interval > 0,
+                    "Exchange interval must be positive, got {exchange_interval}"
+                );
+                assert!(
+                    snapshot_every.is_none_or(|n| n > 0),
+                    "Snapshot interval must be positive, got {snapshot_every:?}"
+                );
+                let nearest_neighbors = granularity.map_or_else(Vec::new, |k| truck_distances.nearest_neighbors(k));
+
+                Self {
+                    customers_count,
+                    customer_ids: customer_ids.into(),
+                    locks: locks.into(),
+                    arcs: arcs.into(),
+                    zones: zones.into(),
+                    trucks_count,
+                    drones_count,
+                    x: x.into(),
+                    y: y.into(),
+                    demands: demands.into(),
+                    dronable: dronable.into(),
+                    drone_service_times,
+                    priorities: priorities.into(),
+                    altitudes: altitudes.map(Arc::new),
+                    truck_distance,
+                    truck_distance_tags: truck_distance_tags.into(),
+                    drone_distance,
+                    truck_distances: truck_distances.into(),
+                    drone_distances: drone_distances.into(),
+                    nearest_neighbors: nearest_neighbors.into(),
+                    truck,
+                    drone,
+                    problem,
+                    config,
+                    tabu_size_factor,
+                    adaptive_iterations,
+                    adaptive_fixed_iterations,
+                    adaptive_segments,
+                    adaptive_fixed_segments,
+                    ejection_chain_iterations,
+                    refine_drone_schedule,
+                    match_drone_sorties,
+                    destroy_rate,
+                    speed_type,
+                    range_type,
+                    waiting_time_limit,
+                    budget,
+                    truck_fixed_cost,
+                    drone_fixed_cost,
+                    truck_cost_per_km,
+                    drone_cost_per_kj,
+                    feasibility_tolerance,
+                    fixed_point,
+                    demand_scenarios: demand_scenarios.into(),
+                    risk_measure,
+                    cvar_alpha,
+                    scenario_seed,
+                    seed,
+                    init,
+                    initial_solution,
+                    decompose,
+                    decompose_parallel,
+                    target_makespan,
+                    move_order,
+                    neighborhoods,
+                    strategy,
+                    algorithm,
+                    initial_temperature,
+                    cooling_rate,
+                    kick_strength,
+                    fix_iteration,
+                    time_limit,
+                    target_cost,
+                    reset_after_factor,
+                    max_elite_size,
+                    path_relinking,
+                    focus_iterations,
+                    dont_look_bits,
+                    dont_look_routes,
+                    insertion_table,
+                    two_opt_best_only,
+                    decisive_top_k,
+                    decisive_epsilon,
+                    granularity,
+                    selection_bias,
+                    parallel_evaluation,
+                    threads,
+                    exchange_interval,
+                    auto_tune_light,
+                    penalty_exponent,
+                    penalty_init,
+                    penalty_retention,
+                    single_truck_route,
+                    max_routes_per_truck,
+                    max_customers_per_truck_route,
+                    single_drone_route,
+                    min_customers_per_drone_route,
+                    max_customers_per_drone_route,
+                    verbose,
+                    route_format,
+                    outputs,
+                    disable_logging,
+                    log_max_size,
+                    log_ring,
+                    trace_moves,
+                    record_iteration_times,
+                    snapshot_every,
+                    inject,
+                    inject_interval,
+                    control,
+                    route_pool,
+                    dry_run,
+                    extra,
+                    paranoid,
+                    verify_final,
+                    cache_dir,
+                }
+            }
+            cli::Commands::Bench { .. }
+            | cli::Commands::Convert { .. }
+            | cli::Commands::ProfileInstance { .. }
+            | cli::Commands::Cluster { .. }
+            | cli::Commands::Perturb { .. } => {
+                panic!("This command does not have a config")
             }
         }
     }
-});
+}
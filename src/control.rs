@@ -0,0 +1,105 @@
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Minimum time between two reads of the metadata of the control file.
+const _POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A command written to the `--control` file by an operator during a run.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControlCommand {
+    /// Stop the search gracefully: the post-optimization runs and the outputs are written as usual.
+    Stop,
+    /// Write the best solution found so far to the given path, or to `<outputs>/checkpoint-<iteration>.json`.
+    Checkpoint(Option<PathBuf>),
+    /// Print the progress of the search to stderr.
+    Report,
+    /// Replace `--time-limit` (in seconds since the start of the run), or lift it with `none`.
+    SetTimeLimit(Option<f64>),
+}
+
+impl FromStr for ControlCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = s.split_whitespace().collect::<Vec<_>>();
+        match words[..] {
+            ["stop"] => Ok(Self::Stop),
+            ["checkpoint"] => Ok(Self::Checkpoint(None)),
+            ["checkpoint", path] => Ok(Self::Checkpoint(Some(PathBuf::from(path)))),
+            ["report"] => Ok(Self::Report),
+            ["set", "time-limit", "none"] => Ok(Self::SetTimeLimit(None)),
+            ["set", "time-limit", limit] => match limit.parse::<f64>() {
+                Ok(limit) if limit >= 0.0 => Ok(Self::SetTimeLimit(Some(limit))),
+                _ => Err(format!("Invalid time limit {limit:?}")),
+            },
+            _ => Err(format!("Unknown command {s:?}")),
+        }
+    }
+}
+
+impl fmt::Display for ControlCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stop => write!(f, "stop"),
+            Self::Checkpoint(None) => write!(f, "checkpoint"),
+            Self::Checkpoint(Some(path)) => write!(f, "checkpoint {}", path.display()),
+            Self::Report => write!(f, "report"),
+            Self::SetTimeLimit(None) => write!(f, "set time-limit none"),
+            Self::SetTimeLimit(Some(limit)) => write!(f, "set time-limit {limit}"),
+        }
+    }
+}
+
+/// Watches the `--control` file for commands, one per line. The file is read again whenever it is modified, so each
+/// write issues its commands once; its content at the start of the run is ignored.
+pub struct ControlWatcher {
+    _path: PathBuf,
+    _modified: Option<SystemTime>,
+    _last_poll: Instant,
+}
+
+impl ControlWatcher {
+    pub fn new(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        Self {
+            _modified: fs::metadata(&path).and_then(|m| m.modified()).ok(),
+            _path: path,
+            _last_poll: Instant::now(),
+        }
+    }
+
+    /// The commands of the control file if it was modified since the last poll, checking at most once per second.
+    /// Lines that are not valid commands are reported and skipped, and empty lines are ignored.
+    pub fn poll(&mut self) -> Vec<ControlCommand> {
+        if self._last_poll.elapsed() < _POLL_INTERVAL {
+            return vec![];
+        }
+        self._last_poll = Instant::now();
+
+        let Ok(modified) = fs::metadata(&self._path).and_then(|m| m.modified()) else {
+            return vec![];
+        };
+        if self._modified == Some(modified) {
+            return vec![];
+        }
+        self._modified = Some(modified);
+
+        let Ok(content) = fs::read_to_string(&self._path) else {
+            return vec![];
+        };
+
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| {
+                line.parse::<ControlCommand>()
+                    .map_err(|e| eprintln!("Ignoring control command in {}: {e}", self._path.display()))
+                    .ok()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,87 @@
+use std::backtrace::Backtrace;
+use std::error::Error;
+use std::fs;
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::Serialize;
+
+use crate::neighborhoods::Neighborhood;
+use crate::solutions::Solution;
+
+/// State of the search at the time of a panic.
+struct _Context {
+    best: Option<Arc<Solution>>,
+    iteration: usize,
+    neighborhood: Option<Neighborhood>,
+}
+
+static _CONTEXT: Mutex<_Context> = Mutex::new(_Context {
+    best: None,
+    iteration: 0,
+    neighborhood: None,
+});
+
+#[derive(Serialize)]
+struct _CrashReport {
+    message: String,
+    location: Option<String>,
+    iteration: usize,
+    neighborhood: Option<String>,
+    best_working_time: Option<f64>,
+    backtrace: String,
+}
+
+/// Record the best solution found so far, to be salvaged on panic.
+pub fn record_best(best: &Arc<Solution>) {
+    _CONTEXT.lock().unwrap_or_else(PoisonError::into_inner).best = Some(best.clone());
+}
+
+/// Record the iteration being performed, to be reported on panic.
+pub fn record_iteration(iteration: usize, neighborhood: Neighborhood) {
+    let mut context = _CONTEXT.lock().unwrap_or_else(PoisonError::into_inner);
+    context.iteration = iteration;
+    context.neighborhood = Some(neighborhood);
+}
+
+fn _salvage(info: &PanicHookInfo, report_path: &PathBuf, solution_path: &PathBuf) -> Result<(), Box<dyn Error>> {
+    let context = _CONTEXT.lock().unwrap_or_else(PoisonError::into_inner);
+
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| (*s).to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    let report = _CrashReport {
+        message,
+        location: info.location().map(ToString::to_string),
+        iteration: context.iteration,
+        neighborhood: context.neighborhood.map(|n| n.to_string()),
+        best_working_time: context.best.as_ref().map(|s| s.working_time),
+        backtrace: Backtrace::force_capture().to_string(),
+    };
+    fs::write(report_path, serde_json::to_string_pretty(&report)?)?;
+    eprintln!("Saved crash report to {}", report_path.display());
+
+    if let Some(ref best) = context.best {
+        fs::write(solution_path, serde_json::to_string(best.as_ref())?)?;
+        eprintln!("Saved the best solution found to {}", solution_path.display());
+    }
+
+    Ok(())
+}
+
+/// Install a panic hook writing a crash report to `report_path` and the best solution recorded with
+/// [`record_best`] to `solution_path`, before the default hook runs.
+pub fn install(report_path: PathBuf, solution_path: PathBuf) {
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Err(e) = _salvage(info, &report_path, &solution_path) {
+            eprintln!("Failed to salvage the search state: {e}");
+        }
+
+        default(info);
+    }));
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Arc;
+use std::{env, fs, thread};
+
+use serde::Deserialize;
+
+use crate::config;
+use crate::errors::ExpectedValue;
+use crate::logger::FileLogger;
+use crate::routes::{DroneRoute, Route, TruckRoute};
+use crate::solutions::Solution;
+use crate::{clusterize, seeding};
+
+/// Options set by [`decompose`] for each run, not to be forwarded from the command line (the boolean is whether the
+/// option takes a value).
+const _OVERRIDDEN: [(&str, bool); 14] = [
+    ("--decompose", true),
+    ("--decompose-parallel", false),
+    ("--customers", true),
+    ("--region", true),
+    ("--trucks-count", true),
+    ("--drones-count", true),
+    ("--outputs", true),
+    ("--disable-logging", false),
+    ("--inject", true),
+    ("--route-pool", true),
+    ("--seed", true),
+    ("--trace-moves", false),
+    ("--verbose", false),
+    ("-v", false),
+];
+
+/// The routes of a solution, as stored in the output solution JSON of a run.
+#[derive(Deserialize)]
+struct _SubSolution {
+    truck_routes: Vec<Vec<Vec<usize>>>,
+    drone_routes: Vec<Vec<Vec<usize>>>,
+}
+
+/// Command line arguments of this run (after the subcommand), without those overridden for the runs of the clusters.
+fn _forwarded_args() -> Vec<String> {
+    let mut result = vec![];
+    let mut args = env::args().skip(2);
+    while let Some(arg) = args.next() {
+        let name = arg.split('=').next().unwrap_or_default();
+        match _OVERRIDDEN.iter().find(|(option, _)| *option == name) {
+            Some(&(_, true)) if !arg.contains('=') => {
+                args.next();
+            }
+            Some(_) => {}
+            None => result.push(arg),
+        }
+    }
+
+    result
+}
+
+/// Solve a cluster with the given numbers of vehicles in a separate run, returning its routes with the customers
+/// mapped back to indices of this run.
+fn _solve(
+    cluster: &[usize],
+    trucks_count: usize,
+    drones_count: usize,
+    directory: &Path,
+    name: &str,
+    seed: Option<u64>,
+    args: &[String],
+) -> Result<_SubSolution, Box<dyn Error>> {
+    let config = config::current();
+    // The run indexes the customers it solves for in the order of the instance file
+    let mut ids = cluster.iter().map(|&c| config.customer_ids[c]).collect::<Vec<_>>();
+    ids.sort_unstable();
+    let customers_path = directory.join(format!("{name}.txt"));
+    fs::write(
+        &customers_path,
+        ids.iter().map(ToString::to_string).collect::<Vec<_>>().join(" "),
+    )?;
+
+    let mut command = Command::new(env::current_exe()?);
+    command.arg("run").args(args);
+    if let Some(seed) = seed {
+        command.arg("--seed").arg(seed.to_string());
+    }
+
+    let output = command
+        .arg("--customers")
+        .arg(&customers_path)
+        .arg("--trucks-count")
+        .arg(trucks_count.to_string())
+        .arg("--drones-count")
+        .arg(drones_count.to_string())
+        .arg("--outputs")
+        .arg(directory)
+        .arg("--disable-logging")
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Run on {name} exited with {}:\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let path = ExpectedValue::cast(stdout.lines().find(|l| l.ends_with("-solution.json")).map(String::from))?;
+    let mut solution = serde_json::from_str::<_SubSolution>(&fs::read_to_string(path)?)?;
+
+    let mapping = (1..).zip(&ids).collect::<HashMap<_, _>>();
+    let index = config
+        .customer_ids
+        .iter()
+        .enumerate()
+        .map(|(i, &id)| (id, i))
+        .collect::<HashMap<_, _>>();
+    for route in solution
+        .truck_routes
+        .iter_mut()
+        .chain(solution.drone_routes.iter_mut())
+        .flatten()
+    {
+        for customer in route.iter_mut() {
+            if *customer != 0 {
+                *customer = index[mapping[customer]];
+            }
+        }
+    }
+
+    Ok(solution)
+}
+
+/// Build a solution by clustering the customers into `k` clusters, solving each of them with its share of the
+/// vehicles in a separate run of this executable and merging the results. Truck (and drone) `v` is given to cluster
+/// `v % k`.
+pub fn decompose(k: usize, logger: &FileLogger) -> Result<Solution, Box<dyn Error>> {
+    let config = config::current();
+    let vehicles_count = if config.trucks_count > 0 {
+        config.trucks_count
+    } else {
+        config.drones_count
+    };
+    if k == 0 || k > vehicles_count {
+        return Err(format!(
+            "--decompose must be between 1 and {vehicles_count} (the number of trucks, or of drones without trucks)"
+        )
+        .into());
+    }
+    if !config.locks.is_empty() || !config.arcs.is_empty() || !config.zones.is_empty() {
+        return Err("--decompose does not support --locks, --arcs and --zones".into());
+    }
+
+    let directory = logger.output_path("-decomposition");
+    fs::create_dir_all(&directory)?;
+
+    let mut index = Vec::from_iter(1..config.customers_count + 1);
+    let clusters = clusterize::clusterize(&mut index, k);
+    let share = |count: usize, cluster: usize| count / k + usize::from(cluster < count % k);
+    let args = _forwarded_args();
+    let solve = |cluster: usize| {
+        _solve(
+            &clusters[cluster],
+            share(config.trucks_count, cluster),
+            share(config.drones_count, cluster),
+            &directory,
+            &format!("cluster-{cluster}"),
+            config
+                .seed
+                .map(|seed| seeding::substream_seed(seed, seeding::CLUSTERS + cluster as u64)),
+            &args,
+        )
+    };
+
+    let solved = if config.decompose_parallel {
+        // The runs are waited for on threads of their own, which need the config to map the customers back
+        thread::scope(|scope| {
+            #[allow(clippy::needless_collect)] // Spawn all runs before waiting for any of them
+            let handles = (0..k)
+                .filter(|&cluster| !clusters[cluster].is_empty())
+                .map(|cluster| {
+                    let config = Arc::clone(&config);
+                    let run = move || config::scope(config, || solve(cluster)).map_err(|e| e.to_string());
+                    (cluster, scope.spawn(run))
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|(cluster, handle)| Ok((cluster, handle.join().unwrap()?)))
+                .collect::<Result<Vec<_>, Box<dyn Error>>>()
+        })?
+    } else {
+        (0..k)
+            .filter(|&cluster| !clusters[cluster].is_empty())
+            .map(|cluster| Ok((cluster, solve(cluster)?)))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?
+    };
+
+    let mut truck_routes = vec![vec![]; config.trucks_count];
+    let mut drone_routes = vec![vec![]; config.drones_count];
+    for (cluster, solution) in solved {
+        for (i, routes) in solution.truck_routes.into_iter().enumerate() {
+            truck_routes[i * k + cluster] = routes.into_iter().map(TruckRoute::new).collect();
+        }
+        for (i, routes) in solution.drone_routes.into_iter().enumerate() {
+            drone_routes[i * k + cluster] = routes.into_iter().map(DroneRoute::new).collect();
+        }
+    }
+
+    let solution = Solution::new(truck_routes, drone_routes);
+    eprintln!(
+        "Merged the solutions of {k} clusters, working time = {:.2}",
+        solution.working_time
+    );
+    Ok(solution)
+}
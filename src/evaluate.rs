@@ -0,0 +1,241 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::config::{self, Config, VehicleKind};
+use crate::numeric::CompensatedSum;
+use crate::routes::{DroneRoute, Route, RouteValues, TruckRoute};
+use crate::solutions::Solution;
+
+/// Working times and violations of a solution, with the violations normalized as in [`Solution::cost`].
+#[derive(Clone, Debug, Serialize)]
+pub struct EvaluationReport {
+    pub truck_working_time: Vec<f64>,
+    pub drone_working_time: Vec<f64>,
+
+    pub working_time: f64,
+    pub energy_violation: f64,
+    pub capacity_violation: f64,
+    pub waiting_time_violation: f64,
+    pub fixed_time_violation: f64,
+    /// Excess of [`Self::vehicle_cost`] over `--budget`, relative to the budget.
+    pub budget_violation: f64,
+
+    /// Fixed cost of the vehicles serving at least one route plus the variable cost of all routes.
+    pub vehicle_cost: f64,
+
+    pub feasible: bool,
+}
+
+/// Sum of the values of the routes of a vehicle, and whether it serves any route (i.e. incurs its fixed cost).
+pub fn vehicle_total(values: impl IntoIterator<Item = RouteValues>) -> (RouteValues, bool) {
+    // Compensated sums reduce the rounding error of the totals over many routes
+    let mut total = [CompensatedSum::default(); 6];
+    let mut used = false;
+    for values in values {
+        total[0] += values.working_time;
+        total[1] += values.capacity_violation;
+        total[2] += values.waiting_time_violation;
+        total[3] += values.energy_violation;
+        total[4] += values.fixed_time_violation;
+        total[5] += values.cost;
+        used = true;
+    }
+
+    let total = RouteValues {
+        working_time: total[0].value(),
+        capacity_violation: total[1].value(),
+        waiting_time_violation: total[2].value(),
+        energy_violation: total[3].value(),
+        fixed_time_violation: total[4].value(),
+        cost: total[5].value(),
+    };
+    (total, used)
+}
+
+impl EvaluationReport {
+    /// Aggregate the values of the routes of each truck and each drone, given along with the class of that vehicle
+    /// in `config` they were evaluated against.
+    pub fn aggregate<T, D>(
+        config: &Config,
+        trucks: impl IntoIterator<Item = (usize, T)>,
+        drones: impl IntoIterator<Item = (usize, D)>,
+    ) -> Self
+    where
+        T: IntoIterator<Item = RouteValues>,
+        D: IntoIterator<Item = RouteValues>,
+    {
+        Self::from_totals(
+            config,
+            trucks.into_iter().map(|(class, values)| (class, vehicle_total(values))),
+            drones.into_iter().map(|(class, values)| (class, vehicle_total(values))),
+        )
+    }
+
+    /// Aggregate the totals of each truck and each drone (see [`vehicle_total`]), given along with the class of that
+    /// vehicle in `config`.
+    pub fn from_totals(
+        config: &Config,
+        trucks: impl IntoIterator<Item = (usize, (RouteValues, bool))>,
+        drones: impl IntoIterator<Item = (usize, (RouteValues, bool))>,
+    ) -> Self {
+        let mut energy_violation = CompensatedSum::default();
+        let mut capacity_violation = CompensatedSum::default();
+        let mut waiting_time_violation = CompensatedSum::default();
+        let mut fixed_time_violation = CompensatedSum::default();
+        let mut vehicle_cost = CompensatedSum::default();
+
+        let mut truck_working_time = vec![];
+        for (class, (total, used)) in trucks {
+            if used {
+                vehicle_cost += config.truck_fixed_cost;
+            }
+
+            truck_working_time.push(total.working_time);
+            capacity_violation += total.capacity_violation / config.truck_classes()[class].capacity;
+            waiting_time_violation += total.waiting_time_violation;
+            vehicle_cost += total.cost;
+        }
+
+        let mut drone_working_time = vec![];
+        for (class, (total, used)) in drones {
+            if used {
+                vehicle_cost += config.drone_fixed_cost;
+            }
+
+            drone_working_time.push(total.working_time);
+            energy_violation += total.energy_violation;
+            capacity_violation += total.capacity_violation / config.drone_classes()[class].capacity();
+            waiting_time_violation += total.waiting_time_violation;
+            fixed_time_violation += total.fixed_time_violation;
+            vehicle_cost += total.cost;
+        }
+
+        let working_time = truck_working_time
+            .iter()
+            .chain(drone_working_time.iter())
+            .copied()
+            .fold(0.0, f64::max);
+
+        let energy_violation = energy_violation.value() / config.drone.battery();
+        let capacity_violation = capacity_violation.value();
+        let waiting_time_violation = waiting_time_violation.value() / config.waiting_time_limit;
+        let fixed_time_violation = fixed_time_violation.value() / config.drone.fixed_time();
+        let vehicle_cost = vehicle_cost.value();
+        let budget_violation = config
+            .budget
+            .map_or(0.0, |budget| (vehicle_cost - budget).max(0.0) / budget);
+
+        let violated = |violation: f64| violation > config.feasibility_tolerance;
+        Self {
+            truck_working_time,
+            drone_working_time,
+            working_time,
+            energy_violation,
+            capacity_violation,
+            waiting_time_violation,
+            fixed_time_violation,
+            budget_violation,
+            vehicle_cost,
+            feasible: !violated(energy_violation)
+                && !violated(capacity_violation)
+                && !violated(waiting_time_violation)
+                && !violated(fixed_time_violation)
+                && !violated(budget_violation),
+        }
+    }
+}
+
+/// Totals of the routes of each vehicle of a solution (see [`vehicle_total`]), against the config in scope. A
+/// neighbor changing the routes of a few vehicles is evaluated from them by summing the routes of these vehicles only,
+/// see [`Self::report`].
+pub struct VehicleTotals {
+    _trucks: Vec<(RouteValues, bool)>,
+    _drones: Vec<(RouteValues, bool)>,
+}
+
+impl VehicleTotals {
+    pub fn new(solution: &Solution) -> Self {
+        let config = config::current();
+        Self {
+            _trucks: solution
+                .truck_routes
+                .iter()
+                .enumerate()
+                .map(|(truck, routes)| Self::_total(routes, config.truck_class(truck)))
+                .collect(),
+            _drones: solution
+                .drone_routes
+                .iter()
+                .enumerate()
+                .map(|(drone, routes)| Self::_total(routes, config.drone_class(drone)))
+                .collect(),
+        }
+    }
+
+    fn _total<R>(routes: &[Arc<R>], class: usize) -> (RouteValues, bool)
+    where
+        R: Route,
+    {
+        vehicle_total(routes.iter().map(|r| *r.values(class)))
+    }
+
+    /// Report of the solution with the routes `truck_routes` and `drone_routes`, which differ from those of the
+    /// solution of these totals in the vehicles of `changed` only. This is the report [`Solution::new`] would compute,
+    /// to the last bit, without summing the routes of the other vehicles.
+    pub fn report(
+        &self,
+        truck_routes: &[Vec<Arc<TruckRoute>>],
+        drone_routes: &[Vec<Arc<DroneRoute>>],
+        changed: &[(VehicleKind, usize)],
+    ) -> EvaluationReport {
+        config::with_current(|config| {
+            EvaluationReport::from_totals(
+                config,
+                self._trucks.iter().enumerate().map(|(truck, &total)| {
+                    let class = config.truck_class(truck);
+                    if changed.contains(&(VehicleKind::Truck, truck)) {
+                        (class, Self::_total(&truck_routes[truck], class))
+                    } else {
+                        (class, total)
+                    }
+                }),
+                self._drones.iter().enumerate().map(|(drone, &total)| {
+                    let class = config.drone_class(drone);
+                    if changed.contains(&(VehicleKind::Drone, drone)) {
+                        (class, Self::_total(&drone_routes[drone], class))
+                    } else {
+                        (class, total)
+                    }
+                }),
+            )
+        })
+    }
+}
+
+/// Evaluate `solution` against `config`, which may differ from the config its routes were built with (e.g. to
+/// assess a solution under other vehicle parameters). The routes are re-evaluated from their customers, without
+/// reading the [`current`](crate::config::current) config or touching the route caches.
+pub fn evaluate(solution: &Solution, config: &Config) -> EvaluationReport {
+    EvaluationReport::aggregate(
+        config,
+        solution.truck_routes.iter().enumerate().map(|(truck, routes)| {
+            let class = config.truck_class(truck);
+            (
+                class,
+                routes
+                    .iter()
+                    .map(move |r| TruckRoute::evaluate(config, &r.data().customers, class)),
+            )
+        }),
+        solution.drone_routes.iter().enumerate().map(|(drone, routes)| {
+            let class = config.drone_class(drone);
+            (
+                class,
+                routes
+                    .iter()
+                    .map(move |r| DroneRoute::evaluate(config, &r.data().customers, class)),
+            )
+        }),
+    )
+}
@@ -0,0 +1,101 @@
+use std::error::Error;
+use std::fs;
+use std::sync::Arc;
+
+use crate::config::{self, Config};
+use crate::logger::FileLogger;
+use crate::solutions::{self, Solution};
+
+/// Run the search on the first `trucks` trucks and `drones` drones of the fleet, writing its outputs to `outputs`.
+/// Returns the solution with empty routes for the vehicles left out, and whether it reaches `target`.
+///
+/// The search runs with the config in scope with the fleet counts changed, which shares its instance data.
+fn _probe(trucks: usize, drones: usize, target: f64, outputs: &str) -> Result<(Solution, bool), Box<dyn Error>> {
+    let config = config::current();
+    let probe = Config {
+        trucks_count: trucks,
+        drones_count: drones,
+        target_makespan: None,
+        outputs: outputs.to_string(),
+        ..Config::clone(&config)
+    };
+
+    let solution = config::scope(Arc::new(probe), || {
+        solutions::reset_penalties();
+        FileLogger::new().map(|mut logger| Solution::tabu_search(Solution::initialize(), &mut logger))
+    })?;
+    let reached = solution.feasible && solution.working_time <= target;
+    eprintln!(
+        "{trucks} trucks and {drones} drones: working time = {:.2} ({})",
+        solution.working_time,
+        if reached { "reached" } else { "missed" }
+    );
+
+    let mut truck_routes = solution.truck_routes;
+    let mut drone_routes = solution.drone_routes;
+    truck_routes.resize(config.trucks_count, vec![]);
+    drone_routes.resize(config.drones_count, vec![]);
+    Ok((Solution::new(truck_routes, drone_routes), reached))
+}
+
+/// Smallest `count` in `lo..=hi` for which `probe(count)` succeeds, assuming that larger counts never fail where
+/// smaller ones succeed, along with the solution found with it. `probe(hi)` must have succeeded with `best`.
+fn _bisect(
+    mut lo: usize,
+    mut hi: usize,
+    mut best: Solution,
+    probe: impl Fn(usize) -> Result<(Solution, bool), Box<dyn Error>>,
+) -> Result<(usize, Solution), Box<dyn Error>> {
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let (solution, reached) = probe(mid)?;
+        if reached {
+            hi = mid;
+            best = solution;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    Ok((hi, best))
+}
+
+/// Find the smallest fleet with which the search reaches a feasible solution of makespan at most `target`: first the
+/// number of trucks (keeping all drones), then the number of drones. Each fleet size tried is solved by a full search
+/// on the first vehicles of the fleet, with its outputs in a directory of its own. Returns the best solution found with
+/// the smallest fleet, or with the whole fleet if even that does not reach the target.
+pub fn minimize_fleet(target: f64, logger: &FileLogger) -> Result<Solution, Box<dyn Error>> {
+    let config = config::current();
+    if config.decompose.is_some() {
+        return Err("--target-makespan does not support --decompose".into());
+    }
+    if !config.locks.is_empty() || !config.zones.is_empty() {
+        return Err("--target-makespan does not support --locks and --zones".into());
+    }
+
+    let directory = logger.output_path("-fleet");
+    fs::create_dir_all(&directory)?;
+    let outputs = directory.to_string_lossy().into_owned();
+
+    let (trucks, drones) = (config.trucks_count, config.drones_count);
+    let (solution, reached) = _probe(trucks, drones, target, &outputs)?;
+    if !reached {
+        eprintln!("The whole fleet does not reach the target makespan {target:.2}");
+        return Ok(solution);
+    }
+
+    // At least one truck is needed if drones cannot serve all customers, and at least one drone without trucks
+    let needs_truck = drones == 0 || !config.dronable[1..].iter().all(|&d| d);
+    let (trucks, solution) = _bisect(usize::from(needs_truck), trucks, solution, |t| {
+        _probe(t, drones, target, &outputs)
+    })?;
+    let (drones, solution) = _bisect(usize::from(trucks == 0), drones, solution, |d| {
+        _probe(trucks, d, target, &outputs)
+    })?;
+
+    eprintln!(
+        "{trucks} trucks and {drones} drones reach the target makespan {target:.2}, with working time = {:.2}",
+        solution.working_time
+    );
+    Ok(solution)
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::Deserialize;
+
+use crate::config;
+use crate::routes::{DroneRoute, Route, TruckRoute};
+use crate::solutions::Solution;
+
+/// The routes of a candidate solution, as stored in the output solution JSON. Other fields are recomputed.
+#[derive(Deserialize)]
+struct _Candidate {
+    truck_routes: Vec<Vec<Vec<usize>>>,
+    drone_routes: Vec<Vec<Vec<usize>>>,
+}
+
+fn _check_structure<R>(vehicle_routes: &[Vec<Vec<usize>>], expected: usize, kind: &str) -> Result<(), String>
+where
+    R: Route,
+{
+    if vehicle_routes.len() != expected {
+        return Err(format!("Expected {expected} {kind}s, got {}", vehicle_routes.len()));
+    }
+
+    for route in vehicle_routes.iter().flatten() {
+        if route.len() < 3 || route.first() != Some(&0) || route.last() != Some(&0) {
+            return Err(format!("Invalid route {route:?}"));
+        }
+
+        if let Some(c) = route.iter().find(|&&c| c > config::current().customers_count) {
+            return Err(format!("Unknown customer {c} in route {route:?}"));
+        }
+
+        if let Some(c) = route.iter().find(|&&c| c != 0 && !R::_servable(c)) {
+            return Err(format!("Customer {c} cannot be served by a {kind} in route {route:?}"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Load a solution from the JSON file at `path` (in the format of the output solution JSON), checking that it serves
+/// the loaded instance with its fleet. Its routes are evaluated again under the config in scope.
+pub fn load(path: &Path) -> Result<Solution, Box<dyn Error>> {
+    let config = config::current();
+    let candidate = serde_json::from_str::<_Candidate>(&fs::read_to_string(path)?)?;
+
+    // Route construction asserts its structure, so check it beforehand.
+    _check_structure::<TruckRoute>(&candidate.truck_routes, config.trucks_count, "truck")?;
+    _check_structure::<DroneRoute>(&candidate.drone_routes, config.drones_count, "drone")?;
+
+    let truck_routes = candidate
+        .truck_routes
+        .into_iter()
+        .map(|routes| routes.into_iter().map(TruckRoute::new).collect())
+        .collect();
+    let drone_routes = candidate
+        .drone_routes
+        .into_iter()
+        .map(|routes| routes.into_iter().map(DroneRoute::new).collect())
+        .collect();
+
+    let solution = Solution::new(truck_routes, drone_routes);
+    solution.validate()?;
+    Ok(solution)
+}
+
+/// Watches a directory for candidate solutions computed by another process during a run.
+pub struct InjectWatcher {
+    _directory: PathBuf,
+    _seen: HashMap<PathBuf, SystemTime>,
+}
+
+impl InjectWatcher {
+    pub fn new(directory: &str) -> Self {
+        assert!(
+            config::current().inject_interval > 0,
+            "--inject-interval must be positive"
+        );
+        Self {
+            _directory: PathBuf::from(directory),
+            _seen: HashMap::new(),
+        }
+    }
+
+    /// Load the JSON files added to or modified in the directory since the last poll. Candidates that cannot be
+    /// read or do not form a valid solution are reported and skipped.
+    pub fn poll(&mut self) -> Vec<Solution> {
+        let Ok(entries) = fs::read_dir(&self._directory) else {
+            return vec![];
+        };
+
+        let mut result = vec![];
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            if path.extension().is_none_or(|e| e != "json") {
+                continue;
+            }
+
+            let Ok(modified) = entry.metadata().and_then(|m| m.modified()) else {
+                continue;
+            };
+            if self._seen.get(&path) == Some(&modified) {
+                continue;
+            }
+
+            self._seen.insert(path.clone(), modified);
+            match load(&path) {
+                Ok(solution) => result.push(solution),
+                Err(e) => eprintln!("Ignoring injected solution {}: {e}", path.display()),
+            }
+        }
+
+        result
+    }
+}
@@ -0,0 +1,430 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::{DronableRule, InstanceFormat};
+
+/// Due date written to Solomon files for customers without a time window, large enough to never be binding.
+const SOLOMON_HORIZON: f64 = 1e9;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Depot {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Customer {
+    pub x: f64,
+    pub y: f64,
+    pub demand: f64,
+    #[serde(default = "_default_dronable")]
+    pub dronable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_time: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_window: Option<(f64, f64)>,
+    /// Handover time when served by a drone, between landing at and taking off from the customer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drone_service_time: Option<f64>,
+    /// Standard deviation of the demand, which is then normally distributed around `demand` (truncated at 0).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub demand_std: Option<f64>,
+    /// Equally likely demand scenarios, overriding `demand_std`. `demand` remains the nominal demand.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub demand_scenarios: Option<Vec<f64>>,
+    /// Weight of the waiting time violation of this customer, 1 by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f64>,
+}
+
+const fn _default_dronable() -> bool {
+    true
+}
+
+/// Customer data of a problem instance, independent of the file format it was read from.
+///
+/// This is also the schema of the JSON instance format. Fields a format cannot represent are `None` (or, for
+/// dronability, `true`) after reading and dropped when writing.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Instance {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trucks_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drones_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacity: Option<f64>,
+    pub depot: Depot,
+    pub customers: Vec<Customer>,
+}
+
+impl Instance {
+    pub fn parse(data: &str, format: InstanceFormat) -> Result<Self, Box<dyn Error>> {
+        match format {
+            InstanceFormat::Native => Self::_parse_native(data),
+            InstanceFormat::Solomon => Self::_parse_solomon(data),
+            InstanceFormat::Cvrplib => Self::_parse_cvrplib(data),
+            InstanceFormat::Json => Ok(serde_json::from_str(data)?),
+        }
+    }
+
+    /// Serialize this instance to `format`. `name` is embedded in the formats that carry an instance name.
+    pub fn serialize(&self, format: InstanceFormat, name: &str) -> Result<String, Box<dyn Error>> {
+        match format {
+            InstanceFormat::Native => self._serialize_native(),
+            InstanceFormat::Solomon => self._serialize_solomon(name),
+            InstanceFormat::Cvrplib => self._serialize_cvrplib(name),
+            InstanceFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    fn _parse_native(data: &str) -> Result<Self, Box<dyn Error>> {
+        let trucks_count_regex = Regex::new(r"trucks_count (\d+)")?;
+        let drones_count_regex = Regex::new(r"drones_count (\d+)")?;
+        let depot_regex = Regex::new(r"depot (-?[\d\.]+)\s+(-?[\d\.]+)")?;
+        let customers_regex =
+            RegexBuilder::new(r"^\s*(-?[\d\.]+)\s+(-?[\d\.]+)\s+(0|1)\s+([\d\.]+)(?:\s+([\d\.]+))?\s*$")
+                .multi_line(true)
+                .build()?;
+
+        let trucks_count = trucks_count_regex
+            .captures(data)
+            .and_then(|caps| caps.get(1))
+            .and_then(|m| m.as_str().parse::<usize>().ok());
+        let drones_count = drones_count_regex
+            .captures(data)
+            .and_then(|caps| caps.get(1))
+            .and_then(|m| m.as_str().parse::<usize>().ok());
+
+        let depot = depot_regex
+            .captures(data)
+            .and_then(|caps| {
+                let x = caps.get(1)?.as_str().parse::<f64>().ok()?;
+                let y = caps.get(2)?.as_str().parse::<f64>().ok()?;
+                Some(Depot { x, y })
+            })
+            .ok_or("Missing depot coordinates")?;
+
+        let mut customers = vec![];
+        for c in customers_regex.captures_iter(data) {
+            // The priority column is optional
+            let field = |i: usize| c.get(i).map(|m| m.as_str());
+            customers.push(Customer {
+                x: field(1).unwrap_or_default().parse::<f64>()?,
+                y: field(2).unwrap_or_default().parse::<f64>()?,
+                demand: field(4).unwrap_or_default().parse::<f64>()?,
+                dronable: matches!(field(3), Some("1")),
+                service_time: None,
+                time_window: None,
+                drone_service_time: None,
+                demand_std: None,
+                demand_scenarios: None,
+                priority: field(5).map(str::parse::<f64>).transpose()?,
+            });
+        }
+
+        Ok(Self {
+            trucks_count,
+            drones_count,
+            capacity: None,
+            depot,
+            customers,
+        })
+    }
+
+    fn _parse_solomon(data: &str) -> Result<Self, Box<dyn Error>> {
+        let vehicle_regex = Regex::new(r"NUMBER\s+CAPACITY\s+(\d+)\s+([\d\.]+)")?;
+        let rows_regex = RegexBuilder::new(
+            r"^\s*\d+\s+(-?[\d\.]+)\s+(-?[\d\.]+)\s+([\d\.]+)\s+([\d\.]+)\s+([\d\.]+)\s+([\d\.]+)\s*$",
+        )
+        .multi_line(true)
+        .build()?;
+
+        let vehicle = vehicle_regex.captures(data).ok_or("Missing VEHICLE section")?;
+        let trucks_count = vehicle[1].parse::<usize>()?;
+        let capacity = vehicle[2].parse::<f64>()?;
+
+        let mut rows = vec![];
+        for c in rows_regex.captures_iter(data) {
+            let (_, values) = c.extract::<6>();
+            rows.push(
+                values
+                    .map(str::parse::<f64>)
+                    .into_iter()
+                    .collect::<Result<Vec<f64>, _>>()?,
+            );
+        }
+
+        // The first row describes the depot
+        let depot = rows.first().ok_or("Missing depot row")?;
+        Ok(Self {
+            trucks_count: Some(trucks_count),
+            drones_count: None,
+            capacity: Some(capacity),
+            depot: Depot {
+                x: depot[0],
+                y: depot[1],
+            },
+            customers: rows[1..]
+                .iter()
+                .map(|row| Customer {
+                    x: row[0],
+                    y: row[1],
+                    demand: row[2],
+                    dronable: true,
+                    service_time: Some(row[5]),
+                    time_window: Some((row[3], row[4])),
+                    drone_service_time: None,
+                    demand_std: None,
+                    demand_scenarios: None,
+                    priority: None,
+                })
+                .collect(),
+        })
+    }
+
+    fn _parse_cvrplib(data: &str) -> Result<Self, Box<dyn Error>> {
+        let mut capacity = None;
+        let mut coordinates = BTreeMap::new();
+        let mut demands = BTreeMap::new();
+        let mut depots = vec![];
+
+        let mut section = "";
+        for line in data.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if let Some((key, value)) = line.split_once(':') {
+                section = "";
+                if key.trim() == "CAPACITY" {
+                    capacity = Some(value.trim().parse::<f64>()?);
+                }
+            } else if line.ends_with("_SECTION") || line == "EOF" {
+                section = line;
+            } else {
+                let fields = line.split_whitespace().collect::<Vec<&str>>();
+                match (section, fields.as_slice()) {
+                    ("NODE_COORD_SECTION", [id, x, y]) => {
+                        coordinates.insert(id.parse::<usize>()?, (x.parse::<f64>()?, y.parse::<f64>()?));
+                    }
+                    ("DEMAND_SECTION", [id, demand]) => {
+                        demands.insert(id.parse::<usize>()?, demand.parse::<f64>()?);
+                    }
+                    ("DEPOT_SECTION", [id]) => {
+                        // The depot list is terminated by -1
+                        if let Ok(id) = id.parse::<usize>() {
+                            depots.push(id);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let [depot] = depots[..] else {
+            return Err(format!("Expected exactly 1 depot, found {}", depots.len()).into());
+        };
+        let &(x, y) = coordinates
+            .get(&depot)
+            .ok_or("Missing depot coordinates (only instances with NODE_COORD_SECTION are supported)")?;
+
+        Ok(Self {
+            trucks_count: None,
+            drones_count: None,
+            capacity,
+            depot: Depot { x, y },
+            customers: coordinates
+                .into_iter()
+                .filter(|&(id, _)| id != depot)
+                .map(|(id, (x, y))| Customer {
+                    x,
+                    y,
+                    demand: demands.get(&id).copied().unwrap_or(0.0),
+                    dronable: true,
+                    service_time: None,
+                    time_window: None,
+                    drone_service_time: None,
+                    demand_std: None,
+                    demand_scenarios: None,
+                    priority: None,
+                })
+                .collect(),
+        })
+    }
+
+    fn _serialize_native(&self) -> Result<String, Box<dyn Error>> {
+        let mut result = String::new();
+        writeln!(
+            result,
+            "trucks_count {}",
+            self.trucks_count.ok_or("Missing trucks count")?
+        )?;
+        writeln!(
+            result,
+            "drones_count {}",
+            self.drones_count.ok_or("Missing drones count")?
+        )?;
+        writeln!(result, "customers {}", self.customers.len())?;
+        writeln!(result, "depot {} {}", self.depot.x, self.depot.y)?;
+        // The priority column is only written when some customer has a priority
+        let priorities = self.customers.iter().any(|c| c.priority.is_some());
+        write!(
+            result,
+            "{:<20} {:<20} {:<8} Demand",
+            "Coordinate X", "Coordinate Y", "Dronable"
+        )?;
+        if priorities {
+            write!(result, " Priority")?;
+        }
+        writeln!(result)?;
+        for c in &self.customers {
+            write!(
+                result,
+                "{:<20} {:<20} {:<8} {}",
+                c.x,
+                c.y,
+                i32::from(c.dronable),
+                c.demand
+            )?;
+            if priorities {
+                write!(result, " {}", c.priority.unwrap_or(1.0))?;
+            }
+            writeln!(result)?;
+        }
+
+        Ok(result)
+    }
+
+    fn _serialize_solomon(&self, name: &str) -> Result<String, Box<dyn Error>> {
+        let mut result = String::new();
+        writeln!(result, "{name}\n\nVEHICLE\nNUMBER     CAPACITY")?;
+        writeln!(
+            result,
+            "{:>5} {:>12}\n",
+            self.trucks_count.ok_or("Missing trucks count")?,
+            self.capacity.ok_or("Missing vehicle capacity")?
+        )?;
+        writeln!(
+            result,
+            "CUSTOMER\nCUST NO.  XCOORD.   YCOORD.    DEMAND   READY TIME  DUE DATE   SERVICE   TIME\n"
+        )?;
+        writeln!(
+            result,
+            "{:>5} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            0, self.depot.x, self.depot.y, 0, 0, SOLOMON_HORIZON, 0
+        )?;
+        for (i, c) in self.customers.iter().enumerate() {
+            let (ready, due) = c.time_window.unwrap_or((0.0, SOLOMON_HORIZON));
+            writeln!(
+                result,
+                "{:>5} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                i + 1,
+                c.x,
+                c.y,
+                c.demand,
+                ready,
+                due,
+                c.service_time.unwrap_or(0.0)
+            )?;
+        }
+
+        Ok(result)
+    }
+
+    fn _serialize_cvrplib(&self, name: &str) -> Result<String, Box<dyn Error>> {
+        let mut result = String::new();
+        writeln!(result, "NAME : {name}")?;
+        writeln!(result, "TYPE : CVRP")?;
+        writeln!(result, "DIMENSION : {}", self.customers.len() + 1)?;
+        writeln!(result, "EDGE_WEIGHT_TYPE : EUC_2D")?;
+        writeln!(
+            result,
+            "CAPACITY : {}",
+            self.capacity.ok_or("Missing vehicle capacity")?
+        )?;
+
+        writeln!(result, "NODE_COORD_SECTION")?;
+        writeln!(result, "1 {} {}", self.depot.x, self.depot.y)?;
+        for (i, c) in self.customers.iter().enumerate() {
+            writeln!(result, "{} {} {}", i + 2, c.x, c.y)?;
+        }
+
+        writeln!(result, "DEMAND_SECTION")?;
+        writeln!(result, "1 0")?;
+        for (i, c) in self.customers.iter().enumerate() {
+            writeln!(result, "{} {}", i + 2, c.demand)?;
+        }
+
+        writeln!(result, "DEPOT_SECTION\n1\n-1\nEOF")?;
+        Ok(result)
+    }
+
+    /// Overwrite the dronability of all customers according to `rule`.
+    pub fn apply_dronable_rule(&mut self, rule: DronableRule, threshold: Option<f64>) {
+        let threshold = || threshold.unwrap_or_else(|| panic!("Dronability rule {rule} requires a threshold"));
+        for c in &mut self.customers {
+            c.dronable = match rule {
+                DronableRule::Keep => c.dronable,
+                DronableRule::All => true,
+                DronableRule::None => false,
+                DronableRule::Demand => c.demand <= threshold(),
+                DronableRule::Distance => (c.x - self.depot.x).hypot(c.y - self.depot.y) <= threshold(),
+            };
+        }
+    }
+}
+
+/// Read an instance file in any supported format and write it in another one.
+pub fn convert(
+    input: &str,
+    output: &str,
+    from: Option<InstanceFormat>,
+    to: Option<InstanceFormat>,
+    trucks_count: Option<usize>,
+    drones_count: Option<usize>,
+    capacity: Option<f64>,
+    dronable: DronableRule,
+    dronable_threshold: Option<f64>,
+) -> Result<(), Box<dyn Error>> {
+    let data = fs::read_to_string(input)?;
+    let from = from.unwrap_or_else(|| InstanceFormat::detect(Path::new(input), Some(&data)));
+    let to = to.unwrap_or_else(|| InstanceFormat::detect(Path::new(output), None));
+
+    let mut instance = Instance::parse(&data, from)?;
+    instance.trucks_count = trucks_count.or(instance.trucks_count);
+    instance.drones_count = drones_count.or(instance.drones_count);
+    instance.capacity = capacity.or(instance.capacity);
+    instance.apply_dronable_rule(dronable, dronable_threshold);
+
+    if matches!(to, InstanceFormat::Solomon | InstanceFormat::Cvrplib) && instance.customers.iter().any(|c| !c.dronable)
+    {
+        eprintln!("Warning: the {to} format cannot represent dronability, all customers will be dronable");
+    }
+    if matches!(to, InstanceFormat::Native | InstanceFormat::Cvrplib)
+        && instance
+            .customers
+            .iter()
+            .any(|c| c.service_time.is_some() || c.time_window.is_some())
+    {
+        eprintln!("Warning: the {to} format cannot represent service times and time windows, dropping them");
+    }
+    if to != InstanceFormat::Json && instance.customers.iter().any(|c| c.drone_service_time.is_some()) {
+        eprintln!("Warning: the {to} format cannot represent drone service times, dropping them");
+    }
+    if matches!(to, InstanceFormat::Solomon | InstanceFormat::Cvrplib)
+        && instance.customers.iter().any(|c| c.priority.is_some())
+    {
+        eprintln!("Warning: the {to} format cannot represent priorities, dropping them");
+    }
+
+    let name = Path::new(output)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("instance");
+    fs::write(output, instance.serialize(to, name)?)?;
+    eprintln!("Converted {input} ({from}) to {output} ({to})");
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+//! Tabu search for the min-makespan vehicle routing problem with drones. The `min-timespan-delivery` executable is a
+//! thin command line front end over this crate; other programs can call the search through [`Solver`].
+
+pub mod bench;
+pub mod checker;
+pub mod cli;
+pub mod clusterize;
+pub mod config;
+pub mod control;
+pub mod crash;
+pub mod decompose;
+pub mod errors;
+pub mod evaluate;
+pub mod fleet;
+pub mod inject;
+pub mod instance;
+pub mod logger;
+pub mod multistart;
+pub mod neighborhoods;
+pub mod numeric;
+pub mod perturb;
+pub mod pool;
+pub mod profile;
+pub mod relink;
+pub mod routes;
+pub mod schedule;
+pub mod seeding;
+pub mod solutions;
+pub mod solver;
+
+pub use config::Config;
+pub use instance::Instance;
+pub use routes::Route;
+pub use solutions::Solution;
+pub use solver::Solver;
@@ -1,19 +1,23 @@
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
+use std::fmt::{self, Write as _};
 use std::fs::{self, File};
-use std::io;
-use std::io::Write;
-use std::path::Path;
-use std::rc::Rc;
-use std::time::SystemTime;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Instant, SystemTime};
+use std::{io, thread};
 
 use rand::Rng;
 use rand::distr::Alphanumeric;
 
-use crate::config::{CONFIG, SerializedConfig};
+use crate::config::{self, Config, DISTANCE_PRECISION, SerializedConfig};
 use crate::errors::ExpectedValue;
-use crate::neighborhoods::Neighborhood;
-use crate::routes::Route;
-use crate::solutions::{Solution, penalty_coeff};
+use crate::neighborhoods::{EVALUATED_MOVES, Neighborhood};
+use crate::routes::{Route, TabuAttribute};
+use crate::schedule::MakespanReport;
+use crate::solutions::{Solution, Termination, penalty_coeff, penalty_coefficients};
 
 #[derive(serde::Serialize)]
 struct RunJSON<'a> {
@@ -29,27 +33,372 @@ struct RunJSON<'a> {
     elapsed: f64,
     post_optimization: f64,
     post_optimization_elapsed: f64,
+    distance_precision: &'static str,
+    evaluated_moves: usize,
+    peak_memory_kb: Option<u64>,
+    makespan: MakespanReport,
+    /// Final penalty coefficients, to warm-start runs on similar instances with `--penalty-init` (which takes all
+    /// but the last one, of the budget violation).
+    penalty_coefficients: [f64; 5],
+    /// Why the search stopped, or `None` if no search was run (e.g. for `evaluate`).
+    termination: Option<Termination>,
+    /// Whether the solution reaches `--target-cost`, or `None` without a target.
+    target_reached: Option<bool>,
+    /// Duration of each iteration but the first in milliseconds with `--record-iteration-times`, or `None` without.
+    iteration_ms: Option<&'a [f64]>,
 }
 
-pub struct Logger<'a> {
+/// Record of an accepted move in the trace file, see [`Logger::trace`].
+#[derive(serde::Serialize)]
+struct _MoveRecord<'a> {
+    iteration: u32,
+    neighborhood: String,
+    customers: &'a [usize],
+    delta: f64,
+}
+
+const _COLUMNS: [&str; 25] = [
+    "Iteration",
+    "Elapsed (ms)",
+    "Cost",
+    "Working time",
+    "Feasible",
+    "Best cost",
+    "Best working time",
+    "Best feasible",
+    "p0",
+    "Energy violation",
+    "p1",
+    "Capacity violation",
+    "p2",
+    "Waiting time violation",
+    "p3",
+    "Fixed time violation",
+    "p4",
+    "Budget violation",
+    "Truck routes",
+    "Drone routes",
+    "Truck routes count",
+    "Drone routes count",
+    "Neighborhood",
+    "Tabu list",
+    "Temperature",
+];
+
+/// Create an iteration log at `path` and write its header.
+fn _create_log(path: &Path) -> Result<File, io::Error> {
+    let mut file = File::create(path)?;
+    writeln!(file, "sep=,\n{}", _COLUMNS.join(","))?;
+    Ok(file)
+}
+
+/// Routes of each vehicle of `vehicle_routes` in the plain-text report, one vehicle per line followed by its routes.
+fn _report_routes<R>(
+    report: &mut String,
+    vehicle_routes: &[Vec<Arc<R>>],
+    working_time: &[f64],
+    class: impl Fn(usize) -> usize,
+) -> fmt::Result
+where
+    R: Route,
+{
+    for (vehicle, routes) in vehicle_routes.iter().enumerate() {
+        writeln!(
+            report,
+            "  {} {vehicle}: {} routes, working time {:.2}s",
+            R::vehicle_kind(),
+            routes.len(),
+            working_time[vehicle],
+        )?;
+        for route in routes {
+            writeln!(report, "    {}", route.display(class(vehicle)))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Peak resident set size of the current process in kB, if the platform exposes it.
+fn _peak_memory_kb() -> Option<u64> {
+    fs::read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+/// Receives the progress and results of a search. [`FileLogger`] writes them to the output directory, as the command
+/// line does; [`NullLogger`] discards them and [`MemoryLogger`] keeps them in memory, for programs embedding the
+/// search.
+pub trait Logger {
+    /// Record an iteration: the current `solution` of the search, the `best` solution found so far and the
+    /// `temperature` of simulated annealing (if enabled), after a move of `neighbor` with the given tabu list.
+    fn log(
+        &mut self,
+        solution: &Solution,
+        best: &Solution,
+        temperature: Option<f64>,
+        neighbor: Neighborhood,
+        tabu_list: &[TabuAttribute],
+    ) -> Result<(), io::Error>;
+
+    /// Record a move of `neighborhood` accepted at `iteration`, relocating `customers` and changing the cost by
+    /// `delta`.
+    fn trace(
+        &mut self,
+        iteration: usize,
+        neighborhood: Neighborhood,
+        customers: &TabuAttribute,
+        delta: f64,
+    ) -> Result<(), bincode::Error>;
+
+    /// Record `best`, a new best solution found at `iteration`, as a snapshot of `--snapshot-every`.
+    fn snapshot(&mut self, iteration: usize, best: &Solution) -> Result<(), io::Error>;
+
+    /// Wall-clock time since the start of this run, in seconds.
+    fn elapsed(&self) -> f64;
+
+    /// Record the final `result` of the run along with the search statistics.
+    fn finalize(
+        &mut self,
+        result: &Solution,
+        tabu_size: usize,
+        reset_after: usize,
+        actual_adaptive_iterations: usize,
+        total_adaptive_segments: usize,
+        last_improved: usize,
+        post_optimization: f64,
+        post_optimization_elapsed: f64,
+        termination: Option<Termination>,
+    ) -> Result<(), Box<dyn Error>>;
+}
+
+/// Logger discarding everything, so that a search writes no file at all. It only keeps the time since its creation
+/// for `--time-limit`.
+pub struct NullLogger {
+    _time_offset: SystemTime,
+}
+
+impl NullLogger {
+    pub fn new() -> Self {
+        Self {
+            _time_offset: SystemTime::now(),
+        }
+    }
+}
+
+impl Default for NullLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Logger for NullLogger {
+    fn log(
+        &mut self,
+        _: &Solution,
+        _: &Solution,
+        _: Option<f64>,
+        _: Neighborhood,
+        _: &[TabuAttribute],
+    ) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn trace(&mut self, _: usize, _: Neighborhood, _: &TabuAttribute, _: f64) -> Result<(), bincode::Error> {
+        Ok(())
+    }
+
+    fn snapshot(&mut self, _: usize, _: &Solution) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn elapsed(&self) -> f64 {
+        SystemTime::now()
+            .duration_since(self._time_offset)
+            .unwrap_or_default()
+            .as_secs_f64()
+    }
+
+    fn finalize(
+        &mut self,
+        _: &Solution,
+        _: usize,
+        _: usize,
+        _: usize,
+        _: usize,
+        _: usize,
+        _: f64,
+        _: f64,
+        _: Option<Termination>,
+    ) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// An iteration recorded by [`MemoryLogger`].
+#[derive(Clone, Debug)]
+pub struct IterationRecord {
+    /// Wall-clock time since the start of the run, in seconds.
+    pub elapsed: f64,
+    pub cost: f64,
+    pub working_time: f64,
+    pub feasible: bool,
+    /// Cost of the best solution found so far, with the penalty coefficients of this iteration.
+    pub best_cost: f64,
+    pub best_working_time: f64,
+    pub best_feasible: bool,
+    /// Energy, capacity, waiting time, fixed time and budget violations of the current solution.
+    pub violations: [f64; 5],
+    /// Penalty coefficients of these violations.
+    pub penalty_coefficients: [f64; 5],
+    pub neighborhood: Neighborhood,
+    /// Temperature of simulated annealing, if enabled.
+    pub temperature: Option<f64>,
+}
+
+/// History of a run recorded by [`MemoryLogger`], for benchmarks and plots without touching the filesystem.
+#[derive(Clone, Debug, Default)]
+pub struct RunHistory {
+    pub iterations: Vec<IterationRecord>,
+    /// Number of accepted moves per neighborhood, as traced by the search.
+    pub moves: HashMap<Neighborhood, usize>,
+    /// Working time and feasibility of the final solution, once the run is finalized.
+    pub result: Option<(f64, bool)>,
+    pub termination: Option<Termination>,
+}
+
+/// Logger keeping the history of a run in memory, see [`Self::into_history`].
+pub struct MemoryLogger {
+    _time_offset: SystemTime,
+    _history: RunHistory,
+}
+
+impl MemoryLogger {
+    pub fn new() -> Self {
+        Self {
+            _time_offset: SystemTime::now(),
+            _history: RunHistory::default(),
+        }
+    }
+
+    /// The history recorded so far.
+    pub const fn history(&self) -> &RunHistory {
+        &self._history
+    }
+
+    pub fn into_history(self) -> RunHistory {
+        self._history
+    }
+}
+
+impl Default for MemoryLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Logger for MemoryLogger {
+    fn log(
+        &mut self,
+        solution: &Solution,
+        best: &Solution,
+        temperature: Option<f64>,
+        neighbor: Neighborhood,
+        _: &[TabuAttribute],
+    ) -> Result<(), io::Error> {
+        self._history.iterations.push(IterationRecord {
+            elapsed: self.elapsed(),
+            cost: solution.cost(),
+            working_time: solution.working_time,
+            feasible: solution.feasible,
+            best_cost: best.cost(),
+            best_working_time: best.working_time,
+            best_feasible: best.feasible,
+            violations: [
+                solution.energy_violation,
+                solution.capacity_violation,
+                solution.waiting_time_violation,
+                solution.fixed_time_violation,
+                solution.budget_violation,
+            ],
+            penalty_coefficients: penalty_coefficients(),
+            neighborhood: neighbor,
+            temperature,
+        });
+        Ok(())
+    }
+
+    fn trace(&mut self, _: usize, neighborhood: Neighborhood, _: &TabuAttribute, _: f64) -> Result<(), bincode::Error> {
+        *self._history.moves.entry(neighborhood).or_default() += 1;
+        Ok(())
+    }
+
+    fn snapshot(&mut self, _: usize, _: &Solution) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn elapsed(&self) -> f64 {
+        SystemTime::now()
+            .duration_since(self._time_offset)
+            .unwrap_or_default()
+            .as_secs_f64()
+    }
+
+    fn finalize(
+        &mut self,
+        result: &Solution,
+        _: usize,
+        _: usize,
+        _: usize,
+        _: usize,
+        _: usize,
+        _: f64,
+        _: f64,
+        termination: Option<Termination>,
+    ) -> Result<(), Box<dyn Error>> {
+        self._history.result = Some((result.working_time, result.feasible));
+        self._history.termination = termination;
+        Ok(())
+    }
+}
+
+/// Logger writing the iteration log, the move trace and the final results of a run to the output directory of
+/// the config in scope, under `<problem>-<id>` file names.
+pub struct FileLogger {
     _iteration: usize,
     _time_offset: SystemTime,
 
-    _outputs: &'a Path,
+    _outputs: PathBuf,
     _problem: String,
     _id: String,
     _writer: Option<File>,
+    /// Bytes of rows written to the current iteration log, for `--log-max-size`.
+    _written: u64,
+    /// Log of the iterations improving on the best feasible solution, kept when the iteration log is rotated.
+    _improvements: Option<File>,
+    _best: f64,
+    /// Iteration, elapsed time and working time of each improvement of the best feasible solution, for the report.
+    _timeline: Vec<(usize, f64, f64)>,
+    /// Last iterations of the log, kept in memory instead of being written with `--log-ring`.
+    _ring: Option<VecDeque<String>>,
+    _trace: Option<BufWriter<File>>,
+    /// Duration of each iteration but the first with `--record-iteration-times`, from the end of the previous
+    /// [`Logger::log`] call to the start of the next one (so that writing the log does not count).
+    _iteration_ms: Option<Vec<f64>>,
+    _logged: Option<Instant>,
 }
 
-impl Logger<'_> {
+impl FileLogger {
     pub fn new() -> Result<Self, Box<dyn Error>> {
-        let outputs = Path::new(&CONFIG.outputs);
+        let config = config::current();
+        let outputs = Path::new(&config.outputs);
         if !outputs.is_dir() {
             fs::create_dir_all(outputs)?;
         }
 
         let problem = ExpectedValue::cast(
-            Path::new(&CONFIG.problem)
+            Path::new(&config.problem)
                 .file_stem()
                 .and_then(|f| f.to_os_string().into_string().ok()),
         )?;
@@ -59,60 +408,168 @@ impl Logger<'_> {
             .map(char::from)
             .collect::<String>();
 
-        let mut writer = if CONFIG.disable_logging {
+        let writer = if config.disable_logging || config.log_ring.is_some() {
             None
         } else {
-            Some(File::create(outputs.join(format!("{problem}-{id}.csv")))?)
+            Some(_create_log(&outputs.join(format!("{problem}-{id}.csv")))?)
         };
 
-        if let Some(ref mut writer) = writer {
+        if let Some(ref writer) = writer {
             eprintln!("Logging iterations to {writer:?}");
+        }
 
-            let columns = vec![
-                "Iteration",
-                "Cost",
-                "Working time",
-                "Feasible",
-                "p0",
-                "Energy violation",
-                "p1",
-                "Capacity violation",
-                "p2",
-                "Waiting time violation",
-                "p3",
-                "Fixed time violation",
-                "Truck routes",
-                "Drone routes",
-                "Truck routes count",
-                "Drone routes count",
-                "Neighborhood",
-                "Tabu list",
-            ]
-            .join(",");
-            writeln!(writer, "sep=,\n{columns}")?;
-        }
-
-        Ok(Logger {
+        let improvements = if writer.is_some() && config.log_max_size.is_some() {
+            Some(_create_log(&outputs.join(format!("{problem}-{id}-improvements.csv")))?)
+        } else {
+            None
+        };
+
+        let ring = if config.disable_logging {
+            None
+        } else {
+            config.log_ring.map(|_| VecDeque::new())
+        };
+
+        let trace = if config.trace_moves {
+            let path = outputs.join(format!("{problem}-{id}-moves.bin"));
+            eprintln!("Tracing moves to {}", path.display());
+            Some(BufWriter::new(File::create(path)?))
+        } else {
+            None
+        };
+
+        Ok(Self {
             _iteration: 0,
             _time_offset: SystemTime::now(),
-            _outputs: outputs,
+            _outputs: outputs.to_path_buf(),
             _id: id,
             _problem: problem,
             _writer: writer,
+            _written: 0,
+            _improvements: improvements,
+            _best: f64::INFINITY,
+            _timeline: vec![],
+            _ring: ring,
+            _trace: trace,
+            _iteration_ms: config.record_iteration_times.then(Vec::new),
+            _logged: None,
         })
     }
 
-    pub fn log(
+    /// Path of the output file of this run with the given suffix, e.g. `-solution.json`.
+    pub fn output_path(&self, suffix: &str) -> PathBuf {
+        self._outputs.join(format!("{}-{}{suffix}", self._problem, self._id))
+    }
+
+    /// Plain-text summary of a run ending with `result`, for readers of the results rather than of the JSON outputs:
+    /// the instance and main parameters, the outcome, the routes of each vehicle and the improvements over time.
+    fn _report(&self, result: &Solution, elapsed: f64, termination: Option<Termination>) -> Result<String, fmt::Error> {
+        let config = config::current();
+        let mut report = String::new();
+        writeln!(report, "Run {} on {}", self._id, config.problem)?;
+        writeln!(
+            report,
+            "Instance: {} customers, {} trucks, {} drones ({} energy model)",
+            config.customers_count, config.trucks_count, config.drones_count, config.config
+        )?;
+        writeln!(
+            report,
+            "Parameters: algorithm {}, strategy {}, waiting time limit {:.0}s, seed {}",
+            config.algorithm,
+            config.strategy,
+            config.waiting_time_limit,
+            config.seed.map_or_else(|| "none".to_string(), |seed| seed.to_string()),
+        )?;
+        writeln!(report)?;
+
+        writeln!(
+            report,
+            "Makespan: {:.2}s ({})",
+            result.working_time,
+            if result.feasible { "feasible" } else { "infeasible" }
+        )?;
+        writeln!(
+            report,
+            "Search: {} iterations in {elapsed:.2}s, {}",
+            self._iteration,
+            termination.map_or_else(|| "no search".to_string(), |t| t.to_string()),
+        )?;
+        writeln!(report, "Violations:")?;
+        for (name, violation) in [
+            ("energy", result.energy_violation),
+            ("capacity", result.capacity_violation),
+            ("waiting time", result.waiting_time_violation),
+            ("fixed time", result.fixed_time_violation),
+            ("budget", result.budget_violation),
+        ] {
+            writeln!(report, "  {name:<12} {violation:.4}")?;
+        }
+        if let Some(budget) = config.budget {
+            writeln!(report, "Vehicle cost: {:.2} (budget {budget:.2})", result.vehicle_cost)?;
+        }
+        writeln!(report)?;
+
+        writeln!(report, "Routes:")?;
+        _report_routes(&mut report, &result.truck_routes, &result.truck_working_time, |t| {
+            config.truck_class(t)
+        })?;
+        _report_routes(&mut report, &result.drone_routes, &result.drone_working_time, |d| {
+            config.drone_class(d)
+        })?;
+        writeln!(report)?;
+
+        writeln!(report, "Improvements of the best feasible solution:")?;
+        writeln!(report, "  {:>9} {:>12} {:>12}", "Iteration", "Elapsed (s)", "Makespan")?;
+        for &(iteration, elapsed, working_time) in &self._timeline {
+            writeln!(report, "  {iteration:>9} {elapsed:>12.2} {working_time:>12.2}")?;
+        }
+
+        Ok(report)
+    }
+
+    /// Write the iterations kept in memory with `--log-ring` to the iteration log.
+    fn _dump_ring(&self) -> Result<(), io::Error> {
+        if let Some(ref ring) = self._ring {
+            let path = self._outputs.join(format!("{}-{}.csv", self._problem, self._id));
+            let mut writer = BufWriter::new(_create_log(&path)?);
+            for row in ring {
+                writeln!(writer, "{row}")?;
+            }
+
+            writer.flush()?;
+            eprintln!("Saved the last {} iterations to {}", ring.len(), path.display());
+        }
+
+        Ok(())
+    }
+
+    /// Move the full iteration log to `<problem>-<id>.1.csv`, replacing the previous one, and start a new one.
+    fn _rotate(&mut self) -> Result<(), io::Error> {
+        let path = self._outputs.join(format!("{}-{}.csv", self._problem, self._id));
+        fs::rename(
+            &path,
+            self._outputs.join(format!("{}-{}.1.csv", self._problem, self._id)),
+        )?;
+
+        self._writer = Some(_create_log(&path)?);
+        self._written = 0;
+        Ok(())
+    }
+
+    /// Record an iteration in the timeline and in the iteration log, see [`Logger::log`].
+    fn _log(
         &mut self,
         solution: &Solution,
+        best: &Solution,
+        temperature: Option<f64>,
         neighbor: Neighborhood,
-        tabu_list: &Vec<Vec<usize>>,
+        tabu_list: &[TabuAttribute],
     ) -> Result<(), io::Error> {
         fn _wrap(content: &String) -> String {
             format!("\"{content}\"")
         }
 
-        fn _expand_routes<T>(routes: &[Vec<Rc<T>>]) -> Vec<Vec<&Vec<usize>>>
+        fn _expand_routes<T>(routes: &[Vec<Arc<T>>]) -> Vec<Vec<&Vec<usize>>>
         where
             T: Route,
         {
@@ -122,37 +579,158 @@ impl Logger<'_> {
                 .collect()
         }
 
+        let config = config::current();
         self._iteration += 1;
+        if best.feasible
+            && self
+                ._timeline
+                .last()
+                .is_none_or(|&(_, _, time)| best.working_time < time)
+        {
+            self._timeline
+                .push((self._iteration, self.elapsed(), best.working_time));
+        }
+
+        if self._writer.is_none() && self._ring.is_none() {
+            return Ok(());
+        }
+
+        let row = format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self._iteration,
+            SystemTime::now()
+                .duration_since(self._time_offset)
+                .unwrap_or_default()
+                .as_secs_f64()
+                * 1e3,
+            solution.cost(),
+            solution.working_time,
+            i32::from(solution.feasible),
+            best.cost(),
+            best.working_time,
+            i32::from(best.feasible),
+            penalty_coeff::<0>(),
+            solution.energy_violation,
+            penalty_coeff::<1>(),
+            solution.capacity_violation,
+            penalty_coeff::<2>(),
+            solution.waiting_time_violation,
+            penalty_coeff::<3>(),
+            solution.fixed_time_violation,
+            penalty_coeff::<4>(),
+            solution.budget_violation,
+            _wrap(&format!("{:?}", _expand_routes(&solution.truck_routes))),
+            _wrap(&format!("{:?}", _expand_routes(&solution.drone_routes))),
+            solution.truck_routes.iter().map(|r| r.len()).sum::<usize>(),
+            solution.drone_routes.iter().map(|r| r.len()).sum::<usize>(),
+            _wrap(&neighbor.to_string()),
+            _wrap(&format!("{tabu_list:?}")),
+            temperature.map(|t| t.to_string()).unwrap_or_default(),
+        );
+
+        if let Some(ref mut ring) = self._ring {
+            ring.push_back(row);
+            if ring.len() > config.log_ring.unwrap_or_default() {
+                ring.pop_front();
+            }
+            return Ok(());
+        }
+
+        if let Some(ref mut improvements) = self._improvements
+            && solution.feasible
+            && solution.working_time < self._best
+        {
+            self._best = solution.working_time;
+            writeln!(improvements, "{row}")?;
+        }
+
+        let size = row.len() as u64 + 1;
+        if let Some(max) = config.log_max_size
+            && self._written > 0
+            && self._written + size > max
+        {
+            self._rotate()?;
+        }
+
         if let Some(ref mut writer) = self._writer {
-            writeln!(
-                writer,
-                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
-                self._iteration,
-                solution.cost(),
-                solution.working_time,
-                i32::from(solution.feasible),
-                penalty_coeff::<0>(),
-                solution.energy_violation,
-                penalty_coeff::<1>(),
-                solution.capacity_violation,
-                penalty_coeff::<2>(),
-                solution.waiting_time_violation,
-                penalty_coeff::<3>(),
-                solution.fixed_time_violation,
-                _wrap(&format!("{:?}", _expand_routes(&solution.truck_routes))),
-                _wrap(&format!("{:?}", _expand_routes(&solution.drone_routes))),
-                solution.truck_routes.iter().map(|r| r.len()).sum::<usize>(),
-                solution.drone_routes.iter().map(|r| r.len()).sum::<usize>(),
-                _wrap(&neighbor.to_string()),
-                _wrap(&format!("{tabu_list:?}")),
+            writeln!(writer, "{row}")?;
+            self._written += size;
+        }
+
+        Ok(())
+    }
+}
+
+impl Logger for FileLogger {
+    /// Append an iteration to the CSV log (or to the ring of `--log-ring`), and record its duration with
+    /// `--record-iteration-times`.
+    fn log(
+        &mut self,
+        solution: &Solution,
+        best: &Solution,
+        temperature: Option<f64>,
+        neighbor: Neighborhood,
+        tabu_list: &[TabuAttribute],
+    ) -> Result<(), io::Error> {
+        if let Some(ref mut iteration_ms) = self._iteration_ms {
+            if let Some(logged) = self._logged {
+                iteration_ms.push(logged.elapsed().as_secs_f64() * 1e3);
+            }
+
+            let result = self._log(solution, best, temperature, neighbor, tabu_list);
+            self._logged = Some(Instant::now());
+            return result;
+        }
+
+        self._log(solution, best, temperature, neighbor, tabu_list)
+    }
+
+    /// Append an accepted move to the trace file, if enabled: a bincode record of the iteration (`u32`), the name of
+    /// the neighborhood in `--neighborhoods` (a `u64` length followed by UTF-8 bytes), the sorted customers involved (as a `u64` length followed by `u64` indices) and the
+    /// cost delta (`f64`), all little-endian.
+    fn trace(
+        &mut self,
+        iteration: usize,
+        neighborhood: Neighborhood,
+        customers: &TabuAttribute,
+        delta: f64,
+    ) -> Result<(), bincode::Error> {
+        if let Some(ref mut trace) = self._trace {
+            bincode::serialize_into(
+                trace,
+                &_MoveRecord {
+                    iteration: iteration as u32,
+                    neighborhood: neighborhood.name(),
+                    customers,
+                    delta,
+                },
             )?;
         }
 
         Ok(())
     }
 
-    pub fn finalize(
-        &self,
+    /// Write `best` to `snapshots/iter<iteration>.json` in the output directory of this run (created if needed), in
+    /// the format of the output solution JSON.
+    fn snapshot(&mut self, iteration: usize, best: &Solution) -> Result<(), io::Error> {
+        let directory = self.output_path("").join("snapshots");
+        fs::create_dir_all(&directory)?;
+        fs::write(
+            directory.join(format!("iter{iteration}.json")),
+            serde_json::to_string(best)?,
+        )
+    }
+
+    fn elapsed(&self) -> f64 {
+        SystemTime::now()
+            .duration_since(self._time_offset)
+            .unwrap_or_default()
+            .as_secs_f64()
+    }
+
+    /// Write the run JSON, the solution, its schedule, the config and the text report.
+    fn finalize(
+        &mut self,
         result: &Solution,
         tabu_size: usize,
         reset_after: usize,
@@ -161,12 +739,11 @@ impl Logger<'_> {
         last_improved: usize,
         post_optimization: f64,
         post_optimization_elapsed: f64,
+        termination: Option<Termination>,
     ) -> Result<(), Box<dyn Error>> {
-        let elapsed = SystemTime::now()
-            .duration_since(self._time_offset)
-            .unwrap()
-            .as_secs_f64();
-        let serialized_config = SerializedConfig::from(CONFIG.clone());
+        let elapsed = self.elapsed();
+        let serialized_config = SerializedConfig::from(Config::clone(&config::current()));
+        self._dump_ring()?;
 
         let json_path = self._outputs.join(format!("{}-{}.json", self._problem, self._id));
         let mut json = File::create(&json_path)?;
@@ -185,6 +762,14 @@ impl Logger<'_> {
                 elapsed,
                 post_optimization,
                 post_optimization_elapsed,
+                distance_precision: DISTANCE_PRECISION,
+                evaluated_moves: EVALUATED_MOVES.load(Ordering::Relaxed),
+                peak_memory_kb: _peak_memory_kb(),
+                makespan: result.makespan_report(),
+                penalty_coefficients: penalty_coefficients(),
+                termination,
+                target_reached: result.reaches_target(),
+                iteration_ms: self._iteration_ms.as_deref(),
             })?
             .as_bytes(),
         )?;
@@ -196,6 +781,13 @@ impl Logger<'_> {
         println!("{}", json_path.display());
         json.write_all(serde_json::to_string(&result)?.as_bytes())?;
 
+        let json_path = self
+            ._outputs
+            .join(format!("{}-{}-schedule.json", self._problem, self._id));
+        let mut json = File::create(&json_path)?;
+        println!("{}", json_path.display());
+        json.write_all(serde_json::to_string(&result.schedule())?.as_bytes())?;
+
         let json_path = self
             ._outputs
             .join(format!("{}-{}-config.json", self._problem, self._id));
@@ -203,6 +795,20 @@ impl Logger<'_> {
         println!("{}", json_path.display());
         json.write_all(serde_json::to_string(&serialized_config)?.as_bytes())?;
 
+        let report_path = self.output_path("-report.txt");
+        println!("{}", report_path.display());
+        fs::write(&report_path, self._report(result, elapsed, termination)?)?;
+
         Ok(())
     }
 }
+
+impl Drop for FileLogger {
+    fn drop(&mut self) {
+        if thread::panicking()
+            && let Err(e) = self._dump_ring()
+        {
+            eprintln!("Failed to save the last iterations: {e}");
+        }
+    }
+}
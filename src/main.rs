@@ -1,59 +1,174 @@
-use std::fs;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::process;
+use std::sync::Arc;
 
 use clap::Parser;
 use colored::Colorize;
 use mimalloc::MiMalloc;
-use routes::Route;
-
-mod cli;
-mod clusterize;
-mod config;
-mod errors;
-mod logger;
-mod neighborhoods;
-mod routes;
-mod solutions;
+use min_timespan_delivery::logger::Logger;
+use min_timespan_delivery::{
+    bench, cli, clusterize, config, crash, evaluate, instance, logger, perturb, profile, solutions, solver,
+};
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
-fn main() {
-    let mut logger = logger::Logger::new().unwrap();
-
-    let solution = match cli::Arguments::parse().command {
-        cli::Commands::Evaluate { solution, .. } => {
-            let data = fs::read_to_string(solution).unwrap();
+/// Report the solution of the `evaluate` and `run` commands, with their config in scope.
+fn _conclude(solution: &solutions::Solution) {
+    solution.print_summary();
+    eprintln!("{}", format!("Result = {}", solution.working_time).red());
+    solution.verify();
+}
 
-            // Note: Solution `s` here contains attributes calculated using its old config.
-            // In order to evaluate `s` with the new config, we construct a new solution.
-            let s = serde_json::from_str::<solutions::Solution>(&data).unwrap();
+/// Evaluate the solution read from `file` against the config in scope.
+fn _evaluate(file: BufReader<File>) {
+    let mut logger = logger::FileLogger::new().unwrap();
+    // Note: The attributes of `s` here are those stored in the file, calculated using its old config. Reading it
+    // fails if the new config evaluates its routes differently from the values stored alongside them.
+    let s = serde_json::from_reader::<_, solutions::Solution>(file).unwrap();
+    let evaluation = evaluate::evaluate(&s, &config::current());
+    eprintln!(
+        "Evaluated working time = {:.2}, violations: energy = {:.4}, capacity = {:.4}, waiting time = {:.4}, \
+         fixed time = {:.4}, budget = {:.4} ({})",
+        evaluation.working_time,
+        evaluation.energy_violation,
+        evaluation.capacity_violation,
+        evaluation.waiting_time_violation,
+        evaluation.fixed_time_violation,
+        evaluation.budget_violation,
+        if evaluation.feasible { "feasible" } else { "infeasible" },
+    );
 
-            let mut truck_routes = vec![vec![]; s.truck_routes.len()];
-            for (truck, routes) in s.truck_routes.into_iter().enumerate() {
-                for route in routes {
-                    let new = routes::TruckRoute::new(route.data().customers.clone());
-                    truck_routes[truck].push(new);
-                }
-            }
+    let s = solutions::Solution::new(s.truck_routes, s.drone_routes);
+    logger.finalize(&s, 0, 0, 0, 0, 0, 0.0, 0.0, None).unwrap();
+    let report = s.makespan_report();
+    eprintln!(
+        "Makespan of {} {}: travel = {:.2}s, takeoff/landing = {:.2}s, service = {:.2}s, idle = {:.2}s",
+        report.vehicle,
+        report.index,
+        report.breakdown.travel,
+        report.breakdown.takeoff_landing,
+        report.breakdown.service,
+        report.breakdown.idle,
+    );
+    _conclude(&s);
+}
 
-            let mut drone_routes = vec![vec![]; s.drone_routes.len()];
-            for (drone, routes) in s.drone_routes.into_iter().enumerate() {
-                for route in routes {
-                    let new = routes::DroneRoute::new(route.data().customers.clone());
-                    drone_routes[drone].push(new);
-                }
-            }
+/// Run the search of the `run` command with its config in scope, saving a crash report to its output directory if the
+/// search panics.
+fn _run() -> Result<(), Box<dyn Error>> {
+    let mut logger = logger::FileLogger::new()?;
+    crash::install(
+        logger.output_path("-crash.json"),
+        logger.output_path("-crash-solution.json"),
+    );
+    _conclude(&solver::Solver::solve(&mut logger)?);
+    Ok(())
+}
 
-            let s = solutions::Solution::new(truck_routes, drone_routes);
-            logger.finalize(&s, 0, 0, 0, 0, 0, 0.0, 0.0).unwrap();
-            s
+fn main() {
+    let arguments = cli::Arguments::parse();
+    match arguments.command {
+        cli::Commands::Evaluate { ref solution, .. } => {
+            eprintln!("Received {arguments:?}");
+            let file = BufReader::new(File::open(solution).unwrap());
+            let config = Arc::new(config::Config::from_command(arguments.command));
+            config::scope(config, || _evaluate(file));
         }
         cli::Commands::Run { .. } => {
-            let root = solutions::Solution::initialize();
-            solutions::Solution::tabu_search(root, &mut logger)
+            eprintln!("Received {arguments:?}");
+            let config = Arc::new(config::Config::from_command(arguments.command));
+            config::scope(config, _run).unwrap();
         }
-    };
-
-    eprintln!("{}", format!("Result = {}", solution.working_time).red());
-    solution.verify();
+        cli::Commands::Bench {
+            problems,
+            repeat,
+            iterations,
+            baseline,
+            save_baseline,
+            args,
+        } => {
+            if let Err(e) = bench::bench(
+                &problems,
+                repeat,
+                iterations,
+                baseline.as_deref(),
+                save_baseline.as_deref(),
+                &args,
+            ) {
+                eprintln!("{}", format!("Benchmark failed: {e}").red());
+                process::exit(1);
+            }
+        }
+        cli::Commands::Convert {
+            input,
+            output,
+            from,
+            to,
+            trucks_count,
+            drones_count,
+            capacity,
+            dronable,
+            dronable_threshold,
+        } => {
+            instance::convert(
+                &input,
+                &output,
+                from,
+                to,
+                trucks_count,
+                drones_count,
+                capacity,
+                dronable,
+                dronable_threshold,
+            )
+            .unwrap();
+        }
+        cli::Commands::ProfileInstance {
+            problems,
+            from,
+            truck_cfg,
+            output,
+        } => {
+            profile::profile_instances(&problems, from, &truck_cfg, output.as_deref()).unwrap();
+        }
+        cli::Commands::Perturb {
+            problem,
+            from,
+            variants,
+            demand,
+            add,
+            remove,
+            jitter,
+            iterations,
+            seed,
+            output,
+            args,
+        } => {
+            perturb::perturb(
+                &problem,
+                from,
+                variants,
+                demand,
+                add,
+                remove,
+                jitter,
+                iterations,
+                seed,
+                output.as_deref(),
+                &args,
+            )
+            .unwrap();
+        }
+        cli::Commands::Cluster {
+            problem,
+            from,
+            clusters,
+            output,
+        } => {
+            clusterize::cluster_instance(&problem, from, clusters, output.as_deref()).unwrap();
+        }
+    }
 }
@@ -0,0 +1,162 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use crate::config::{self, Config};
+use crate::inject;
+use crate::logger::{Logger, NullLogger};
+use crate::seeding;
+use crate::solutions::{self, Solution, TabuSearch};
+
+/// Elite pool shared by the searches of `--threads`: the best distinct feasible solutions they published, in
+/// increasing order of working time and at most `--max-elite-size` (or 1) of them.
+///
+/// Solutions are compared by working time rather than by cost, since the penalty coefficients the cost depends on
+/// belong to each search (they are thread-local), while the working time of a feasible solution is its cost.
+pub struct SharedElitePool {
+    _solutions: Mutex<Vec<Arc<Solution>>>,
+    _capacity: usize,
+}
+
+impl SharedElitePool {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            _solutions: Mutex::new(vec![]),
+            _capacity: capacity.max(1),
+        }
+    }
+
+    /// Add `solution` to the pool if it is feasible, not already in it and better than its worst solution (or the
+    /// pool is not full).
+    pub fn publish(&self, solution: &Arc<Solution>) {
+        if !solution.feasible {
+            return;
+        }
+
+        let mut solutions = self._solutions.lock().unwrap_or_else(PoisonError::into_inner);
+        if solutions
+            .iter()
+            .any(|s| Arc::ptr_eq(s, solution) || s.hamming_distance(solution) == 0)
+        {
+            return;
+        }
+
+        let index = solutions.partition_point(|s| s.working_time <= solution.working_time);
+        if index < self._capacity {
+            solutions.insert(index, solution.clone());
+            solutions.truncate(self._capacity);
+        }
+    }
+
+    /// The best solution of the pool.
+    pub fn best(&self) -> Option<Arc<Solution>> {
+        self._solutions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .first()
+            .cloned()
+    }
+
+    /// Publish the best solution of `search`, then inject the best solution of the pool into it if it is better.
+    pub fn exchange(&self, search: &mut TabuSearch) {
+        self.publish(search.best());
+        if let Some(best) = self.best()
+            && best.working_time < search.best().working_time
+        {
+            search.inject(Solution::clone(&best));
+        }
+    }
+}
+
+/// Run `--threads` tabu searches in parallel and return the best solution found by any of them, as
+/// [`Solution::tabu_search`] does for a single one.
+///
+/// The search of the calling thread starts from `root`, reports to `logger` and polls `--control` and `--inject`. The
+/// others start from initial solutions of their own (constructed from substreams of `--seed`, or loaded from
+/// `--initial-solution`) and run silently on threads of their own until it finishes. Every `--exchange-interval`
+/// iterations, each search publishes its best solution to a [`SharedElitePool`] and takes the best one of the pool if
+/// it improves on its own. The best solutions of the other searches are finally merged into the search of the calling
+/// thread, which runs the post-optimization and writes the results.
+pub fn tabu_search(root: Solution, logger: &mut dyn Logger) -> Solution {
+    let config = config::current();
+    let pool = SharedElitePool::new(config.max_elite_size);
+    let stopped = AtomicBool::new(false);
+    let exchange = |search: &mut TabuSearch| {
+        if search.iteration().is_multiple_of(config.exchange_interval) {
+            pool.exchange(search);
+        }
+    };
+
+    let mut search = TabuSearch::new(root, logger);
+    let results = thread::scope(|scope| {
+        #[allow(clippy::needless_collect)] // Spawn all searches before running the one of this thread
+        let handles = (1..config.threads)
+            .map(|index| {
+                let config = _worker_config(&config, index);
+                let (pool, stopped) = (&pool, &stopped);
+                scope.spawn(move || config::scope(config, || _worker(pool, stopped)))
+            })
+            .collect::<Vec<_>>();
+
+        search.run(exchange);
+        stopped.store(true, Ordering::Relaxed);
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let mut improved = 0;
+    for solution in results {
+        improved += usize::from(search.inject(solution));
+    }
+    if config.verbose {
+        eprintln!(
+            "Merged the best solutions of the {} other searches, improving the best cost {improved} times",
+            config.threads - 1
+        );
+    }
+
+    // Merging into a search that ran out of elite solutions resumes it
+    search.run(exchange);
+    search.finish()
+}
+
+/// Config of the additional search `index` of `--threads`: `config`, drawing from substream `seeding::THREADS + index`
+/// of `--seed` and without progress output. It shares the instance data of `config`.
+fn _worker_config(config: &Config, index: usize) -> Arc<Config> {
+    Arc::new(Config {
+        seed: config
+            .seed
+            .map(|seed| seeding::substream_seed(seed, seeding::THREADS + index as u64)),
+        verbose: false,
+        ..Config::clone(config)
+    })
+}
+
+/// Run an additional search of `--threads` until it finishes or `stopped` is set, exchanging solutions with `pool`.
+/// Returns the best solution it found.
+fn _worker(pool: &SharedElitePool, stopped: &AtomicBool) -> Solution {
+    let config = config::current();
+    solutions::reset_penalties();
+    let root = match &config.initial_solution {
+        Some(path) => inject::load(Path::new(path)).unwrap_or_else(|e| panic!("Invalid initial solution {path}: {e}")),
+        None => Solution::initialize(),
+    };
+
+    let mut logger = NullLogger::new();
+    let mut search = TabuSearch::new(root, &mut logger);
+    while !stopped.load(Ordering::Relaxed) {
+        if search.iteration().is_multiple_of(config.exchange_interval) {
+            pool.exchange(&mut search);
+        }
+
+        if !search.step() {
+            break;
+        }
+    }
+
+    pool.publish(search.best());
+    Solution::clone(search.best())
+}
@@ -1,19 +1,41 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Display};
 use std::ptr;
 use std::rc::Rc;
-
-use crate::routes::{AnyRoute, DroneRoute, Route, TruckRoute};
-use crate::solutions::Solution;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use smallvec::smallvec;
+
+use crate::cli::MoveOrder;
+use crate::config::{self, VehicleKind};
+use crate::evaluate::{EvaluationReport, VehicleTotals};
+use crate::routes::{AnyRoute, DroneRoute, Route, TabuAttribute, TruckRoute};
+use crate::solutions::{self, Solution};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum Neighborhood {
-    Move10,
-    Move11,
-    Move20,
-    Move21,
-    Move22,
+    /// Exchange of a segment of `k` consecutive customers with a segment of `l` consecutive customers (or relocation
+    /// of the first segment when `l` is 0), see [`Route::move_segments`].
+    Move(usize, usize),
     TwoOpt,
     EjectionChain,
+    /// Relocation of a segment of 1 to 3 consecutive customers, optionally reversed.
+    OrOpt,
+    /// Relocation of a whole route, as is, to another vehicle of the same type.
+    RouteRelocate,
+    /// Split of a route into two routes of the same vehicle (with a return to the depot in between), or merge of two
+    /// routes of the same vehicle into one.
+    SplitMerge,
+    /// Reassignment of all drone routes to the drones, equalizing their working times.
+    DroneRebalance,
     // CrossExchange,
 }
 
@@ -23,19 +45,286 @@ impl Display for Neighborhood {
             f,
             "{}",
             match self {
-                Self::Move10 => "Move (1, 0)".to_string(),
-                Self::Move11 => "Move (1, 1)".to_string(),
-                Self::Move20 => "Move (2, 0)".to_string(),
-                Self::Move21 => "Move (2, 1)".to_string(),
-                Self::Move22 => "Move (2, 2)".to_string(),
+                Self::Move(k, l) => format!("Move ({k}, {l})"),
                 Self::TwoOpt => "2-opt".to_string(),
                 Self::EjectionChain => "Ejection-chain".to_string(),
+                Self::OrOpt => "Or-opt".to_string(),
+                Self::RouteRelocate => "Route relocate".to_string(),
+                Self::SplitMerge => "Split/merge".to_string(),
+                Self::DroneRebalance => "Drone rebalance".to_string(),
                 // Self::CrossExchange => "Cross-exchange".to_string(),
             }
         )
     }
 }
 
+impl Neighborhood {
+    /// Name of this neighborhood in `--neighborhoods`.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Move(k, l) => format!("move-{k}-{l}"),
+            Self::TwoOpt => "2-opt".to_string(),
+            Self::EjectionChain => "ejection-chain".to_string(),
+            Self::OrOpt => "or-opt".to_string(),
+            Self::RouteRelocate => "route-relocate".to_string(),
+            Self::SplitMerge => "split-merge".to_string(),
+            Self::DroneRebalance => "drone-rebalance".to_string(),
+        }
+    }
+}
+
+/// Parse a neighborhood searched by the tabu search from its name in `--neighborhoods`, e.g. `move-3-2` or `2-opt`.
+impl FromStr for Neighborhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "2-opt" => Ok(Self::TwoOpt),
+            "or-opt" => Ok(Self::OrOpt),
+            "route-relocate" => Ok(Self::RouteRelocate),
+            "split-merge" => Ok(Self::SplitMerge),
+            "drone-rebalance" => Ok(Self::DroneRebalance),
+            name => {
+                let segments = name
+                    .strip_prefix("move-")
+                    .and_then(|segments| segments.split_once('-'))
+                    .ok_or_else(|| format!("Unknown neighborhood {name:?}"))?;
+                let k = segments.0.parse::<usize>().map_err(|e| e.to_string())?;
+                let l = segments.1.parse::<usize>().map_err(|e| e.to_string())?;
+                if k == 0 {
+                    return Err(format!(
+                        "Move ({k}, {l}) must move at least 1 customer, use move-{l}-{k} instead"
+                    ));
+                }
+
+                Ok(Self::Move(k, l))
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Neighborhood {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Neighborhood> for String {
+    fn from(value: Neighborhood) -> Self {
+        value.name()
+    }
+}
+
+/// Total number of candidate solutions evaluated by all neighborhoods so far.
+pub static EVALUATED_MOVES: AtomicUsize = AtomicUsize::new(0);
+
+/// Orders the candidate moves of a neighborhood, so that the most promising ones are evaluated first. Since the search
+/// keeps the first of equally good moves, the order decides between them (and matters for any search that stops
+/// before evaluating all candidates).
+pub trait MoveScorer: Send + Sync {
+    /// Score of the move of `neighborhood` relocating the customers `tabu`, which changes the total length of the
+    /// routes it modifies by `distance_delta`. Moves with lower scores are evaluated first.
+    fn score(&self, neighborhood: Neighborhood, tabu: &TabuAttribute, distance_delta: f64) -> f64;
+}
+
+/// Orders moves by the change of the total length of the routes they modify.
+pub struct ArcLengthScorer;
+
+impl MoveScorer for ArcLengthScorer {
+    fn score(&self, _: Neighborhood, _: &TabuAttribute, distance_delta: f64) -> f64 {
+        distance_delta
+    }
+}
+
+/// Scorer selected by `--move-order`, or `None` to evaluate moves in the order they are generated.
+fn _move_scorer() -> Option<Box<dyn MoveScorer>> {
+    match config::with_current(|config| config.move_order) {
+        MoveOrder::Natural => None,
+        MoveOrder::ArcLength => Some(Box::new(ArcLengthScorer)),
+    }
+}
+
+/// Sort `candidates` by the score of [`_move_scorer`], where `delta` gives the change of the total length of the routes
+/// modified by a candidate and its tabu attribute.
+fn _order_moves<T>(neighborhood: Neighborhood, candidates: &mut [T], delta: impl Fn(&T) -> (f64, &TabuAttribute)) {
+    if let Some(scorer) = _move_scorer() {
+        let score = |candidate: &T| {
+            let (distance_delta, tabu) = delta(candidate);
+            scorer.score(neighborhood, tabu, distance_delta)
+        };
+        candidates.sort_by(|a, b| score(a).total_cmp(&score(b)));
+    }
+}
+
+/// The "hot" customers a search is focused on (e.g. those perturbed by a restart): only the moves relocating at least
+/// one of them are evaluated, so that regions of the solution left untouched are not explored again.
+///
+/// It may also hold "cold" routes (identified by address, see [`Self::with_cold_routes`]): the moves between a cold
+/// source route and a cold partner route, and the intra-route moves of cold source routes, are skipped before being
+/// generated.
+#[derive(Clone, Debug)]
+pub struct FocusList {
+    hot: Vec<bool>,
+    cold_sources: HashSet<*const ()>,
+    cold_partners: HashSet<*const ()>,
+}
+
+impl FocusList {
+    pub fn new(customers: impl IntoIterator<Item = usize>) -> Self {
+        let mut hot = vec![false; config::current().customers_count + 1];
+        for c in customers {
+            hot[c] = true;
+        }
+
+        Self {
+            hot,
+            cold_sources: HashSet::new(),
+            cold_partners: HashSet::new(),
+        }
+    }
+
+    /// Mark the routes of `looked` as cold, `looked` being a solution in which a neighborhood found no improving move:
+    /// the routes of its decisive vehicles (see [`Neighborhood::decisive_vehicles`]) as sources and all of its routes
+    /// as partners. The caller must keep `looked` alive while this list is in use, so that the addresses of its routes
+    /// are not reused.
+    pub fn with_cold_routes(mut self, looked: &Solution) -> Self {
+        fn _addresses<R>(routes: &[Arc<R>]) -> impl Iterator<Item = *const ()> {
+            routes.iter().map(_address)
+        }
+
+        for (vehicle, is_truck) in Neighborhood::decisive_vehicles(looked) {
+            if is_truck {
+                self.cold_sources.extend(_addresses(&looked.truck_routes[vehicle]));
+            } else {
+                self.cold_sources.extend(_addresses(&looked.drone_routes[vehicle]));
+            }
+        }
+        self.cold_partners
+            .extend(looked.truck_routes.iter().flat_map(|r| _addresses(r)));
+        self.cold_partners
+            .extend(looked.drone_routes.iter().flat_map(|r| _addresses(r)));
+
+        self
+    }
+
+    /// The customers whose vehicle, predecessor or successor in `after` differ from those in `before`.
+    pub fn perturbed(before: &Solution, after: &Solution) -> Self {
+        fn _positions(solution: &Solution) -> Vec<(VehicleKind, usize, usize, usize)> {
+            let mut result = vec![(VehicleKind::Truck, 0, 0, 0); config::current().customers_count + 1];
+            let mut record = |kind, vehicle, customers: &[usize]| {
+                for i in 1..customers.len() - 1 {
+                    result[customers[i]] = (kind, vehicle, customers[i - 1], customers[i + 1]);
+                }
+            };
+            for (truck, routes) in solution.truck_routes.iter().enumerate() {
+                for route in routes {
+                    record(VehicleKind::Truck, truck, &route.data().customers);
+                }
+            }
+            for (drone, routes) in solution.drone_routes.iter().enumerate() {
+                for route in routes {
+                    record(VehicleKind::Drone, drone, &route.data().customers);
+                }
+            }
+
+            result
+        }
+
+        let (before, after) = (_positions(before), _positions(after));
+        Self::new((1..=config::current().customers_count).filter(|&c| before[c] != after[c]))
+    }
+
+    pub fn contains(&self, customer: usize) -> bool {
+        self.hot[customer]
+    }
+
+    /// Whether one of the customers of `route` is hot.
+    fn _touches<R>(&self, route: &Arc<R>) -> bool
+    where
+        R: Route,
+    {
+        let customers = &route.data().customers;
+        customers[1..customers.len() - 1].iter().any(|&c| self.hot[c])
+    }
+
+    /// Number of hot customers.
+    pub fn len(&self) -> usize {
+        self.hot.iter().filter(|&&h| h).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.hot.contains(&true)
+    }
+}
+
+/// Candidates of an inter-route neighborhood for a pair of routes, see [`Route::inter_route`].
+type _Candidates<RI, RJ> = Vec<(Option<Arc<RI>>, Option<Arc<RJ>>, TabuAttribute)>;
+
+/// Candidates of the inter-route neighborhoods generated for a pair of routes. The entry holds both routes, so that
+/// their addresses cannot be reused by other routes while it is cached.
+struct _CachedCandidates {
+    _routes: (Arc<dyn Any>, Arc<dyn Any>),
+    candidates: Rc<dyn Any>,
+}
+
+thread_local! {
+    /// Candidates of the inter-route neighborhoods per pair of routes (identified by address) and neighborhood, kept
+    /// across iterations of the search running on this thread. Routes are immutable and shared between successive
+    /// solutions, so the candidates of the pairs of routes a move leaves untouched remain valid and are not generated
+    /// again. The entries of routes no longer in the solution searched are evicted, see [`_evict_candidates`].
+    static CANDIDATES: RefCell<HashMap<(*const (), *const (), Neighborhood), _CachedCandidates>> =
+        RefCell::new(HashMap::new());
+}
+
+fn _address<R>(route: &Arc<R>) -> *const () {
+    Arc::as_ptr(route).cast()
+}
+
+/// Evict the cached candidates of the pairs of routes not both in `solution`.
+fn _evict_candidates(solution: &Solution) {
+    let routes = solution
+        .truck_routes
+        .iter()
+        .flatten()
+        .map(_address)
+        .chain(solution.drone_routes.iter().flatten().map(_address))
+        .collect::<HashSet<_>>();
+    CANDIDATES.with_borrow_mut(|cache| cache.retain(|(i, j, _), _| routes.contains(i) && routes.contains(j)));
+}
+
+/// The candidates of `neighborhood` for `route_i` and `route_j`, from the cache or from `generate`.
+fn _cached_candidates<RI, RJ>(
+    route_i: &Arc<RI>,
+    route_j: &Arc<RJ>,
+    neighborhood: Neighborhood,
+    generate: impl FnOnce() -> _Candidates<RI, RJ>,
+) -> Rc<_Candidates<RI, RJ>>
+where
+    RI: Route + 'static,
+    RJ: Route + 'static,
+{
+    let key = (_address(route_i), _address(route_j), neighborhood);
+    if let Some(cached) = CANDIDATES.with_borrow(|cache| cache.get(&key).map(|entry| entry.candidates.clone()))
+        && let Ok(candidates) = cached.downcast::<_Candidates<RI, RJ>>()
+    {
+        return candidates;
+    }
+
+    let candidates = Rc::new(generate());
+    CANDIDATES.with_borrow_mut(|cache| {
+        cache.insert(
+            key,
+            _CachedCandidates {
+                _routes: (route_i.clone(), route_j.clone()),
+                candidates: candidates.clone(),
+            },
+        )
+    });
+    candidates
+}
+
 /// Opposite of `Vec::swap_remove` - push an element to the end of the vector
 /// and swap it with the element at the given index.
 fn _swap_push<T>(vec: &mut Vec<T>, index: usize, element: T) {
@@ -44,17 +333,219 @@ fn _swap_push<T>(vec: &mut Vec<T>, index: usize, element: T) {
     vec.swap(index, l);
 }
 
+/// Evaluate the solutions obtained by applying `assign` to copies of the routes being searched for each of `moves`,
+/// in parallel on the rayon pool (see `--parallel-evaluation`), then update `state` with them in the order of `moves`
+/// as the sequential search would. The move selected thus does not depend on the number of threads.
+fn _evaluate_parallel<M>(
+    state: &mut _IterationState,
+    truck_cloned: &[Vec<Arc<TruckRoute>>],
+    drone_cloned: &[Vec<Arc<DroneRoute>>],
+    changed: &[(VehicleKind, usize)],
+    moves: &[M],
+    assign: impl Fn(&mut Vec<Vec<Arc<TruckRoute>>>, &mut Vec<Vec<Arc<DroneRoute>>>, &M) + Sync,
+    tabu: impl Fn(&M) -> &TabuAttribute,
+) where
+    M: Sync,
+{
+    // Worker threads do not inherit the config in scope
+    let config = config::current();
+    let totals = &state.totals;
+    let evaluated = moves
+        .par_iter()
+        .map(|m| {
+            config::scope(Arc::clone(&config), || {
+                let mut truck_routes = truck_cloned.to_vec();
+                let mut drone_routes = drone_cloned.to_vec();
+                assign(&mut truck_routes, &mut drone_routes, m);
+                let report = totals.report(&truck_routes, &drone_routes, changed);
+                (truck_routes, drone_routes, report)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for ((truck_routes, drone_routes, report), m) in evaluated.into_iter().zip(moves) {
+        let candidate = _Candidate::Delta {
+            truck_routes: &truck_routes,
+            drone_routes: &drone_routes,
+            report,
+        };
+        Neighborhood::_internal_update(state, &candidate, tabu(m));
+    }
+}
+
+/// A candidate solution of a neighborhood, as considered by [`Neighborhood::_internal_update`].
+enum _Candidate<'r> {
+    /// A solution constructed in full.
+    Full(&'r Solution),
+    /// The routes being searched with a move applied, and their report derived from the vehicle totals of the original
+    /// solution (see [`_IterationState::_candidate`]). The [`Solution`] is only constructed if the candidate is kept.
+    Delta {
+        truck_routes: &'r [Vec<Arc<TruckRoute>>],
+        drone_routes: &'r [Vec<Arc<DroneRoute>>],
+        report: EvaluationReport,
+    },
+}
+
+impl _Candidate<'_> {
+    const fn feasible(&self) -> bool {
+        match self {
+            Self::Full(solution) => solution.feasible,
+            Self::Delta { report, .. } => report.feasible,
+        }
+    }
+
+    fn cost(&self) -> f64 {
+        match self {
+            Self::Full(solution) => solution.cost(),
+            Self::Delta { report, .. } => solutions::penalized_cost(
+                report.working_time,
+                [
+                    report.energy_violation,
+                    report.capacity_violation,
+                    report.waiting_time_violation,
+                    report.fixed_time_violation,
+                    report.budget_violation,
+                ],
+            ),
+        }
+    }
+
+    /// The candidate as a solution. Routes whose values were derived (see [`Route::_derived`]) are evaluated in full
+    /// first, along with the solution holding them.
+    fn build(&self) -> Solution {
+        let (truck_routes, drone_routes) = match self {
+            Self::Full(solution) => (&solution.truck_routes[..], &solution.drone_routes[..]),
+            Self::Delta {
+                truck_routes,
+                drone_routes,
+                ..
+            } => (*truck_routes, *drone_routes),
+        };
+
+        fn _derived<R>(vehicle_routes: &[Vec<Arc<R>>]) -> bool
+        where
+            R: Route,
+        {
+            vehicle_routes.iter().flatten().any(|route| route._derived())
+        }
+
+        fn _exact<R>(vehicle_routes: &[Vec<Arc<R>>]) -> Vec<Vec<Arc<R>>>
+        where
+            R: Route,
+        {
+            vehicle_routes
+                .iter()
+                .map(|routes| routes.iter().map(R::_exact).collect())
+                .collect()
+        }
+
+        if _derived(truck_routes) || _derived(drone_routes) {
+            return Solution::new(_exact(truck_routes), _exact(drone_routes));
+        }
+
+        match self {
+            Self::Full(solution) => Solution::clone(solution),
+            Self::Delta {
+                truck_routes,
+                drone_routes,
+                report,
+            } => Solution::from_report(truck_routes.to_vec(), drone_routes.to_vec(), report.clone()),
+        }
+    }
+}
+
+/// Uniform sampling of one of the candidate solutions of a neighborhood by reservoir sampling, for simulated annealing.
+struct _Sampler {
+    rng: StdRng,
+    seen: usize,
+    chosen: Option<(Solution, TabuAttribute)>,
+}
+
+/// The `rank + 1` best admissible candidates of a neighborhood, sorted by cost, for the biased randomized selection
+/// of `--selection-bias`.
+struct _Ranked {
+    rank: usize,
+    candidates: Vec<(f64, Solution, TabuAttribute)>,
+}
+
+impl _Ranked {
+    /// The candidate of rank `rank`, wrapping around if there are fewer admissible candidates.
+    fn pick(mut self) -> Option<(Solution, TabuAttribute)> {
+        if self.candidates.is_empty() {
+            return None;
+        }
+
+        let index = self.rank % self.candidates.len();
+        let (_, solution, tabu) = self.candidates.swap_remove(index);
+        Some((solution, tabu))
+    }
+}
+
 struct _IterationState<'a> {
     pub original: &'a Solution,
-    pub tabu_list: &'a [Vec<usize>],
+    /// Vehicle totals of `original`, from which the candidates are evaluated.
+    pub totals: VehicleTotals,
+    pub tabu_list: &'a [TabuAttribute],
     pub aspiration_cost: &'a mut f64,
     pub min_cost: &'a mut f64,
     pub require_feasible: &'a mut bool,
-    pub result: &'a mut (Solution, Vec<usize>),
+    pub result: &'a mut (Solution, TabuAttribute),
+    /// When set, candidates are sampled instead of keeping the best non-tabu one, and `result` is left untouched.
+    pub sampler: Option<&'a mut _Sampler>,
+    /// When set, the admissible candidates are ranked instead of keeping the best one only, and `result` is left
+    /// untouched unless the aspiration criterion is met.
+    pub ranked: Option<&'a mut _Ranked>,
+    /// When set, only the moves relocating one of its customers are evaluated.
+    pub focus: Option<&'a FocusList>,
+}
+
+impl _IterationState<'_> {
+    /// The candidate with the routes `truck_routes` and `drone_routes`, which differ from those of the original
+    /// solution in the vehicles of `changed` only.
+    fn _candidate<'r>(
+        &self,
+        truck_routes: &'r [Vec<Arc<TruckRoute>>],
+        drone_routes: &'r [Vec<Arc<DroneRoute>>],
+        changed: &[(VehicleKind, usize)],
+    ) -> _Candidate<'r> {
+        _Candidate::Delta {
+            truck_routes,
+            drone_routes,
+            report: self.totals.report(truck_routes, drone_routes, changed),
+        }
+    }
+
+    fn _focused(&self, tabu: &TabuAttribute) -> bool {
+        self.focus.is_none_or(|focus| tabu.iter().any(|&c| focus.contains(c)))
+    }
+
+    /// Whether some move of `route` (intra-route, or to a new route) may be focused.
+    fn _focused_route<R>(&self, route: &Arc<R>) -> bool
+    where
+        R: Route,
+    {
+        self.focus
+            .is_none_or(|focus| focus._touches(route) && !focus.cold_sources.contains(&_address(route)))
+    }
+
+    /// Whether some move between `route_i` and `route_j` may be focused.
+    fn _focused_pair<RI, RJ>(&self, route_i: &Arc<RI>, route_j: &Arc<RJ>) -> bool
+    where
+        RI: Route,
+        RJ: Route,
+    {
+        self.focus.is_none_or(|focus| {
+            (focus._touches(route_i) || focus._touches(route_j))
+                && !(focus.cold_sources.contains(&_address(route_i))
+                    && focus.cold_partners.contains(&_address(route_j)))
+        })
+    }
 }
 
 impl Neighborhood {
-    fn _find_decisive_vehicle(solution: &Solution) -> (usize, bool) {
+    /// The vehicle with the longest working time, whose routes the neighborhoods move customers from, and whether it
+    /// is a truck.
+    pub fn decisive_vehicle(solution: &Solution) -> (usize, bool) {
         let mut max_time = f64::MIN;
         let mut vehicle = 0;
         let mut is_truck = true;
@@ -78,17 +569,75 @@ impl Neighborhood {
         (vehicle, is_truck)
     }
 
-    fn _internal_update(state: &mut _IterationState, solution: &Solution, tabu: &Vec<usize>) -> bool {
-        let feasible = solution.feasible;
+    /// The vehicles whose routes the neighborhoods move customers from, starting with the decisive one (see
+    /// [`Neighborhood::decisive_vehicle`]): the `--decisive-top-k` vehicles with the longest working times, and every
+    /// vehicle whose working time is less than `--decisive-epsilon` below the longest one.
+    pub fn decisive_vehicles(solution: &Solution) -> Vec<(usize, bool)> {
+        let config = config::current();
+        let mut vehicles = solution
+            .truck_working_time
+            .iter()
+            .enumerate()
+            .map(|(truck, &time)| (time, truck, true))
+            .chain(
+                solution
+                    .drone_working_time
+                    .iter()
+                    .enumerate()
+                    .map(|(drone, &time)| (time, drone, false)),
+            )
+            .collect::<Vec<_>>();
+
+        // Stable sort, so that ties are broken as in `decisive_vehicle`
+        vehicles.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let max_time = vehicles.first().map_or(0.0, |v| v.0);
+        vehicles
+            .into_iter()
+            .enumerate()
+            .take_while(|&(i, (time, _, _))| i < config.decisive_top_k || max_time - time < config.decisive_epsilon)
+            .map(|(_, (_, vehicle, is_truck))| (vehicle, is_truck))
+            .collect()
+    }
+
+    fn _internal_update(state: &mut _IterationState, candidate: &_Candidate, tabu: &TabuAttribute) -> bool {
+        EVALUATED_MOVES.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(sampler) = state.sampler.as_deref_mut() {
+            sampler.seen += 1;
+            if sampler.rng.random_range(0..sampler.seen) == 0 {
+                sampler.chosen = Some((candidate.build(), tabu.clone()));
+            }
+
+            return false;
+        }
+
+        let feasible = candidate.feasible();
         if *state.require_feasible && !feasible {
             return false;
         }
 
-        let cost = solution.cost();
+        let cost = candidate.cost();
         let new_best_global_solution = cost < *state.aspiration_cost && feasible;
+        if !new_best_global_solution
+            && !*state.require_feasible
+            && let Some(ranked) = state.ranked.as_deref_mut()
+        {
+            if state.tabu_list.contains(tabu)
+                || ranked.candidates.len() > ranked.rank && cost >= ranked.candidates[ranked.rank].0
+            {
+                return false;
+            }
+
+            let index = ranked.candidates.partition_point(|(c, _, _)| *c <= cost);
+            ranked.candidates.insert(index, (cost, candidate.build(), tabu.clone()));
+            ranked.candidates.truncate(ranked.rank + 1);
+            return true;
+        }
+
         if new_best_global_solution || (!state.tabu_list.contains(tabu) && cost < *state.min_cost) {
             *state.min_cost = cost;
-            *state.result = (solution.clone(), tabu.clone());
+            *state.result = (candidate.build(), tabu.clone());
             if new_best_global_solution {
                 *state.aspiration_cost = cost;
                 *state.require_feasible = true;
@@ -103,25 +652,25 @@ impl Neighborhood {
     fn _inter_route_internal<RI>(
         self,
         state: &mut _IterationState,
-        mut truck_cloned: Vec<Vec<Rc<TruckRoute>>>,
-        mut drone_cloned: Vec<Vec<Rc<DroneRoute>>>,
+        mut truck_cloned: Vec<Vec<Arc<TruckRoute>>>,
+        mut drone_cloned: Vec<Vec<Arc<DroneRoute>>>,
         vehicle_i: usize,
-    ) -> (Vec<Vec<Rc<TruckRoute>>>, Vec<Vec<Rc<DroneRoute>>>)
+    ) -> (Vec<Vec<Arc<TruckRoute>>>, Vec<Vec<Arc<DroneRoute>>>)
     where
-        RI: Route,
+        RI: Route + 'static,
     {
         fn iterate_route_j<RI, RJ>(
             neighborhood: Neighborhood,
             state: &mut _IterationState,
-            mut truck_cloned: Vec<Vec<Rc<TruckRoute>>>,
-            mut drone_cloned: Vec<Vec<Rc<DroneRoute>>>,
+            mut truck_cloned: Vec<Vec<Arc<TruckRoute>>>,
+            mut drone_cloned: Vec<Vec<Arc<DroneRoute>>>,
             vehicle_i: usize,
             route_idx_i: usize,
-            route_i: &Rc<RI>,
-        ) -> (Vec<Vec<Rc<TruckRoute>>>, Vec<Vec<Rc<DroneRoute>>>)
+            route_i: &Arc<RI>,
+        ) -> (Vec<Vec<Arc<TruckRoute>>>, Vec<Vec<Arc<DroneRoute>>>)
         where
-            RI: Route,
-            RJ: Route,
+            RI: Route + 'static,
+            RJ: Route + 'static,
         {
             let original_routes_i = RI::get_correct_route(&state.original.truck_routes, &state.original.drone_routes);
             let original_routes_j = RJ::get_correct_route(&state.original.truck_routes, &state.original.drone_routes);
@@ -130,46 +679,60 @@ impl Neighborhood {
             for (vehicle_j, routes_j) in original_routes_j.iter().enumerate() {
                 for (route_idx_j, route_j) in routes_j.iter().enumerate() {
                     // Dirty trick to compare 2 routes (because each customer can only be served exactly once)
-                    if route_i.data().customers[1] == route_j.data().customers[1] {
+                    if route_i.data().customers[1] == route_j.data().customers[1]
+                        || !state._focused_pair(route_i, route_j)
+                    {
                         continue;
                     }
 
-                    let mut neighbors = route_i.inter_route(route_j.clone(), neighborhood);
-                    let asymmetric = neighborhood == Neighborhood::Move10
-                        || neighborhood == Neighborhood::Move20
-                        || neighborhood == Neighborhood::Move21;
-                    if asymmetric {
-                        neighbors.extend(
-                            route_j
-                                .inter_route(route_i.clone(), neighborhood)
-                                .into_iter()
-                                .map(|t| (t.1, t.0, t.2)),
-                        );
-                    }
-
-                    for (new_route_i, new_route_j, tabu) in neighbors {
-                        if let Some(ref new_route_i) = new_route_i
-                            && RI::single_customer()
-                            && new_route_i.data().customers.len() != 3
-                        {
-                            continue;
-                        }
-                        if let Some(ref new_route_j) = new_route_j
-                            && RJ::single_customer()
-                            && new_route_j.data().customers.len() != 3
-                        {
-                            continue;
+                    let neighbors = _cached_candidates(route_i, route_j, neighborhood, || {
+                        let mut neighbors = route_i.inter_route(route_j.clone(), neighborhood);
+                        let asymmetric = matches!(neighborhood, Neighborhood::Move(k, l) if k != l)
+                            || neighborhood == Neighborhood::OrOpt;
+                        if asymmetric {
+                            neighbors.extend(
+                                route_j
+                                    .inter_route(route_i.clone(), neighborhood)
+                                    .into_iter()
+                                    .map(|t| (t.1, t.0, t.2)),
+                            );
                         }
 
-                        // Temporary assign new routes.
-                        // Make use of `swap_remove` due to its O(1) complexity and the route order
-                        // of each vehicle is not important.
+                        let old_distance = route_i.distance() + route_j.distance();
+                        _order_moves(neighborhood, &mut neighbors, |(new_route_i, new_route_j, tabu)| {
+                            let new_distance = new_route_i.as_ref().map_or(0.0, |r| r.distance())
+                                + new_route_j.as_ref().map_or(0.0, |r| r.distance());
+                            (new_distance - old_distance, tabu)
+                        });
+                        neighbors
+                    });
+
+                    let candidates = neighbors
+                        .iter()
+                        .filter(|(new_route_i, new_route_j, tabu)| {
+                            state._focused(tabu)
+                                && new_route_i.as_ref().is_none_or(|r| {
+                                    r._length_respected() && r._assignable(vehicle_i) && r._arcs_respected()
+                                })
+                                && new_route_j.as_ref().is_none_or(|r| {
+                                    r._length_respected() && r._assignable(vehicle_j) && r._arcs_respected()
+                                })
+                        })
+                        .collect::<Vec<_>>();
+
+                    // Temporary assign new routes, returning the index of `route_j` afterwards.
+                    // Make use of `swap_remove` due to its O(1) complexity and the route order
+                    // of each vehicle is not important.
+                    let assign = |truck_cloned: &mut Vec<Vec<Arc<TruckRoute>>>,
+                                  drone_cloned: &mut Vec<Vec<Arc<DroneRoute>>>,
+                                  new_route_i: &Option<Arc<RI>>,
+                                  new_route_j: &Option<Arc<RJ>>| {
                         let mut route_idx_j_after_swap_remove = route_idx_j;
 
                         // Wrap code blocks to drop the mutable references afterwards
                         {
-                            let cloned_routes_i = RI::get_correct_route_mut(&mut truck_cloned, &mut drone_cloned);
-                            match &new_route_i {
+                            let cloned_routes_i = RI::get_correct_route_mut(truck_cloned, drone_cloned);
+                            match new_route_i {
                                 Some(new_route_i) => {
                                     cloned_routes_i[vehicle_i][route_idx_i] = new_route_i.clone();
                                 }
@@ -184,8 +747,8 @@ impl Neighborhood {
                         }
 
                         {
-                            let cloned_routes_j = RJ::get_correct_route_mut(&mut truck_cloned, &mut drone_cloned);
-                            match &new_route_j {
+                            let cloned_routes_j = RJ::get_correct_route_mut(truck_cloned, drone_cloned);
+                            match new_route_j {
                                 Some(new_route_j) => {
                                     cloned_routes_j[vehicle_j][route_idx_j_after_swap_remove] = new_route_j.clone();
                                 }
@@ -195,16 +758,33 @@ impl Neighborhood {
                             }
                         }
 
-                        // Construct the new solution: move `truck_cloned` and `drone_cloned` to the temp solution
-                        // and get them back later during restoration
-                        let s = Solution::new(truck_cloned, drone_cloned);
+                        route_idx_j_after_swap_remove
+                    };
+
+                    let changed = [(RI::vehicle_kind(), vehicle_i), (RJ::vehicle_kind(), vehicle_j)];
+                    if config::with_current(|config| config.parallel_evaluation) {
+                        _evaluate_parallel(
+                            state,
+                            &truck_cloned,
+                            &drone_cloned,
+                            &changed,
+                            &candidates,
+                            |truck, drone, (new_route_i, new_route_j, _)| {
+                                assign(truck, drone, new_route_i, new_route_j);
+                            },
+                            |(_, _, tabu)| tabu,
+                        );
+                        continue;
+                    }
+
+                    for (new_route_i, new_route_j, tabu) in candidates {
+                        let route_idx_j_after_swap_remove =
+                            assign(&mut truck_cloned, &mut drone_cloned, new_route_i, new_route_j);
 
-                        Neighborhood::_internal_update(state, &s, &tabu);
+                        let candidate = state._candidate(&truck_cloned, &drone_cloned, &changed);
+                        Neighborhood::_internal_update(state, &candidate, tabu);
 
                         // Restore old routes
-                        truck_cloned = s.truck_routes;
-                        drone_cloned = s.drone_routes;
-
                         {
                             let cloned_routes_j = RJ::get_correct_route_mut(&mut truck_cloned, &mut drone_cloned);
                             match new_route_j {
@@ -267,30 +847,50 @@ impl Neighborhood {
     fn _inter_route_extract_internal<RI>(
         self,
         state: &mut _IterationState,
-        mut truck_cloned: Vec<Vec<Rc<TruckRoute>>>,
-        mut drone_cloned: Vec<Vec<Rc<DroneRoute>>>,
+        mut truck_cloned: Vec<Vec<Arc<TruckRoute>>>,
+        mut drone_cloned: Vec<Vec<Arc<DroneRoute>>>,
         vehicle_i: usize,
-    ) -> (Vec<Vec<Rc<TruckRoute>>>, Vec<Vec<Rc<DroneRoute>>>)
+    ) -> (Vec<Vec<Arc<TruckRoute>>>, Vec<Vec<Arc<DroneRoute>>>)
     where
         RI: Route,
     {
         fn iterate_route_j_append<RI, RJ>(
             neighborhood: Neighborhood,
             state: &mut _IterationState,
-            mut truck_cloned: Vec<Vec<Rc<TruckRoute>>>,
-            mut drone_cloned: Vec<Vec<Rc<DroneRoute>>>,
+            mut truck_cloned: Vec<Vec<Arc<TruckRoute>>>,
+            mut drone_cloned: Vec<Vec<Arc<DroneRoute>>>,
             vehicle_i: usize,
             route_idx_i: usize,
-            route_i: &Rc<RI>,
-        ) -> (Vec<Vec<Rc<TruckRoute>>>, Vec<Vec<Rc<DroneRoute>>>)
+            route_i: &Arc<RI>,
+        ) -> (Vec<Vec<Arc<TruckRoute>>>, Vec<Vec<Arc<DroneRoute>>>)
         where
             RI: Route,
             RJ: Route,
         {
             let original_routes_j = RJ::get_correct_route(&state.original.truck_routes, &state.original.drone_routes);
+            if original_routes_j.is_empty() || !state._focused_route(route_i) {
+                // No vehicle of this type to append to (e.g. when solving without drones), or no customer to extract
+                // in focus, skip building candidates
+                return (truck_cloned, drone_cloned);
+            }
+
+            let mut neighbors = route_i.inter_route_extract::<RJ>(neighborhood);
+            _order_moves(neighborhood, &mut neighbors, |(new_route_i, new_route_j, tabu)| {
+                (
+                    new_route_i.distance() + new_route_j.distance() - route_i.distance(),
+                    tabu,
+                )
+            });
 
-            for (new_route_i, new_route_j, tabu) in route_i.inter_route_extract::<RJ>(neighborhood) {
-                if RJ::single_customer() && new_route_j.data().customers.len() != 3 {
+            for (new_route_i, new_route_j, tabu) in neighbors {
+                if !state._focused(&tabu) {
+                    continue;
+                }
+                if !new_route_i._length_respected()
+                    || !new_route_j._length_respected()
+                    || !new_route_i._arcs_respected()
+                    || !new_route_j._arcs_respected()
+                {
                     continue;
                 }
 
@@ -300,7 +900,7 @@ impl Neighborhood {
                 }
 
                 for vehicle_j in 0..original_routes_j.len() {
-                    if RJ::single_route() && !original_routes_j[vehicle_j].is_empty() {
+                    if original_routes_j[vehicle_j].len() >= RJ::max_routes() || !new_route_j._assignable(vehicle_j) {
                         continue;
                     }
 
@@ -309,14 +909,11 @@ impl Neighborhood {
                         cloned_routes_j[vehicle_j].push(new_route_j.clone());
                     }
 
-                    let s = Solution::new(truck_cloned, drone_cloned);
-
-                    Neighborhood::_internal_update(state, &s, &tabu);
+                    let changed = [(RI::vehicle_kind(), vehicle_i), (RJ::vehicle_kind(), vehicle_j)];
+                    let candidate = state._candidate(&truck_cloned, &drone_cloned, &changed);
+                    Neighborhood::_internal_update(state, &candidate, &tabu);
 
                     // Restore old routes
-                    truck_cloned = s.truck_routes;
-                    drone_cloned = s.drone_routes;
-
                     let cloned_routes_j = RJ::get_correct_route_mut(&mut truck_cloned, &mut drone_cloned);
                     cloned_routes_j[vehicle_j].pop();
                 }
@@ -353,6 +950,143 @@ impl Neighborhood {
         (truck_cloned, drone_cloned)
     }
 
+    fn _route_relocate_internal<R>(
+        state: &mut _IterationState,
+        mut truck_cloned: Vec<Vec<Arc<TruckRoute>>>,
+        mut drone_cloned: Vec<Vec<Arc<DroneRoute>>>,
+        vehicle_i: usize,
+    ) -> (Vec<Vec<Arc<TruckRoute>>>, Vec<Vec<Arc<DroneRoute>>>)
+    where
+        R: Route,
+    {
+        let original_routes = R::get_correct_route(&state.original.truck_routes, &state.original.drone_routes);
+        for (route_idx, route) in original_routes[vehicle_i].iter().enumerate() {
+            let mut tabu = TabuAttribute::from_slice(&route.data().customers[1..route.data().customers.len() - 1]);
+            tabu.sort();
+            if !state._focused(&tabu) {
+                continue;
+            }
+
+            for vehicle_j in 0..original_routes.len() {
+                if vehicle_j == vehicle_i
+                    || original_routes[vehicle_j].len() >= R::max_routes()
+                    || !route._assignable(vehicle_j)
+                {
+                    continue;
+                }
+
+                // The route itself is moved, so its cached values are kept
+                {
+                    let cloned_routes = R::get_correct_route_mut(&mut truck_cloned, &mut drone_cloned);
+                    let moved = cloned_routes[vehicle_i].swap_remove(route_idx);
+                    cloned_routes[vehicle_j].push(moved);
+                }
+
+                let changed = [(R::vehicle_kind(), vehicle_i), (R::vehicle_kind(), vehicle_j)];
+                let candidate = state._candidate(&truck_cloned, &drone_cloned, &changed);
+                Self::_internal_update(state, &candidate, &tabu);
+
+                // Restore old routes
+                let cloned_routes = R::get_correct_route_mut(&mut truck_cloned, &mut drone_cloned);
+                let moved = cloned_routes[vehicle_j].pop().unwrap();
+                _swap_push(&mut cloned_routes[vehicle_i], route_idx, moved);
+            }
+        }
+
+        (truck_cloned, drone_cloned)
+    }
+
+    fn _split_merge_internal<R>(
+        state: &mut _IterationState,
+        mut truck_cloned: Vec<Vec<Arc<TruckRoute>>>,
+        mut drone_cloned: Vec<Vec<Arc<DroneRoute>>>,
+        vehicle_i: usize,
+    ) -> (Vec<Vec<Arc<TruckRoute>>>, Vec<Vec<Arc<DroneRoute>>>)
+    where
+        R: Route,
+    {
+        let original_routes =
+            &R::get_correct_route(&state.original.truck_routes, &state.original.drone_routes)[vehicle_i];
+        let mut candidates = vec![];
+
+        // Split a route in two, returning to the depot between 2 consecutive customers
+        if original_routes.len() < R::max_routes() {
+            for (route_idx, route) in original_routes.iter().enumerate() {
+                let customers = &route.data().customers;
+                for idx in 2..customers.len() - 1 {
+                    let mut head = customers[..idx].to_vec();
+                    head.push(0);
+                    let mut tail = vec![0];
+                    tail.extend_from_slice(&customers[idx..]);
+
+                    let mut routes = original_routes.clone();
+                    routes[route_idx] = R::new(head);
+                    routes.push(R::new(tail));
+                    candidates.push((routes, smallvec![customers[idx - 1], customers[idx]]));
+                }
+            }
+        }
+
+        // Merge 2 routes in either order, skipping the return to the depot between them
+        for (route_idx_i, route_i) in original_routes.iter().enumerate() {
+            for (route_idx_j, route_j) in original_routes.iter().enumerate() {
+                let (customers_i, customers_j) = (&route_i.data().customers, &route_j.data().customers);
+                if route_idx_i == route_idx_j || customers_i.len() + customers_j.len() - 4 > R::max_customers() {
+                    continue;
+                }
+
+                let mut merged = customers_i[..customers_i.len() - 1].to_vec();
+                merged.extend_from_slice(&customers_j[1..]);
+
+                let mut routes = original_routes.clone();
+                routes[route_idx_i] = R::new(merged);
+                routes.swap_remove(route_idx_j);
+                candidates.push((routes, smallvec![customers_i[customers_i.len() - 2], customers_j[1]]));
+            }
+        }
+
+        for (routes, mut tabu) in candidates {
+            tabu.sort();
+            if !state._focused(&tabu) || !routes.iter().all(|r| r._length_respected() && r._arcs_respected()) {
+                continue;
+            }
+
+            R::get_correct_route_mut(&mut truck_cloned, &mut drone_cloned)[vehicle_i] = routes;
+
+            let candidate = state._candidate(&truck_cloned, &drone_cloned, &[(R::vehicle_kind(), vehicle_i)]);
+            Self::_internal_update(state, &candidate, &tabu);
+        }
+
+        R::get_correct_route_mut(&mut truck_cloned, &mut drone_cloned)[vehicle_i].clone_from(original_routes);
+        (truck_cloned, drone_cloned)
+    }
+
+    /// Reassign the drone routes to the drones by the branch and bound assigning them at the end of the search (see
+    /// [`Solution::refine_drone_schedule`]), relocating the customers of the routes changing drone.
+    fn _drone_rebalance_internal(state: &mut _IterationState) {
+        let Some(drone_routes) = state.original.rebalanced_drone_routes() else {
+            return;
+        };
+
+        let mut tabu = TabuAttribute::new();
+        for (drone, routes) in drone_routes.iter().enumerate() {
+            for route in routes {
+                if !state.original.drone_routes[drone].iter().any(|r| Arc::ptr_eq(r, route)) {
+                    let customers = &route.data().customers;
+                    tabu.extend_from_slice(&customers[1..customers.len() - 1]);
+                }
+            }
+        }
+
+        tabu.sort();
+        if tabu.is_empty() || !state._focused(&tabu) {
+            return;
+        }
+
+        let s = Solution::new(state.original.truck_routes.clone(), drone_routes);
+        Self::_internal_update(state, &_Candidate::Full(&s), &tabu);
+    }
+
     fn _ejection_chain_internal(self, state: &mut _IterationState) {
         #[derive(Clone)]
         struct _IndexingHelper {
@@ -392,6 +1126,16 @@ impl Neighborhood {
                     == self.route_index(second_vehicle, second_route).customers()[1]
             }
 
+            fn assignable(&self, vehicle: usize, route: &AnyRoute) -> bool {
+                let assignable = if vehicle < self.truck_routes.len() {
+                    route._assignable(vehicle)
+                } else {
+                    route._assignable(vehicle - self.truck_routes.len())
+                };
+
+                assignable && route._length_respected() && route._arcs_respected()
+            }
+
             fn update(&mut self, vehicle: usize, route_idx: usize, new_route: AnyRoute) {
                 if vehicle < self.truck_routes.len() {
                     self.truck_routes[vehicle][route_idx] = new_route;
@@ -431,6 +1175,16 @@ impl Neighborhood {
                                     if new_route_i.is_none() {
                                         continue; // Avoid changing route configuration
                                     }
+                                    if !state._focused(&tabu) {
+                                        continue;
+                                    }
+
+                                    if !indexer.assignable(vehicle_j, &new_route_j)
+                                        || !indexer.assignable(vehicle_k, &new_route_k)
+                                        || new_route_i.as_ref().is_some_and(|r| !indexer.assignable(vehicle_i, r))
+                                    {
+                                        continue;
+                                    }
 
                                     let mut new_indexer = indexer.clone();
                                     new_indexer.update(vehicle_k, route_idx_k, new_route_k.clone());
@@ -451,7 +1205,7 @@ impl Neighborhood {
                                     }
 
                                     let s = AnyRoute::to_solution(new_indexer.truck_routes, new_indexer.drone_routes);
-                                    if Self::_internal_update(state, &s, &tabu) {
+                                    if Self::_internal_update(state, &_Candidate::Full(&s), &tabu) {
                                         // eprintln!(
                                         //     "Ejection-chain ({:?} {:?} {:?})\n{:?}\n{:?}\n->\n{:?}\n{:?}",
                                         //     indexer.route_index(vehicle_i, route_idx_i),
@@ -478,51 +1232,96 @@ impl Neighborhood {
     pub fn inter_route(
         self,
         solution: &Solution,
-        tabu_list: &[Vec<usize>],
+        tabu_list: &[TabuAttribute],
+        aspiration_cost: f64,
+        focus: Option<&FocusList>,
+    ) -> (Solution, TabuAttribute) {
+        self._inter_route(solution, tabu_list, aspiration_cost, None, None, focus)
+    }
+
+    fn _inter_route(
+        self,
+        solution: &Solution,
+        tabu_list: &[TabuAttribute],
         mut aspiration_cost: f64,
-    ) -> (Solution, Vec<usize>) {
-        let (vehicle_i, is_truck) = Self::_find_decisive_vehicle(solution);
+        sampler: Option<&mut _Sampler>,
+        ranked: Option<&mut _Ranked>,
+        focus: Option<&FocusList>,
+    ) -> (Solution, TabuAttribute) {
+        _evict_candidates(solution);
+        let vehicles = Self::decisive_vehicles(solution);
 
         let mut truck_cloned = solution.truck_routes.clone();
         let mut drone_cloned = solution.drone_routes.clone();
 
         let mut min_cost = f64::MAX;
         let mut require_feasible = false;
-        let mut result = (solution.clone(), vec![]);
+        let mut result = (solution.clone(), TabuAttribute::new());
 
         let mut state = _IterationState {
             original: solution,
+            totals: VehicleTotals::new(solution),
             tabu_list,
             aspiration_cost: &mut aspiration_cost,
             min_cost: &mut min_cost,
             require_feasible: &mut require_feasible,
             result: &mut result,
+            sampler,
+            ranked,
+            focus,
         };
 
         match self {
-            Self::Move10
-            | Self::Move11
-            | Self::Move20
-            | Self::Move21
-            | Self::Move22
-            | Self::TwoOpt
-            // | Self::CrossExchange
-            => {
-                (truck_cloned, drone_cloned) = if is_truck {
-                    self._inter_route_internal::<TruckRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i)
-                } else {
-                    self._inter_route_internal::<DroneRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i)
-                };
+            Self::EjectionChain => {
+                self._ejection_chain_internal(&mut state);
+            }
 
-                if is_truck {
-                    self._inter_route_extract_internal::<TruckRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i);
-                } else {
-                    self._inter_route_extract_internal::<DroneRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i);
+            Self::DroneRebalance => {
+                if vehicles.iter().any(|&(_, is_truck)| !is_truck) {
+                    Self::_drone_rebalance_internal(&mut state);
                 }
             }
 
-            Self::EjectionChain => {
-                self._ejection_chain_internal(&mut state);
+            _ => {
+                for (vehicle_i, is_truck) in vehicles {
+                    (truck_cloned, drone_cloned) = match self {
+                        Self::Move(..)
+                        | Self::TwoOpt
+                        | Self::OrOpt
+                        // | Self::CrossExchange
+                        => {
+                            (truck_cloned, drone_cloned) = if is_truck {
+                                self._inter_route_internal::<TruckRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i)
+                            } else {
+                                self._inter_route_internal::<DroneRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i)
+                            };
+
+                            if is_truck {
+                                self._inter_route_extract_internal::<TruckRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i)
+                            } else {
+                                self._inter_route_extract_internal::<DroneRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i)
+                            }
+                        }
+
+                        Self::RouteRelocate => {
+                            if is_truck {
+                                Self::_route_relocate_internal::<TruckRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i)
+                            } else {
+                                Self::_route_relocate_internal::<DroneRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i)
+                            }
+                        }
+
+                        Self::SplitMerge => {
+                            if is_truck {
+                                Self::_split_merge_internal::<TruckRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i)
+                            } else {
+                                Self::_split_merge_internal::<DroneRoute>(&mut state, truck_cloned, drone_cloned, vehicle_i)
+                            }
+                        }
+
+                        Self::EjectionChain | Self::DroneRebalance => unreachable!(),
+                    };
+                }
             }
         }
 
@@ -532,15 +1331,28 @@ impl Neighborhood {
     pub fn intra_route(
         self,
         solution: &Solution,
-        tabu_list: &[Vec<usize>],
+        tabu_list: &[TabuAttribute],
+        aspiration_cost: f64,
+        focus: Option<&FocusList>,
+    ) -> (Solution, TabuAttribute) {
+        self._intra_route(solution, tabu_list, aspiration_cost, None, None, focus)
+    }
+
+    fn _intra_route(
+        self,
+        solution: &Solution,
+        tabu_list: &[TabuAttribute],
         mut aspiration_cost: f64,
-    ) -> (Solution, Vec<usize>) {
-        let mut result = (solution.clone(), vec![]);
-        if let Self::EjectionChain = self {
+        sampler: Option<&mut _Sampler>,
+        ranked: Option<&mut _Ranked>,
+        focus: Option<&FocusList>,
+    ) -> (Solution, TabuAttribute) {
+        let mut result = (solution.clone(), TabuAttribute::new());
+        if let Self::EjectionChain | Self::RouteRelocate | Self::SplitMerge | Self::DroneRebalance = self {
             return result;
         }
 
-        let (vehicle, is_truck) = Self::_find_decisive_vehicle(solution);
+        let vehicles = Self::decisive_vehicles(solution);
 
         let mut truck_cloned = solution.truck_routes.clone();
         let mut drone_cloned = solution.drone_routes.clone();
@@ -550,56 +1362,138 @@ impl Neighborhood {
 
         let mut state = _IterationState {
             original: solution,
+            totals: VehicleTotals::new(solution),
             tabu_list,
             aspiration_cost: &mut aspiration_cost,
             min_cost: &mut min_cost,
             require_feasible: &mut require_feasible,
             result: &mut result,
+            sampler,
+            ranked,
+            focus,
         };
 
         macro_rules! search_route {
-            ($original_routes:expr, $cloned_routes:expr) => {
+            ($original_routes:expr, $cloned_routes:expr, $route_type:ty, $vehicle:expr) => {
+                let vehicle = $vehicle;
                 for (i, route) in $original_routes[vehicle].iter().enumerate() {
-                    for (new_route, tabu) in route.intra_route(self).iter() {
+                    if !state._focused_route(route) {
+                        continue;
+                    }
+
+                    let mut neighbors = route.intra_route(self);
+                    _order_moves(self, &mut neighbors, |(new_route, tabu)| {
+                        (new_route.distance() - route.distance(), tabu)
+                    });
+
+                    if config::with_current(|config| config.parallel_evaluation) {
+                        let candidates = neighbors
+                            .iter()
+                            .filter(|(new_route, tabu)| state._focused(tabu) && new_route._arcs_respected())
+                            .collect::<Vec<_>>();
+                        _evaluate_parallel(
+                            &mut state,
+                            &truck_cloned,
+                            &drone_cloned,
+                            &[(<$route_type>::vehicle_kind(), vehicle)],
+                            &candidates,
+                            |truck, drone, (new_route, _)| {
+                                <$route_type>::get_correct_route_mut(truck, drone)[vehicle][i] = new_route.clone();
+                            },
+                            |(_, tabu)| tabu,
+                        );
+                        continue;
+                    }
+
+                    for (new_route, tabu) in neighbors.iter() {
+                        if !state._focused(tabu) || !new_route._arcs_respected() {
+                            continue;
+                        }
+
                         // Temporary assign new route
                         $cloned_routes[vehicle][i] = new_route.clone();
 
-                        // Construct the new solution: move `truck_cloned` and `drone_cloned` to the temp solution
-                        // and get them back later during restoration
-                        let s = Solution::new(truck_cloned, drone_cloned);
-
-                        Self::_internal_update(&mut state, &s, &tabu);
+                        let candidate = state._candidate(
+                            &truck_cloned,
+                            &drone_cloned,
+                            &[(<$route_type>::vehicle_kind(), vehicle)],
+                        );
+                        Self::_internal_update(&mut state, &candidate, &tabu);
 
                         // Restore old route
-                        truck_cloned = s.truck_routes;
-                        drone_cloned = s.drone_routes;
                         $cloned_routes[vehicle][i] = route.clone();
                     }
                 }
             };
         }
 
-        if is_truck {
-            search_route!(solution.truck_routes, truck_cloned);
-        } else {
-            search_route!(solution.drone_routes, drone_cloned);
+        for (vehicle, is_truck) in vehicles {
+            if is_truck {
+                search_route!(solution.truck_routes, truck_cloned, TruckRoute, vehicle);
+            } else {
+                search_route!(solution.drone_routes, drone_cloned, DroneRoute, vehicle);
+            }
         }
 
         result
     }
 
+    /// A candidate solution of this neighborhood (intra-route or inter-route) drawn uniformly at random, ignoring the
+    /// tabu lists, along with its sorted tabu attribute. Returns `None` if the neighborhood is empty.
+    pub fn sample(
+        &self,
+        solution: &Solution,
+        rng: &mut impl Rng,
+        focus: Option<&FocusList>,
+    ) -> Option<(Solution, TabuAttribute)> {
+        let mut sampler = _Sampler {
+            rng: StdRng::seed_from_u64(rng.random()),
+            seen: 0,
+            chosen: None,
+        };
+        self._intra_route(solution, &[], f64::MIN, Some(&mut sampler), None, focus);
+        self._inter_route(solution, &[], f64::MIN, Some(&mut sampler), None, focus);
+
+        sampler.chosen.map(|(solution, mut tabu)| {
+            tabu.sort();
+            (solution, tabu)
+        })
+    }
+
     pub fn search(
         &self,
         solution: &Solution,
-        tabu_list: &mut Vec<Vec<usize>>,
+        tabu_list: &mut Vec<TabuAttribute>,
+        tabu_size: usize,
+        aspiration_cost: f64,
+        focus: Option<&FocusList>,
+    ) -> Option<(Solution, TabuAttribute)> {
+        self.search_ranked(solution, tabu_list, tabu_size, aspiration_cost, focus, 0)
+    }
+
+    /// Same as [`Self::search`], but the move taken is the admissible candidate of rank `rank` by cost (0 being the
+    /// best one), unless a candidate meets the aspiration criterion.
+    pub fn search_ranked(
+        &self,
+        solution: &Solution,
+        tabu_list: &mut Vec<TabuAttribute>,
         tabu_size: usize,
         aspiration_cost: f64,
-    ) -> Option<Solution> {
-        let intra = self.intra_route(solution, tabu_list, aspiration_cost);
-        let inter = self.inter_route(solution, tabu_list, aspiration_cost);
+        focus: Option<&FocusList>,
+        rank: usize,
+    ) -> Option<(Solution, TabuAttribute)> {
+        let mut ranked = (rank > 0).then(|| _Ranked {
+            rank,
+            candidates: vec![],
+        });
+        let intra = self._intra_route(solution, tabu_list, aspiration_cost, None, ranked.as_mut(), focus);
+        let inter = self._inter_route(solution, tabu_list, aspiration_cost, None, ranked.as_mut(), focus);
 
         #[allow(clippy::if_same_then_else)]
-        let (result, mut tabu) = if intra.1.is_empty() {
+        let (result, mut tabu) = if intra.1.is_empty() && inter.1.is_empty() {
+            // No candidate meets the aspiration criterion
+            ranked.and_then(_Ranked::pick).unwrap_or(inter)
+        } else if intra.1.is_empty() {
             inter // Intra-route neighborhood is empty
         } else if inter.1.is_empty() {
             intra // Inter-route neighborhood is empty
@@ -627,6 +1521,6 @@ impl Neighborhood {
             }
         }
 
-        Some(result)
+        Some((result, tabu))
     }
 }
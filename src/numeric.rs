@@ -0,0 +1,28 @@
+use std::ops::AddAssign;
+
+/// Neumaier's compensated summation, keeping track of the low-order bits lost by each floating-point addition to
+/// reduce the rounding error of long accumulations (though not to make them independent of the order of their terms).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompensatedSum {
+    _sum: f64,
+    _compensation: f64,
+}
+
+impl CompensatedSum {
+    pub fn value(self) -> f64 {
+        self._sum + self._compensation
+    }
+}
+
+impl AddAssign<f64> for CompensatedSum {
+    fn add_assign(&mut self, value: f64) {
+        let sum = self._sum + value;
+        if self._sum.abs() >= value.abs() {
+            self._compensation += (self._sum - sum) + value;
+        } else {
+            self._compensation += (value - sum) + self._sum;
+        }
+
+        self._sum = sum;
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+use std::{env, fs};
+
+use rand::distr::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::seq::index;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::cli::InstanceFormat;
+use crate::errors::ExpectedValue;
+use crate::instance::Instance;
+
+/// Fields of the output solution JSON that the study relies on.
+#[derive(Deserialize)]
+struct _RunSolution {
+    working_time: f64,
+    truck_routes: Vec<Vec<Vec<usize>>>,
+    drone_routes: Vec<Vec<Vec<usize>>>,
+}
+
+#[derive(Serialize)]
+struct _VariantResult {
+    customers: usize,
+    working_time: f64,
+    /// Relative change of the working time against the base solution, in percent.
+    cost_change: f64,
+    /// Number of customers common to both instances whose successor differs from the base solution.
+    hamming_distance: usize,
+    /// Hamming distance over the number of common customers.
+    normalized_hamming_distance: f64,
+}
+
+#[derive(Serialize)]
+struct _StudyResult {
+    base_working_time: f64,
+    variants: Vec<_VariantResult>,
+    mean_normalized_hamming_distance: f64,
+    mean_working_time: f64,
+    std_working_time: f64,
+    min_working_time: f64,
+    max_working_time: f64,
+}
+
+/// Uniform offset of up to `percent` percent of `extent` in either direction.
+fn _offset(rng: &mut impl Rng, percent: f64, extent: f64) -> f64 {
+    rng.random_range(-1.0..=1.0) * percent / 100.0 * extent
+}
+
+/// Perturb `base`, returning the variant and the identity of each of its customers: its index in `base` (1-based),
+/// or an index past the customers of `base` for added customers.
+fn _perturb(
+    base: &Instance,
+    demand: f64,
+    add: usize,
+    remove: usize,
+    jitter: f64,
+    rng: &mut impl Rng,
+) -> (Instance, Vec<usize>) {
+    let n = base.customers.len();
+    let (min_x, max_x, min_y, max_y) = base.customers.iter().fold(
+        (base.depot.x, base.depot.x, base.depot.y, base.depot.y),
+        |(min_x, max_x, min_y, max_y), c| (min_x.min(c.x), max_x.max(c.x), min_y.min(c.y), max_y.max(c.y)),
+    );
+
+    // Keep at least one customer
+    let removed = index::sample(rng, n, remove.min(n.saturating_sub(1))).into_vec();
+    let mut variant = base.clone();
+    let mut ids = vec![0];
+    variant.customers.clear();
+    for (i, customer) in base.customers.iter().enumerate() {
+        if removed.contains(&i) {
+            continue;
+        }
+
+        let mut customer = customer.clone();
+        customer.demand = (customer.demand + _offset(rng, demand, customer.demand)).max(0.0);
+        customer.x += _offset(rng, jitter, max_x - min_x);
+        customer.y += _offset(rng, jitter, max_y - min_y);
+        variant.customers.push(customer);
+        ids.push(i + 1);
+    }
+
+    // Added customers copy a random customer, placed anywhere within the extent of the instance
+    for k in 0..add {
+        let mut customer = base.customers[rng.random_range(0..n)].clone();
+        customer.x = if max_x > min_x {
+            rng.random_range(min_x..max_x)
+        } else {
+            min_x
+        };
+        customer.y = if max_y > min_y {
+            rng.random_range(min_y..max_y)
+        } else {
+            min_y
+        };
+        variant.customers.push(customer);
+        ids.push(n + 1 + k);
+    }
+
+    (variant, ids)
+}
+
+fn _solve(problem: &Path, iterations: usize, outputs: &Path, args: &[String]) -> Result<_RunSolution, Box<dyn Error>> {
+    let output = Command::new(env::current_exe()?)
+        .arg("run")
+        .arg(problem)
+        .arg("--fix-iteration")
+        .arg(iterations.to_string())
+        .arg("--disable-logging")
+        .arg("--outputs")
+        .arg(outputs)
+        .args(args)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Run on {} exited with {}:\n{}",
+            problem.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let path = ExpectedValue::cast(stdout.lines().find(|l| l.ends_with("-solution.json")).map(String::from))?;
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Successor of each customer of `solution`, identified by `ids` (0 for the depot).
+fn _successors(solution: &_RunSolution, ids: &[usize]) -> HashMap<usize, usize> {
+    let mut result = HashMap::new();
+    for route in solution.truck_routes.iter().chain(&solution.drone_routes).flatten() {
+        for i in 1..route.len() - 1 {
+            result.insert(ids[route[i]], ids[route[i + 1]]);
+        }
+    }
+
+    result
+}
+
+/// Generate `variants` perturbed copies of `problem` (scaling each demand by up to `demand` percent, removing `remove`
+/// and adding `add` customers, moving each coordinate by up to `jitter` percent of the extent of the instance), solve
+/// the base instance and each variant with a fixed iteration budget (forwarding `args` to every run) and report how
+/// much the solutions change, optionally storing the metrics as JSON to `output`.
+pub fn perturb(
+    problem: &str,
+    from: Option<InstanceFormat>,
+    variants: usize,
+    demand: f64,
+    add: usize,
+    remove: usize,
+    jitter: f64,
+    iterations: usize,
+    seed: Option<u64>,
+    output: Option<&str>,
+    args: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let data = fs::read_to_string(problem)?;
+    let format = from.unwrap_or_else(|| InstanceFormat::detect(Path::new(problem), Some(&data)));
+    let base = Instance::parse(&data, format)?;
+    if base.customers.is_empty() {
+        return Err(format!("{problem} has no customers").into());
+    }
+
+    let mut rng = seed.map_or_else(|| StdRng::from_rng(&mut rand::rng()), StdRng::seed_from_u64);
+    let id = rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(char::from)
+        .collect::<String>();
+    let outputs = env::temp_dir().join(format!("min-timespan-delivery-perturb-{id}"));
+    fs::create_dir_all(&outputs)?;
+
+    // Instances are written as JSON, the only format representing all of their fields
+    let base_path = outputs.join("base.json");
+    fs::write(&base_path, base.serialize(InstanceFormat::Json, "base")?)?;
+    eprint!("Solving the base instance     \r");
+    let base_solution = _solve(&base_path, iterations, &outputs, args)?;
+    let base_ids = (0..=base.customers.len()).collect::<Vec<_>>();
+    let base_successors = _successors(&base_solution, &base_ids);
+
+    let mut results = vec![];
+    for v in 0..variants {
+        eprint!("Solving variant {}/{variants}     \r", v + 1);
+        let (variant, ids) = _perturb(&base, demand, add, remove, jitter, &mut rng);
+        let path = outputs.join(format!("variant-{v}.json"));
+        fs::write(&path, variant.serialize(InstanceFormat::Json, &format!("variant-{v}"))?)?;
+        let solution = _solve(&path, iterations, &outputs, args)?;
+
+        let successors = _successors(&solution, &ids);
+        let mut common = 0_usize;
+        let mut hamming_distance = 0;
+        for (customer, successor) in &successors {
+            if let Some(base_successor) = base_successors.get(customer) {
+                common += 1;
+                hamming_distance += usize::from(successor != base_successor);
+            }
+        }
+
+        results.push(_VariantResult {
+            customers: variant.customers.len(),
+            working_time: solution.working_time,
+            cost_change: (solution.working_time - base_solution.working_time) / base_solution.working_time * 100.0,
+            hamming_distance,
+            normalized_hamming_distance: hamming_distance as f64 / common.max(1) as f64,
+        });
+    }
+    eprintln!();
+
+    let _ = fs::remove_dir_all(&outputs);
+
+    let count = results.len().max(1) as f64;
+    let mean_working_time = results.iter().map(|r| r.working_time).sum::<f64>() / count;
+    let study = _StudyResult {
+        base_working_time: base_solution.working_time,
+        mean_normalized_hamming_distance: results.iter().map(|r| r.normalized_hamming_distance).sum::<f64>() / count,
+        mean_working_time,
+        std_working_time: (results
+            .iter()
+            .map(|r| (r.working_time - mean_working_time).powi(2))
+            .sum::<f64>()
+            / count)
+            .sqrt(),
+        min_working_time: results.iter().map(|r| r.working_time).fold(f64::INFINITY, f64::min),
+        max_working_time: results.iter().map(|r| r.working_time).fold(f64::NEG_INFINITY, f64::max),
+        variants: results,
+    };
+
+    println!(
+        "{:<10} {:>9} {:>14} {:>10} {:>9} {:>12}",
+        "Variant", "Customers", "Working time", "Change", "Hamming", "Normalized"
+    );
+    println!(
+        "{:<10} {:>9} {:>14.2}",
+        "base",
+        base.customers.len(),
+        study.base_working_time
+    );
+    for (v, result) in study.variants.iter().enumerate() {
+        println!(
+            "{:<10} {:>9} {:>14.2} {:>9.2}% {:>9} {:>12.3}",
+            v,
+            result.customers,
+            result.working_time,
+            result.cost_change,
+            result.hamming_distance,
+            result.normalized_hamming_distance,
+        );
+    }
+    println!(
+        "Working time {:.2} ± {:.2} (range {:.2} to {:.2}), mean normalized Hamming distance {:.3}",
+        study.mean_working_time,
+        study.std_working_time,
+        study.min_working_time,
+        study.max_working_time,
+        study.mean_normalized_hamming_distance,
+    );
+
+    if let Some(path) = output {
+        fs::write(path, serde_json::to_string_pretty(&study)?)?;
+        eprintln!("Saved the study to {path}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::routes::Route;
+use crate::solutions::Solution;
+
+/// A route pool file. Customers are identified as in the instance file (without the depot), so that runs on different
+/// subsets of its customers can share a pool.
+#[derive(Default, Deserialize, Serialize)]
+struct _PoolFile {
+    truck_routes: Vec<Vec<usize>>,
+    drone_routes: Vec<Vec<usize>>,
+}
+
+/// Routes loaded from [`Config::route_pool`](crate::config::Config::route_pool), as customer sequences of this run
+/// (including the depot at both ends). Routes serving customers outside of this run are dropped.
+#[derive(Default)]
+pub struct RoutePool {
+    pub truck_routes: Vec<Vec<usize>>,
+    pub drone_routes: Vec<Vec<usize>>,
+}
+
+/// Load the route pool of the config in scope, which is empty without `--route-pool`.
+pub fn load() -> RoutePool {
+    let config = config::current();
+    let Some(ref path) = config.route_pool else {
+        return RoutePool::default();
+    };
+
+    let file = match _read(Path::new(path)) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Ignoring route pool {path}: {e}");
+            return RoutePool::default();
+        }
+    };
+
+    let index = config
+        .customer_ids
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &id)| (id, i))
+        .collect::<HashMap<_, _>>();
+    let convert = |routes: Vec<Vec<usize>>, dronable: bool| {
+        routes
+            .into_iter()
+            .filter_map(|route| {
+                let mut customers = vec![0];
+                for id in route {
+                    let &c = index.get(&id)?;
+                    if dronable && !config.dronable[c] {
+                        return None;
+                    }
+
+                    customers.push(c);
+                }
+
+                customers.push(0);
+                (customers.len() > 2).then_some(customers)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let pool = RoutePool {
+        truck_routes: convert(file.truck_routes, false),
+        drone_routes: convert(file.drone_routes, true),
+    };
+    eprintln!(
+        "Loaded {} truck routes and {} drone routes from route pool {path}",
+        pool.truck_routes.len(),
+        pool.drone_routes.len()
+    );
+    pool
+}
+
+fn _read(path: &Path) -> Result<_PoolFile, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(_PoolFile::default());
+    }
+
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Add the routes of `solution` to the pool file at `path`, creating it if needed.
+pub fn save(path: &str, solution: &Solution) -> Result<(), Box<dyn Error>> {
+    fn _extend<R>(pool: &mut Vec<Vec<usize>>, vehicle_routes: &[Vec<Arc<R>>])
+    where
+        R: Route,
+    {
+        let mut seen = pool.iter().cloned().collect::<HashSet<_>>();
+        for route in vehicle_routes.iter().flatten() {
+            let customers = &route.data().customers;
+            let ids = customers[1..customers.len() - 1]
+                .iter()
+                .map(|&c| config::current().customer_ids[c])
+                .collect::<Vec<_>>();
+            if seen.insert(ids.clone()) {
+                pool.push(ids);
+            }
+        }
+    }
+
+    let path = Path::new(path);
+    let mut file = _read(path)?;
+    _extend(&mut file.truck_routes, &solution.truck_routes);
+    _extend(&mut file.drone_routes, &solution.drone_routes);
+    fs::write(path, serde_json::to_string(&file)?)?;
+    eprintln!("Saved the routes of the best solution to route pool {}", path.display());
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::cli::InstanceFormat;
+use crate::config::TruckConfig;
+use crate::instance::Instance;
+
+/// Descriptive statistics of an instance, used to stratify benchmark sets.
+#[derive(Debug, Serialize)]
+pub struct InstanceProfile {
+    customers: usize,
+    trucks_count: Option<usize>,
+    drones_count: Option<usize>,
+    capacity: f64,
+
+    /// Mean distance of the customers to the depot.
+    mean_depot_distance: f64,
+    max_depot_distance: f64,
+    /// Standard distance of the customers around their centroid.
+    spread: f64,
+
+    total_demand: f64,
+    /// Total demand over the vehicle capacity, i.e. the minimum number of full truck loads.
+    demand_capacity_ratio: f64,
+    /// Largest demand of a single customer over the vehicle capacity.
+    max_demand_ratio: f64,
+    dronable_fraction: f64,
+
+    mean_nearest_neighbor: f64,
+    min_nearest_neighbor: f64,
+    max_nearest_neighbor: f64,
+    /// Clark-Evans aggregation index: the mean nearest-neighbor distance over its expectation for customers spread
+    /// uniformly over their bounding box. Values below 1 indicate clustered customers, values above 1 regularly
+    /// spaced ones.
+    clustering: f64,
+
+    /// Rough difficulty score, growing with the number of customers, the number of truck loads, the fraction of
+    /// dronable customers (which multiplies the assignment choices) and the lack of clustering. Only meaningful to
+    /// compare instances against each other.
+    difficulty: f64,
+}
+
+fn _profile(instance: &Instance, capacity: f64) -> InstanceProfile {
+    let customers = &instance.customers;
+    let n = customers.len();
+    let count = n.max(1) as f64;
+
+    let depot_distances = customers
+        .iter()
+        .map(|c| (c.x - instance.depot.x).hypot(c.y - instance.depot.y))
+        .collect::<Vec<_>>();
+
+    let centroid_x = customers.iter().map(|c| c.x).sum::<f64>() / count;
+    let centroid_y = customers.iter().map(|c| c.y).sum::<f64>() / count;
+    let spread = (customers
+        .iter()
+        .map(|c| (c.x - centroid_x).hypot(c.y - centroid_y).powi(2))
+        .sum::<f64>()
+        / count)
+        .sqrt();
+
+    let total_demand = customers.iter().map(|c| c.demand).sum::<f64>();
+    let max_demand = customers.iter().map(|c| c.demand).fold(0.0, f64::max);
+    let dronable_fraction = customers.iter().filter(|c| c.dronable).count() as f64 / count;
+
+    let mut nearest = vec![f64::INFINITY; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let distance = (customers[i].x - customers[j].x).hypot(customers[i].y - customers[j].y);
+            nearest[i] = nearest[i].min(distance);
+            nearest[j] = nearest[j].min(distance);
+        }
+    }
+    if n < 2 {
+        nearest.fill(0.0);
+    }
+
+    let mean_nearest_neighbor = nearest.iter().sum::<f64>() / count;
+
+    let (min_x, max_x) = customers
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), c| {
+            (lo.min(c.x), hi.max(c.x))
+        });
+    let (min_y, max_y) = customers
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), c| {
+            (lo.min(c.y), hi.max(c.y))
+        });
+    let area = (max_x - min_x) * (max_y - min_y);
+    let clustering = if n >= 2 && area > 0.0 {
+        mean_nearest_neighbor / (0.5 * (area / count).sqrt())
+    } else {
+        1.0
+    };
+
+    let demand_capacity_ratio = total_demand / capacity;
+    let difficulty = (1.0 + count).ln()
+        * (1.0 + demand_capacity_ratio.ln_1p())
+        * (1.0 + dronable_fraction)
+        * clustering.clamp(0.5, 2.0);
+
+    InstanceProfile {
+        customers: n,
+        trucks_count: instance.trucks_count,
+        drones_count: instance.drones_count,
+        capacity,
+        mean_depot_distance: depot_distances.iter().sum::<f64>() / count,
+        max_depot_distance: depot_distances.iter().copied().fold(0.0, f64::max),
+        spread,
+        total_demand,
+        demand_capacity_ratio,
+        max_demand_ratio: max_demand / capacity,
+        dronable_fraction,
+        mean_nearest_neighbor,
+        min_nearest_neighbor: nearest.iter().copied().reduce(f64::min).unwrap_or(0.0),
+        max_nearest_neighbor: nearest.iter().copied().reduce(f64::max).unwrap_or(0.0),
+        clustering,
+        difficulty,
+    }
+}
+
+/// Profile each of `problems` and print a summary table, optionally storing all statistics as JSON to `output`.
+/// Instances without a capacity of their own are profiled against the truck capacity in `truck_cfg`.
+pub fn profile_instances(
+    problems: &[String],
+    from: Option<InstanceFormat>,
+    truck_cfg: &str,
+    output: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let truck = serde_json::from_str::<TruckConfig>(&fs::read_to_string(truck_cfg)?)?;
+
+    let mut results = BTreeMap::new();
+    for problem in problems {
+        let data = fs::read_to_string(problem)?;
+        let format = from.unwrap_or_else(|| InstanceFormat::detect(Path::new(problem), Some(&data)));
+        let instance = Instance::parse(&data, format)?;
+
+        results.insert(
+            problem.clone(),
+            _profile(&instance, instance.capacity.unwrap_or(truck.capacity)),
+        );
+    }
+
+    println!(
+        "{:<40} {:>9} {:>12} {:>12} {:>10} {:>10} {:>12} {:>10} {:>10}",
+        "Instance", "Customers", "Spread", "Mean NN", "Clustering", "Dronable", "Loads", "Max load", "Difficulty"
+    );
+    for (problem, profile) in &results {
+        println!(
+            "{:<40} {:>9} {:>12.2} {:>12.2} {:>10.3} {:>10.3} {:>12.3} {:>10.3} {:>10.2}",
+            problem,
+            profile.customers,
+            profile.spread,
+            profile.mean_nearest_neighbor,
+            profile.clustering,
+            profile.dronable_fraction,
+            profile.demand_capacity_ratio,
+            profile.max_demand_ratio,
+            profile.difficulty,
+        );
+    }
+
+    if let Some(path) = output {
+        fs::write(path, serde_json::to_string_pretty(&results)?)?;
+        eprintln!("Saved profiles to {path}");
+    }
+
+    Ok(())
+}
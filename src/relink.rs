@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use crate::config::{self, VehicleKind};
+use crate::routes::{DroneRoute, Route, TruckRoute};
+use crate::solutions::Solution;
+
+/// Vehicle serving each customer of `solution` (the depot is reported as served by truck 0).
+fn _assignment(solution: &Solution) -> Vec<(VehicleKind, usize)> {
+    let mut result = vec![(VehicleKind::Truck, 0); config::current().customers_count + 1];
+    for (truck, routes) in solution.truck_routes.iter().enumerate() {
+        for route in routes {
+            for &c in &route.data().customers {
+                result[c] = (VehicleKind::Truck, truck);
+            }
+        }
+    }
+    for (drone, routes) in solution.drone_routes.iter().enumerate() {
+        for route in routes {
+            for &c in &route.data().customers {
+                result[c] = (VehicleKind::Drone, drone);
+            }
+        }
+    }
+
+    result[0] = (VehicleKind::Truck, 0);
+    result
+}
+
+/// Remove the customers of `unit` from the routes of `vehicle_routes`, dropping a route left empty.
+fn _remove<R>(vehicle_routes: &mut [Vec<Arc<R>>], unit: &[usize])
+where
+    R: Route,
+{
+    for routes in vehicle_routes.iter_mut() {
+        if let Some(r) = routes.iter().position(|r| r.data().customers.contains(&unit[0])) {
+            let mut customers = routes[r].data().customers.clone();
+            customers.retain(|c| !unit.contains(c));
+            if customers.len() > 2 {
+                routes[r] = R::new(customers);
+            } else {
+                routes.swap_remove(r);
+            }
+
+            return;
+        }
+    }
+}
+
+/// Insert `unit` into the routes of `vehicle` at the position lengthening them the least, or as a route of its own if
+/// the vehicle may operate one more. Returns `false` if no position respects the route and assignment constraints.
+fn _insert<R>(vehicle_routes: &mut [Vec<Arc<R>>], vehicle: usize, unit: &[usize]) -> bool
+where
+    R: Route,
+{
+    let config = config::current();
+    if !unit
+        .iter()
+        .all(|&c| R::_servable(c) && config.allows(R::vehicle_kind(), vehicle, c))
+    {
+        return false;
+    }
+
+    let distances = R::distances(&config);
+    let (first, last) = (unit[0], unit[unit.len() - 1]);
+    let mut best = None;
+    if vehicle_routes[vehicle].len() < R::max_routes() && unit.len() <= R::max_customers() {
+        best = Some((distances.get(0, first) + distances.get(last, 0), None));
+    }
+
+    for (r, route) in vehicle_routes[vehicle].iter().enumerate() {
+        let customers = &route.data().customers;
+        if customers.len() - 2 + unit.len() > R::max_customers() {
+            continue;
+        }
+
+        for i in 1..customers.len() {
+            let (a, b) = (customers[i - 1], customers[i]);
+            let delta = distances.get(a, first) + distances.get(last, b) - distances.get(a, b);
+            if best.as_ref().is_none_or(|&(d, _)| delta < d) {
+                let mut inserted = customers.clone();
+                inserted.splice(i..i, unit.iter().copied());
+                if config.arcs.respected(&inserted) {
+                    best = Some((delta, Some((r, inserted))));
+                }
+            }
+        }
+    }
+
+    match best {
+        Some((_, Some((r, customers)))) => vehicle_routes[vehicle][r] = R::new(customers),
+        Some((_, None)) => {
+            let mut customers = vec![0];
+            customers.extend_from_slice(unit);
+            customers.push(0);
+            vehicle_routes[vehicle].push(R::new(customers));
+        }
+        None => return false,
+    }
+
+    true
+}
+
+/// Guided Move(1, 0): relocate `unit` (a chain of required arcs) of `solution` to `target`.
+fn _relocate(solution: &Solution, unit: &[usize], target: (VehicleKind, usize)) -> Option<Solution> {
+    let mut truck_routes = solution.truck_routes.clone();
+    let mut drone_routes = solution.drone_routes.clone();
+    _remove(&mut truck_routes, unit);
+    _remove(&mut drone_routes, unit);
+
+    let inserted = match target {
+        (VehicleKind::Truck, truck) => _insert(&mut truck_routes, truck, unit),
+        (VehicleKind::Drone, drone) => _insert(&mut drone_routes, drone, unit),
+    };
+    inserted.then(|| Solution::new(truck_routes, drone_routes))
+}
+
+/// Guided Move(1, 1): exchange the positions of customers `a` and `b` of `solution`, which are not linked by required
+/// arcs.
+fn _exchange(solution: &Solution, a: usize, b: usize, assignment: &[(VehicleKind, usize)]) -> Option<Solution> {
+    fn _swap<R>(vehicle_routes: &mut [Vec<Arc<R>>], a: usize, b: usize)
+    where
+        R: Route,
+    {
+        for routes in vehicle_routes.iter_mut() {
+            for route in routes.iter_mut() {
+                let customers = &route.data().customers;
+                if customers.contains(&a) || customers.contains(&b) {
+                    let swapped = customers
+                        .iter()
+                        .map(|&c| {
+                            if c == a {
+                                b
+                            } else if c == b {
+                                a
+                            } else {
+                                c
+                            }
+                        })
+                        .collect();
+                    *route = R::new(swapped);
+                }
+            }
+        }
+    }
+
+    let config = config::current();
+    let (kind_a, vehicle_a) = assignment[a];
+    let (kind_b, vehicle_b) = assignment[b];
+    let servable = |kind, c| match kind {
+        VehicleKind::Truck => TruckRoute::_servable(c),
+        VehicleKind::Drone => DroneRoute::_servable(c),
+    };
+    if !servable(kind_b, a)
+        || !servable(kind_a, b)
+        || !config.allows(kind_b, vehicle_b, a)
+        || !config.allows(kind_a, vehicle_a, b)
+    {
+        return None;
+    }
+
+    let mut truck_routes = solution.truck_routes.clone();
+    let mut drone_routes = solution.drone_routes.clone();
+    _swap(&mut truck_routes, a, b);
+    _swap(&mut drone_routes, a, b);
+    Some(Solution::new(truck_routes, drone_routes))
+}
+
+/// Walk from `initiating` towards the vehicle assignment of `guiding`, each step applying the cheapest guided move:
+/// relocating a misassigned chain of required arcs to its vehicle in `guiding` (Move(1, 0)), or exchanging two
+/// misassigned customers each on the vehicle of the other in `guiding` (Move(1, 1)). Returns the cheapest solution
+/// met along the way (excluding `initiating`), or `None` if no guided move is possible.
+pub fn relink(initiating: &Solution, guiding: &Solution) -> Option<Solution> {
+    let config = config::current();
+    let target = _assignment(guiding);
+    let mut current = initiating.clone();
+    let mut best: Option<Solution> = None;
+    loop {
+        let assignment = _assignment(&current);
+        let mut units = (1..=config.customers_count)
+            .filter(|&c| assignment[c] != target[c])
+            .map(|c| config.arcs.chain(c))
+            .collect::<Vec<_>>();
+        units.sort_unstable();
+        units.dedup();
+
+        // Exchanges only apply to customers without required arcs, which are their own chain
+        let mut pairs = vec![];
+        for (i, a) in units.iter().enumerate() {
+            for b in &units[i + 1..] {
+                if a.len() == 1 && b.len() == 1 && assignment[a[0]] == target[b[0]] && assignment[b[0]] == target[a[0]]
+                {
+                    pairs.push((a[0], b[0]));
+                }
+            }
+        }
+
+        let relocations = units
+            .iter()
+            .filter_map(|unit| _relocate(&current, unit, target[unit[0]]));
+        let exchanges = pairs
+            .into_iter()
+            .filter_map(|(a, b)| _exchange(&current, a, b, &assignment));
+        let Some(next) = relocations
+            .chain(exchanges)
+            .min_by(|x, y| x.cost().total_cmp(&y.cost()))
+        else {
+            break;
+        };
+        if best.as_ref().is_none_or(|b| next.cost() < b.cost()) {
+            best = Some(next.clone());
+        }
+
+        current = next;
+    }
+
+    best
+}
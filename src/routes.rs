@@ -1,12 +1,22 @@
 use std::collections::VecDeque;
 use std::fmt;
-use std::mem::swap;
-use std::rc::Rc;
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
 
-use crate::config::CONFIG;
+use serde::{Deserialize, Serialize};
+use smallvec::{SmallVec, smallvec};
+
+use crate::cli::RouteFormat;
+use crate::config::{self, Config, DistanceMatrix, DroneConfig, TruckConfig, VehicleKind};
 use crate::neighborhoods::Neighborhood;
+use crate::numeric::CompensatedSum;
+use crate::schedule::{Stop, TimeBreakdown, Trip};
 use crate::solutions::Solution;
 
+/// Customers whose relocation identifies a move in the tabu lists (at most 4 for the built-in neighborhoods, so
+/// these are stored inline to avoid heap allocations in the hottest loops).
+pub type TabuAttribute = SmallVec<[usize; 4]>;
+
 #[derive(Debug)]
 struct _RouteDataValues {
     distance: f64,
@@ -17,10 +27,35 @@ struct _RouteDataValues {
 pub struct _RouteData {
     pub customers: Vec<usize>,
     value: _RouteDataValues,
+    /// Cheapest insertion table of the route, see [`Route::cheapest_insertion`].
+    _insertions: OnceLock<Box<[Option<(usize, f64)>]>>,
+    /// Suffix aggregates of the route, see [`Route::_suffixes`].
+    _suffixes: OnceLock<_Suffixes>,
+    /// Prefix aggregates of the route, see [`Route::_prefixes`].
+    _prefixes: OnceLock<_Prefixes>,
+}
+
+/// Aggregates of the tail of a route from each of its positions: the distance from the customer there to the end of
+/// the route, and the total demand of the customers from there on.
+#[derive(Debug)]
+pub struct _Suffixes {
+    pub distance: Box<[f64]>,
+    pub weight: Box<[f64]>,
+}
+
+/// Aggregates of the head of a route up to each of its positions, when served by a vehicle of class 0: the time at
+/// which the vehicle is done with the customer there, the sums of the priorities and of the priority-weighted times
+/// over the previous positions, and the total demand of the customers before it.
+#[derive(Debug)]
+pub struct _Prefixes {
+    pub time: Box<[f64]>,
+    pub priority_sum: Box<[f64]>,
+    pub time_sum: Box<[f64]>,
+    pub weight: Box<[f64]>,
 }
 
 impl _RouteData {
-    fn _construct(customers: Vec<usize>, distances: &[Vec<f64>]) -> Self {
+    fn _construct(customers: Vec<usize>, distances: &DistanceMatrix, demands: &[f64]) -> Self {
         assert_eq!(customers.first(), Some(&0));
         assert_eq!(customers.last(), Some(&0));
         assert!(customers.len() >= 3);
@@ -28,60 +63,398 @@ impl _RouteData {
         let mut distance = 0.0;
         let mut weight = 0.0;
         for i in 0..customers.len() - 1 {
-            distance += distances[customers[i]][customers[i + 1]];
-            weight += CONFIG.demands[customers[i]];
+            distance += distances.get(customers[i], customers[i + 1]);
+            weight += demands[customers[i]];
         }
 
         Self {
             customers,
             value: _RouteDataValues { distance, weight },
+            _insertions: OnceLock::new(),
+            _suffixes: OnceLock::new(),
+            _prefixes: OnceLock::new(),
+        }
+    }
+}
+
+/// Values of a route evaluated against the parameters of a single vehicle class.
+///
+/// `energy_violation` and `fixed_time_violation` are always zero for truck routes.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RouteValues {
+    pub working_time: f64,
+    pub capacity_violation: f64,
+    pub waiting_time_violation: f64,
+    pub energy_violation: f64,
+    pub fixed_time_violation: f64,
+    /// Variable cost of the route towards `--budget`: the distance traveled by a truck or the energy consumed by a
+    /// drone, priced per kilometer or kilojoule.
+    pub cost: f64,
+}
+
+impl RouteValues {
+    /// These values rounded to the `--fixed-point` decimals of `config`.
+    fn _quantized(self, config: &Config) -> Self {
+        Self {
+            working_time: config.quantize(self.working_time),
+            capacity_violation: config.quantize(self.capacity_violation),
+            waiting_time_violation: config.quantize(self.waiting_time_violation),
+            energy_violation: config.quantize(self.energy_violation),
+            fixed_time_violation: config.quantize(self.fixed_time_violation),
+            cost: config.quantize(self.cost),
+        }
+    }
+}
+
+/// Values of a route cached per vehicle class.
+///
+/// Every route is evaluated against class 0 upfront, since that is what the search compares routes by. The values
+/// against any other class are only computed the first time they are requested, then shared by all vehicles of that
+/// class (and all solutions holding the route).
+pub struct _RouteValuesCache {
+    _default: RouteValues,
+    _others: Box<[OnceLock<RouteValues>]>,
+    /// Whether the waiting time violation of `_default` was derived from the prefix aggregates of another route (see
+    /// [`Route::waiting_time_violation_with`]) rather than evaluated, so that it may differ from a full evaluation in
+    /// the last bits.
+    _derived: bool,
+}
+
+impl _RouteValuesCache {
+    fn new(default: RouteValues, classes: usize) -> Self {
+        Self {
+            _default: default,
+            _others: (1..classes).map(|_| OnceLock::new()).collect(),
+            _derived: false,
         }
     }
 }
 
-pub trait Route: Sized {
-    fn new(customers: Vec<usize>) -> Rc<Self>;
-    fn single(customer: usize) -> Rc<Self> {
+pub trait Route: Sized + Send + Sync {
+    /// Parameters of a vehicle class able to serve this type of route.
+    type Vehicle: 'static;
+
+    fn new(customers: Vec<usize>) -> Arc<Self>;
+
+    /// Same as [`Self::new`], for a route obtained by changing some customers of this one, so that the evaluation may
+    /// reuse what this route has in common with it. Some of its values may then be derived, see [`Self::_derived`].
+    fn _derive(&self, customers: Vec<usize>) -> Arc<Self> {
+        Self::new(customers)
+    }
+
+    fn single(customer: usize) -> Arc<Self> {
         Self::new(vec![0, customer, 0])
     }
     fn get_correct_route<'a>(
-        truck_routes: &'a [Vec<Rc<TruckRoute>>],
-        drone_routes: &'a [Vec<Rc<DroneRoute>>],
-    ) -> &'a [Vec<Rc<Self>>];
+        truck_routes: &'a [Vec<Arc<TruckRoute>>],
+        drone_routes: &'a [Vec<Arc<DroneRoute>>],
+    ) -> &'a [Vec<Arc<Self>>];
     fn get_correct_route_mut<'a>(
-        truck_routes: &'a mut Vec<Vec<Rc<TruckRoute>>>,
-        drone_routes: &'a mut Vec<Vec<Rc<DroneRoute>>>,
-    ) -> &'a mut Vec<Vec<Rc<Self>>>;
+        truck_routes: &'a mut Vec<Vec<Arc<TruckRoute>>>,
+        drone_routes: &'a mut Vec<Vec<Arc<DroneRoute>>>,
+    ) -> &'a mut Vec<Vec<Arc<Self>>>;
+
+    /// Bounds on the number of customers of a route of this type. Moves never create routes outside of them, while
+    /// the initial heuristics only respect the upper bound.
+    fn min_customers() -> usize;
+    fn max_customers() -> usize;
+    fn single_customer() -> bool {
+        Self::max_customers() == 1
+    }
 
-    fn single_customer() -> bool;
-    fn single_route() -> bool;
+    /// Maximum number of routes a vehicle of this type may operate.
+    fn max_routes() -> usize;
+    fn single_route() -> bool {
+        Self::max_routes() == 1
+    }
+
+    /// Parameters of all vehicle classes of this route type under `config`, indexed by class.
+    fn vehicle_classes(config: &Config) -> &[Self::Vehicle];
 
     fn data(&self) -> &_RouteData;
-    fn working_time(&self) -> f64;
-    fn capacity_violation(&self) -> f64;
-    fn waiting_time_violation(&self) -> f64;
+    fn _values_cache(&self) -> &_RouteValuesCache;
+
+    /// Distances traveled by this type of route under `config`.
+    fn distances(config: &Config) -> &DistanceMatrix;
+
+    /// Capacity of a vehicle of class `class` serving this type of route.
+    fn capacity(class: usize) -> f64;
+
+    /// Suffix aggregates of this route, computed when first queried.
+    fn _suffixes(&self) -> &_Suffixes {
+        self.data()._suffixes.get_or_init(|| {
+            config::with_current(|config| {
+                let customers = &self.data().customers;
+                let distances = Self::distances(config);
+                let mut distance = vec![0.0; customers.len()];
+                let mut weight = vec![0.0; customers.len()];
+                for i in (0..customers.len() - 1).rev() {
+                    distance[i] = distance[i + 1] + distances.get(customers[i], customers[i + 1]);
+                    weight[i] = weight[i + 1] + config.demands[customers[i]];
+                }
+
+                _Suffixes {
+                    distance: distance.into(),
+                    weight: weight.into(),
+                }
+            })
+        })
+    }
+
+    /// Time taken by a vehicle of class 0 to travel from customer `from` to customer `to` and serve it, when carrying
+    /// `weight`.
+    fn _leg_time(config: &Config, from: usize, to: usize, weight: f64) -> f64;
+
+    /// Positions of a route of this type with `length` stops whose waiting time is penalized, see
+    /// [`Self::waiting_time_violation_with`].
+    fn _waiting_positions(length: usize) -> Range<usize>;
+
+    /// Prefix aggregates of this route, computed when first queried.
+    fn _prefixes(&self) -> &_Prefixes {
+        self.data()._prefixes.get_or_init(|| {
+            config::with_current(|config| {
+                let customers = &self.data().customers;
+                let mut time = vec![0.0; customers.len()];
+                let mut priority_sum = vec![0.0; customers.len() + 1];
+                let mut time_sum = vec![0.0; customers.len() + 1];
+                let mut weight = vec![0.0; customers.len()];
+                for i in 1..customers.len() + 1 {
+                    let priority = config.priorities[customers[i - 1]];
+                    priority_sum[i] = priority_sum[i - 1] + priority;
+                    time_sum[i] = priority.mul_add(time[i - 1], time_sum[i - 1]);
+                    if i < customers.len() {
+                        time[i] = time[i - 1] + Self::_leg_time(config, customers[i - 1], customers[i], weight[i - 1]);
+                        weight[i] = weight[i - 1] + config.demands[customers[i - 1]];
+                    }
+                }
+
+                _Prefixes {
+                    time: time.into(),
+                    priority_sum: priority_sum.into(),
+                    time_sum: time_sum.into(),
+                    weight: weight.into(),
+                }
+            })
+        })
+    }
+
+    /// Waiting time violation (against class 0 of `config`) of the route obtained by replacing the customers at
+    /// positions `start..end` of this route by `segment`, when its working time is `working_time`. The legs from `end`
+    /// on must take the same time as in this route: either `segment` carries the same demand as the customers it
+    /// replaces (e.g. it is a permutation of them, as for intra-route moves), or the leg times do not depend on the load
+    /// (as for truck routes).
+    ///
+    /// The times before `start` are unchanged and those from `end` on are all shifted by the same amount, so only
+    /// `segment` is walked through: since times increase along the route, the positions whose waiting time exceeds
+    /// the limit are looked up by binary search in [`Self::_prefixes`], and their weighted violations summed from the
+    /// prefix sums of the priorities and of the weighted times. This takes O(`segment` + log n) time instead of O(n)
+    /// for a full evaluation.
+    fn waiting_time_violation_with(
+        &self,
+        config: &Config,
+        start: usize,
+        end: usize,
+        segment: &[usize],
+        working_time: f64,
+    ) -> f64 {
+        let customers = &self.data().customers;
+        let prefixes = self._prefixes();
+        let positions = Self::_waiting_positions(customers.len());
+
+        // Sum of the violations at the positions `from..to` whose times are shifted by `offset`
+        let shifted = |from: usize, to: usize, offset: f64| {
+            let (from, to) = (from.max(positions.start), to.min(positions.end));
+            if from >= to {
+                return 0.0;
+            }
+
+            let threshold = working_time - config.waiting_time_limit - offset;
+            let last = from + prefixes.time[from..to].partition_point(|&t| t < threshold);
+            (prefixes.priority_sum[last] - prefixes.priority_sum[from])
+                .mul_add(threshold, prefixes.time_sum[from] - prefixes.time_sum[last])
+        };
+
+        let mut violation = shifted(0, start, 0.0);
+
+        let (mut previous, mut time, mut weight) = (
+            customers[start - 1],
+            prefixes.time[start - 1],
+            prefixes.weight[start - 1],
+        );
+        for &customer in segment {
+            time += Self::_leg_time(config, previous, customer, weight);
+            weight += config.demands[previous];
+            previous = customer;
+            violation += config.priorities[customer] * (working_time - time - config.waiting_time_limit).max(0.0);
+        }
+
+        let time = time + Self::_leg_time(config, previous, customers[end], weight);
+        violation + shifted(end, customers.len(), time - prefixes.time[end])
+    }
+
+    /// Whether the waiting time violation of this route was derived rather than evaluated in full, see
+    /// [`Self::waiting_time_violation_with`].
+    fn _derived(&self) -> bool {
+        self._values_cache()._derived
+    }
+
+    /// `route`, or the same route evaluated in full if its values were derived (see [`Self::_derived`]), as the
+    /// solutions kept by the search must carry exactly the values of their routes.
+    fn _exact(route: &Arc<Self>) -> Arc<Self> {
+        if route._derived() {
+            Self::new(route.data().customers.clone())
+        } else {
+            route.clone()
+        }
+    }
+
+    /// Evaluate the route described by `data` against the parameters of vehicle class `class` of `config`.
+    fn _evaluate(config: &Config, data: &_RouteData, class: usize) -> RouteValues;
+
+    /// Values of the route visiting `customers` when served by a vehicle of class `class`, evaluated against `config`
+    /// instead of the config in scope and without caching.
+    fn evaluate(config: &Config, customers: &[usize], class: usize) -> RouteValues {
+        let data = _RouteData::_construct(customers.to_vec(), Self::distances(config), &config.demands);
+        Self::_evaluate(config, &data, class)._quantized(config)
+    }
+
+    /// Values of this route when served by a vehicle of class `class`.
+    fn values(&self, class: usize) -> &RouteValues {
+        let cache = self._values_cache();
+        match class {
+            0 => &cache._default,
+            _ => cache._others[class - 1].get_or_init(|| {
+                config::with_current(|config| Self::_evaluate(config, self.data(), class)._quantized(config))
+            }),
+        }
+    }
+
+    fn working_time(&self) -> f64 {
+        self._values_cache()._default.working_time
+    }
+
+    /// Position in this route at which inserting the consecutive customers of `segment` adds the least distance without
+    /// breaking the arc constraints of the config in scope, or `None` if there is no such position.
+    ///
+    /// Single customers are looked up in a table of the cheapest position of every customer, computed when first
+    /// queried and shared by all solutions holding the route: as routes are immutable, only the routes changed by a
+    /// move ever compute their table again.
+    fn cheapest_insertion(&self, segment: &[usize]) -> Option<usize> {
+        let config = config::current();
+        let customers = &self.data().customers;
+        let distances = Self::distances(&config);
+        let scan = |first: usize, last: usize| {
+            let mut best: Option<(usize, f64)> = None;
+            for i in 1..customers.len() {
+                let (a, b) = (customers[i - 1], customers[i]);
+                let delta = distances.get(a, first) + distances.get(last, b) - distances.get(a, b);
+                if best.is_none_or(|(_, d)| delta < d) && config.arcs.insertable(a, first, last, b) {
+                    best = Some((i, delta));
+                }
+            }
+
+            best
+        };
+
+        match segment {
+            &[customer] => self
+                .data()
+                ._insertions
+                .get_or_init(|| (0..=config.customers_count).map(|c| scan(c, c)).collect())[customer]
+                .map(|(i, _)| i),
+            _ => scan(segment[0], segment[segment.len() - 1]).map(|(i, _)| i),
+        }
+    }
+
+    /// Total length of this route, measured with the distances of its vehicle type.
+    fn distance(&self) -> f64 {
+        self.data().value.distance
+    }
+
+    /// This route as printed with the `--route-format` of the config in scope, when operated by a vehicle of class `class`.
+    fn display(&self, class: usize) -> String {
+        let customers = &self.data().customers;
+        match config::current().route_format {
+            RouteFormat::List => format!("{customers:?}"),
+            RouteFormat::Arrows => format!(
+                "{} (t={:.0}s, load={:.1}kg)",
+                customers
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" → "),
+                self.values(class).working_time,
+                self.weight(),
+            ),
+        }
+    }
+
+    /// Total demand of the customers served by this route.
+    fn weight(&self) -> f64 {
+        self.data().value.weight
+    }
+
+    fn capacity_violation(&self) -> f64 {
+        self._values_cache()._default.capacity_violation
+    }
+
+    fn waiting_time_violation(&self) -> f64 {
+        self._values_cache()._default.waiting_time_violation
+    }
 
-    fn push(&self, customer: usize) -> Rc<Self> {
+    fn push(&self, customer: usize) -> Arc<Self> {
         let customers = &self.data().customers;
         let mut new_customers = customers.clone();
         new_customers.insert(customers.len() - 1, customer);
         Self::new(new_customers)
     }
 
-    fn pop(&self) -> Rc<Self> {
+    fn pop(&self) -> Arc<Self> {
         let customers = &self.data().customers;
         let mut new_customers = customers.clone();
         new_customers.remove(customers.len() - 2);
         Self::new(new_customers)
     }
 
+    /// Timeline of this route when operated by a vehicle of class `class` leaving the depot at `departure`.
+    fn trip(&self, class: usize, departure: f64) -> Trip;
+
+    /// Decomposition of the working time of this route when operated by a vehicle of class `class`.
+    fn time_breakdown(&self, class: usize) -> TimeBreakdown;
+
     fn _servable(customer: usize) -> bool;
 
+    fn vehicle_kind() -> VehicleKind;
+
+    /// Whether the number of customers of this route is within [`Self::min_customers`] and [`Self::max_customers`].
+    fn _length_respected(&self) -> bool {
+        (Self::min_customers()..=Self::max_customers()).contains(&(self.data().customers.len() - 2))
+    }
+
+    /// Whether this route satisfies the arc constraints in [`Config::arcs`](crate::config::Config::arcs).
+    fn _arcs_respected(&self) -> bool {
+        config::with_current(|config| config.arcs.respected(&self.data().customers))
+    }
+
+    /// Whether all customers of this route may be served by vehicle `vehicle` of this route type, according to the
+    /// assignment constraints in [`Config::locks`](crate::config::Config::locks) and the zones in
+    /// [`Config::zones`](crate::config::Config::zones).
+    fn _assignable(&self, vehicle: usize) -> bool {
+        config::with_current(|config| {
+            (config.locks.is_empty() && config.zones.is_empty())
+                || self
+                    .data()
+                    .customers
+                    .iter()
+                    .all(|&c| config.allows(Self::vehicle_kind(), vehicle, c))
+        })
+    }
+
     /// Extract customer subsegments from this route to form a new route during an inter-route operation.
     ///
     /// Note that if the current route becomes empty after extracting the subsegment, the result set will be
     /// empty.
-    fn inter_route_extract<T>(&self, neighborhood: Neighborhood) -> Vec<(Rc<Self>, Rc<T>, Vec<usize>)>
+    fn inter_route_extract<T>(&self, neighborhood: Neighborhood) -> Vec<(Arc<Self>, Arc<T>, TabuAttribute)>
     where
         T: Route,
     {
@@ -89,8 +462,7 @@ pub trait Route: Sized {
         let mut results = vec![];
         let mut queue = VecDeque::new();
         let size = match neighborhood {
-            Neighborhood::Move10 => 1,
-            Neighborhood::Move20 => 2,
+            Neighborhood::Move(k, 0) => k,
             _default => 0,
         };
 
@@ -113,7 +485,7 @@ pub trait Route: Sized {
                     route.extend(queue.iter().copied());
                     route.push(0);
 
-                    let tabu = customers[i - size + 1..i + 1].to_vec();
+                    let tabu = TabuAttribute::from_slice(&customers[i - size + 1..i + 1]);
                     results.push((Self::new(original), T::new(route), tabu));
                 }
             } else {
@@ -124,195 +496,242 @@ pub trait Route: Sized {
         results
     }
 
-    /// Perform inter-route neighborhood search.
+    /// Candidates of the inter-route Move (`k`, `l`) neighborhood: every segment of `k` consecutive customers of this
+    /// route is exchanged with every segment of `l` consecutive customers of `other`, or inserted at every position of
+    /// `other` (only the cheapest one with `--insertion-table`) when `l` is 0.
     ///
-    /// This function is non-commutative (i.e. `r1.inter_route(r2, n) != r2.inter_route(r1, n)`). For example,
-    /// `r1.inter_route(r2, Neighborhood::Move10)` will move 1 customer from `r1` to `r2`, but not from `r2` to `r1`.
-    ///
-    /// For symmetric neighborhoods (e.g. `Neighborhood::Move11`), this function will be commutative though.
-    fn inter_route<T>(
-        &self,
-        other: Rc<T>,
-        neighborhood: Neighborhood,
-    ) -> Vec<(Option<Rc<Self>>, Option<Rc<T>>, Vec<usize>)>
+    /// The route of the removed segment is `None` if it becomes empty.
+    fn move_segments<T>(&self, other: &T, k: usize, l: usize) -> Vec<(Option<Arc<Self>>, Option<Arc<T>>, TabuAttribute)>
     where
         T: Route,
     {
+        let config = config::current();
         let customers_i = &self.data().customers;
         let customers_j = &other.data().customers;
 
         let length_i = customers_i.len();
         let length_j = customers_j.len();
 
-        let mut buffer_i = customers_i.clone();
-        let mut buffer_j = customers_j.clone();
-
         let mut results = vec![];
-
-        match neighborhood {
-            Neighborhood::Move10 => {
-                for (idx_i, &customer_i) in customers_i.iter().enumerate().take(length_i - 1).skip(1) {
-                    if !T::_servable(customer_i) {
-                        continue;
-                    }
-
-                    let removed = buffer_i.remove(idx_i);
-                    let route_i = if length_i == 3 {
-                        None
-                    } else {
-                        Some(Self::new(buffer_i.clone()))
-                    };
-                    let tabu = vec![removed];
-
-                    buffer_j.insert(1, removed);
-
-                    for idx_j in 1..length_j {
-                        let ptr = T::new(buffer_j.clone());
-                        results.push((route_i.clone(), Some(ptr), tabu.clone()));
-
-                        buffer_j.swap(idx_j, idx_j + 1);
-                    }
-
-                    buffer_i.insert(idx_i, removed);
-                    buffer_j.pop();
-                }
+        for idx_i in 1..length_i.saturating_sub(k) {
+            let segment_i = &customers_i[idx_i..idx_i + k];
+            if !segment_i.iter().all(|&c| T::_servable(c)) {
+                continue;
             }
-            Neighborhood::Move11 => {
-                for idx_i in 1..length_i - 1 {
-                    if !T::_servable(buffer_i[idx_i]) {
-                        continue;
-                    }
-
-                    for idx_j in 1..length_j - 1 {
-                        if !Self::_servable(buffer_j[idx_j]) {
-                            continue;
-                        }
-
-                        swap(&mut buffer_i[idx_i], &mut buffer_j[idx_j]);
 
-                        let ptr_i = Self::new(buffer_i.clone());
-                        let ptr_j = T::new(buffer_j.clone());
-                        let tabu = vec![customers_i[idx_i], customers_j[idx_j]];
-                        results.push((Some(ptr_i), Some(ptr_j), tabu));
-
-                        swap(&mut buffer_i[idx_i], &mut buffer_j[idx_j]);
-                    }
-                }
-            }
-            Neighborhood::Move20 => {
-                for idx_i in 1..length_i - 2 {
-                    if !T::_servable(buffer_i[idx_i]) || !T::_servable(buffer_i[idx_i + 1]) {
+            if l == 0 {
+                let route_i = if length_i - 2 == k {
+                    None
+                } else {
+                    let mut buffer_i = customers_i[..idx_i].to_vec();
+                    buffer_i.extend_from_slice(&customers_i[idx_i + k..]);
+                    Some(self._derive(buffer_i))
+                };
+                let tabu = TabuAttribute::from_slice(segment_i);
+
+                let positions = if config.insertion_table {
+                    other.cheapest_insertion(segment_i).into_iter().collect::<Vec<_>>()
+                } else {
+                    (1..length_j).collect()
+                };
+                for idx_j in positions {
+                    if !config.candidate_arc(customers_j[idx_j - 1], segment_i[0])
+                        && !config.candidate_arc(segment_i[k - 1], customers_j[idx_j])
+                    {
                         continue;
                     }
 
-                    let removed_x = buffer_i.remove(idx_i);
-                    let removed_y = buffer_i.remove(idx_i);
-
-                    let route_i = if length_i == 4 {
-                        None
-                    } else {
-                        Some(Self::new(buffer_i.clone()))
-                    };
-                    let tabu = vec![removed_x, removed_y];
-
-                    buffer_j.insert(1, removed_x);
-                    buffer_j.insert(2, removed_y);
-
-                    for idx_j in 1..length_j {
-                        let ptr = T::new(buffer_j.clone());
-                        results.push((route_i.clone(), Some(ptr), tabu.clone()));
-
-                        buffer_j.swap(idx_j + 1, idx_j + 2);
-                        buffer_j.swap(idx_j, idx_j + 1);
-                    }
-
-                    buffer_i.insert(idx_i, removed_x);
-                    buffer_i.insert(idx_i + 1, removed_y);
-                    buffer_j.pop();
-                    buffer_j.pop();
+                    let mut buffer_j = customers_j[..idx_j].to_vec();
+                    buffer_j.extend_from_slice(segment_i);
+                    buffer_j.extend_from_slice(&customers_j[idx_j..]);
+                    results.push((route_i.clone(), Some(other._derive(buffer_j)), tabu.clone()));
                 }
-            }
-            Neighborhood::Move21 => {
-                for idx_i in 1..length_i - 2 {
-                    if !T::_servable(buffer_i[idx_i]) || !T::_servable(buffer_i[idx_i + 1]) {
+            } else {
+                for idx_j in 1..length_j.saturating_sub(l) {
+                    let segment_j = &customers_j[idx_j..idx_j + l];
+                    if !segment_j.iter().all(|&c| Self::_servable(c))
+                        || !(config.candidate_arc(customers_j[idx_j - 1], segment_i[0])
+                            || config.candidate_arc(segment_i[k - 1], customers_j[idx_j + l])
+                            || config.candidate_arc(customers_i[idx_i - 1], segment_j[0])
+                            || config.candidate_arc(segment_j[l - 1], customers_i[idx_i + k]))
+                    {
                         continue;
                     }
 
-                    swap(&mut buffer_i[idx_i], &mut buffer_j[1]);
-                    buffer_j.insert(2, buffer_i.remove(idx_i + 1));
+                    let mut buffer_i = customers_i[..idx_i].to_vec();
+                    buffer_i.extend_from_slice(segment_j);
+                    buffer_i.extend_from_slice(&customers_i[idx_i + k..]);
 
-                    for idx_j in 1..length_j - 1 {
-                        if Self::_servable(buffer_j[idx_j]) {
-                            let ptr_i = Self::new(buffer_i.clone());
-                            let ptr_j = T::new(buffer_j.clone());
-                            let tabu = vec![buffer_j[idx_j], buffer_j[idx_j + 1], buffer_i[idx_i]];
-                            results.push((Some(ptr_i), Some(ptr_j), tabu));
-                        }
-
-                        swap(&mut buffer_i[idx_i], &mut buffer_j[idx_j + 2]);
-                        buffer_j.swap(idx_j + 1, idx_j + 2);
-                        buffer_j.swap(idx_j, idx_j + 1);
-                    }
+                    let mut buffer_j = customers_j[..idx_j].to_vec();
+                    buffer_j.extend_from_slice(segment_i);
+                    buffer_j.extend_from_slice(&customers_j[idx_j + l..]);
 
-                    swap(&mut buffer_i[idx_i], &mut buffer_j[length_j - 1]);
-                    buffer_i.insert(idx_i + 1, buffer_j.pop().unwrap());
+                    let tabu = segment_i.iter().chain(segment_j).copied().collect();
+                    results.push((Some(self._derive(buffer_i)), Some(other._derive(buffer_j)), tabu));
                 }
             }
-            Neighborhood::Move22 => {
-                for idx_i in 1..length_i - 2 {
-                    if !T::_servable(buffer_i[idx_i]) || !T::_servable(buffer_i[idx_i + 1]) {
-                        continue;
-                    }
+        }
 
-                    for idx_j in 1..length_j - 2 {
-                        if !Self::_servable(buffer_j[idx_j]) || !Self::_servable(buffer_j[idx_j + 1]) {
-                            continue;
-                        }
+        results
+    }
 
-                        swap(&mut buffer_i[idx_i], &mut buffer_j[idx_j]);
-                        swap(&mut buffer_i[idx_i + 1], &mut buffer_j[idx_j + 1]);
+    /// Perform inter-route neighborhood search.
+    ///
+    /// This function is non-commutative (i.e. `r1.inter_route(r2, n) != r2.inter_route(r1, n)`). For example,
+    /// `r1.inter_route(r2, Neighborhood::Move(1, 0))` will move 1 customer from `r1` to `r2`, but not from `r2` to `r1`.
+    ///
+    /// For symmetric neighborhoods (e.g. `Neighborhood::Move(1, 1)`), this function will be commutative though.
+    fn inter_route<T>(
+        &self,
+        other: Arc<T>,
+        neighborhood: Neighborhood,
+    ) -> Vec<(Option<Arc<Self>>, Option<Arc<T>>, TabuAttribute)>
+    where
+        T: Route,
+    {
+        let config = config::current();
+        let customers_i = &self.data().customers;
+        let customers_j = &other.data().customers;
 
-                        let ptr_i = Self::new(buffer_i.clone());
-                        let ptr_j = T::new(buffer_j.clone());
-                        let tabu = vec![
-                            buffer_i[idx_i],
-                            buffer_i[idx_i + 1],
-                            buffer_j[idx_j],
-                            buffer_j[idx_j + 1],
-                        ];
-                        results.push((Some(ptr_i), Some(ptr_j), tabu));
+        let length_i = customers_i.len();
+        let length_j = customers_j.len();
 
-                        swap(&mut buffer_i[idx_i], &mut buffer_j[idx_j]);
-                        swap(&mut buffer_i[idx_i + 1], &mut buffer_j[idx_j + 1]);
-                    }
-                }
+        let mut results = vec![];
+
+        match neighborhood {
+            Neighborhood::Move(k, l) => {
+                results = self.move_segments(other.as_ref(), k, l);
             }
             Neighborhood::TwoOpt => {
                 let mut offset_i = length_i - 1;
-                while offset_i > 1 && T::_servable(buffer_i[offset_i - 1]) {
+                while offset_i > 1 && T::_servable(customers_i[offset_i - 1]) {
                     offset_i -= 1;
                 }
 
                 let mut offset_j = length_j - 1;
-                while offset_j > 1 && Self::_servable(buffer_j[offset_j - 1]) {
+                while offset_j > 1 && Self::_servable(customers_j[offset_j - 1]) {
                     offset_j -= 1;
                 }
 
-                for idx_i in offset_i..length_i - 1 {
-                    for idx_j in offset_j..length_j - 1 {
-                        // Construct separate buffers from scratch
-                        let mut buffer_i = customers_i[..idx_i].to_vec();
-                        let mut buffer_j = customers_j[..idx_j].to_vec();
-
-                        buffer_i.extend_from_slice(&customers_j[idx_j..]);
-                        buffer_j.extend_from_slice(&customers_i[idx_i..]);
+                // Whether the tails joined by a 2-opt move are linked by a candidate arc of the granular neighborhoods
+                let granular = |idx_i: usize, idx_j: usize| {
+                    config.candidate_arc(customers_i[idx_i - 1], customers_j[idx_j])
+                        || config.candidate_arc(customers_j[idx_j - 1], customers_i[idx_i])
+                };
+
+                let mut swap_tails = |idx_i: usize, idx_j: usize| {
+                    // Construct separate buffers from scratch
+                    let mut buffer_i = customers_i[..idx_i].to_vec();
+                    let mut buffer_j = customers_j[..idx_j].to_vec();
+
+                    buffer_i.extend_from_slice(&customers_j[idx_j..]);
+                    buffer_j.extend_from_slice(&customers_i[idx_i..]);
+
+                    let tabu = smallvec![buffer_i[idx_i], buffer_j[idx_j]];
+
+                    // Move the buffers to the new routes
+                    let ptr_i = Self::new(buffer_i);
+                    let ptr_j = T::new(buffer_j);
+                    results.push((Some(ptr_i), Some(ptr_j), tabu));
+                };
+
+                if config.two_opt_best_only && Self::vehicle_kind() == T::vehicle_kind() {
+                    // Both routes are measured with the same distances, so the new routes can be measured from the
+                    // suffix aggregates and the two arcs joining the tails
+                    let (suffixes_i, suffixes_j) = (self._suffixes(), other._suffixes());
+                    let distances = Self::distances(&config);
+                    let (capacity_i, capacity_j) = (Self::capacity(0), T::capacity(0));
+                    let (bounds_i, bounds_j) = (
+                        Self::min_customers()..=Self::max_customers(),
+                        T::min_customers()..=T::max_customers(),
+                    );
+
+                    let mut best: Option<((f64, f64), usize, usize)> = None;
+                    for idx_i in offset_i..length_i - 1 {
+                        for idx_j in offset_j..length_j - 1 {
+                            if !bounds_i.contains(&(idx_i - 1 + length_j - 1 - idx_j))
+                                || !bounds_j.contains(&(idx_j - 1 + length_i - 1 - idx_i))
+                                || !granular(idx_i, idx_j)
+                            {
+                                continue;
+                            }
+
+                            let distance_i = suffixes_i.distance[0] - suffixes_i.distance[idx_i - 1]
+                                + distances.get(customers_i[idx_i - 1], customers_j[idx_j])
+                                + suffixes_j.distance[idx_j];
+                            let distance_j = suffixes_j.distance[0] - suffixes_j.distance[idx_j - 1]
+                                + distances.get(customers_j[idx_j - 1], customers_i[idx_i])
+                                + suffixes_i.distance[idx_i];
+                            let weight_i = suffixes_i.weight[0] - suffixes_i.weight[idx_i] + suffixes_j.weight[idx_j];
+                            let weight_j = suffixes_j.weight[0] - suffixes_j.weight[idx_j] + suffixes_i.weight[idx_i];
+
+                            let score = (
+                                (weight_i - capacity_i).max(0.0) + (weight_j - capacity_j).max(0.0),
+                                distance_i.max(distance_j),
+                            );
+                            if best.is_none_or(|(s, _, _)| score < s) {
+                                best = Some((score, idx_i, idx_j));
+                            }
+                        }
+                    }
 
-                        let tabu = vec![buffer_i[idx_i], buffer_j[idx_j]];
+                    if let Some((_, idx_i, idx_j)) = best {
+                        swap_tails(idx_i, idx_j);
+                    }
+                } else {
+                    for idx_i in offset_i..length_i - 1 {
+                        for idx_j in offset_j..length_j - 1 {
+                            if granular(idx_i, idx_j) {
+                                swap_tails(idx_i, idx_j);
+                            }
+                        }
+                    }
+                }
+            }
+            Neighborhood::OrOpt => {
+                for size in 1..=3 {
+                    for idx_i in 1..length_i.saturating_sub(size) {
+                        let segment = &customers_i[idx_i..idx_i + size];
+                        if !segment.iter().all(|&c| T::_servable(c)) {
+                            continue;
+                        }
 
-                        // Move the buffers to the new routes
-                        let ptr_i = Self::new(buffer_i);
-                        let ptr_j = T::new(buffer_j);
-                        results.push((Some(ptr_i), Some(ptr_j), tabu));
+                        let route_i = if length_i - 2 == size {
+                            None
+                        } else {
+                            let mut buffer_i = customers_i[..idx_i].to_vec();
+                            buffer_i.extend_from_slice(&customers_i[idx_i + size..]);
+                            Some(Self::new(buffer_i))
+                        };
+                        let tabu = TabuAttribute::from_slice(segment);
+
+                        for idx_j in 1..length_j {
+                            for reversed in [false, true] {
+                                if reversed && size == 1 {
+                                    continue;
+                                }
+
+                                let (first, last) = if reversed {
+                                    (segment[size - 1], segment[0])
+                                } else {
+                                    (segment[0], segment[size - 1])
+                                };
+                                if !config.candidate_arc(customers_j[idx_j - 1], first)
+                                    && !config.candidate_arc(last, customers_j[idx_j])
+                                {
+                                    continue;
+                                }
+
+                                let mut buffer_j = customers_j[..idx_j].to_vec();
+                                if reversed {
+                                    buffer_j.extend(segment.iter().rev());
+                                } else {
+                                    buffer_j.extend_from_slice(segment);
+                                }
+                                buffer_j.extend_from_slice(&customers_j[idx_j..]);
+                                results.push((route_i.clone(), Some(T::new(buffer_j)), tabu.clone()));
+                            }
+                        }
                     }
                 }
             }
@@ -370,10 +789,10 @@ pub trait Route: Sized {
 
     fn inter_route_3<T1, T2>(
         &self,
-        other_x: Rc<T1>,
-        other_y: Rc<T2>,
+        other_x: Arc<T1>,
+        other_y: Arc<T2>,
         neighborhood: Neighborhood,
-    ) -> Vec<(Option<Rc<Self>>, Rc<T1>, Rc<T2>, Vec<usize>)>
+    ) -> Vec<(Option<Arc<Self>>, Arc<T1>, Arc<T2>, TabuAttribute)>
     where
         T1: Route,
         T2: Route,
@@ -409,7 +828,7 @@ pub trait Route: Sized {
                         buffer_j[idx_j] = remove_x;
 
                         for idx_k in 1..length_k {
-                            let tabu = vec![remove_x, buffer_k[idx_k]];
+                            let tabu = smallvec![remove_x, buffer_k[idx_k]];
 
                             let ptr_i = if buffer_i.len() == 2 {
                                 None
@@ -436,20 +855,20 @@ pub trait Route: Sized {
     }
 
     /// Returns a pointer to the underlying cached intra-route neighbors.
-    fn intra_route(&self, neighborhood: Neighborhood) -> Vec<(Rc<Self>, Vec<usize>)> {
+    fn intra_route(&self, neighborhood: Neighborhood) -> Vec<(Arc<Self>, TabuAttribute)> {
         let data = self.data();
 
         let length = data.customers.len();
         let mut results = vec![];
         let mut buffer = data.customers.clone();
         match neighborhood {
-            Neighborhood::Move10 => {
+            Neighborhood::Move(1, 0) => {
                 for i in 1..length - 2 {
                     for j in i..length - 2 {
                         buffer.swap(j, j + 1);
 
-                        let ptr = Self::new(buffer.clone());
-                        let tabu = vec![data.customers[i]];
+                        let ptr = self._derive(buffer.clone());
+                        let tabu = smallvec![data.customers[i]];
                         // println!("buffer = {:?}, tabu = {:?}", buffer, tabu);
                         results.push((ptr, tabu));
                     }
@@ -461,8 +880,8 @@ pub trait Route: Sized {
                     for j in (2..i + 1).rev() {
                         buffer.swap(j - 1, j);
 
-                        let ptr = Self::new(buffer.clone());
-                        let tabu = vec![data.customers[i]];
+                        let ptr = self._derive(buffer.clone());
+                        let tabu = smallvec![data.customers[i]];
                         // println!("buffer = {:?}, tabu = {:?}", buffer, tabu);
                         results.push((ptr, tabu));
                     }
@@ -470,14 +889,14 @@ pub trait Route: Sized {
                     buffer[1..i + 1].rotate_left(1);
                 }
             }
-            Neighborhood::Move11 => {
+            Neighborhood::Move(1, 1) => {
                 for i in 1..length - 2 {
                     for j in i..length - 2 {
                         buffer.swap(j, j + 1);
                         buffer.swap(i, j);
 
-                        let ptr = Self::new(buffer.clone());
-                        let tabu = vec![data.customers[i], data.customers[j + 1]];
+                        let ptr = self._derive(buffer.clone());
+                        let tabu = smallvec![data.customers[i], data.customers[j + 1]];
                         // println!("buffer = {:?}, tabu = {:?}", buffer, tabu);
                         results.push((ptr, tabu));
                     }
@@ -485,14 +904,14 @@ pub trait Route: Sized {
                     buffer.swap(i, length - 2);
                 }
             }
-            Neighborhood::Move20 => {
+            Neighborhood::Move(2, 0) => {
                 for i in 1..length - 3 {
                     for j in i + 1..length - 2 {
                         buffer.swap(j, j + 1);
                         buffer.swap(j - 1, j);
 
-                        let ptr = Self::new(buffer.clone());
-                        let tabu = vec![data.customers[i], data.customers[i + 1]];
+                        let ptr = self._derive(buffer.clone());
+                        let tabu = smallvec![data.customers[i], data.customers[i + 1]];
                         // println!("buffer = {:?}, tabu = {:?}", buffer, tabu);
                         results.push((ptr, tabu));
                     }
@@ -505,8 +924,8 @@ pub trait Route: Sized {
                         buffer.swap(j + 1, j + 2);
                         buffer.swap(j, j + 2);
 
-                        let ptr = Self::new(buffer.clone());
-                        let tabu = vec![data.customers[i], data.customers[i + 1]];
+                        let ptr = self._derive(buffer.clone());
+                        let tabu = smallvec![data.customers[i], data.customers[i + 1]];
                         // println!("buffer = {:?}, tabu = {:?}", buffer, tabu);
                         results.push((ptr, tabu));
                     }
@@ -514,15 +933,15 @@ pub trait Route: Sized {
                     buffer[1..i + 2].rotate_left(2);
                 }
             }
-            Neighborhood::Move21 => {
+            Neighborhood::Move(2, 1) => {
                 for i in 1..length - 3 {
                     for j in i..length - 3 {
                         buffer.swap(j + 1, j + 2);
                         buffer.swap(j, j + 1);
                         buffer.swap(i, j);
 
-                        let ptr = Self::new(buffer.clone());
-                        let tabu = vec![data.customers[i], data.customers[i + 1], data.customers[j + 2]];
+                        let ptr = self._derive(buffer.clone());
+                        let tabu = smallvec![data.customers[i], data.customers[i + 1], data.customers[j + 2]];
                         // println!("buffer = {:?}, tabu = {:?}", buffer, tabu);
                         results.push((ptr, tabu));
                     }
@@ -537,8 +956,8 @@ pub trait Route: Sized {
                         buffer.swap(j, j + 2);
                         buffer.swap(j + 2, i + 1);
 
-                        let ptr = Self::new(buffer.clone());
-                        let tabu = vec![data.customers[i], data.customers[i + 1], data.customers[j]];
+                        let ptr = self._derive(buffer.clone());
+                        let tabu = smallvec![data.customers[i], data.customers[i + 1], data.customers[j]];
                         // println!("buffer = {:?}, tabu = {:?}", buffer, tabu);
                         results.push((ptr, tabu));
                     }
@@ -547,14 +966,14 @@ pub trait Route: Sized {
                     buffer[2..i + 2].rotate_left(1);
                 }
             }
-            Neighborhood::Move22 => {
+            Neighborhood::Move(2, 2) => {
                 for i in 1..length.saturating_sub(4) {
                     {
                         buffer.swap(i, i + 2);
                         buffer.swap(i + 1, i + 3);
 
-                        let ptr = Self::new(buffer.clone());
-                        let tabu = vec![
+                        let ptr = self._derive(buffer.clone());
+                        let tabu = smallvec![
                             data.customers[i],
                             data.customers[i + 1],
                             data.customers[i + 2],
@@ -570,8 +989,8 @@ pub trait Route: Sized {
                         buffer.swap(j, j + 1);
                         buffer.swap(j - 1, j);
 
-                        let ptr = Self::new(buffer.clone());
-                        let tabu = vec![
+                        let ptr = self._derive(buffer.clone());
+                        let tabu = smallvec![
                             data.customers[i],
                             data.customers[i + 1],
                             data.customers[j],
@@ -590,8 +1009,8 @@ pub trait Route: Sized {
                     {
                         buffer.swap(i, i + 1);
 
-                        let ptr = Self::new(buffer.clone());
-                        let tabu = vec![data.customers[i], data.customers[i + 1]];
+                        let ptr = self._derive(buffer.clone());
+                        let tabu = smallvec![data.customers[i], data.customers[i + 1]];
                         // println!("buffer = {:?}, tabu = {:?}", buffer, tabu);
                         results.push((ptr, tabu));
                     }
@@ -599,8 +1018,8 @@ pub trait Route: Sized {
                     for j in i + 2..length - 1 {
                         buffer[i..j + 1].rotate_right(1);
 
-                        let ptr = Self::new(buffer.clone());
-                        let tabu = vec![data.customers[i], data.customers[j]];
+                        let ptr = self._derive(buffer.clone());
+                        let tabu = smallvec![data.customers[i], data.customers[j]];
                         // println!("buffer = {:?}, tabu = {:?}", buffer, tabu);
                         results.push((ptr, tabu));
                     }
@@ -608,6 +1027,72 @@ pub trait Route: Sized {
                     buffer[i..length - 1].reverse();
                 }
             }
+            Neighborhood::OrOpt => {
+                // Moving all customers of the route would only reverse it, which 2-opt already covers
+                for size in (1..=3).take_while(|&size| size < length - 2) {
+                    for i in 1..length - size {
+                        let segment = &data.customers[i..i + size];
+                        let mut rest = data.customers[..i].to_vec();
+                        rest.extend_from_slice(&data.customers[i + size..]);
+
+                        for position in 1..rest.len() {
+                            for reversed in [false, true] {
+                                if (reversed && size == 1) || (!reversed && position == i) {
+                                    continue; // The route itself
+                                }
+
+                                let mut customers = rest[..position].to_vec();
+                                if reversed {
+                                    customers.extend(segment.iter().rev());
+                                } else {
+                                    customers.extend_from_slice(segment);
+                                }
+                                customers.extend_from_slice(&rest[position..]);
+                                results.push((self._derive(customers), TabuAttribute::from_slice(segment)));
+                            }
+                        }
+                    }
+                }
+            }
+            Neighborhood::Move(k, l) => {
+                // Any other segment lengths: exchange 2 disjoint segments, or relocate a segment when `l` is 0
+                let customers = &data.customers;
+                for a in 1..length.saturating_sub(k) {
+                    let segment_a = &customers[a..a + k];
+                    if l == 0 {
+                        let mut rest = customers[..a].to_vec();
+                        rest.extend_from_slice(&customers[a + k..]);
+                        for position in (1..rest.len()).filter(|&position| position != a) {
+                            let mut buffer = rest[..position].to_vec();
+                            buffer.extend_from_slice(segment_a);
+                            buffer.extend_from_slice(&rest[position..]);
+                            results.push((self._derive(buffer), TabuAttribute::from_slice(segment_a)));
+                        }
+
+                        continue;
+                    }
+
+                    for b in 1..length.saturating_sub(l) {
+                        // Exchanging 2 segments of equal lengths is symmetric, only generate it once
+                        let (first, second) = if a + k <= b {
+                            ((a, k), (b, l))
+                        } else if b + l <= a && k != l {
+                            ((b, l), (a, k))
+                        } else {
+                            continue;
+                        };
+
+                        let mut buffer = customers[..first.0].to_vec();
+                        buffer.extend_from_slice(&customers[second.0..second.0 + second.1]);
+                        buffer.extend_from_slice(&customers[first.0 + first.1..second.0]);
+                        buffer.extend_from_slice(&customers[first.0..first.0 + first.1]);
+                        buffer.extend_from_slice(&customers[second.0 + second.1..]);
+
+                        let tabu = segment_a.iter().chain(&customers[b..b + l]).copied().collect();
+                        results.push((self._derive(buffer), tabu));
+                    }
+                }
+            }
             _ => panic!("intra_route called with invalid neighborhood {neighborhood}"),
         }
 
@@ -621,9 +1106,7 @@ pub trait Route: Sized {
 
 pub struct TruckRoute {
     _data: _RouteData,
-    _working_time: f64,
-    _capacity_violation: f64,
-    _waiting_time_violation: f64,
+    _values: _RouteValuesCache,
 }
 
 impl fmt::Debug for TruckRoute {
@@ -632,93 +1115,316 @@ impl fmt::Debug for TruckRoute {
     }
 }
 
+impl TruckRoute {
+    /// Values of the route described by `data` when served by `truck`, given its waiting time violation.
+    fn _values(config: &Config, data: &_RouteData, truck: &TruckConfig, waiting_time_violation: f64) -> RouteValues {
+        RouteValues {
+            working_time: data.value.distance / truck.speed,
+            capacity_violation: config.capacity_violation(&data.customers, data.value.weight, truck.capacity),
+            waiting_time_violation,
+            energy_violation: 0.0,
+            fixed_time_violation: 0.0,
+            cost: config.truck_cost_per_km * data.value.distance / 1000.0,
+        }
+    }
+}
+
 impl Route for TruckRoute {
-    fn new(customers: Vec<usize>) -> Rc<Self> {
-        Rc::new(Self::_construct(_RouteData::_construct(
-            customers.clone(),
-            &CONFIG.truck_distances,
-        )))
+    type Vehicle = TruckConfig;
+
+    fn new(customers: Vec<usize>) -> Arc<Self> {
+        config::with_current(|config| {
+            let data = _RouteData::_construct(customers, Self::distances(config), &config.demands);
+            Arc::new(Self {
+                _values: _RouteValuesCache::new(
+                    Self::_evaluate(config, &data, 0)._quantized(config),
+                    Self::vehicle_classes(config).len(),
+                ),
+                _data: data,
+            })
+        })
+    }
+
+    /// Only the customers between the first and the last that differ from this route are walked through: the waiting
+    /// time violation is derived from the prefix aggregates of this route with [`Self::waiting_time_violation_with`].
+    fn _derive(&self, customers: Vec<usize>) -> Arc<Self> {
+        let original = &self.data().customers;
+        let common = original.len().min(customers.len());
+        let start = original
+            .iter()
+            .zip(&customers)
+            .take_while(|(a, b)| a == b)
+            .count()
+            .min(common - 1);
+        let tail = original
+            .iter()
+            .rev()
+            .zip(customers.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count()
+            .min(common - start);
+        let (end, segment_end) = (original.len() - tail, customers.len() - tail);
+
+        config::with_current(|config| {
+            let data = _RouteData::_construct(customers, Self::distances(config), &config.demands);
+            let truck = &Self::vehicle_classes(config)[0];
+            let waiting_time_violation = self.waiting_time_violation_with(
+                config,
+                start,
+                end,
+                &data.customers[start..segment_end],
+                data.value.distance / truck.speed,
+            );
+            let mut values = _RouteValuesCache::new(
+                Self::_values(config, &data, truck, waiting_time_violation)._quantized(config),
+                Self::vehicle_classes(config).len(),
+            );
+            values._derived = true;
+            Arc::new(Self {
+                _values: values,
+                _data: data,
+            })
+        })
     }
 
     fn get_correct_route<'a>(
-        truck_routes: &'a [Vec<Rc<TruckRoute>>],
-        _: &'a [Vec<Rc<DroneRoute>>],
-    ) -> &'a [Vec<Rc<Self>>] {
+        truck_routes: &'a [Vec<Arc<TruckRoute>>],
+        _: &'a [Vec<Arc<DroneRoute>>],
+    ) -> &'a [Vec<Arc<Self>>] {
         truck_routes
     }
 
     fn get_correct_route_mut<'a>(
-        truck_routes: &'a mut Vec<Vec<Rc<TruckRoute>>>,
-        _: &'a mut Vec<Vec<Rc<DroneRoute>>>,
-    ) -> &'a mut Vec<Vec<Rc<Self>>> {
+        truck_routes: &'a mut Vec<Vec<Arc<TruckRoute>>>,
+        _: &'a mut Vec<Vec<Arc<DroneRoute>>>,
+    ) -> &'a mut Vec<Vec<Arc<Self>>> {
         truck_routes
     }
 
-    fn single_customer() -> bool {
-        false
+    fn min_customers() -> usize {
+        1
     }
 
-    fn single_route() -> bool {
-        CONFIG.single_truck_route
+    fn max_customers() -> usize {
+        config::with_current(|config| config.max_customers_per_truck_route.unwrap_or(usize::MAX))
     }
 
-    fn data(&self) -> &_RouteData {
-        &self._data
+    fn max_routes() -> usize {
+        config::with_current(|config| {
+            if config.single_truck_route {
+                1
+            } else {
+                config.max_routes_per_truck.unwrap_or(usize::MAX)
+            }
+        })
     }
 
-    fn working_time(&self) -> f64 {
-        self._working_time
+    fn vehicle_classes(config: &Config) -> &[TruckConfig] {
+        config.truck_classes()
     }
 
-    fn capacity_violation(&self) -> f64 {
-        self._capacity_violation
+    fn capacity(class: usize) -> f64 {
+        config::with_current(|config| Self::vehicle_classes(config)[class].capacity)
     }
 
-    fn waiting_time_violation(&self) -> f64 {
-        self._waiting_time_violation
+    fn _leg_time(config: &Config, from: usize, to: usize, _: f64) -> f64 {
+        config.truck_distances.get(from, to) / Self::vehicle_classes(config)[0].speed
     }
 
-    fn _servable(_customer: usize) -> bool {
-        true
+    fn _waiting_positions(length: usize) -> Range<usize> {
+        1..length - 1
     }
-}
 
-impl TruckRoute {
-    fn _calculate_waiting_time_violation(customers: &[usize], working_time: f64) -> f64 {
-        let speed = CONFIG.truck.speed;
+    fn distances(config: &Config) -> &DistanceMatrix {
+        &config.truck_distances
+    }
+
+    fn data(&self) -> &_RouteData {
+        &self._data
+    }
+
+    fn _values_cache(&self) -> &_RouteValuesCache {
+        &self._values
+    }
+
+    fn _evaluate(config: &Config, data: &_RouteData, class: usize) -> RouteValues {
+        let truck = &config.truck_classes()[class];
+        let customers = &data.customers;
+
+        let working_time = data.value.distance / truck.speed;
         let mut waiting_time_violation = 0.0;
         let mut accumulate_time = 0.0;
         for i in 1..customers.len() - 1 {
-            accumulate_time += CONFIG.truck_distances[customers[i - 1]][customers[i]] / speed;
-            waiting_time_violation += (working_time - accumulate_time - CONFIG.waiting_time_limit).max(0.0);
+            accumulate_time += config.truck_distances.get(customers[i - 1], customers[i]) / truck.speed;
+            waiting_time_violation +=
+                config.priorities[customers[i]] * (working_time - accumulate_time - config.waiting_time_limit).max(0.0);
         }
 
-        waiting_time_violation
+        Self::_values(config, data, truck, waiting_time_violation)
     }
 
-    fn _construct(data: _RouteData) -> Self {
-        let speed = CONFIG.truck.speed;
-        let _working_time = data.value.distance / speed;
-        let _capacity_violation = (data.value.weight - CONFIG.truck.capacity).max(0.0);
-        let _waiting_time_violation = Self::_calculate_waiting_time_violation(&data.customers, _working_time);
+    fn trip(&self, class: usize, departure: f64) -> Trip {
+        let config = config::current();
+        let truck = &Self::vehicle_classes(&config)[class];
+        let customers = &self.data().customers;
 
-        Self {
-            _data: data,
-            _working_time,
-            _capacity_violation,
-            _waiting_time_violation,
+        let mut time = departure;
+        let mut stops = Vec::with_capacity(customers.len() - 2);
+        for i in 1..customers.len() - 1 {
+            time += config.truck_distances.get(customers[i - 1], customers[i]) / truck.speed;
+            stops.push(Stop {
+                customer: customers[i],
+                arrival: time,
+                service_start: time,
+                service_end: time,
+            });
+        }
+
+        Trip {
+            departure,
+            stops,
+            return_time: departure + self.values(class).working_time,
+        }
+    }
+
+    fn time_breakdown(&self, class: usize) -> TimeBreakdown {
+        TimeBreakdown {
+            travel: self.data().value.distance / Self::vehicle_classes(&config::current())[class].speed,
+            ..TimeBreakdown::default()
         }
     }
+
+    fn _servable(customer: usize) -> bool {
+        config::with_current(|config| {
+            config.lock(customer).is_none_or(|l| l.vehicle == VehicleKind::Truck)
+                && config.zones.servable(VehicleKind::Truck, customer)
+        })
+    }
+
+    fn vehicle_kind() -> VehicleKind {
+        VehicleKind::Truck
+    }
+}
+
+/// Extra takeoff and landing time of a drone route caused by the payload carried and the altitude flown on each leg
+/// (0 when the durations depend on neither). The payload of each leg matches the energy integration in
+/// [`DroneRoute::_evaluate`].
+fn _vertical_delay(config: &Config, drone: &DroneConfig, customers: &[usize]) -> f64 {
+    let takeoff = drone.takeoff_time(0.0);
+    let landing = drone.landing_time(0.0);
+
+    let mut delay = 0.0;
+    let mut weight = 0.0;
+    for leg in customers.windows(2) {
+        let scale = config.altitude_scale(drone, leg[0], leg[1]);
+        delay +=
+            drone.takeoff_time(weight).mul_add(scale, -takeoff) + drone.landing_time(weight).mul_add(scale, -landing);
+        weight += config.demands[leg[0]];
+    }
+
+    delay
 }
 
 pub struct DroneRoute {
     _data: _RouteData,
-    _working_time: f64,
-    _capacity_violation: f64,
-    _waiting_time_violation: f64,
+    _values: _RouteValuesCache,
+    /// States of the integration along the route by a drone of class 0, see [`DroneRoute::_legs`].
+    _legs: OnceLock<Box<[_LegState]>>,
+}
 
-    pub energy_violation: f64,
-    pub fixed_time_violation: f64,
+/// State of the integration of the time and energy along a drone route, after a number of legs.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct _LegState {
+    time: f64,
+    /// Compensated, since the power models integrate many small terms.
+    energy: CompensatedSum,
+    /// Payload carried on the next leg.
+    weight: f64,
+}
+
+impl _LegState {
+    /// Fly the leg from customer `from` to customer `to` with `drone` and serve `to`.
+    fn _advance(&mut self, config: &Config, drone: &DroneConfig, from: usize, to: usize) {
+        let scale = config.altitude_scale(drone, from, to);
+        let takeoff = drone.takeoff_time(self.weight) * scale;
+        let landing = drone.landing_time(self.weight) * scale;
+        let cruise = drone.cruise_time(config.drone_distances.get(from, to));
+
+        // The handover at the next customer happens on the ground, after landing and before the next takeoff
+        self.time += takeoff + cruise + landing + config.drone_service_times[to];
+        self.energy += drone.landing_power(self.weight).mul_add(
+            landing,
+            drone
+                .takeoff_power(self.weight)
+                .mul_add(takeoff, drone.cruise_power(self.weight) * cruise),
+        );
+        self.weight += config.demands[from];
+    }
+}
+
+impl DroneRoute {
+    /// States of the integration along this route by a drone of class 0 after each number of legs, computed when
+    /// first queried (i.e. for the routes moves start from, not for every candidate).
+    fn _legs(&self) -> &[_LegState] {
+        self._legs.get_or_init(|| {
+            config::with_current(|config| {
+                let customers = &self.data().customers;
+                let drone = &Self::vehicle_classes(config)[0];
+                let mut state = _LegState::default();
+                let mut result = Vec::with_capacity(customers.len());
+                result.push(state);
+                for i in 0..customers.len() - 1 {
+                    state._advance(config, drone, customers[i], customers[i + 1]);
+                    result.push(state);
+                }
+
+                result.into()
+            })
+        })
+    }
+
+    /// Values of the route described by `data` when served by `drone`, integrating the legs from the states `legs` of
+    /// a route sharing its first `legs.len() - 1` legs.
+    fn _evaluate_from(config: &Config, data: &_RouteData, drone: &DroneConfig, legs: &[_LegState]) -> RouteValues {
+        let customers = &data.customers;
+
+        let service_time = customers.iter().map(|&c| config.drone_service_times[c]).sum::<f64>();
+        let working_time = (drone.takeoff_time(0.0) + drone.landing_time(0.0)).mul_add(
+            customers.len() as f64 - 1.0,
+            drone.cruise_time(data.value.distance) + service_time,
+        ) + _vertical_delay(config, drone, customers);
+        let capacity_violation = config.capacity_violation(customers, data.value.weight, drone.capacity());
+
+        let mut waiting_time_violation = 0.0;
+        for (&customer, state) in customers.iter().zip(legs).skip(1) {
+            waiting_time_violation +=
+                config.priorities[customer] * (working_time - state.time - config.waiting_time_limit).max(0.0);
+        }
+
+        let mut state = legs[legs.len() - 1];
+        for i in legs.len() - 1..customers.len() - 1 {
+            state._advance(config, drone, customers[i], customers[i + 1]);
+            waiting_time_violation +=
+                config.priorities[customers[i + 1]] * (working_time - state.time - config.waiting_time_limit).max(0.0);
+        }
+
+        RouteValues {
+            working_time,
+            capacity_violation,
+            waiting_time_violation,
+            energy_violation: (state.energy.value() - drone.battery()).max(0.0),
+            fixed_time_violation: (working_time - drone.fixed_time()).max(0.0),
+            cost: config.drone_cost_per_kj * state.energy.value() / 1000.0,
+        }
+    }
+
+    fn _construct(config: &Config, data: _RouteData, values: RouteValues) -> Arc<Self> {
+        Arc::new(Self {
+            _values: _RouteValuesCache::new(values._quantized(config), Self::vehicle_classes(config).len()),
+            _data: data,
+            _legs: OnceLock::new(),
+        })
+    }
 }
 
 impl fmt::Debug for DroneRoute {
@@ -728,107 +1434,172 @@ impl fmt::Debug for DroneRoute {
 }
 
 impl Route for DroneRoute {
-    fn new(customers: Vec<usize>) -> Rc<Self> {
-        Rc::new(Self::_construct(_RouteData::_construct(
-            customers.clone(),
-            &CONFIG.drone_distances,
-        )))
+    type Vehicle = DroneConfig;
+
+    fn new(customers: Vec<usize>) -> Arc<Self> {
+        config::with_current(|config| {
+            let data = _RouteData::_construct(customers, Self::distances(config), &config.demands);
+            let values = Self::_evaluate(config, &data, 0);
+            Self::_construct(config, data, values)
+        })
+    }
+
+    /// The legs before the first customer that differs from this route are not integrated again: their energy
+    /// (whose power models are the costliest part of the evaluation) and times are taken from [`Self::_legs`].
+    fn _derive(&self, customers: Vec<usize>) -> Arc<Self> {
+        let common = self
+            .data()
+            .customers
+            .iter()
+            .zip(&customers)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let legs = &self._legs()[..common];
+        config::with_current(|config| {
+            let data = _RouteData::_construct(customers, Self::distances(config), &config.demands);
+            let values = Self::_evaluate_from(config, &data, &Self::vehicle_classes(config)[0], legs);
+            Self::_construct(config, data, values)
+        })
     }
 
     fn get_correct_route<'a>(
-        _: &'a [Vec<Rc<TruckRoute>>],
-        drone_routes: &'a [Vec<Rc<DroneRoute>>],
-    ) -> &'a [Vec<Rc<Self>>] {
+        _: &'a [Vec<Arc<TruckRoute>>],
+        drone_routes: &'a [Vec<Arc<DroneRoute>>],
+    ) -> &'a [Vec<Arc<Self>>] {
         drone_routes
     }
 
     fn get_correct_route_mut<'a>(
-        _: &'a mut Vec<Vec<Rc<TruckRoute>>>,
-        drone_routes: &'a mut Vec<Vec<Rc<DroneRoute>>>,
-    ) -> &'a mut Vec<Vec<Rc<Self>>> {
+        _: &'a mut Vec<Vec<Arc<TruckRoute>>>,
+        drone_routes: &'a mut Vec<Vec<Arc<DroneRoute>>>,
+    ) -> &'a mut Vec<Vec<Arc<Self>>> {
         drone_routes
     }
 
-    fn single_customer() -> bool {
-        CONFIG.single_drone_route
+    fn min_customers() -> usize {
+        config::with_current(|config| config.min_customers_per_drone_route)
     }
 
-    fn single_route() -> bool {
-        false
+    fn max_customers() -> usize {
+        config::with_current(|config| {
+            if config.single_drone_route {
+                1
+            } else {
+                config.max_customers_per_drone_route.unwrap_or(usize::MAX)
+            }
+        })
     }
 
-    fn data(&self) -> &_RouteData {
-        &self._data
+    fn max_routes() -> usize {
+        usize::MAX
     }
 
-    fn working_time(&self) -> f64 {
-        self._working_time
+    fn vehicle_classes(config: &Config) -> &[DroneConfig] {
+        config.drone_classes()
     }
 
-    fn capacity_violation(&self) -> f64 {
-        self._capacity_violation
+    fn capacity(class: usize) -> f64 {
+        config::with_current(|config| Self::vehicle_classes(config)[class].capacity())
     }
 
-    fn waiting_time_violation(&self) -> f64 {
-        self._waiting_time_violation
+    fn _leg_time(config: &Config, from: usize, to: usize, weight: f64) -> f64 {
+        let drone = &Self::vehicle_classes(config)[0];
+        let scale = config.altitude_scale(drone, from, to);
+        (drone.takeoff_time(weight) + drone.landing_time(weight)).mul_add(
+            scale,
+            drone.cruise_time(config.drone_distances.get(from, to)) + config.drone_service_times[to],
+        )
     }
 
-    fn _servable(customer: usize) -> bool {
-        CONFIG.dronable[customer]
+    /// The time back at the depot is penalized as well, as the working time of a drone route is not exactly the time
+    /// it takes to fly it.
+    fn _waiting_positions(length: usize) -> Range<usize> {
+        1..length
     }
-}
 
-impl DroneRoute {
-    fn _construct(data: _RouteData) -> Self {
-        let customers = &data.customers;
-        let distances = &CONFIG.drone_distances;
-        let drone = &CONFIG.drone;
+    fn distances(config: &Config) -> &DistanceMatrix {
+        &config.drone_distances
+    }
 
-        let _working_time = (CONFIG.drone.takeoff_time() + CONFIG.drone.landing_time()).mul_add(
-            customers.len() as f64 - 1.0,
-            CONFIG.drone.cruise_time(data.value.distance),
-        );
-        let _capacity_violation = (data.value.weight - CONFIG.drone.capacity()).max(0.0);
+    fn data(&self) -> &_RouteData {
+        &self._data
+    }
 
-        let mut time = 0.0;
-        let mut energy = 0.0;
-        let mut weight = 0.0;
-        let mut _waiting_time_violation = 0.0;
+    fn _values_cache(&self) -> &_RouteValuesCache {
+        &self._values
+    }
 
-        let takeoff = drone.takeoff_time();
-        let landing = drone.landing_time();
-        for i in 0..customers.len() - 1 {
-            let cruise = drone.cruise_time(distances[customers[i]][customers[i + 1]]);
-
-            time += takeoff + cruise + landing;
-            energy += drone.landing_power(weight).mul_add(
-                landing,
-                drone
-                    .takeoff_power(weight)
-                    .mul_add(takeoff, drone.cruise_power(weight) * cruise),
-            );
-            weight += CONFIG.demands[customers[i]];
-            _waiting_time_violation += (_working_time - time - CONFIG.waiting_time_limit).max(0.0);
+    fn _evaluate(config: &Config, data: &_RouteData, class: usize) -> RouteValues {
+        Self::_evaluate_from(config, data, &config.drone_classes()[class], &[_LegState::default()])
+    }
+
+    fn trip(&self, class: usize, departure: f64) -> Trip {
+        let config = config::current();
+        let drone = &Self::vehicle_classes(&config)[class];
+        let customers = &self.data().customers;
+
+        let mut time = departure;
+        let mut weight = 0.0;
+        let mut stops = Vec::with_capacity(customers.len() - 2);
+        for i in 1..customers.len() - 1 {
+            // Each leg consists of a takeoff, a cruise and a landing, followed by the handover at the customer
+            let scale = config.altitude_scale(drone, customers[i - 1], customers[i]);
+            let takeoff = drone.takeoff_time(weight) * scale;
+            let landing = drone.landing_time(weight) * scale;
+            let arrival = time
+                + takeoff
+                + drone.cruise_time(config.drone_distances.get(customers[i - 1], customers[i]))
+                + landing;
+            weight += config.demands[customers[i - 1]];
+            time = arrival + config.drone_service_times[customers[i]];
+            stops.push(Stop {
+                customer: customers[i],
+                arrival,
+                service_start: arrival,
+                service_end: time,
+            });
         }
 
-        let energy_violation = (energy - CONFIG.drone.battery()).max(0.0);
-        let fixed_time_violation = (_working_time - CONFIG.drone.fixed_time()).max(0.0);
+        Trip {
+            departure,
+            stops,
+            return_time: departure + self.values(class).working_time,
+        }
+    }
 
-        Self {
-            _data: data,
-            _working_time,
-            _capacity_violation,
-            _waiting_time_violation,
-            energy_violation,
-            fixed_time_violation,
+    fn time_breakdown(&self, class: usize) -> TimeBreakdown {
+        let config = config::current();
+        let drone = &Self::vehicle_classes(&config)[class];
+        let data = self.data();
+        TimeBreakdown {
+            travel: drone.cruise_time(data.value.distance),
+            takeoff_landing: (drone.takeoff_time(0.0) + drone.landing_time(0.0)).mul_add(
+                data.customers.len() as f64 - 1.0,
+                _vertical_delay(&config, drone, &data.customers),
+            ),
+            service: data.customers.iter().map(|&c| config.drone_service_times[c]).sum(),
+            ..TimeBreakdown::default()
         }
     }
+
+    fn _servable(customer: usize) -> bool {
+        config::with_current(|config| {
+            config.dronable[customer]
+                && config.lock(customer).is_none_or(|l| l.vehicle == VehicleKind::Drone)
+                && config.zones.servable(VehicleKind::Drone, customer)
+        })
+    }
+
+    fn vehicle_kind() -> VehicleKind {
+        VehicleKind::Drone
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum AnyRoute {
-    Truck(Rc<TruckRoute>),
-    Drone(Rc<DroneRoute>),
+    Truck(Arc<TruckRoute>),
+    Drone(Arc<DroneRoute>),
 }
 
 impl AnyRoute {
@@ -876,6 +1647,27 @@ impl AnyRoute {
         )
     }
 
+    /// See [`Route::_arcs_respected`].
+    pub fn _arcs_respected(&self) -> bool {
+        config::with_current(|config| config.arcs.respected(self.customers()))
+    }
+
+    /// See [`Route::_length_respected`].
+    pub fn _length_respected(&self) -> bool {
+        match self {
+            Self::Truck(route) => route._length_respected(),
+            Self::Drone(route) => route._length_respected(),
+        }
+    }
+
+    /// See [`Route::_assignable`].
+    pub fn _assignable(&self, vehicle: usize) -> bool {
+        match self {
+            Self::Truck(route) => route._assignable(vehicle),
+            Self::Drone(route) => route._assignable(vehicle),
+        }
+    }
+
     pub fn customers(&self) -> &[usize] {
         match self {
             Self::Truck(route) => &route.data().customers,
@@ -888,7 +1680,7 @@ impl AnyRoute {
         other_x: &Self,
         other_y: &Self,
         neighborhood: Neighborhood,
-    ) -> Vec<(Option<Self>, Self, Self, Vec<usize>)> {
+    ) -> Vec<(Option<Self>, Self, Self, TabuAttribute)> {
         let mut result = vec![];
         match (self, other_x, other_y) {
             (Self::Truck(r1), Self::Truck(r2), Self::Truck(r3)) => {
@@ -0,0 +1,48 @@
+use serde::Serialize;
+
+use crate::config::VehicleKind;
+
+/// Visit of a customer within a [`Trip`]. All times are in seconds since the start of the planning horizon.
+#[derive(Clone, Debug, Serialize)]
+pub struct Stop {
+    pub customer: usize,
+    pub arrival: f64,
+    pub service_start: f64,
+    pub service_end: f64,
+}
+
+/// A single route operated by a vehicle, from leaving the depot until returning to it.
+#[derive(Clone, Debug, Serialize)]
+pub struct Trip {
+    pub departure: f64,
+    pub stops: Vec<Stop>,
+    #[serde(rename = "return")]
+    pub return_time: f64,
+}
+
+/// Exact timeline of a solution, with the trips of each vehicle operated back to back.
+#[derive(Clone, Debug, Serialize)]
+pub struct Schedule {
+    pub trucks: Vec<Vec<Trip>>,
+    pub drones: Vec<Vec<Trip>>,
+}
+
+/// Decomposition of the time a vehicle spends working (in seconds).
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct TimeBreakdown {
+    /// Time spent driving (trucks) or cruising (drones).
+    pub travel: f64,
+    pub takeoff_landing: f64,
+    pub service: f64,
+    pub idle: f64,
+}
+
+/// Decomposition of the makespan of a solution into the working time components of its bottleneck vehicle.
+#[derive(Clone, Debug, Serialize)]
+pub struct MakespanReport {
+    pub vehicle: VehicleKind,
+    pub index: usize,
+    pub makespan: f64,
+    #[serde(flatten)]
+    pub breakdown: TimeBreakdown,
+}
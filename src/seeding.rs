@@ -0,0 +1,44 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::config;
+
+/// Stream of the initial solution construction.
+pub const INITIALIZE: u64 = 0;
+
+/// Stream of the tabu search, including destroy-and-repair.
+pub const SEARCH: u64 = 1;
+
+/// Stream of the demand scenarios of `--demand-samples`, unless `--scenario-seed` is given.
+pub const SCENARIOS: u64 = 2;
+
+/// Stream of the consistency checks of `--paranoid`.
+pub const CHECKS: u64 = 3;
+
+/// First stream of the runs of the clusters of `--decompose`, the run of cluster `i` using stream `CLUSTERS + i`.
+pub const CLUSTERS: u64 = 1 << 32;
+
+/// First stream of the additional searches of `--threads`, search `i` (from 1) using stream `THREADS + i`.
+pub const THREADS: u64 = 2 << 32;
+
+/// The SplitMix64 output function, a bijection scrambling consecutive inputs into statistically independent outputs.
+const fn _splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Seed of substream `stream` of the master seed `seed`. Each worker or component draws from the substream of its own
+/// fixed index, so that the numbers it gets do not depend on how the workers are scheduled.
+pub const fn substream_seed(seed: u64, stream: u64) -> u64 {
+    _splitmix64(_splitmix64(seed) ^ _splitmix64(stream.wrapping_add(0x632b_e59b_d9b4_e019)))
+}
+
+/// Random number generator of substream `stream` of `--seed`, or seeded from the system entropy without a seed.
+pub fn substream(stream: u64) -> StdRng {
+    match config::current().seed {
+        Some(seed) => StdRng::seed_from_u64(substream_seed(seed, stream)),
+        None => StdRng::from_rng(&mut rand::rng()),
+    }
+}
@@ -1,240 +1,1582 @@
-use std::collections::{BTreeSet, BinaryHeap, HashSet};
+use std::cell::Cell;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashSet};
+use std::error::Error;
 use std::marker::PhantomData;
-use std::rc::Rc;
-use std::sync::LazyLock;
-use std::sync::atomic::Ordering;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::SystemTime;
-use std::{cmp, fmt};
+use std::{cmp, fmt, fs, iter, mem};
 
+use colored::Colorize;
+use rand::Rng;
 use rand::distr::weighted::WeightedIndex;
 use rand::prelude::*;
-use rand::seq::SliceRandom;
-use rand::{Rng, rng};
-use serde::de::{SeqAccess, Visitor};
+use rand::seq::{SliceRandom, index};
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::cli::Strategy;
-use crate::clusterize;
-use crate::config::CONFIG;
+use crate::cli::{Algorithm, InitMethod, Strategy};
+use crate::config::{self, DistanceMatrix, InstanceFingerprint, VehicleKind};
+use crate::control::{ControlCommand, ControlWatcher};
+use crate::evaluate::EvaluationReport;
+use crate::inject::InjectWatcher;
 use crate::logger::Logger;
-use crate::neighborhoods::Neighborhood;
-use crate::routes::{DroneRoute, Route, TruckRoute};
+use crate::neighborhoods::{FocusList, Neighborhood};
+use crate::pool::{self, RoutePool};
+use crate::routes::{DroneRoute, Route, RouteValues, TabuAttribute, TruckRoute};
+use crate::schedule::{MakespanReport, Schedule, TimeBreakdown, Trip};
+use crate::{clusterize, crash, multistart, relink, seeding};
+
+/// Check a route read from a solution file before constructing it, so that malformed routes are reported as
+/// deserialization errors. `served` records the customers of the routes read so far.
+fn _check_route(customers: &[usize], served: &mut [bool]) -> Result<(), String> {
+    if customers.len() < 3 || customers.first() != Some(&0) || customers.last() != Some(&0) {
+        return Err(format!("Invalid route {customers:?}"));
+    }
+
+    for &c in &customers[1..customers.len() - 1] {
+        match served.get_mut(c) {
+            Some(true) => return Err(format!("Customer {c} is served more than once")),
+            Some(s) if c != 0 => *s = true,
+            _ => return Err(format!("Invalid customer {c} in route {customers:?}")),
+        }
+    }
+
+    Ok(())
+}
+
+/// Deserialize the routes of each vehicle one route at a time, checking and constructing each route as soon as it is
+/// read, so that large solution files are never held in memory as raw customer lists and errors surface early.
+fn _deserialize_routes<'de, R, D>(deserializer: D) -> Result<Vec<Vec<Arc<R>>>, D::Error>
+where
+    R: Route,
+    D: Deserializer<'de>,
+{
+    struct VehicleVisitor<'a, R> {
+        served: &'a mut [bool],
+        _route: PhantomData<R>,
+    }
+
+    impl<'de, R: Route> DeserializeSeed<'de> for VehicleVisitor<'_, R> {
+        type Value = Vec<Arc<R>>;
+
+        fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_seq(self)
+        }
+    }
+
+    impl<'de, R: Route> Visitor<'de> for VehicleVisitor<'_, R> {
+        type Value = Vec<Arc<R>>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("Expected the routes of a vehicle")
+        }
+
+        fn visit_seq<S>(self, mut seq: S) -> Result<Self::Value, S::Error>
+        where
+            S: SeqAccess<'de>,
+        {
+            let mut routes = vec![];
+            while let Some(customers) = seq.next_element::<Vec<usize>>()? {
+                _check_route(&customers, self.served).map_err(de::Error::custom)?;
+                routes.push(R::new(customers));
+            }
+
+            Ok(routes)
+        }
+    }
+
+    struct RouteVisitor<R>(PhantomData<R>);
+    impl<'de, R: Route> Visitor<'de> for RouteVisitor<R> {
+        type Value = Vec<Vec<Arc<R>>>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("Expected route data")
+        }
+
+        fn visit_seq<S>(self, mut seq: S) -> Result<Self::Value, S::Error>
+        where
+            S: SeqAccess<'de>,
+        {
+            let mut served = vec![false; config::current().customers_count + 1];
+            let mut result = vec![];
+            while let Some(routes) = seq.next_element_seed(VehicleVisitor::<R> {
+                served: &mut served,
+                _route: PhantomData,
+            })? {
+                result.push(routes);
+            }
+
+            Ok(result)
+        }
+    }
+
+    let visitor = RouteVisitor(PhantomData);
+    deserializer.deserialize_seq(visitor)
+}
+
+fn _serialize_routes<S>(routes: &[Vec<Arc<impl Route>>], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(routes.iter().map(|r| {
+        r.iter()
+            .map(|r| r.data().customers.clone())
+            .collect::<Vec<Vec<usize>>>()
+    }))
+}
+
+/// Values of each route of `vehicle_routes`, evaluated against the class of its vehicle.
+fn _route_values<R>(vehicle_routes: &[Vec<Arc<R>>], class: impl Fn(usize) -> usize) -> Vec<Vec<RouteValues>>
+where
+    R: Route,
+{
+    vehicle_routes
+        .iter()
+        .enumerate()
+        .map(|(vehicle, routes)| routes.iter().map(|r| *r.values(class(vehicle))).collect())
+        .collect()
+}
+
+/// Check that the routes of `vehicle_routes` read from a solution file have the `expected` values stored alongside
+/// them (within [`TOLERANCE`], relative to the larger of 1 and the expected value), i.e. that the config in scope
+/// evaluates them as the config the solution was written under did.
+fn _check_route_values<R>(
+    vehicle_routes: &[Vec<Arc<R>>],
+    expected: &[Vec<RouteValues>],
+    class: impl Fn(usize) -> usize,
+) -> Result<(), String>
+where
+    R: Route,
+{
+    let kind = R::vehicle_kind();
+    if expected.len() != vehicle_routes.len() || iter::zip(expected, vehicle_routes).any(|(e, r)| e.len() != r.len()) {
+        return Err(format!("Route values of the {kind}s do not match their routes"));
+    }
+
+    for (vehicle, routes) in vehicle_routes.iter().enumerate() {
+        for (route, expected) in iter::zip(routes, &expected[vehicle]) {
+            let actual = route.values(class(vehicle));
+            for (name, actual, expected) in [
+                ("working time", actual.working_time, expected.working_time),
+                (
+                    "capacity violation",
+                    actual.capacity_violation,
+                    expected.capacity_violation,
+                ),
+                (
+                    "waiting time violation",
+                    actual.waiting_time_violation,
+                    expected.waiting_time_violation,
+                ),
+                ("energy violation", actual.energy_violation, expected.energy_violation),
+                (
+                    "fixed time violation",
+                    actual.fixed_time_violation,
+                    expected.fixed_time_violation,
+                ),
+                ("cost", actual.cost, expected.cost),
+            ] {
+                if (actual - expected).abs() > TOLERANCE * expected.abs().max(1.0) {
+                    return Err(format!(
+                        "Route {:?} of {kind} {vehicle} has {name} {actual} under the current config, but {expected} \
+                         in the solution file. Was it written under a different config?",
+                        route.data().customers,
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Layout of a [`Solution`] in JSON. Along with the customers of each route, the fingerprint of the instance and the
+/// values of each route are stored as evaluated under the config the solution was written with, so that reading it
+/// under the config of another instance, or one evaluating its routes differently, fails instead of silently yielding
+/// another solution.
+#[derive(Deserialize, Serialize)]
+struct _SolutionJSON {
+    /// Instance the solution was computed for, missing from solution files written before it was recorded.
+    #[serde(default)]
+    instance: Option<InstanceFingerprint>,
+    #[serde(deserialize_with = "_deserialize_routes", serialize_with = "_serialize_routes")]
+    truck_routes: Vec<Vec<Arc<TruckRoute>>>,
+    #[serde(deserialize_with = "_deserialize_routes", serialize_with = "_serialize_routes")]
+    drone_routes: Vec<Vec<Arc<DroneRoute>>>,
+    /// Values of each route of `truck_routes`, missing from solution files written before these were recorded (whose
+    /// routes are then not checked).
+    #[serde(default)]
+    truck_route_values: Option<Vec<Vec<RouteValues>>>,
+    /// Values of each route of `drone_routes`, see `truck_route_values`.
+    #[serde(default)]
+    drone_route_values: Option<Vec<Vec<RouteValues>>>,
+
+    truck_working_time: Vec<f64>,
+    drone_working_time: Vec<f64>,
+
+    working_time: f64,
+    energy_violation: f64,
+    capacity_violation: f64,
+    waiting_time_violation: f64,
+    fixed_time_violation: f64,
+    #[serde(default)]
+    budget_violation: f64,
+    #[serde(default)]
+    vehicle_cost: f64,
+
+    feasible: bool,
+}
+
+impl From<Solution> for _SolutionJSON {
+    fn from(solution: Solution) -> Self {
+        let config = config::current();
+        Self {
+            instance: Some(config.fingerprint()),
+            truck_route_values: Some(_route_values(&solution.truck_routes, |t| config.truck_class(t))),
+            drone_route_values: Some(_route_values(&solution.drone_routes, |d| config.drone_class(d))),
+            truck_routes: solution.truck_routes,
+            drone_routes: solution.drone_routes,
+            truck_working_time: solution.truck_working_time,
+            drone_working_time: solution.drone_working_time,
+            working_time: solution.working_time,
+            energy_violation: solution.energy_violation,
+            capacity_violation: solution.capacity_violation,
+            waiting_time_violation: solution.waiting_time_violation,
+            fixed_time_violation: solution.fixed_time_violation,
+            budget_violation: solution.budget_violation,
+            vehicle_cost: solution.vehicle_cost,
+            feasible: solution.feasible,
+        }
+    }
+}
+
+impl TryFrom<_SolutionJSON> for Solution {
+    type Error = String;
+
+    fn try_from(json: _SolutionJSON) -> Result<Self, Self::Error> {
+        let config = config::current();
+        let fingerprint = config.fingerprint();
+        if let Some(instance) = json.instance
+            && instance != fingerprint
+        {
+            return Err(format!(
+                "The solution was computed for another instance ({instance}) than the one of the config ({fingerprint})"
+            ));
+        }
+        if let Some(values) = &json.truck_route_values {
+            _check_route_values(&json.truck_routes, values, |t| config.truck_class(t))?;
+        }
+        if let Some(values) = &json.drone_route_values {
+            _check_route_values(&json.drone_routes, values, |d| config.drone_class(d))?;
+        }
+
+        Ok(Self {
+            truck_routes: json.truck_routes,
+            drone_routes: json.drone_routes,
+            truck_working_time: json.truck_working_time,
+            drone_working_time: json.drone_working_time,
+            working_time: json.working_time,
+            energy_violation: json.energy_violation,
+            capacity_violation: json.capacity_violation,
+            waiting_time_violation: json.waiting_time_violation,
+            fixed_time_violation: json.fixed_time_violation,
+            budget_violation: json.budget_violation,
+            vehicle_cost: json.vehicle_cost,
+            feasible: json.feasible,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(into = "_SolutionJSON", try_from = "_SolutionJSON")]
+pub struct Solution {
+    pub truck_routes: Vec<Vec<Arc<TruckRoute>>>,
+    pub drone_routes: Vec<Vec<Arc<DroneRoute>>>,
+
+    pub truck_working_time: Vec<f64>,
+    pub drone_working_time: Vec<f64>,
+
+    pub working_time: f64,
+    pub energy_violation: f64,
+    pub capacity_violation: f64,
+    pub waiting_time_violation: f64,
+    pub fixed_time_violation: f64,
+    pub budget_violation: f64,
+    /// Total vehicle cost towards `--budget`, see [`EvaluationReport::vehicle_cost`].
+    pub vehicle_cost: f64,
+
+    pub feasible: bool,
+}
+
+// Solutions (and the routes they share) must be movable across and shareable between worker threads.
+const _: () = {
+    const fn _assert_send_sync<T: Send + Sync>() {}
+    _assert_send_sync::<Solution>();
+};
+
+thread_local! {
+    /// Penalty coefficients of the search running on this thread, starting from `--penalty-init` of the config in
+    /// scope when first read (see [`reset_penalties`]).
+    static PENALTY_COEFF: [Cell<f64>; 5] = _initial_penalties().map(Cell::new);
+}
+
+const TOLERANCE: f64 = 0.001;
+
+/// Maximum number of nodes explored by [`_assign_drone_routes`] before keeping the best assignment found.
+const _SCHEDULE_NODE_LIMIT: usize = 1_000_000;
+
+pub fn penalty_coeff<const N: usize>() -> f64 {
+    PENALTY_COEFF.with(|coeff| coeff[N].get())
+}
+
+/// Current penalty coefficients of the energy, capacity, waiting time, fixed time and budget violations.
+pub fn penalty_coefficients() -> [f64; 5] {
+    [
+        penalty_coeff::<0>(),
+        penalty_coeff::<1>(),
+        penalty_coeff::<2>(),
+        penalty_coeff::<3>(),
+        penalty_coeff::<4>(),
+    ]
+}
+
+/// Cost of a solution with working time `working_time` and the energy, capacity, waiting time, fixed time and budget
+/// `violations` (see [`Solution::cost`]), under the current penalty coefficients.
+pub fn penalized_cost(working_time: f64, violations: [f64; 5]) -> f64 {
+    let [energy, capacity, waiting_time, fixed_time, budget] = violations;
+    config::with_current(|config| {
+        config.quantize(
+            working_time
+                * penalty_coeff::<4>()
+                    .mul_add(
+                        budget,
+                        penalty_coeff::<3>().mul_add(
+                            fixed_time,
+                            penalty_coeff::<2>().mul_add(
+                                waiting_time,
+                                penalty_coeff::<1>().mul_add(capacity, penalty_coeff::<0>().mul_add(energy, 1.0)),
+                            ),
+                        ),
+                    )
+                    .powf(config.penalty_exponent),
+        )
+    })
+}
+
+/// Initial penalty coefficients: `--penalty-init` of the config in scope, and 1 for the budget violation.
+fn _initial_penalties() -> [f64; 5] {
+    let [e, c, w, f] = config::current().penalty_init.0;
+    [e, c, w, f, 1.0].map(|value| value.clamp(1.0, 1e3))
+}
+
+/// Whether `violation` exceeds the floating-point residuals tolerated by `--feasibility-tolerance`.
+fn _violated(violation: f64) -> bool {
+    config::with_current(|config| violation > config.feasibility_tolerance)
+}
+
+fn _update_violation<const N: usize>(violation: f64) {
+    let mut value = penalty_coeff::<N>();
+    if _violated(violation) {
+        value *= 1.5;
+    } else {
+        value /= 1.5;
+    };
+
+    PENALTY_COEFF.with(|coeff| coeff[N].set(value.clamp(1.0, 1e3)));
+}
+
+/// Set the penalty coefficients of the current thread to their initial values, as at the start of a run.
+pub fn reset_penalties() {
+    _set_penalties(_initial_penalties());
+}
+
+fn _set_penalties(values: [f64; 5]) {
+    PENALTY_COEFF.with(|coeff| {
+        for (coeff, value) in coeff.iter().zip(values) {
+            coeff.set(value.clamp(1.0, 1e3));
+        }
+    });
+}
+
+/// Pull the penalty coefficients back towards their initial values when restarting from an elite solution, keeping
+/// `(coefficient / initial) ^ config.penalty_retention` of their growth.
+fn _dampen_penalties() {
+    let mut values = penalty_coefficients();
+    for (value, initial) in values.iter_mut().zip(_initial_penalties()) {
+        *value = initial * (*value / initial).powf(config::current().penalty_retention);
+    }
+
+    _set_penalties(values);
+}
+
+/// Remove the customers for which `remove(vehicle, customer)` holds from the routes of each vehicle, dropping
+/// routes left empty. Returns the removed customers.
+fn _remove_customers<R>(vehicle_routes: &mut [Vec<Arc<R>>], remove: impl Fn(usize, usize) -> bool) -> Vec<usize>
+where
+    R: Route,
+{
+    let mut removed = vec![];
+    for (vehicle, routes) in vehicle_routes.iter_mut().enumerate() {
+        let mut i = 0;
+        while i < routes.len() {
+            let mut buffer = vec![];
+            for &customer in &routes[i].data().customers {
+                if customer != 0 && remove(vehicle, customer) {
+                    removed.push(customer);
+                } else {
+                    buffer.push(customer);
+                }
+            }
+
+            if buffer.len() > 2 {
+                if buffer.len() < routes[i].data().customers.len() {
+                    routes[i] = R::new(buffer);
+                }
+
+                i += 1;
+            } else {
+                routes.swap_remove(i);
+            }
+        }
+    }
+
+    removed
+}
+
+/// Assign `routes` to `vehicles` vehicles, longest route first, each to the vehicle with the least working time so far
+/// (evaluated against the class `class(vehicle)` of that vehicle).
+fn _distribute<R>(mut routes: Vec<Arc<R>>, vehicles: usize, class: impl Fn(usize) -> usize) -> Vec<Vec<Arc<R>>>
+where
+    R: Route,
+{
+    routes.sort_by(|f, s| f.working_time().total_cmp(&s.working_time()).reverse());
+
+    let mut vehicle_routes = vec![vec![]; vehicles];
+    let mut working_time = vec![0.0; vehicles];
+    for route in routes {
+        // Least busy vehicle which may still operate another route, if any
+        let room = vehicle_routes.iter().any(|r| r.len() < R::max_routes());
+        let mut min_idx = 0;
+        let mut min_time = f64::INFINITY;
+        for (i, &time) in working_time.iter().enumerate() {
+            if room && vehicle_routes[i].len() >= R::max_routes() {
+                continue;
+            }
+
+            if time < min_time {
+                min_time = time;
+                min_idx = i;
+            }
+        }
+
+        working_time[min_idx] += route.values(class(min_idx)).working_time;
+        vehicle_routes[min_idx].push(route);
+    }
+
+    vehicle_routes
+}
+
+/// Assignment of `routes` to `drones` drones minimizing the latest drone completion time, by branch and bound (up to
+/// `_SCHEDULE_NODE_LIMIT` nodes). Returns `None` unless it ends before `bound`.
+fn _assign_drone_routes(
+    mut routes: Vec<Arc<DroneRoute>>,
+    drones: usize,
+    bound: f64,
+) -> Option<Vec<Vec<Arc<DroneRoute>>>> {
+    struct _BranchAndBound {
+        /// times[r][d]: working time of route `r` on drone `d`, infinite if the drone may not serve it
+        times: Vec<Vec<f64>>,
+        /// remaining[r]: lower bound on the total working time of routes `r..`
+        remaining: Vec<f64>,
+        loads: Vec<f64>,
+        assignment: Vec<usize>,
+        best: f64,
+        best_assignment: Option<Vec<usize>>,
+        nodes: usize,
+        symmetric: bool,
+    }
+
+    impl _BranchAndBound {
+        fn branch(&mut self, route: usize) {
+            if self.nodes >= _SCHEDULE_NODE_LIMIT {
+                return;
+            }
+            self.nodes += 1;
+
+            let current = self.loads.iter().copied().fold(0.0, f64::max);
+            if route == self.times.len() {
+                if current + TOLERANCE < self.best {
+                    self.best = current;
+                    self.best_assignment = Some(self.assignment.clone());
+                }
+
+                return;
+            }
+
+            let average = (self.loads.iter().sum::<f64>() + self.remaining[route]) / self.loads.len() as f64;
+            if current.max(average) + TOLERANCE >= self.best {
+                return;
+            }
+
+            // Try the least busy drones first to find good assignments early
+            let mut order = (0..self.loads.len()).collect::<Vec<_>>();
+            order.sort_by(|&a, &b| self.loads[a].total_cmp(&self.loads[b]));
+            for (i, &drone) in order.iter().enumerate() {
+                let time = self.times[route][drone];
+                if !time.is_finite() || self.loads[drone] + time + TOLERANCE >= self.best {
+                    continue;
+                }
+
+                // Interchangeable drones with the same load lead to the same subtrees
+                if self.symmetric && i > 0 && self.loads[order[i - 1]] == self.loads[drone] {
+                    continue;
+                }
+
+                let load = self.loads[drone];
+                self.loads[drone] = load + time;
+                self.assignment[route] = drone;
+                self.branch(route + 1);
+                self.loads[drone] = load;
+            }
+        }
+    }
+
+    let config = config::current();
+    // Longest routes first, so that the bounds prune early
+    routes.sort_by(|a, b| b.working_time().total_cmp(&a.working_time()));
+
+    let times = routes
+        .iter()
+        .map(|route| {
+            (0..drones)
+                .map(|drone| {
+                    if route._assignable(drone) {
+                        route.values(config.drone_class(drone)).working_time
+                    } else {
+                        f64::INFINITY
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut remaining = vec![0.0; routes.len() + 1];
+    for r in (0..routes.len()).rev() {
+        remaining[r] = remaining[r + 1] + times[r].iter().copied().fold(f64::INFINITY, f64::min);
+    }
+
+    let classes = (0..drones).map(|d| config.drone_class(d)).collect::<HashSet<_>>();
+    let mut search = _BranchAndBound {
+        times,
+        remaining,
+        loads: vec![0.0; drones],
+        assignment: vec![0; routes.len()],
+        best: bound,
+        best_assignment: None,
+        nodes: 0,
+        symmetric: classes.len() == 1 && config.locks.is_empty() && config.zones.is_empty(),
+    };
+    search.branch(0);
+
+    let assignment = search.best_assignment?;
+    let mut drone_routes = vec![vec![]; drones];
+    for (route, drone) in routes.into_iter().zip(assignment) {
+        drone_routes[drone].push(route);
+    }
+
+    Some(drone_routes)
+}
+
+/// Whether `route` violates no constraint on its own when served by a vehicle of class `class`.
+fn _route_feasible(route: &impl Route, class: usize) -> bool {
+    let values = route.values(class);
+    !_violated(values.capacity_violation)
+        && !_violated(values.waiting_time_violation)
+        && !_violated(values.energy_violation)
+        && !_violated(values.fixed_time_violation)
+}
+
+/// Number of constraints violated by `route` on its own when served by a vehicle of class `class`.
+fn _route_violations(route: &impl Route, class: usize) -> usize {
+    let values = route.values(class);
+    [
+        values.capacity_violation,
+        values.waiting_time_violation,
+        values.energy_violation,
+        values.fixed_time_violation,
+    ]
+    .into_iter()
+    .filter(|&violation| _violated(violation))
+    .count()
+}
+
+/// Positions of `route` to try inserting the chain `unit` at when repairing: only the cheapest one with
+/// `--insertion-table`, otherwise all of them (but the last).
+fn _insertion_positions<R>(route: &R, unit: &[usize]) -> Vec<usize>
+where
+    R: Route,
+{
+    if config::current().insertion_table {
+        route.cheapest_insertion(unit).into_iter().collect()
+    } else {
+        (1..route.data().customers.len() - 1).collect()
+    }
+}
+
+/// Remove whole chains of required arcs from each route violating a constraint on its own until it is feasible, each
+/// time the chain leaving the fewest violated constraints (then the shortest working time), dropping routes left
+/// empty. Returns the removed chains.
+fn _unload<R>(vehicle_routes: &mut [Vec<Arc<R>>], class: impl Fn(usize) -> usize) -> Vec<Vec<usize>>
+where
+    R: Route,
+{
+    let mut unloaded = vec![];
+    for (vehicle, routes) in vehicle_routes.iter_mut().enumerate() {
+        let class = class(vehicle);
+        let mut kept = vec![];
+        for mut route in mem::take(routes) {
+            loop {
+                if _route_feasible(&*route, class) {
+                    kept.push(route);
+                    break;
+                }
+
+                let customers = &route.data().customers;
+                let mut chains = customers[1..customers.len() - 1]
+                    .iter()
+                    .map(|&c| config::current().arcs.chain(c))
+                    .collect::<Vec<_>>();
+                chains.sort_unstable();
+                chains.dedup();
+
+                let mut best: Option<((usize, f64), Vec<usize>, Vec<usize>)> = None;
+                for chain in chains {
+                    let rest = customers
+                        .iter()
+                        .copied()
+                        .filter(|c| !chain.contains(c))
+                        .collect::<Vec<_>>();
+                    let key = if rest.len() > 2 {
+                        let rest = R::new(rest.clone());
+                        (_route_violations(&*rest, class), rest.values(class).working_time)
+                    } else {
+                        (0, 0.0)
+                    };
+
+                    if best
+                        .as_ref()
+                        .is_none_or(|(k, ..)| key.0 < k.0 || (key.0 == k.0 && key.1 < k.1))
+                    {
+                        best = Some((key, chain, rest));
+                    }
+                }
+
+                let (_, chain, rest) = best.unwrap();
+                unloaded.push(chain);
+                if rest.len() <= 2 {
+                    break;
+                }
+
+                route = R::new(rest);
+            }
+        }
+
+        *routes = kept;
+    }
+
+    unloaded
+}
+
+/// The vehicle that would complete earliest after operating `chain` as a route of its own, if any can do so without
+/// violating a constraint, along with that completion time and the route.
+fn _split_target<R>(
+    vehicle_routes: &[Vec<Arc<R>>],
+    chain: &[usize],
+    class: impl Fn(usize) -> usize,
+) -> Option<(f64, usize, Arc<R>)>
+where
+    R: Route,
+{
+    if !chain.iter().all(|&c| R::_servable(c)) {
+        return None;
+    }
+
+    let mut customers = vec![0];
+    customers.extend_from_slice(chain);
+    customers.push(0);
+    let route = R::new(customers);
+    if !route._length_respected() || !route._arcs_respected() {
+        return None;
+    }
+
+    (0..vehicle_routes.len())
+        .filter(|&v| {
+            vehicle_routes[v].len() < R::max_routes() && route._assignable(v) && _route_feasible(&*route, class(v))
+        })
+        .map(|v| {
+            let completion = vehicle_routes[v]
+                .iter()
+                .chain(iter::once(&route))
+                .map(|r| r.values(class(v)).working_time)
+                .sum::<f64>();
+            (completion, v)
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(completion, v)| (completion, v, route.clone()))
+}
+
+/// Rebuild the routes of `pool` (in random order) whose customers all belong to `customers`, each on the vehicle with
+/// the least working time able to operate it, and remove their customers from `customers`.
+fn _insert_pooled<R>(
+    vehicle_routes: &mut [Vec<Arc<R>>],
+    pool: &[Vec<usize>],
+    customers: &mut HashSet<usize>,
+    class: impl Fn(usize) -> usize,
+    rng: &mut impl Rng,
+) where
+    R: Route,
+{
+    let mut order = pool.iter().collect::<Vec<_>>();
+    order.shuffle(rng);
+    for route in order {
+        let inner = &route[1..route.len() - 1];
+        if !inner.iter().all(|c| customers.contains(c)) {
+            continue;
+        }
+
+        let route = R::new(route.clone());
+        if !route._length_respected() || !route._arcs_respected() {
+            continue;
+        }
+
+        let working_time = |v: usize| {
+            vehicle_routes[v]
+                .iter()
+                .map(|r| r.values(class(v)).working_time)
+                .sum::<f64>()
+        };
+        let vehicle = (0..vehicle_routes.len())
+            .filter(|&v| {
+                vehicle_routes[v].len() < R::max_routes() && route._assignable(v) && _route_feasible(&*route, class(v))
+            })
+            .min_by(|&a, &b| working_time(a).total_cmp(&working_time(b)));
+        if let Some(v) = vehicle {
+            for c in inner {
+                customers.remove(c);
+            }
+            vehicle_routes[v].push(route);
+        }
+    }
+}
+
+/// Clarke-Wright construction of routes serving `customers`: starting from one route per customer, join the route
+/// ending with `i` to the route starting with `j` in decreasing order of the savings `d(i, 0) + d(0, j) - d(i, j)`, as
+/// long as the joined route remains feasible. If vehicles of this type may only operate a bounded number of routes,
+/// routes are then joined regardless of feasibility until the `vehicles` can operate all of them.
+fn _savings_routes<R>(customers: &[usize], distances: &DistanceMatrix, vehicles: usize) -> Vec<Arc<R>>
+where
+    R: Route,
+{
+    let mut savings = vec![];
+    if !R::single_customer() {
+        for &i in customers {
+            for &j in customers {
+                if i != j {
+                    savings.push((distances.get(i, 0) + distances.get(0, j) - distances.get(i, j), i, j));
+                }
+            }
+        }
+
+        savings.sort_by(|f, s| f.0.total_cmp(&s.0).reverse());
+    }
+
+    let mut routes = customers.iter().map(|&c| vec![c]).collect::<Vec<_>>();
+    let mut route_of = vec![0; config::current().customers_count + 1];
+    for (r, &c) in customers.iter().enumerate() {
+        route_of[c] = r;
+    }
+
+    let mut remaining = routes.len();
+    for force in [false, true] {
+        if force && remaining <= vehicles.saturating_mul(R::max_routes()) {
+            break;
+        }
+
+        for &(_, i, j) in &savings {
+            let (ri, rj) = (route_of[i], route_of[j]);
+            if ri == rj || routes[ri].last() != Some(&i) || routes[rj].first() != Some(&j) {
+                continue;
+            }
+
+            if routes[ri].len() + routes[rj].len() > R::max_customers() {
+                continue;
+            }
+
+            if !force {
+                let mut joined = vec![0];
+                joined.extend_from_slice(&routes[ri]);
+                joined.extend_from_slice(&routes[rj]);
+                joined.push(0);
+                if !_route_feasible(&*R::new(joined), 0) {
+                    continue;
+                }
+            }
+
+            let tail = mem::take(&mut routes[rj]);
+            for &c in &tail {
+                route_of[c] = ri;
+            }
+            routes[ri].extend(tail);
+
+            remaining -= 1;
+            if force && remaining <= vehicles.saturating_mul(R::max_routes()) {
+                break;
+            }
+        }
+    }
+
+    routes
+        .into_iter()
+        .filter(|r| !r.is_empty())
+        .map(|r| {
+            let mut customers = vec![0];
+            customers.extend(r);
+            customers.push(0);
+            R::new(customers)
+        })
+        .collect()
+}
+
+/// Prins' split: cut the giant tour `tour` into consecutive feasible routes served by vehicles of class `class`,
+/// minimizing their total working time. If vehicles of this type may only operate a bounded number of routes, use at
+/// most that many routes per vehicle in total, and if that is a single route, minimize the longest one instead.
+/// Returns `None` if there is no such split.
+fn _split<R>(tour: &[usize], class: usize, vehicles: usize) -> Option<Vec<Arc<R>>>
+where
+    R: Route,
+{
+    let n = tour.len();
+    let longest = cmp::min(R::max_customers(), n);
+
+    // routes[i][k] serves `tour[i..i + k + 1]`, routes are extended until they become infeasible (every constraint
+    // only tightens as a route grows) and are skipped if they break an arc constraint
+    let mut routes = vec![vec![]; n];
+    for (i, routes) in routes.iter_mut().enumerate() {
+        for j in i + 1..cmp::min(i + longest, n) + 1 {
+            let mut customers = vec![0];
+            customers.extend_from_slice(&tour[i..j]);
+            customers.push(0);
+            let route = R::new(customers);
+            if !_route_feasible(&*route, class) {
+                break;
+            }
+
+            routes.push(Some(route).filter(|r| r._arcs_respected()));
+        }
+    }
+
+    let route = |i: usize, j: usize| routes[i].get(j - i - 1).cloned().flatten();
+    let time = |i: usize, j: usize| route(i, j).map(|r| r.values(class).working_time);
+
+    // starts[j] = start of the last route of the best split of `tour[..j]`
+    let mut starts = vec![0; n + 1];
+    if R::max_routes() < usize::MAX {
+        // With a bounded number of routes, the best split of a prefix depends on its number of routes: best[k][j] is
+        // the longest route (or the total working time, if vehicles may operate several routes) of the best split of
+        // `tour[..j]` into `k` routes
+        let limit = cmp::min(vehicles.saturating_mul(R::max_routes()), n);
+        let combine = if R::single_route() {
+            f64::max
+        } else {
+            |a: f64, b: f64| a + b
+        };
+        let mut best = vec![vec![f64::INFINITY; n + 1]; limit + 1];
+        let mut previous = vec![vec![0; n + 1]; limit + 1];
+        best[0][0] = 0.0;
+        for k in 1..limit + 1 {
+            for i in 0..n {
+                if !best[k - 1][i].is_finite() {
+                    continue;
+                }
+
+                for j in i + 1..i + routes[i].len() + 1 {
+                    if let Some(time) = time(i, j) {
+                        let value = combine(best[k - 1][i], time);
+                        if value < best[k][j] {
+                            best[k][j] = value;
+                            previous[k][j] = i;
+                        }
+                    }
+                }
+            }
+        }
+
+        let k = (1..limit + 1).min_by(|&a, &b| best[a][n].total_cmp(&best[b][n]))?;
+        if !best[k][n].is_finite() {
+            return None;
+        }
+
+        let mut j = n;
+        for k in (1..k + 1).rev() {
+            starts[j] = previous[k][j];
+            j = starts[j];
+        }
+    } else {
+        // best[j] is the total working time of the best split of `tour[..j]`
+        let mut best = vec![f64::INFINITY; n + 1];
+        best[0] = 0.0;
+        for i in 0..n {
+            if !best[i].is_finite() {
+                continue;
+            }
+
+            for j in i + 1..i + routes[i].len() + 1 {
+                if let Some(time) = time(i, j)
+                    && best[i] + time < best[j]
+                {
+                    best[j] = best[i] + time;
+                    starts[j] = i;
+                }
+            }
+        }
+
+        if !best[n].is_finite() {
+            return None;
+        }
+    }
+
+    let mut result = vec![];
+    let mut j = n;
+    while j > 0 {
+        result.push(route(starts[j], j).unwrap());
+        j = starts[j];
+    }
+
+    result.reverse();
+    Some(result)
+}
+
+/// Giant tour over `customers` built by the nearest neighbor heuristic from the depot.
+fn _giant_tour(customers: &[usize], distances: &DistanceMatrix) -> Vec<usize> {
+    let mut remaining = customers.to_vec();
+    let mut tour = Vec::with_capacity(customers.len());
+    let mut last = 0;
+    while !remaining.is_empty() {
+        let index = (0..remaining.len())
+            .min_by(|&a, &b| {
+                distances
+                    .get(last, remaining[a])
+                    .total_cmp(&distances.get(last, remaining[b]))
+            })
+            .unwrap();
+        last = remaining.swap_remove(index);
+        tour.push(last);
+    }
+
+    tour
+}
+
+/// Re-split the routes of each vehicle, concatenated in order, whenever this shortens its working time.
+fn _resplit<R>(vehicle_routes: &mut [Vec<Arc<R>>], class: impl Fn(usize) -> usize)
+where
+    R: Route,
+{
+    for (vehicle, routes) in vehicle_routes.iter_mut().enumerate() {
+        if routes.len() < 2 && !routes.iter().any(|r| r.data().customers.len() > 3) {
+            continue;
+        }
+
+        let class = class(vehicle);
+        let tour = routes
+            .iter()
+            .flat_map(|r| r.data().customers.iter().copied().filter(|&c| c != 0))
+            .collect::<Vec<_>>();
+        if let Some(split) = _split::<R>(&tour, class, 1) {
+            let time = |routes: &[Arc<R>]| routes.iter().map(|r| r.values(class).working_time).sum::<f64>();
+            if time(&split) < time(routes) {
+                *routes = split;
+            }
+        }
+    }
+}
+
+/// Greedily move chains of consecutive `dronable` customers out of the truck routes into new drone routes (each
+/// assigned to the least busy drone), as long as this lowers the makespan, or keeps it while lowering the total
+/// working time.
+fn _extract_drone_chains(
+    truck_routes: &mut [Vec<Arc<TruckRoute>>],
+    drone_routes: &mut [Vec<Arc<DroneRoute>>],
+    dronable: &[bool],
+) {
+    fn _vehicle_time<R>(routes: &[Arc<R>], class: usize) -> f64
+    where
+        R: Route,
+    {
+        routes.iter().map(|r| r.values(class).working_time).sum()
+    }
+
+    let config = config::current();
+    let mut truck_time = truck_routes
+        .iter()
+        .enumerate()
+        .map(|(t, routes)| _vehicle_time(routes, config.truck_class(t)))
+        .collect::<Vec<_>>();
+    let mut drone_time = drone_routes
+        .iter()
+        .enumerate()
+        .map(|(d, routes)| _vehicle_time(routes, config.drone_class(d)))
+        .collect::<Vec<_>>();
+
+    loop {
+        let makespan = truck_time.iter().chain(&drone_time).copied().fold(0.0, f64::max);
+        let total = truck_time.iter().chain(&drone_time).sum::<f64>();
+
+        let drone = (0..drone_time.len())
+            .min_by(|&a, &b| drone_time[a].total_cmp(&drone_time[b]))
+            .unwrap();
+        let drone_class = config.drone_class(drone);
+
+        // (makespan, total working time, truck, route, chain start, chain end)
+        let mut best: Option<(f64, f64, usize, usize, usize, usize)> = None;
+        for (truck, routes) in truck_routes.iter().enumerate() {
+            let truck_class = config.truck_class(truck);
+            for (r, route) in routes.iter().enumerate() {
+                let customers = &route.data().customers;
+                for start in 1..customers.len() - 1 {
+                    for end in start..customers.len() - 1 {
+                        if !dronable[customers[end]] || end - start >= DroneRoute::max_customers() {
+                            break;
+                        }
+
+                        let mut chain = vec![0];
+                        chain.extend_from_slice(&customers[start..=end]);
+                        chain.push(0);
+                        let chain = DroneRoute::new(chain);
+                        if _violated(chain.values(drone_class).capacity_violation) {
+                            break;
+                        }
+                        if !_route_feasible(&*chain, drone_class) {
+                            continue;
+                        }
+
+                        let mut rest = customers[..start].to_vec();
+                        rest.extend_from_slice(&customers[end + 1..]);
+                        let rest_time = if rest.len() > 2 {
+                            TruckRoute::new(rest).values(truck_class).working_time
+                        } else {
+                            0.0
+                        };
+
+                        let new_truck_time = truck_time[truck] - route.values(truck_class).working_time + rest_time;
+                        let new_drone_time = drone_time[drone] + chain.values(drone_class).working_time;
+                        let new_makespan = truck_time
+                            .iter()
+                            .enumerate()
+                            .map(|(t, &time)| if t == truck { new_truck_time } else { time })
+                            .chain(
+                                drone_time
+                                    .iter()
+                                    .enumerate()
+                                    .map(|(d, &time)| if d == drone { new_drone_time } else { time }),
+                            )
+                            .fold(0.0, f64::max);
+                        let new_total = total - truck_time[truck] - drone_time[drone] + new_truck_time + new_drone_time;
+
+                        let (best_makespan, best_total) = best.map_or((makespan, total), |b| (b.0, b.1));
+                        if (new_makespan, new_total) < (best_makespan, best_total) {
+                            best = Some((new_makespan, new_total, truck, r, start, end));
+                        }
+                    }
+                }
+            }
+        }
 
-fn _deserialize_routes<'de, R, D>(deserializer: D) -> Result<Vec<Vec<Rc<R>>>, D::Error>
-where
-    R: Route,
-    D: Deserializer<'de>,
-{
-    struct RouteVisitor<R>(PhantomData<R>);
-    impl<'de, R: Route> Visitor<'de> for RouteVisitor<R> {
-        type Value = Vec<Vec<Rc<R>>>;
+        let Some((_, _, truck, r, start, end)) = best else {
+            break;
+        };
 
-        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("Expected route data")
+        let customers = &truck_routes[truck][r].data().customers;
+        let mut chain = vec![0];
+        chain.extend_from_slice(&customers[start..=end]);
+        chain.push(0);
+        let mut rest = customers[..start].to_vec();
+        rest.extend_from_slice(&customers[end + 1..]);
+
+        if rest.len() > 2 {
+            truck_routes[truck][r] = TruckRoute::new(rest);
+        } else {
+            truck_routes[truck].swap_remove(r);
         }
+        drone_routes[drone].push(DroneRoute::new(chain));
 
-        fn visit_seq<S>(self, mut seq: S) -> Result<Self::Value, S::Error>
+        truck_time[truck] = _vehicle_time(&truck_routes[truck], config.truck_class(truck));
+        drone_time[drone] += drone_routes[drone].last().unwrap().values(drone_class).working_time;
+    }
+}
+
+impl Solution {
+    pub fn new(truck_routes: Vec<Vec<Arc<TruckRoute>>>, drone_routes: Vec<Vec<Arc<DroneRoute>>>) -> Self {
+        // Sum the route values of each vehicle separately, as evaluated against the class of that vehicle
+        let report = config::with_current(|config| {
+            EvaluationReport::aggregate(
+                config,
+                truck_routes.iter().enumerate().map(|(truck, routes)| {
+                    let class = config.truck_class(truck);
+                    (class, routes.iter().map(move |r| *r.values(class)))
+                }),
+                drone_routes.iter().enumerate().map(|(drone, routes)| {
+                    let class = config.drone_class(drone);
+                    (class, routes.iter().map(move |r| *r.values(class)))
+                }),
+            )
+        });
+        Self::from_report(truck_routes, drone_routes, report)
+    }
+
+    /// The solution with the given routes, whose values were already aggregated into `report` (e.g. by
+    /// [`VehicleTotals::report`](crate::evaluate::VehicleTotals::report)).
+    pub fn from_report(
+        truck_routes: Vec<Vec<Arc<TruckRoute>>>,
+        drone_routes: Vec<Vec<Arc<DroneRoute>>>,
+        report: EvaluationReport,
+    ) -> Self {
+        Self {
+            truck_routes,
+            drone_routes,
+            truck_working_time: report.truck_working_time,
+            drone_working_time: report.drone_working_time,
+            working_time: report.working_time,
+            energy_violation: report.energy_violation,
+            capacity_violation: report.capacity_violation,
+            waiting_time_violation: report.waiting_time_violation,
+            fixed_time_violation: report.fixed_time_violation,
+            budget_violation: report.budget_violation,
+            vehicle_cost: report.vehicle_cost,
+            feasible: report.feasible,
+        }
+    }
+
+    /// Exact timeline of this solution under the configured vehicle models, with departure, arrival, service and
+    /// return times of every trip. Unlike [`Self::cost`], this involves no penalty.
+    pub fn schedule(&self) -> Schedule {
+        fn _trips<R>(routes: &[Arc<R>], class: usize) -> Vec<Trip>
         where
-            S: SeqAccess<'de>,
+            R: Route,
         {
-            let mut result = vec![];
-            while let Some(routes) = seq.next_element::<Vec<Vec<usize>>>()? {
-                let mut to_push = vec![];
-                for route in routes {
-                    to_push.push(R::new(route));
-                }
+            let mut time = 0.0;
+            routes
+                .iter()
+                .map(|route| {
+                    let trip = route.trip(class, time);
+                    time = trip.return_time;
+                    trip
+                })
+                .collect()
+        }
+
+        let config = config::current();
+        Schedule {
+            trucks: self
+                .truck_routes
+                .iter()
+                .enumerate()
+                .map(|(truck, routes)| _trips(routes, config.truck_class(truck)))
+                .collect(),
+            drones: self
+                .drone_routes
+                .iter()
+                .enumerate()
+                .map(|(drone, routes)| _trips(routes, config.drone_class(drone)))
+                .collect(),
+        }
+    }
 
-                result.push(to_push);
+    /// Decomposition of the makespan into the working time components of the bottleneck vehicle, showing whether it
+    /// is bound by distance, sortie overhead or service.
+    pub fn makespan_report(&self) -> MakespanReport {
+        fn _breakdown<R>(routes: &[Arc<R>], class: usize) -> TimeBreakdown
+        where
+            R: Route,
+        {
+            let mut total = TimeBreakdown::default();
+            for breakdown in routes.iter().map(|r| r.time_breakdown(class)) {
+                total.travel += breakdown.travel;
+                total.takeoff_landing += breakdown.takeoff_landing;
+                total.service += breakdown.service;
+                total.idle += breakdown.idle;
             }
 
-            Ok(result)
+            total
+        }
+
+        let config = config::current();
+        let (vehicle, index, _) = self
+            .truck_working_time
+            .iter()
+            .enumerate()
+            .map(|(truck, &time)| (VehicleKind::Truck, truck, time))
+            .chain(
+                self.drone_working_time
+                    .iter()
+                    .enumerate()
+                    .map(|(drone, &time)| (VehicleKind::Drone, drone, time)),
+            )
+            .max_by(|a, b| a.2.total_cmp(&b.2))
+            .expect("Solution has no vehicles");
+        let breakdown = match vehicle {
+            VehicleKind::Truck => _breakdown(&self.truck_routes[index], config.truck_class(index)),
+            VehicleKind::Drone => _breakdown(&self.drone_routes[index], config.drone_class(index)),
+        };
+
+        MakespanReport {
+            vehicle,
+            index,
+            makespan: self.working_time,
+            breakdown,
         }
     }
 
-    let visitor = RouteVisitor(PhantomData);
-    deserializer.deserialize_seq(visitor)
-}
+    /// Print to stderr how this solution differs from `previous`: the routes removed and added (per vehicle) and the
+    /// customers that changed vehicle.
+    pub fn print_diff(&self, previous: &Self) {
+        /// The routes of `solution` by vehicle and customers, with their text in the `--route-format`.
+        fn _routes(solution: &Solution) -> BTreeMap<(VehicleKind, usize, &[usize]), String> {
+            let config = config::current();
+            let mut result = BTreeMap::new();
+            for (truck, routes) in solution.truck_routes.iter().enumerate() {
+                for route in routes {
+                    result.insert(
+                        (VehicleKind::Truck, truck, route.data().customers.as_slice()),
+                        route.display(config.truck_class(truck)),
+                    );
+                }
+            }
+            for (drone, routes) in solution.drone_routes.iter().enumerate() {
+                for route in routes {
+                    result.insert(
+                        (VehicleKind::Drone, drone, route.data().customers.as_slice()),
+                        route.display(config.drone_class(drone)),
+                    );
+                }
+            }
 
-fn _serialize_routes<S>(routes: &[Vec<Rc<impl Route>>], serializer: S) -> Result<S::Ok, S::Error>
-where
-    S: Serializer,
-{
-    serializer.collect_seq(routes.iter().map(|r| {
-        r.iter()
-            .map(|r| r.data().customers.clone())
-            .collect::<Vec<Vec<usize>>>()
-    }))
-}
+            result
+        }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct Solution {
-    #[serde(deserialize_with = "_deserialize_routes", serialize_with = "_serialize_routes")]
-    pub truck_routes: Vec<Vec<Rc<TruckRoute>>>,
-    #[serde(deserialize_with = "_deserialize_routes", serialize_with = "_serialize_routes")]
-    pub drone_routes: Vec<Vec<Rc<DroneRoute>>>,
+        fn _assignment(routes: &BTreeMap<(VehicleKind, usize, &[usize]), String>) -> Vec<Option<(VehicleKind, usize)>> {
+            let mut result = vec![None; config::current().customers_count + 1];
+            for &(kind, vehicle, customers) in routes.keys() {
+                for &c in &customers[1..customers.len() - 1] {
+                    result[c] = Some((kind, vehicle));
+                }
+            }
 
-    pub truck_working_time: Vec<f64>,
-    pub drone_working_time: Vec<f64>,
+            result
+        }
 
-    pub working_time: f64,
-    pub energy_violation: f64,
-    pub capacity_violation: f64,
-    pub waiting_time_violation: f64,
-    pub fixed_time_violation: f64,
+        eprintln!(
+            "{}",
+            format!(
+                "New best {:.2} ({:+.2})",
+                self.working_time,
+                self.working_time - previous.working_time
+            )
+            .bold()
+        );
 
-    pub feasible: bool,
-}
+        let old_routes = _routes(previous);
+        let new_routes = _routes(self);
+        for ((kind, vehicle, _), text) in old_routes.iter().filter(|(k, _)| !new_routes.contains_key(*k)) {
+            eprintln!("{}", format!("  - {kind} {vehicle} {text}").red());
+        }
+        for ((kind, vehicle, _), text) in new_routes.iter().filter(|(k, _)| !old_routes.contains_key(*k)) {
+            eprintln!("{}", format!("  + {kind} {vehicle} {text}").green());
+        }
 
-static PENALTY_COEFF: LazyLock<[atomic_float::AtomicF64; 4]> = LazyLock::new(|| {
-    [
-        atomic_float::AtomicF64::new(1.0),
-        atomic_float::AtomicF64::new(1.0),
-        atomic_float::AtomicF64::new(1.0),
-        atomic_float::AtomicF64::new(1.0),
-    ]
-});
+        let old_assignment = _assignment(&old_routes);
+        let new_assignment = _assignment(&new_routes);
+        for (c, (old, new)) in old_assignment.iter().zip(&new_assignment).enumerate() {
+            if let (Some((old_kind, old_vehicle)), Some((new_kind, new_vehicle))) = (old, new)
+                && old != new
+            {
+                eprintln!(
+                    "{}",
+                    format!("  ~ customer {c}: {old_kind} {old_vehicle} -> {new_kind} {new_vehicle}").yellow()
+                );
+            }
+        }
+    }
 
-static NEIGHBORHOODS: LazyLock<[Neighborhood; 6]> = LazyLock::new(|| {
-    [
-        Neighborhood::Move10,
-        Neighborhood::Move11,
-        Neighborhood::Move20,
-        Neighborhood::Move21,
-        Neighborhood::Move22,
-        Neighborhood::TwoOpt,
-    ]
-});
+    /// Print a per-vehicle summary table to stderr, highlighting the bottleneck vehicle and nonzero violations.
+    pub fn print_summary(&self) {
+        fn _rows<R>(
+            vehicle_routes: &[Vec<Arc<R>>],
+            working_time: &[f64],
+            kind: VehicleKind,
+            class: impl Fn(usize) -> usize,
+            bottleneck: (VehicleKind, usize),
+        ) where
+            R: Route,
+        {
+            for (vehicle, routes) in vehicle_routes.iter().enumerate() {
+                let mut violations = [0.0; 4];
+                for route in routes {
+                    let values = route.values(class(vehicle));
+                    violations[0] += values.energy_violation;
+                    violations[1] += values.capacity_violation;
+                    violations[2] += values.waiting_time_violation;
+                    violations[3] += values.fixed_time_violation;
+                }
 
-const TOLERANCE: f64 = 0.001;
+                let row = format!(
+                    "{:<10} {:>6} {:>9} {:>12.2} {:>10.2}",
+                    format!("{kind} {vehicle}"),
+                    routes.len(),
+                    routes.iter().map(|r| r.data().customers.len() - 2).sum::<usize>(),
+                    working_time[vehicle],
+                    routes.iter().map(|r| r.weight()).sum::<f64>(),
+                );
+                let violations = violations
+                    .iter()
+                    .map(|&v| {
+                        let cell = format!(" {v:>10.2}");
+                        if _violated(v) { cell.yellow() } else { cell.normal() }
+                    })
+                    .map(|cell| cell.to_string())
+                    .collect::<String>();
+
+                if (kind, vehicle) == bottleneck {
+                    eprintln!("{}{violations}", row.red().bold());
+                } else {
+                    eprintln!("{row}{violations}");
+                }
+            }
+        }
 
-pub fn penalty_coeff<const N: usize>() -> f64 {
-    PENALTY_COEFF[N].load(Ordering::Relaxed)
-}
+        let config = config::current();
+        eprintln!(
+            "{:<10} {:>6} {:>9} {:>12} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "Vehicle", "Routes", "Customers", "Working time", "Load", "Energy", "Capacity", "Waiting", "Fixed time"
+        );
+
+        let report = self.makespan_report();
+        let bottleneck = (report.vehicle, report.index);
+        _rows(
+            &self.truck_routes,
+            &self.truck_working_time,
+            VehicleKind::Truck,
+            |truck| config.truck_class(truck),
+            bottleneck,
+        );
+        _rows(
+            &self.drone_routes,
+            &self.drone_working_time,
+            VehicleKind::Drone,
+            |drone| config.drone_class(drone),
+            bottleneck,
+        );
+
+        if let Some(budget) = config.budget {
+            let line = format!("Vehicle cost = {:.2} (budget {budget:.2})", self.vehicle_cost);
+            if _violated(self.budget_violation) {
+                eprintln!("{}", line.yellow());
+            } else {
+                eprintln!("{line}");
+            }
+        }
+    }
 
-fn _update_violation<const N: usize>(violation: f64) {
-    let mut value = PENALTY_COEFF[N].load(Ordering::Relaxed);
-    if violation > 0.0 {
-        value *= 1.5;
-    } else {
-        value /= 1.5;
-    };
+    pub fn verify(&self) {
+        if let Err(e) = self.validate() {
+            panic!("{e}");
+        }
 
-    PENALTY_COEFF[N].store(value.clamp(1.0, 1e3), Ordering::Relaxed)
-}
+        let feasible = [
+            self.energy_violation,
+            self.capacity_violation,
+            self.waiting_time_violation,
+            self.fixed_time_violation,
+            self.budget_violation,
+        ]
+        .into_iter()
+        .all(|v| !_violated(v));
+        assert_eq!(
+            self.feasible, feasible,
+            "Feasibility flag does not match the violations"
+        );
+    }
 
-impl Solution {
-    pub fn new(truck_routes: Vec<Vec<Rc<TruckRoute>>>, drone_routes: Vec<Vec<Rc<DroneRoute>>>) -> Self {
-        let mut working_time: f64 = 0.0;
-        let mut energy_violation = 0.0;
-        let mut capacity_violation = 0.0;
-        let mut waiting_time_violation = 0.0;
-        let mut fixed_time_violation = 0.0;
-        for routes in &truck_routes {
-            working_time = working_time.max(routes.iter().map(|r| r.working_time()).sum());
-            capacity_violation += routes.iter().map(|r| r.capacity_violation()).sum::<f64>() / CONFIG.truck.capacity;
-            waiting_time_violation += routes.iter().map(|r| r.waiting_time_violation()).sum::<f64>();
-        }
-        for routes in &drone_routes {
-            working_time = working_time.max(routes.iter().map(|r| r.working_time()).sum::<f64>());
-            energy_violation += routes.iter().map(|r| r.energy_violation).sum::<f64>();
-            capacity_violation += routes.iter().map(|r| r.capacity_violation()).sum::<f64>() / CONFIG.drone.capacity();
-            waiting_time_violation += routes.iter().map(|r| r.waiting_time_violation()).sum::<f64>();
-            fixed_time_violation += routes.iter().map(|r| r.fixed_time_violation).sum::<f64>();
-        }
-
-        let truck_working_time = truck_routes
-            .iter()
-            .map(|r| r.iter().map(|r| r.working_time()).sum())
-            .collect();
-        let drone_working_time = drone_routes
-            .iter()
-            .map(|r| r.iter().map(|r| r.working_time()).sum())
-            .collect();
+    /// Check that the values of this solution and of its routes are those of the solution rebuilt from scratch from
+    /// its customer lists, i.e. that no cached value went stale. Values are compared exactly, since the evaluation is
+    /// deterministic.
+    pub fn verify_values(&self) -> Result<(), String> {
+        fn _rebuild<R>(vehicle_routes: &[Vec<Arc<R>>]) -> Vec<Vec<Arc<R>>>
+        where
+            R: Route,
+        {
+            vehicle_routes
+                .iter()
+                .map(|routes| routes.iter().map(|r| R::new(r.data().customers.clone())).collect())
+                .collect()
+        }
+
+        fn _compare<R>(
+            kind: VehicleKind,
+            cached: &[Vec<Arc<R>>],
+            fresh: &[Vec<Arc<R>>],
+            class: impl Fn(usize) -> usize,
+        ) -> Result<(), String>
+        where
+            R: Route + fmt::Debug,
+        {
+            for (vehicle, (cached, fresh)) in cached.iter().zip(fresh).enumerate() {
+                let class = class(vehicle);
+                for (cached, fresh) in cached.iter().zip(fresh) {
+                    if cached.values(class) != fresh.values(class) {
+                        return Err(format!(
+                            "Route {cached:?} of {kind} {vehicle} has values {:?}, recomputation gives {:?}",
+                            cached.values(class),
+                            fresh.values(class),
+                        ));
+                    }
+                }
+            }
 
-        energy_violation /= CONFIG.drone.battery();
-        waiting_time_violation /= CONFIG.waiting_time_limit;
-        fixed_time_violation /= CONFIG.drone.fixed_time();
+            Ok(())
+        }
 
-        Self {
-            truck_routes,
-            drone_routes,
-            working_time,
-            energy_violation,
-            capacity_violation,
-            waiting_time_violation,
-            fixed_time_violation,
-            feasible: energy_violation == 0.0
-                && capacity_violation == 0.0
-                && waiting_time_violation == 0.0
-                && fixed_time_violation == 0.0,
-            truck_working_time,
-            drone_working_time,
+        let config = config::current();
+        let fresh = Self::new(_rebuild(&self.truck_routes), _rebuild(&self.drone_routes));
+        _compare(VehicleKind::Truck, &self.truck_routes, &fresh.truck_routes, |t| {
+            config.truck_class(t)
+        })?;
+        _compare(VehicleKind::Drone, &self.drone_routes, &fresh.drone_routes, |d| {
+            config.drone_class(d)
+        })?;
+
+        let values = |s: &Self| {
+            [
+                s.working_time,
+                s.energy_violation,
+                s.capacity_violation,
+                s.waiting_time_violation,
+                s.fixed_time_violation,
+                s.budget_violation,
+                s.vehicle_cost,
+            ]
+        };
+        if values(self) != values(&fresh)
+            || self.truck_working_time != fresh.truck_working_time
+            || self.drone_working_time != fresh.drone_working_time
+            || self.feasible != fresh.feasible
+        {
+            return Err(format!(
+                "Solution has values {:?} (working times {:?} and {:?}, feasible = {}), recomputation gives {:?} \
+                 (working times {:?} and {:?}, feasible = {})",
+                values(self),
+                self.truck_working_time,
+                self.drone_working_time,
+                self.feasible,
+                values(&fresh),
+                fresh.truck_working_time,
+                fresh.drone_working_time,
+                fresh.feasible,
+            ));
         }
+
+        Ok(())
     }
 
-    pub fn verify(&self) {
-        let mut served = vec![false; CONFIG.customers_count + 1];
+    /// Check that this solution serves every customer exactly once while respecting the route structure, arc and
+    /// assignment constraints. Constraints that are penalized in [`Self::cost`] are not checked here.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut served = vec![false; config::current().customers_count + 1];
         served[0] = true;
 
-        fn _check_routes<R>(vehicle_routes: &[Vec<Rc<R>>], served: &mut [bool])
+        fn _check_routes<R>(vehicle_routes: &[Vec<Arc<R>>], served: &mut [bool]) -> Result<(), String>
         where
             R: Route + fmt::Debug,
         {
-            for routes in vehicle_routes {
-                if R::single_route() && routes.len() > 1 {
-                    panic!("Vehicle {routes:?} has more than one route");
+            for (vehicle, routes) in vehicle_routes.iter().enumerate() {
+                if routes.len() > R::max_routes() {
+                    return Err(format!("Vehicle {routes:?} has more than {} routes", R::max_routes()));
                 }
 
                 for route in routes {
                     let customers = &route.data().customers;
 
-                    if R::single_customer() && customers.len() != 3 {
-                        panic!("Route {route:?} has more than one customer");
+                    if customers.len() - 2 > R::max_customers() {
+                        return Err(format!(
+                            "Route {route:?} has more than {} customers",
+                            R::max_customers()
+                        ));
                     }
 
                     if customers.first() != Some(&0) || customers.last() != Some(&0) {
-                        panic!("Invalid route {customers:?}");
+                        return Err(format!("Invalid route {customers:?}"));
                     }
 
                     for &c in customers.iter().skip(1).take(customers.len() - 2) {
                         if served[c] {
-                            panic!("Customer {c} is served more than once");
+                            return Err(format!("Customer {c} is served more than once"));
                         }
 
                         served[c] = true;
                     }
+
+                    if !route._arcs_respected() {
+                        return Err(format!("Route {route:?} violates the arc constraints"));
+                    }
+
+                    if !route._assignable(vehicle) {
+                        return Err(format!(
+                            "Route {route:?} violates the assignment constraints of {} {vehicle}",
+                            R::vehicle_kind()
+                        ));
+                    }
                 }
             }
+
+            Ok(())
         }
 
-        _check_routes(&self.truck_routes, &mut served);
-        _check_routes(&self.drone_routes, &mut served);
+        _check_routes(&self.truck_routes, &mut served)?;
+        _check_routes(&self.drone_routes, &mut served)?;
 
         for (c, s) in served.iter().enumerate() {
             if !s {
-                panic!("Customer {c} is not served");
+                return Err(format!("Customer {c} is not served"));
             }
         }
+
+        Ok(())
+    }
+
+    /// Whether this solution is feasible with a working time of at most `--target-cost`, or `None` without a target.
+    pub fn reaches_target(&self) -> Option<bool> {
+        config::current()
+            .target_cost
+            .map(|target| self.feasible && self.working_time <= target)
     }
 
     pub fn cost(&self) -> f64 {
-        self.working_time
-            * penalty_coeff::<3>()
-                .mul_add(
-                    self.fixed_time_violation,
-                    penalty_coeff::<2>().mul_add(
-                        self.waiting_time_violation,
-                        penalty_coeff::<1>().mul_add(
-                            self.capacity_violation,
-                            penalty_coeff::<0>().mul_add(self.energy_violation, 1.0),
-                        ),
-                    ),
-                )
-                .powf(CONFIG.penalty_exponent)
+        penalized_cost(
+            self.working_time,
+            [
+                self.energy_violation,
+                self.capacity_violation,
+                self.waiting_time_violation,
+                self.fixed_time_violation,
+                self.budget_violation,
+            ],
+        )
     }
 
     pub fn hamming_distance(&self, other: &Self) -> usize {
-        fn fill_repr<T>(vehicle_routes: &Vec<Vec<Rc<T>>>, repr: &mut [usize])
+        fn fill_repr<T>(vehicle_routes: &Vec<Vec<Arc<T>>>, repr: &mut [usize])
         where
             T: Route,
         {
@@ -248,47 +1590,222 @@ impl Solution {
             }
         }
 
-        let mut self_repr = vec![0; CONFIG.customers_count + 1];
+        let config = config::current();
+        let mut self_repr = vec![0; config.customers_count + 1];
         fill_repr(&self.truck_routes, &mut self_repr);
         fill_repr(&self.drone_routes, &mut self_repr);
 
-        let mut other_repr = vec![0; CONFIG.customers_count + 1];
+        let mut other_repr = vec![0; config.customers_count + 1];
         fill_repr(&other.truck_routes, &mut other_repr);
         fill_repr(&other.drone_routes, &mut other_repr);
 
         self_repr.iter().zip(other_repr.iter()).filter(|(a, b)| a != b).count()
     }
 
-    // pub fn post_optimization(&self) -> Self {
-    //     let mut result = Rc::new(self.clone());
-
-    //     let mut improved = true;
-    //     while improved {
-    //         improved = false;
-    //         for neighborhood in NEIGHBORHOODS.iter() {
-    //             if let Some(best) = neighborhood.search(&result, &mut vec![], 0, result.cost()) {
-    //                 if best.cost() + TOLERANCE < result.cost() && best.feasible {
-    //                     result = Rc::new(best);
-    //                     improved = true;
-    //                 }
-    //             }
-    //         }
-
-    //         let (best, _) = Neighborhood::EjectionChain.inter_route(&result, &[], result.cost());
-    //         if best.cost() + TOLERANCE < result.cost() && best.feasible {
-    //             result = Rc::new(best);
-    //             improved = true;
-    //         }
-
-    //         let (best, _) = Neighborhood::CrossExchange.inter_route(&result, &[], result.cost());
-    //         if best.cost() + TOLERANCE < result.cost() && best.feasible {
-    //             result = Rc::new(best);
-    //             improved = true;
-    //         }
-    //     }
-
-    //     Self::clone(&result)
-    // }
+    /// Local descent: apply the best move of each neighborhood in turn, as long as one of them lowers the cost.
+    pub fn post_optimization(&self) -> Self {
+        let mut result = self.clone();
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for neighborhood in config::current().neighborhoods.iter() {
+                if let Some((best, _)) = neighborhood.search(&result, &mut vec![], 0, result.cost(), None)
+                    && best.cost() + TOLERANCE < result.cost()
+                {
+                    result = best;
+                    improved = true;
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Perturbation of iterated local search: `--kick-strength` double-bridge moves (exchanging two consecutive
+    /// segments) on random truck routes, and as many relocations of random drone routes to another drone. Moves that
+    /// would break an arc, assignment or route count constraint are skipped.
+    pub fn kick(&self, rng: &mut impl Rng) -> Self {
+        let config = config::current();
+        let mut truck_routes = self.truck_routes.clone();
+        let mut drone_routes = self.drone_routes.clone();
+
+        for _ in 0..config.kick_strength {
+            let candidates = truck_routes
+                .iter()
+                .enumerate()
+                .flat_map(|(t, routes)| (0..routes.len()).map(move |r| (t, r)))
+                .filter(|&(t, r)| truck_routes[t][r].data().customers.len() >= 5)
+                .collect::<Vec<_>>();
+            if let Some(&(truck, r)) = candidates.choose(rng) {
+                let customers = &truck_routes[truck][r].data().customers;
+                let mut cuts = index::sample(rng, customers.len() - 2, 3).into_vec();
+                cuts.sort_unstable();
+                let [i, j, k] = [cuts[0] + 1, cuts[1] + 1, cuts[2] + 1];
+
+                let mut bridged = customers[..i].to_vec();
+                bridged.extend_from_slice(&customers[j..k]);
+                bridged.extend_from_slice(&customers[i..j]);
+                bridged.extend_from_slice(&customers[k..]);
+                if config.arcs.respected(&bridged) {
+                    truck_routes[truck][r] = TruckRoute::new(bridged);
+                }
+            }
+
+            let candidates = drone_routes
+                .iter()
+                .enumerate()
+                .flat_map(|(d, routes)| (0..routes.len()).map(move |r| (d, r)))
+                .collect::<Vec<_>>();
+            if drone_routes.len() > 1
+                && let Some(&(from, r)) = candidates.choose(rng)
+            {
+                let to = (from + rng.random_range(1..drone_routes.len())) % drone_routes.len();
+                if drone_routes[to].len() < DroneRoute::max_routes() && drone_routes[from][r]._assignable(to) {
+                    let route = drone_routes[from].swap_remove(r);
+                    drone_routes[to].push(route);
+                }
+            }
+        }
+
+        Self::new(truck_routes, drone_routes)
+    }
+
+    /// The nearest feasible solution obtainable by greedy unload and split operations: customers are removed from each
+    /// route violating a constraint on its own (whole chains of required arcs at a time) until it is feasible, then
+    /// each removed chain is operated as a route of its own by the vehicle completing it earliest. Returns a copy of
+    /// this solution if it is already feasible, or the reason why no feasible solution was obtained.
+    pub fn project_feasible(&self) -> Result<Self, String> {
+        let config = config::current();
+        if self.feasible {
+            return Ok(self.clone());
+        }
+
+        let mut truck_routes = self.truck_routes.clone();
+        let mut drone_routes = self.drone_routes.clone();
+        let mut chains = _unload(&mut truck_routes, |t| config.truck_class(t));
+        chains.extend(_unload(&mut drone_routes, |d| config.drone_class(d)));
+
+        for chain in chains {
+            let truck = _split_target(&truck_routes, &chain, |t| config.truck_class(t));
+            let drone = _split_target(&drone_routes, &chain, |d| config.drone_class(d));
+            match (truck, drone) {
+                (Some((t, ..)), Some((d, drone, route))) if d < t => drone_routes[drone].push(route),
+                (Some((_, truck, route)), _) => truck_routes[truck].push(route),
+                (None, Some((_, drone, route))) => drone_routes[drone].push(route),
+                (None, None) => {
+                    return Err(format!(
+                        "No vehicle can operate {chain:?} as a feasible route of its own"
+                    ));
+                }
+            }
+        }
+
+        let solution = Self::new(truck_routes, drone_routes);
+        if solution.feasible {
+            Ok(solution)
+        } else {
+            Err(format!(
+                "The projected solution still violates the budget by {:.2}%",
+                100.0 * solution.budget_violation
+            ))
+        }
+    }
+
+    /// The drone routes of this solution reassigned to the drones with an earlier latest drone completion time (see
+    /// [`Self::refine_drone_schedule`]), or `None` if there is no such assignment (or none was found).
+    pub fn rebalanced_drone_routes(&self) -> Option<Vec<Vec<Arc<DroneRoute>>>> {
+        let drones = self.drone_routes.len();
+        if drones < 2 {
+            return None;
+        }
+
+        let routes = self.drone_routes.iter().flatten().cloned().collect::<Vec<_>>();
+        let bound = self.drone_working_time.iter().copied().fold(0.0, f64::max);
+        _assign_drone_routes(routes, drones, bound)
+    }
+
+    /// Reassign the drone routes to the drones to minimize the latest drone completion time. Since a drone operates
+    /// its routes back to back, its completion time does not depend on their order, so this solves the drone
+    /// assignment and scheduling problem exactly by branch and bound (unless it runs out of nodes). Returns a copy of
+    /// this solution if this does not lower its cost.
+    pub fn refine_drone_schedule(&self) -> Self {
+        match self.rebalanced_drone_routes() {
+            Some(drone_routes) => {
+                let refined = Self::new(self.truck_routes.clone(), drone_routes);
+                if refined.cost() < self.cost() {
+                    refined
+                } else {
+                    self.clone()
+                }
+            }
+            None => self.clone(),
+        }
+    }
+
+    /// Starting from the drone routes of this solution, replace each route serving several customers by
+    /// single-customer sorties whenever the resulting pool of sorties can be assigned to the drones with an earlier
+    /// latest drone completion time (see [`Self::refine_drone_schedule`]). Returns a copy of this solution if this
+    /// does not lower its cost.
+    pub fn match_drone_sorties(&self) -> Self {
+        let drones = self.drone_routes.len();
+        if drones < 2 {
+            return self.clone();
+        }
+
+        let mut pool = self.drone_routes.iter().flatten().cloned().collect::<Vec<_>>();
+        pool.sort_by(|a, b| b.working_time().total_cmp(&a.working_time()));
+
+        let mut bound = self.drone_working_time.iter().copied().fold(0.0, f64::max);
+        let mut best = None;
+        let mut i = 0;
+        while i < pool.len() {
+            let customers = &pool[i].data().customers;
+            let sorties = customers[1..customers.len() - 1]
+                .iter()
+                .map(|&c| DroneRoute::single(c))
+                .collect::<Vec<_>>();
+            let valid = |sortie: &Arc<DroneRoute>| {
+                sortie._length_respected() && sortie._arcs_respected() && _route_feasible(&**sortie, 0)
+            };
+            if sorties.len() < 2 || !sorties.iter().all(valid) {
+                i += 1;
+                continue;
+            }
+
+            let mut candidate = pool.clone();
+            candidate.swap_remove(i);
+            candidate.extend(sorties);
+            match _assign_drone_routes(candidate.clone(), drones, bound) {
+                Some(drone_routes) => {
+                    bound = drone_routes
+                        .iter()
+                        .enumerate()
+                        .map(|(d, routes)| {
+                            routes
+                                .iter()
+                                .map(|r| r.values(config::current().drone_class(d)).working_time)
+                                .sum::<f64>()
+                        })
+                        .fold(0.0, f64::max);
+                    best = Some(drone_routes);
+                    pool = candidate; // Another route was swapped to index `i`
+                }
+                None => i += 1,
+            }
+        }
+
+        match best {
+            Some(drone_routes) => {
+                let matched = Self::new(self.truck_routes.clone(), drone_routes);
+                if matched.cost() < self.cost() {
+                    matched
+                } else {
+                    self.clone()
+                }
+            }
+            None => self.clone(),
+        }
+    }
 
     pub fn initialize() -> Self {
         fn _sort_cluster_with_starting_point(cluster: &mut [usize], mut start: usize, distance: &[Vec<f64>]) {
@@ -312,39 +1829,63 @@ impl Solution {
             }
         }
 
-        fn _feasible(truck_routes: Vec<Vec<Rc<TruckRoute>>>, drone_routes: Vec<Vec<Rc<DroneRoute>>>) -> bool {
-            let solution = Solution::new(truck_routes, drone_routes);
-            solution.feasible
-        }
+        fn _feasible(truck_routes: Vec<Vec<Arc<TruckRoute>>>, drone_routes: Vec<Vec<Arc<DroneRoute>>>) -> bool {
+            fn _within_max<R>(vehicle_routes: &[Vec<Arc<R>>]) -> bool
+            where
+                R: Route,
+            {
+                vehicle_routes
+                    .iter()
+                    .flatten()
+                    .all(|r| r.data().customers.len() - 2 <= R::max_customers())
+            }
 
-        let mut index = Vec::from_iter(1..CONFIG.customers_count + 1);
-        let mut clusters = clusterize::clusterize(&mut index, CONFIG.trucks_count);
+            if !_within_max(&truck_routes) || !_within_max(&drone_routes) {
+                return false;
+            }
 
-        let mut truck_routes = vec![vec![]; CONFIG.trucks_count];
-        let mut drone_routes = vec![vec![]; CONFIG.trucks_count];
+            // The budget applies to the whole plan rather than to the routes being built, so it is left to the search
+            let solution = Solution::new(truck_routes, drone_routes);
+            [
+                solution.energy_violation,
+                solution.capacity_violation,
+                solution.waiting_time_violation,
+                solution.fixed_time_violation,
+            ]
+            .into_iter()
+            .all(|v| !_violated(v))
+        }
 
-        let mut clusters_mapping = vec![0; CONFIG.customers_count + 1];
-        for (i, cluster) in clusters.iter().enumerate() {
-            for &customer in cluster {
-                clusters_mapping[customer] = i;
-            }
+        let config = config::current();
+        // Customers are clustered per truck, with one drone working alongside each truck during construction (drone
+        // routes are redistributed among the actual drones afterwards). Without trucks, cluster per drone instead.
+        let clusters_count = if config.trucks_count > 0 {
+            config.trucks_count
+        } else {
+            config.drones_count
+        };
+        if clusters_count == 0 {
+            panic!("At least one truck or drone is required");
         }
 
-        let mut truckable = vec![false; CONFIG.customers_count + 1];
-        if CONFIG.trucks_count > 0 {
+        let mut truck_routes = vec![vec![]; config.trucks_count];
+        let mut drone_routes = vec![vec![]; if config.drones_count > 0 { clusters_count } else { 0 }];
+
+        let mut truckable = vec![false; config.customers_count + 1];
+        if config.trucks_count > 0 {
             truckable[0] = true;
-            for (customer, truckable) in truckable.iter_mut().enumerate().skip(1).take(CONFIG.customers_count) {
+            for (customer, truckable) in truckable.iter_mut().enumerate().skip(1).take(config.customers_count) {
                 truck_routes[0].push(TruckRoute::single(customer));
-                *truckable = _feasible(truck_routes.clone(), drone_routes.clone());
+                *truckable = TruckRoute::_servable(customer) && _feasible(truck_routes.clone(), drone_routes.clone());
                 truck_routes[0].pop();
             }
         }
 
-        let mut dronable = vec![false; CONFIG.customers_count + 1];
-        if CONFIG.drones_count > 0 {
+        let mut dronable = vec![false; config.customers_count + 1];
+        if config.drones_count > 0 {
             dronable[0] = true;
-            for (customer, dronable) in dronable.iter_mut().enumerate().skip(1).take(CONFIG.customers_count) {
-                if CONFIG.dronable[customer] {
+            for (customer, dronable) in dronable.iter_mut().enumerate().skip(1).take(config.customers_count) {
+                if DroneRoute::_servable(customer) {
                     drone_routes[0].push(DroneRoute::single(customer));
                     *dronable = _feasible(truck_routes.clone(), drone_routes.clone());
                     drone_routes[0].pop();
@@ -352,9 +1893,53 @@ impl Solution {
             }
         }
 
-        for customer in 1..CONFIG.customers_count + 1 {
-            if !truckable[customer] && !dronable[customer] {
-                panic!("Customer {customer} cannot be served by neither trucks nor drones")
+        let unservable = (1..config.customers_count + 1)
+            .filter(|&c| !truckable[c] && !dronable[c])
+            .collect::<Vec<_>>();
+        if !unservable.is_empty() {
+            panic!("Customers {unservable:?} cannot be served by neither trucks nor drones")
+        }
+
+        if config.init != InitMethod::Heap {
+            fn _routes<R>(customers: &[usize], distances: &DistanceMatrix, vehicles: usize) -> Vec<Arc<R>>
+            where
+                R: Route,
+            {
+                match config::current().init {
+                    InitMethod::Split => _split(&_giant_tour(customers, distances), 0, vehicles)
+                        .unwrap_or_else(|| _savings_routes(customers, distances, vehicles)),
+                    _ => _savings_routes(customers, distances, vehicles),
+                }
+            }
+
+            // Customers servable by both go to trucks first, drones then take over the chains that pay off
+            let (truck_customers, drone_customers) =
+                (1..config.customers_count + 1).partition::<Vec<_>, _>(|&c| truckable[c]);
+
+            let mut truck_routes = _distribute(
+                _routes::<TruckRoute>(&truck_customers, &config.truck_distances, config.trucks_count),
+                config.trucks_count,
+                |t| config.truck_class(t),
+            );
+            let mut drone_routes = _distribute(
+                _routes::<DroneRoute>(&drone_customers, &config.drone_distances, config.drones_count),
+                config.drones_count,
+                |d| config.drone_class(d),
+            );
+            if config.trucks_count > 0 && config.drones_count > 0 {
+                _extract_drone_chains(&mut truck_routes, &mut drone_routes, &dronable);
+            }
+
+            return Self::_enforce_constraints(truck_routes, drone_routes);
+        }
+
+        let mut index = Vec::from_iter(1..config.customers_count + 1);
+        let mut clusters = clusterize::clusterize(&mut index, clusters_count);
+
+        let mut clusters_mapping = vec![0; config.customers_count + 1];
+        for (i, cluster) in clusters.iter().enumerate() {
+            for &customer in cluster {
+                clusters_mapping[customer] = i;
             }
         }
 
@@ -388,7 +1973,7 @@ impl Solution {
         impl Eq for _State {}
 
         let mut queue = BinaryHeap::new();
-        let mut rng = rng();
+        let mut rng = seeding::substream(seeding::INITIALIZE);
         for (i, cluster) in clusters.iter_mut().enumerate() {
             if cluster.is_empty() {
                 continue;
@@ -409,7 +1994,12 @@ impl Solution {
                 }
             }
 
-            cluster.sort_by(|&i, &j| CONFIG.drone_distances[0][i].total_cmp(&CONFIG.drone_distances[0][j]));
+            cluster.sort_by(|&i, &j| {
+                config
+                    .drone_distances
+                    .get(0, i)
+                    .total_cmp(&config.drone_distances.get(0, j))
+            });
             for &customer in cluster.iter() {
                 if dronable[customer] {
                     queue.push(_State {
@@ -425,7 +2015,7 @@ impl Solution {
             }
         }
 
-        let mut global = BTreeSet::from_iter(1..CONFIG.customers_count + 1);
+        let mut global = BTreeSet::from_iter(1..config.customers_count + 1);
 
         fn truck_next(
             truckable: &[bool],
@@ -433,24 +2023,25 @@ impl Solution {
             clusters_mapping: &[usize],
             queue: &mut BinaryHeap<_State>,
             global: &BTreeSet<usize>,
-            truck_routes: &mut [Vec<Rc<TruckRoute>>],
-            drone_routes: &[Vec<Rc<DroneRoute>>],
+            truck_routes: &mut [Vec<Arc<TruckRoute>>],
+            drone_routes: &[Vec<Arc<DroneRoute>>],
             parent: usize,
             vehicle: usize,
         ) {
+            let config = config::current();
             let mut min_distance = f64::INFINITY;
             let mut min_idx = 0;
             for &customer in &clusters[clusters_mapping[parent]] {
-                if truckable[customer] && CONFIG.truck_distances[parent][customer] < min_distance {
-                    min_distance = CONFIG.truck_distances[parent][customer];
+                if truckable[customer] && config.truck_distances.get(parent, customer) < min_distance {
+                    min_distance = config.truck_distances.get(parent, customer);
                     min_idx = customer;
                 }
             }
 
             if min_idx == 0 {
                 for &customer in global.iter() {
-                    if truckable[customer] && CONFIG.truck_distances[parent][customer] < min_distance {
-                        min_distance = CONFIG.truck_distances[parent][customer];
+                    if truckable[customer] && config.truck_distances.get(parent, customer) < min_distance {
+                        min_distance = config.truck_distances.get(parent, customer);
                         min_idx = customer;
                     }
                 }
@@ -474,24 +2065,25 @@ impl Solution {
             clusters_mapping: &[usize],
             queue: &mut BinaryHeap<_State>,
             global: &BTreeSet<usize>,
-            truck_routes: &[Vec<Rc<TruckRoute>>],
-            drone_routes: &mut [Vec<Rc<DroneRoute>>],
+            truck_routes: &[Vec<Arc<TruckRoute>>],
+            drone_routes: &mut [Vec<Arc<DroneRoute>>],
             parent: usize,
             vehicle: usize,
         ) {
+            let config = config::current();
             let mut min_distance = f64::INFINITY;
             let mut min_idx = 0;
             for &customer in &clusters[clusters_mapping[parent]] {
-                if dronable[customer] && CONFIG.drone_distances[parent][customer] < min_distance {
-                    min_distance = CONFIG.drone_distances[parent][customer];
+                if dronable[customer] && config.drone_distances.get(parent, customer) < min_distance {
+                    min_distance = config.drone_distances.get(parent, customer);
                     min_idx = customer;
                 }
             }
 
             if min_idx == 0 {
                 for &customer in global.iter() {
-                    if dronable[customer] && CONFIG.drone_distances[parent][customer] < min_distance {
-                        min_distance = CONFIG.drone_distances[parent][customer];
+                    if dronable[customer] && config.drone_distances.get(parent, customer) < min_distance {
+                        min_distance = config.drone_distances.get(parent, customer);
                         min_idx = customer;
                     }
                 }
@@ -554,7 +2146,7 @@ impl Solution {
                                 &global,
                                 &truck_routes,
                                 &mut drone_routes,
-                                if CONFIG.single_drone_route { 0 } else { packed.index },
+                                if config.single_drone_route { 0 } else { packed.index },
                                 packed.vehicle,
                             );
                         }
@@ -566,7 +2158,7 @@ impl Solution {
                             *route = route.pop();
                         }
 
-                        if !CONFIG.single_truck_route {
+                        if truck_routes[packed.vehicle].len() < TruckRoute::max_routes() {
                             truck_next(
                                 &truckable,
                                 &clusters,
@@ -622,7 +2214,7 @@ impl Solution {
                             &global,
                             &truck_routes,
                             &mut drone_routes,
-                            if CONFIG.single_drone_route { 0 } else { packed.parent },
+                            if config.single_drone_route { 0 } else { packed.parent },
                             packed.vehicle,
                         );
                     }
@@ -630,41 +2222,115 @@ impl Solution {
             }
         }
 
-        if CONFIG.drones_count > 0 {
-            // Resize drone routes to `CONFIG.drones_count`
-            let mut all_routes = vec![];
-            for routes in &drone_routes {
-                all_routes.extend(routes.iter().cloned());
-            }
-            all_routes.sort_by(|f, s| f.working_time().total_cmp(&s.working_time()).reverse());
+        // Resize drone routes to `config.drones_count`
+        let drone_routes = _distribute(drone_routes.into_iter().flatten().collect(), config.drones_count, |d| {
+            config.drone_class(d)
+        });
 
-            drone_routes.clear();
-            drone_routes.resize(CONFIG.drones_count, vec![]);
+        Self::_enforce_constraints(truck_routes, drone_routes)
+    }
 
-            let mut working_time = vec![0.0; CONFIG.drones_count];
-            for route in all_routes {
-                let mut min_idx = 0;
-                let mut min_time = f64::INFINITY;
-                for (i, &time) in working_time.iter().enumerate() {
-                    if time < min_time {
-                        min_time = time;
-                        min_idx = i;
+    /// Finish an initial solution: the construction only respects the vehicle type of each lock and zone and ignores
+    /// arc constraints, so move customers that ended up in the wrong vehicle to the right one and reinsert constrained
+    /// customers.
+    fn _enforce_constraints(
+        mut truck_routes: Vec<Vec<Arc<TruckRoute>>>,
+        mut drone_routes: Vec<Vec<Arc<DroneRoute>>>,
+    ) -> Self {
+        let config = config::current();
+        if !config.locks.is_empty() || !config.zones.is_empty() || !config.arcs.is_empty() {
+            let mut misplaced = _remove_customers(&mut truck_routes, |truck, c| {
+                !config.allows(VehicleKind::Truck, truck, c) || config.arcs.constrained(c)
+            });
+            misplaced.extend(_remove_customers(&mut drone_routes, |drone, c| {
+                !config.allows(VehicleKind::Drone, drone, c) || config.arcs.constrained(c)
+            }));
+
+            return Self::_repair(truck_routes, drone_routes, &misplaced);
+        }
+
+        Self::new(truck_routes, drone_routes)
+    }
+
+    /// Order crossover adapted to vehicle routes: the offspring inherits a random run of consecutive routes of this
+    /// solution (listing the truck routes, then the drone routes, vehicle by vehicle) on the same vehicles, then the
+    /// other customers in the order of the routes of `other`, each route of `other` minus the inherited customers
+    /// becoming a route of the same vehicle (or joining its last route if it may not operate more). The routes of each
+    /// vehicle are then re-split. Returns `None` if the offspring breaks a constraint checked by [`Self::validate`].
+    pub fn crossover(&self, other: &Self, rng: &mut impl Rng) -> Option<Self> {
+        /// Add the routes of `vehicle_routes` minus the `inherited` customers to the `offspring`.
+        fn _fill<R>(vehicle_routes: &[Vec<Arc<R>>], offspring: &mut [Vec<Arc<R>>], inherited: &[bool])
+        where
+            R: Route,
+        {
+            for (vehicle, routes) in vehicle_routes.iter().enumerate() {
+                for route in routes {
+                    let mut customers = route.data().customers.clone();
+                    customers.retain(|&c| c == 0 || !inherited[c]);
+                    if customers.len() <= 2 {
+                        continue;
+                    }
+
+                    if offspring[vehicle].len() < R::max_routes() {
+                        offspring[vehicle].push(R::new(customers));
+                    } else if let Some(last) = offspring[vehicle].last_mut() {
+                        let mut joined = last.data().customers.clone();
+                        joined.pop();
+                        joined.extend_from_slice(&customers[1..]);
+                        *last = R::new(joined);
                     }
                 }
+            }
+        }
+
+        let config = config::current();
+        let routes = self
+            .truck_routes
+            .iter()
+            .enumerate()
+            .flat_map(|(t, routes)| routes.iter().map(move |r| (true, t, &r.data().customers)))
+            .chain(
+                self.drone_routes
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(d, routes)| routes.iter().map(move |r| (false, d, &r.data().customers))),
+            )
+            .collect::<Vec<_>>();
+        if routes.is_empty() {
+            return None;
+        }
+
+        let start = rng.random_range(0..routes.len());
+        let end = rng.random_range(start + 1..=routes.len());
+
+        let mut truck_routes = vec![vec![]; self.truck_routes.len()];
+        let mut drone_routes = vec![vec![]; self.drone_routes.len()];
+        let mut inherited = vec![false; config.customers_count + 1];
+        for &(is_truck, vehicle, customers) in &routes[start..end] {
+            for &c in &customers[1..customers.len() - 1] {
+                inherited[c] = true;
+            }
 
-                drone_routes[min_idx].push(route.clone());
-                working_time[min_idx] += route.working_time();
+            if is_truck {
+                truck_routes[vehicle].push(TruckRoute::new(customers.clone()));
+            } else {
+                drone_routes[vehicle].push(DroneRoute::new(customers.clone()));
             }
-        } else {
-            drone_routes.clear();
         }
 
-        Self::new(truck_routes, drone_routes)
+        _fill(&other.truck_routes, &mut truck_routes, &inherited);
+        _fill(&other.drone_routes, &mut drone_routes, &inherited);
+        _resplit(&mut truck_routes, |t| config.truck_class(t));
+        _resplit(&mut drone_routes, |d| config.drone_class(d));
+
+        let offspring = Self::new(truck_routes, drone_routes);
+        offspring.validate().ok().map(|()| offspring)
     }
 
-    pub fn destroy_and_repair(&self, edge_records: &[Vec<f64>]) -> Self {
+    pub fn destroy_and_repair(&self, edge_records: &[Vec<f64>], pool: &RoutePool, rng: &mut impl Rng) -> Self {
+        let config = config::current();
         // TODO: Implement
-        let mut scores = vec![0.0; CONFIG.customers_count + 1];
+        let mut scores = vec![0.0; config.customers_count + 1];
         for routes in &self.truck_routes {
             for route in routes {
                 let customers = &route.data().customers;
@@ -684,81 +2350,96 @@ impl Solution {
             }
         }
 
-        let mut ordered = (1..CONFIG.customers_count + 1).collect::<Vec<usize>>();
+        let mut ordered = (1..config.customers_count + 1).collect::<Vec<usize>>();
         ordered.sort_unstable_by(|&a, &b| scores[a].total_cmp(&scores[b]));
 
-        let mut rng = rng();
-        let destroy_count = (CONFIG.customers_count as f64 * CONFIG.destroy_rate) as usize;
+        let destroy_count = (config.customers_count as f64 * config.destroy_rate) as usize;
         let mut to_destroy = HashSet::new();
         while to_destroy.len() < destroy_count {
             let index = rng.random_range(0..ordered.len()).pow(2) / ordered.len();
-            to_destroy.insert(ordered[index]);
+
+            // Customers linked by required arcs are always destroyed together
+            to_destroy.extend(config.arcs.chain(ordered[index]));
         }
 
         let mut truck_routes = self.truck_routes.clone();
         let mut drone_routes = self.drone_routes.clone();
 
         // Destroy phase
-        for routes in &mut truck_routes {
-            let mut i = 0;
-            while i < routes.len() {
-                let mut buffer = vec![];
-                for customer in &routes[i].data().customers {
-                    if !to_destroy.contains(customer) {
-                        buffer.push(*customer);
-                    }
-                }
+        _remove_customers(&mut truck_routes, |_, c| to_destroy.contains(&c));
+        _remove_customers(&mut drone_routes, |_, c| to_destroy.contains(&c));
+
+        // Rebuild routes of previous runs serving only destroyed customers, then repair the others
+        _insert_pooled(
+            &mut truck_routes,
+            &pool.truck_routes,
+            &mut to_destroy,
+            |t| config.truck_class(t),
+            rng,
+        );
+        _insert_pooled(
+            &mut drone_routes,
+            &pool.drone_routes,
+            &mut to_destroy,
+            |d| config.drone_class(d),
+            rng,
+        );
 
-                if buffer.len() > 2 {
-                    routes[i] = TruckRoute::new(buffer);
-                    i += 1;
-                } else {
-                    routes.swap_remove(i);
-                }
-            }
-        }
+        // Repair phase
+        // Sorted first, as the iteration order of the set would make seeded runs irreproducible
+        let mut to_destroy = to_destroy.into_iter().collect::<Vec<usize>>();
+        to_destroy.sort_unstable();
+        to_destroy.shuffle(rng);
 
-        for routes in &mut drone_routes {
-            let mut i = 0;
-            while i < routes.len() {
-                let mut buffer = vec![];
-                for customer in &routes[i].data().customers {
-                    if !to_destroy.contains(customer) {
-                        buffer.push(*customer);
-                    }
-                }
+        let repaired = Self::_repair(truck_routes, drone_routes, &to_destroy);
 
-                if buffer.len() > 2 {
-                    routes[i] = DroneRoute::new(buffer);
-                    i += 1;
-                } else {
-                    routes.swap_remove(i);
-                }
-            }
-        }
+        // Cheapest insertion leaves the route boundaries of each vehicle as they were, split them optimally again
+        let mut truck_routes = repaired.truck_routes;
+        let mut drone_routes = repaired.drone_routes;
+        _resplit(&mut truck_routes, |t| config.truck_class(t));
+        _resplit(&mut drone_routes, |d| config.drone_class(d));
 
-        // Repair phase
-        let mut to_destroy = to_destroy.into_iter().collect::<Vec<usize>>();
-        to_destroy.shuffle(&mut rng);
+        Self::new(truck_routes, drone_routes)
+    }
 
-        let old_penalty = [
-            penalty_coeff::<0>(),
-            penalty_coeff::<1>(),
-            penalty_coeff::<2>(),
-            penalty_coeff::<3>(),
-        ];
-        for i in 0..4 {
-            PENALTY_COEFF[i].store(1e3, Ordering::Relaxed);
-        }
+    /// Insert `customers` one by one at the cheapest position among the vehicles allowed to serve them. Customers
+    /// linked by required arcs are inserted together as a single chain, so `customers` must contain whole chains.
+    fn _repair(
+        mut truck_routes: Vec<Vec<Arc<TruckRoute>>>,
+        mut drone_routes: Vec<Vec<Arc<DroneRoute>>>,
+        customers: &[usize],
+    ) -> Self {
+        let config = config::current();
+        let old_penalty = penalty_coefficients();
+        _set_penalties([1e3; 5]);
+
+        let mut inserted = vec![false; config.customers_count + 1];
+        for &customer in customers {
+            if inserted[customer] {
+                continue;
+            }
+
+            let unit = config.arcs.chain(customer);
+            for &c in &unit {
+                inserted[c] = true;
+            }
 
-        for customer in to_destroy {
             let mut min_cost = f64::MAX;
             let mut insert = (true, true, 0, 0, 0);
 
+            let allowed = |kind, vehicle| unit.iter().all(|&c| config.allows(kind, vehicle, c));
+            let mut single = vec![0];
+            single.extend_from_slice(&unit);
+            single.push(0);
+
             for truck in 0..truck_routes.len() {
+                if !unit.iter().all(|&c| TruckRoute::_servable(c)) || !allowed(VehicleKind::Truck, truck) {
+                    continue;
+                }
+
                 // Try appending
-                if !CONFIG.single_truck_route || truck_routes[truck].is_empty() {
-                    truck_routes[truck].push(TruckRoute::single(customer));
+                if truck_routes[truck].len() < TruckRoute::max_routes() {
+                    truck_routes[truck].push(TruckRoute::new(single.clone()));
                     let temp = Self::new(truck_routes, drone_routes);
                     if temp.cost() < min_cost {
                         min_cost = temp.cost();
@@ -774,11 +2455,18 @@ impl Solution {
                 for route in 0..truck_routes[truck].len() {
                     let recover = truck_routes[truck][route].clone();
                     let customers = &recover.data().customers;
-                    let mut buffer = customers.clone();
+                    if customers.len() - 2 + unit.len() > TruckRoute::max_customers() {
+                        continue;
+                    }
 
-                    buffer.insert(1, customer);
-                    for i in 1..customers.len() - 1 {
-                        truck_routes[truck][route] = TruckRoute::new(buffer.clone());
+                    for i in _insertion_positions(&*recover, &unit) {
+                        let mut buffer = customers.clone();
+                        buffer.splice(i..i, unit.iter().copied());
+                        if !config.arcs.respected(&buffer) {
+                            continue;
+                        }
+
+                        truck_routes[truck][route] = TruckRoute::new(buffer);
 
                         let temp = Self::new(truck_routes, drone_routes);
                         if temp.cost() < min_cost {
@@ -788,19 +2476,20 @@ impl Solution {
 
                         truck_routes = temp.truck_routes;
                         drone_routes = temp.drone_routes;
-
-                        buffer.swap(i, i + 1);
                     }
 
-                    // buffer.pop();  // No need to recover, we're throwing `buffer` away anyway.
                     truck_routes[truck][route] = recover;
                 }
             }
 
-            if CONFIG.dronable[customer] {
+            if unit.iter().all(|&c| DroneRoute::_servable(c)) {
                 for drone in 0..drone_routes.len() {
+                    if !allowed(VehicleKind::Drone, drone) {
+                        continue;
+                    }
+
                     // Try appending
-                    drone_routes[drone].push(DroneRoute::single(customer));
+                    drone_routes[drone].push(DroneRoute::new(single.clone()));
                     let temp = Self::new(truck_routes.clone(), drone_routes.clone());
                     if temp.cost() < min_cost {
                         min_cost = temp.cost();
@@ -811,15 +2500,22 @@ impl Solution {
                     drone_routes[drone].pop();
 
                     // Try inserting
-                    if !CONFIG.single_drone_route {
+                    if !config.single_drone_route {
                         for route in 0..drone_routes[drone].len() {
                             let recover = drone_routes[drone][route].clone();
                             let customers = &recover.data().customers;
-                            let mut buffer = customers.clone();
+                            if customers.len() - 2 + unit.len() > DroneRoute::max_customers() {
+                                continue;
+                            }
+
+                            for i in _insertion_positions(&*recover, &unit) {
+                                let mut buffer = customers.clone();
+                                buffer.splice(i..i, unit.iter().copied());
+                                if !config.arcs.respected(&buffer) {
+                                    continue;
+                                }
 
-                            buffer.insert(1, customer);
-                            for i in 1..customers.len() - 1 {
-                                drone_routes[drone][route] = DroneRoute::new(buffer.clone());
+                                drone_routes[drone][route] = DroneRoute::new(buffer);
 
                                 let temp = Self::new(truck_routes.clone(), drone_routes.clone());
                                 if temp.cost() < min_cost {
@@ -829,8 +2525,6 @@ impl Solution {
 
                                 truck_routes = temp.truck_routes;
                                 drone_routes = temp.drone_routes;
-
-                                buffer.swap(i, i + 1);
                             }
 
                             drone_routes[drone][route] = recover;
@@ -839,9 +2533,13 @@ impl Solution {
                 }
             }
 
+            if min_cost == f64::MAX {
+                panic!("No vehicle can serve customers {unit:?}");
+            }
+
             fn _insert<T>(
-                routes: &mut [Vec<Rc<T>>],
-                customer: usize,
+                routes: &mut [Vec<Arc<T>>],
+                unit: &[usize],
                 append: bool,
                 vehicle: usize,
                 route: usize,
@@ -850,31 +2548,172 @@ impl Solution {
                 T: Route,
             {
                 if append {
-                    routes[vehicle].push(T::single(customer));
+                    let mut buffer = vec![0];
+                    buffer.extend_from_slice(unit);
+                    buffer.push(0);
+                    routes[vehicle].push(T::new(buffer));
                 } else {
                     let mut buffer = routes[vehicle][route].data().customers.clone();
-                    buffer.insert(index, customer);
+                    buffer.splice(index..index, unit.iter().copied());
                     routes[vehicle][route] = T::new(buffer);
                 }
             }
 
             let (is_truck, append, vehicle, route, index) = insert;
             if is_truck {
-                _insert(&mut truck_routes, customer, append, vehicle, route, index);
+                _insert(&mut truck_routes, &unit, append, vehicle, route, index);
             } else {
-                _insert(&mut drone_routes, customer, append, vehicle, route, index);
+                _insert(&mut drone_routes, &unit, append, vehicle, route, index);
             }
         }
 
-        for i in 0..4 {
-            PENALTY_COEFF[i].store(old_penalty[i], Ordering::Relaxed);
-        }
+        _set_penalties(old_penalty);
 
         Self::new(truck_routes, drone_routes)
         // s.verify();
     }
 
-    pub fn tabu_search(root: Self, logger: &mut Logger) -> Self {
+    pub fn tabu_search(root: Self, logger: &mut dyn Logger) -> Self {
+        if config::current().threads > 1 {
+            return multistart::tabu_search(root, logger);
+        }
+
+        let mut search = TabuSearch::new(root, logger);
+        search.run(|_| {});
+        search.finish()
+    }
+}
+
+struct _AdaptiveState {
+    segment: usize,
+    segment_reset: usize,
+    last_improved_segment: usize,
+    scores: Vec<f64>,
+    weights: Vec<f64>,
+    occurences: Vec<u32>,
+}
+
+/// Neighborhood weights of [`Strategy::Bottleneck`], indexed by the side defining the makespan (see [`_bottleneck`]).
+struct _BottleneckState {
+    scores: [Vec<f64>; 2],
+    weights: [Vec<f64>; 2],
+    occurences: [Vec<u32>; 2],
+}
+
+/// The side defining the makespan of a solution (0 for the trucks, 1 for the drones) and its working time.
+fn _bottleneck(s: &Solution) -> (usize, f64) {
+    let truck = s.truck_working_time.iter().copied().fold(0.0, f64::max);
+    let drone = s.drone_working_time.iter().copied().fold(0.0, f64::max);
+    if drone > truck { (1, drone) } else { (0, truck) }
+}
+
+/// Working time of a side of a solution, as returned by [`_bottleneck`].
+fn _side_working_time(s: &Solution, side: usize) -> f64 {
+    let times = if side == 0 {
+        &s.truck_working_time
+    } else {
+        &s.drone_working_time
+    };
+    times.iter().copied().fold(0.0, f64::max)
+}
+
+/// A number of failures before the first success of Bernoulli trials with success probability `bias`, i.e. a
+/// geometric distribution over the ranks of the candidates of `--selection-bias`.
+fn _geometric_rank(rng: &mut impl Rng, bias: f64) -> usize {
+    if bias >= 1.0 {
+        return 0;
+    }
+
+    // `1 - random` lies in (0, 1], so the logarithm is finite
+    (1.0 - rng.random::<f64>()).log(1.0 - bias).floor() as usize
+}
+
+fn _update_violation_solution(s: &Solution) {
+    _update_violation::<0>(s.energy_violation);
+    _update_violation::<1>(s.capacity_violation);
+    _update_violation::<2>(s.waiting_time_violation);
+    _update_violation::<3>(s.fixed_time_violation);
+    _update_violation::<4>(s.budget_violation);
+}
+
+/// Reason why a tabu search stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Termination {
+    /// The elite set ran out of solutions to restart from.
+    EliteSetExhausted,
+    /// `--fix-iteration` iterations were performed.
+    FixIteration,
+    /// `--time-limit` was exceeded, during the search or before the post-optimization was completed.
+    TimeLimit,
+    /// A feasible solution reaching `--target-cost` was found.
+    TargetCost,
+    /// `--dry-run` skipped the search.
+    DryRun,
+    /// A `stop` command was written to the `--control` file.
+    Stopped,
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::EliteSetExhausted => "stopped as the elite set was exhausted",
+                Self::FixIteration => "stopped after --fix-iteration iterations",
+                Self::TimeLimit => "stopped by --time-limit",
+                Self::TargetCost => "stopped on reaching --target-cost",
+                Self::DryRun => "skipped by --dry-run",
+                Self::Stopped => "stopped by a command of --control",
+            }
+        )
+    }
+}
+
+/// Iteration-level handle on the tabu search, so that an outer system can interleave it with other work, inject
+/// solutions found elsewhere and stop on its own criteria. [`Solution::tabu_search`] simply runs it to completion.
+pub struct TabuSearch<'a, 'l> {
+    logger: &'a mut (dyn Logger + 'l),
+    tabu_size: usize,
+    adaptive_iterations: usize,
+    reset_after: usize,
+
+    result: Arc<Solution>,
+    current: Arc<Solution>,
+    iteration: usize,
+    last_improved_iteration: usize,
+    /// Number of new best solutions found so far, for `--snapshot-every`.
+    incumbents: usize,
+    adaptive: _AdaptiveState,
+    bottleneck: _BottleneckState,
+    edge_records: Vec<Vec<f64>>,
+    elite_set: Vec<Arc<Solution>>,
+    neighborhood_idx: usize,
+    tabu_lists: Vec<Vec<TabuAttribute>>,
+    route_pool: RoutePool,
+    rng: StdRng,
+    /// Current temperature of `--algorithm sa`.
+    temperature: f64,
+    /// Customers perturbed by the last restart, which moves are restricted to until iteration `focus_until` (see
+    /// `--focus-iterations`).
+    focus: Option<FocusList>,
+    focus_until: usize,
+    /// Don't-look bits of `--dont-look-bits`, per neighborhood and customer, as of `dont_look_solution`.
+    dont_look: Vec<Vec<bool>>,
+    dont_look_solution: Arc<Solution>,
+    /// Route-level don't-look bits of `--dont-look-routes`: per neighborhood, the solution in which it last found no
+    /// improving move, if it has not found one since (see [`FocusList::with_cold_routes`]).
+    looked: Vec<Option<Arc<Solution>>>,
+    termination: Option<Termination>,
+    /// `--time-limit`, unless replaced by a command of `--control`.
+    time_limit: Option<f64>,
+}
+
+#[allow(dead_code)] // Not all of the API for embedding the search is used by the CLI itself
+impl<'a, 'l> TabuSearch<'a, 'l> {
+    pub fn new(root: Solution, logger: &'a mut (dyn Logger + 'l)) -> Self {
+        let config = config::current();
         let mut total_vehicle = 0;
         for truck in &root.truck_routes {
             total_vehicle += usize::from(!truck.is_empty());
@@ -882,309 +2721,704 @@ impl Solution {
         for drone in &root.drone_routes {
             total_vehicle += usize::from(!drone.is_empty());
         }
-        let base_hyperparameter = CONFIG.customers_count as f64 / total_vehicle as f64;
-        let tabu_size = (CONFIG.tabu_size_factor * base_hyperparameter) as usize;
+        let base_hyperparameter = config.customers_count as f64 / total_vehicle as f64;
+        let tabu_size = (config.tabu_size_factor * base_hyperparameter) as usize;
 
-        let adaptive_iterations = (CONFIG.adaptive_iterations as f64 * base_hyperparameter) as usize;
+        let adaptive_iterations = (config.adaptive_iterations as f64 * base_hyperparameter) as usize;
 
-        let reset_after = if CONFIG.fix_iteration.is_some() {
+        let reset_after = if config.fix_iteration.is_some() {
             i64::MAX as usize // usize::MAX cannot be stored in SQLite
         } else {
-            (CONFIG.reset_after_factor * base_hyperparameter) as usize
+            (config.reset_after_factor * base_hyperparameter) as usize
         };
 
-        let mut result = Rc::new(root);
+        let result = Arc::new(root);
+        crash::record_best(&result);
+        Self {
+            logger,
+            tabu_size,
+            adaptive_iterations,
+            reset_after,
+            current: result.clone(),
+            elite_set: vec![result.clone()],
+            dont_look_solution: result.clone(),
+            result,
+            iteration: 0,
+            last_improved_iteration: 0,
+            incumbents: 0,
+            adaptive: _AdaptiveState {
+                segment: 0,
+                segment_reset: 0,
+                last_improved_segment: 0,
+                scores: vec![0.0; config.neighborhoods.len()],
+                weights: vec![1.0; config.neighborhoods.len()],
+                occurences: vec![0; config.neighborhoods.len()],
+            },
+            bottleneck: _BottleneckState {
+                scores: [
+                    vec![0.0; config.neighborhoods.len()],
+                    vec![0.0; config.neighborhoods.len()],
+                ],
+                weights: [
+                    vec![1.0; config.neighborhoods.len()],
+                    vec![1.0; config.neighborhoods.len()],
+                ],
+                occurences: [vec![0; config.neighborhoods.len()], vec![0; config.neighborhoods.len()]],
+            },
+            edge_records: vec![vec![f64::MAX; config.customers_count + 1]; config.customers_count + 1],
+            neighborhood_idx: 0,
+            tabu_lists: vec![vec![]; config.neighborhoods.len()],
+            route_pool: pool::load(),
+            rng: seeding::substream(seeding::SEARCH),
+            temperature: config.initial_temperature,
+            focus: None,
+            focus_until: 0,
+            dont_look: vec![vec![false; config.customers_count + 1]; config.neighborhoods.len()],
+            looked: vec![None; config.neighborhoods.len()],
+            termination: config.dry_run.then_some(Termination::DryRun),
+            time_limit: config.time_limit,
+        }
+    }
 
-        let mut last_improved_iteration = 0;
+    /// The best feasible solution found so far.
+    pub fn best(&self) -> &Arc<Solution> {
+        &self.result
+    }
 
-        struct _AdaptiveState {
-            segment: usize,
-            segment_reset: usize,
-            last_improved_segment: usize,
-            scores: Vec<f64>,
-            weights: Vec<f64>,
-            occurences: Vec<u32>,
-        }
+    /// The solution the search is currently exploring from, which may be infeasible.
+    pub fn current(&self) -> &Arc<Solution> {
+        &self.current
+    }
 
-        let mut adaptive = _AdaptiveState {
-            segment: 0,
-            segment_reset: 0,
-            last_improved_segment: 0,
-            scores: vec![0.0; NEIGHBORHOODS.len()],
-            weights: vec![1.0; NEIGHBORHOODS.len()],
-            occurences: vec![0; NEIGHBORHOODS.len()],
-        };
+    /// Number of iterations performed so far.
+    pub const fn iteration(&self) -> usize {
+        self.iteration
+    }
 
-        let mut post_optimization = 0.0;
-        let mut post_optimization_elapsed = 0.0;
-        if !CONFIG.dry_run {
-            let mut current = result.clone();
-            let mut edge_records = vec![vec![f64::MAX; CONFIG.customers_count + 1]; CONFIG.customers_count + 1];
-            let mut elite_set = vec![];
-            elite_set.push(result.clone());
+    /// Whether the search stopped on its own, see [`Self::termination`].
+    pub const fn finished(&self) -> bool {
+        self.termination.is_some()
+    }
 
-            let mut neighborhood_idx = 0;
+    /// Why the search stopped, or `None` while it is running.
+    pub const fn termination(&self) -> Option<Termination> {
+        self.termination
+    }
 
-            let iteration_range = match CONFIG.fix_iteration {
-                Some(iteration) => 1..iteration + 1,
-                None => 1..usize::MAX,
-            };
-            let mut rng = rand::rng();
-
-            let mut tabu_lists = vec![vec![]; NEIGHBORHOODS.len()];
-
-            fn _record_new_solution(
-                neighbor: &Rc<Solution>,
-                result: &mut Rc<Solution>,
-                last_improved_iteration: &mut usize,
-                last_improved_segment: &mut usize,
-                iteration: usize,
-                segment: usize,
-                edge_records: &mut [Vec<f64>],
-                elite_set: &mut Vec<Rc<Solution>>,
-            ) {
-                if neighbor.cost() + TOLERANCE < result.cost() && neighbor.feasible {
-                    *result = neighbor.clone();
-                    *last_improved_iteration = iteration;
-                    *last_improved_segment = segment;
-
-                    for routes in &neighbor.truck_routes {
-                        for route in routes {
-                            let customers = &route.data().customers;
-                            for i in 0..customers.len() - 1 {
-                                let r = &mut edge_records[customers[i]][customers[i + 1]];
-                                *r = r.min(neighbor.working_time);
-                            }
-                        }
-                    }
+    /// Whether the time limit (see [`Self::set_time_limit`]) has been exceeded.
+    fn _out_of_time(&self) -> bool {
+        self.time_limit.is_some_and(|limit| self.logger.elapsed() >= limit)
+    }
 
-                    if CONFIG.max_elite_size > 0 {
-                        if elite_set.len() == CONFIG.max_elite_size {
-                            let (idx, _) = elite_set
-                                .iter()
-                                .enumerate()
-                                .min_by_key(|s| s.1.hamming_distance(result))
-                                .unwrap();
-                            elite_set.remove(idx);
-                        }
+    /// Replace `--time-limit` (in seconds since the start of the run) for the rest of the search and the
+    /// post-optimization, or lift it with `None`.
+    pub const fn set_time_limit(&mut self, limit: Option<f64>) {
+        self.time_limit = limit;
+    }
 
-                        elite_set.push(neighbor.clone());
-                    }
+    /// Stop the search before its next iteration, as if it had finished on its own.
+    pub const fn stop(&mut self) {
+        if self.termination.is_none() {
+            self.termination = Some(Termination::Stopped);
+        }
+    }
+
+    /// Write the best solution found so far to `path`, in the format of the output solution JSON.
+    pub fn checkpoint(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string(self.result.as_ref())?)?;
+        Ok(())
+    }
+
+    /// Carry out a command of the `--control` file, reporting its outcome to stderr.
+    pub fn control(&mut self, command: &ControlCommand) {
+        let config = config::current();
+        eprintln!("\nControl command at iteration #{}: {command}", self.iteration);
+        match command {
+            ControlCommand::Stop => self.stop(),
+            ControlCommand::Checkpoint(path) => {
+                let path = path
+                    .clone()
+                    .unwrap_or_else(|| Path::new(&config.outputs).join(format!("checkpoint-{}.json", self.iteration)));
+                match self.checkpoint(&path) {
+                    Ok(()) => eprintln!("Saved the best solution found to {}", path.display()),
+                    Err(e) => eprintln!("Failed to save a checkpoint to {}: {e}", path.display()),
                 }
             }
+            ControlCommand::Report => eprintln!(
+                "Elapsed {:.2}s, current cost {:.2}, best cost {:.2} (working time {:.2}, {}), elite set {}/{}",
+                self.logger.elapsed(),
+                self.current.cost(),
+                self.result.cost(),
+                self.result.working_time,
+                if self.result.feasible { "feasible" } else { "infeasible" },
+                self.elite_set.len(),
+                config.max_elite_size,
+            ),
+            ControlCommand::SetTimeLimit(limit) => self.set_time_limit(*limit),
+        }
+    }
 
-            fn _update_violation_solution(s: &Solution) {
-                _update_violation::<0>(s.energy_violation);
-                _update_violation::<1>(s.capacity_violation);
-                _update_violation::<2>(s.waiting_time_violation);
-                _update_violation::<3>(s.fixed_time_violation);
-            }
-
-            for iteration in iteration_range {
-                if CONFIG.verbose {
-                    let extra = if let Strategy::Adaptive = CONFIG.strategy {
-                        format!(
-                            "(segments before reset {})",
-                            if CONFIG.adaptive_fixed_segments {
-                                adaptive
-                                    .segment
-                                    .saturating_sub(adaptive.segment_reset + CONFIG.adaptive_segments)
-                            } else {
-                                CONFIG.adaptive_segments.saturating_sub(
-                                    adaptive.segment - cmp::max(adaptive.segment_reset, adaptive.last_improved_segment),
-                                )
-                            }
-                        )
-                    } else {
-                        format!(
-                            "(reset in {})",
-                            reset_after.saturating_sub((iteration - last_improved_iteration) % reset_after)
-                        )
-                    };
+    /// Metropolis criterion of simulated annealing: whether to move from the current solution to `neighbor`.
+    fn _accept(&mut self, neighbor: &Solution) -> bool {
+        let delta = neighbor.cost() - self.current.cost();
+        delta <= 0.0 || self.rng.random::<f64>() < (-delta / self.temperature).exp()
+    }
 
-                    eprint!(
-                        "Iteration #{} {}: {:.2}/{:.2}, elite set {}/{}     \r",
-                        iteration,
-                        extra,
-                        current.cost(),
-                        result.cost(),
-                        elite_set.len(),
-                        CONFIG.max_elite_size
-                    );
-                }
+    /// Customers of `--dont-look-bits` that the search in the neighborhood at `neighborhood_idx` may move: those not
+    /// marked for it (nor left out of the focus of the last restart). The bits of the customers of routes changed since
+    /// the last call are cleared first.
+    fn _dont_look_focus(&mut self, neighborhood_idx: usize) -> FocusList {
+        fn _routes<R>(vehicle_routes: &[Vec<Arc<R>>]) -> impl Iterator<Item = &Arc<R>> {
+            vehicle_routes.iter().flatten()
+        }
 
-                let neighborhood = NEIGHBORHOODS[neighborhood_idx];
+        let old = &self.dont_look_solution;
+        let old_routes = _routes(&old.truck_routes)
+            .map(|r| Arc::as_ptr(r).cast::<()>())
+            .chain(_routes(&old.drone_routes).map(|r| Arc::as_ptr(r).cast::<()>()))
+            .collect::<HashSet<_>>();
+        let changed = _routes(&self.current.truck_routes)
+            .filter(|r| !old_routes.contains(&Arc::as_ptr(r).cast::<()>()))
+            .map(|r| &r.data().customers)
+            .chain(
+                _routes(&self.current.drone_routes)
+                    .filter(|r| !old_routes.contains(&Arc::as_ptr(r).cast::<()>()))
+                    .map(|r| &r.data().customers),
+            );
+        for customers in changed {
+            for dont_look in &mut self.dont_look {
+                customers.iter().for_each(|&c| dont_look[c] = false);
+            }
+        }
+        self.dont_look_solution = self.current.clone();
 
-                let old_current = current.clone();
-                if let Some(neighbor) =
-                    neighborhood.search(&current, &mut tabu_lists[neighborhood_idx], tabu_size, result.cost())
-                {
-                    let neighbor = Rc::new(neighbor);
-
-                    // Update adaptive state
-                    if neighbor.feasible {
-                        if neighbor.cost() + TOLERANCE < result.cost() {
-                            adaptive.scores[neighborhood_idx] += 0.3;
-                        } else if neighbor.cost() < current.cost() {
-                            adaptive.scores[neighborhood_idx] += 0.2;
-                        } else {
-                            adaptive.scores[neighborhood_idx] += 0.1;
-                        }
-                    }
+        let dont_look = &self.dont_look[neighborhood_idx];
+        FocusList::new(
+            (1..=config::current().customers_count)
+                .filter(|&c| !dont_look[c] && self.focus.as_ref().is_none_or(|focus| focus.contains(c))),
+        )
+    }
 
-                    _record_new_solution(
-                        &neighbor,
-                        &mut result,
-                        &mut last_improved_iteration,
-                        &mut adaptive.last_improved_segment,
-                        iteration,
-                        adaptive.segment,
-                        &mut edge_records,
-                        &mut elite_set,
-                    );
+    fn _set_best(&mut self, solution: Arc<Solution>) {
+        let config = config::current();
+        if config.verbose {
+            eprintln!();
+            solution.print_diff(&self.result);
+        }
+
+        crash::record_best(&solution);
+        self.incumbents += 1;
+        if config
+            .snapshot_every
+            .is_some_and(|every| self.incumbents.is_multiple_of(every))
+        {
+            self.logger.snapshot(self.iteration, &solution).unwrap();
+        }
+
+        self.result = solution;
+        self.last_improved_iteration = self.iteration;
+        self.adaptive.last_improved_segment = self.adaptive.segment;
+    }
+
+    fn _record_new_solution(&mut self, neighbor: &Arc<Solution>) {
+        if neighbor.cost() + TOLERANCE < self.result.cost() && neighbor.feasible {
+            self._set_best(neighbor.clone());
+            self._record_edges(neighbor);
+            self._push_elite(neighbor.clone());
+        }
+    }
 
-                    current = neighbor;
+    fn _record_edges(&mut self, solution: &Solution) {
+        for routes in &solution.truck_routes {
+            for route in routes {
+                let customers = &route.data().customers;
+                for i in 0..customers.len() - 1 {
+                    let r = &mut self.edge_records[customers[i]][customers[i + 1]];
+                    *r = r.min(solution.working_time);
                 }
+            }
+        }
+    }
+
+    fn _push_elite(&mut self, solution: Arc<Solution>) {
+        let config = config::current();
+        if config.max_elite_size > 0 {
+            if self.elite_set.len() == config.max_elite_size {
+                let (idx, _) = self
+                    .elite_set
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|s| s.1.hamming_distance(&self.result))
+                    .unwrap();
+                self.elite_set.remove(idx);
+            }
 
-                adaptive.occurences[neighborhood_idx] += 1;
+            self.elite_set.push(solution);
+        }
+    }
 
-                let end_of_segment = if CONFIG.adaptive_fixed_iterations {
-                    iteration > 0 && iteration % adaptive_iterations == 0
-                } else {
-                    iteration != last_improved_iteration
-                        && (iteration - last_improved_iteration) % adaptive_iterations == 0
-                };
-                if end_of_segment {
-                    adaptive.segment += 1;
+    /// Merge an externally found solution into the search. Feasible solutions join the elite set (and thus become
+    /// candidates for the next restart) and replace the best solution if they improve on it; infeasible ones are
+    /// rejected. Returns whether the solution improved on the best one.
+    pub fn inject(&mut self, solution: Solution) -> bool {
+        if !solution.feasible {
+            return false;
+        }
+
+        let solution = Arc::new(solution);
+        let improved = solution.cost() + TOLERANCE < self.result.cost();
+        if improved {
+            self._set_best(solution.clone());
+        }
+
+        self._record_edges(&solution);
+        self._push_elite(solution);
+        if self.termination == Some(Termination::EliteSetExhausted) {
+            // The elite set is no longer exhausted
+            self.termination = None;
+        }
+
+        improved
+    }
+
+    /// Step the search until it finishes, polling `--control` and `--inject` and calling `before_step` before each
+    /// iteration.
+    pub fn run(&mut self, mut before_step: impl FnMut(&mut Self)) {
+        let config = config::current();
+        let mut watcher = config.inject.as_deref().map(InjectWatcher::new);
+        let mut control = config.control.as_deref().map(ControlWatcher::new);
+        loop {
+            if let Some(control) = &mut control {
+                for command in control.poll() {
+                    self.control(&command);
                 }
+            }
 
-                let reset = if let Strategy::Adaptive = CONFIG.strategy {
-                    if CONFIG.adaptive_fixed_segments {
-                        adaptive.segment >= adaptive.segment_reset + CONFIG.adaptive_segments
-                    } else {
-                        adaptive.segment
-                            >= cmp::max(adaptive.segment_reset, adaptive.last_improved_segment)
-                                + CONFIG.adaptive_segments
+            if let Some(watcher) = &mut watcher
+                && self.iteration().is_multiple_of(config.inject_interval)
+            {
+                for solution in watcher.poll() {
+                    let cost = solution.cost();
+                    if self.inject(solution) && config.verbose {
+                        eprintln!("Injected solution improved the best cost to {cost:.2}");
                     }
-                } else {
-                    iteration != last_improved_iteration && (iteration - last_improved_iteration) % reset_after == 0
-                };
+                }
+            }
+
+            before_step(self);
+            if !self.step() {
+                break;
+            }
+        }
+    }
 
-                if reset {
-                    adaptive.segment_reset = adaptive.segment;
-                    adaptive.weights = vec![1.0; NEIGHBORHOODS.len()];
+    /// Perform one tabu search iteration. Returns `false` without doing anything once the search has finished.
+    pub fn step(&mut self) -> bool {
+        let config = config::current();
+        if self.termination.is_some() {
+            return false;
+        }
 
-                    if elite_set.is_empty() {
-                        break;
+        self.iteration += 1;
+        let iteration = self.iteration;
+        let adaptive_iterations = self.adaptive_iterations;
+        let reset_after = self.reset_after;
+
+        if config.verbose {
+            let adaptive = &self.adaptive;
+            let extra = if let Strategy::Adaptive = config.strategy {
+                format!(
+                    "(segments before reset {})",
+                    if config.adaptive_fixed_segments {
+                        adaptive
+                            .segment
+                            .saturating_sub(adaptive.segment_reset + config.adaptive_segments)
+                    } else {
+                        config.adaptive_segments.saturating_sub(
+                            adaptive.segment - cmp::max(adaptive.segment_reset, adaptive.last_improved_segment),
+                        )
                     }
+                )
+            } else {
+                format!(
+                    "(reset in {})",
+                    reset_after.saturating_sub((iteration - self.last_improved_iteration) % reset_after)
+                )
+            };
 
-                    let i = rng.random_range(0..elite_set.len());
-                    current = Rc::new(elite_set.swap_remove(i).destroy_and_repair(&edge_records));
-                    for tabu_list in &mut tabu_lists {
-                        tabu_list.clear();
-                    }
-                }
+            eprint!(
+                "Iteration #{} {}: {:.2}/{:.2}, elite set {}/{}     \r",
+                iteration,
+                extra,
+                self.current.cost(),
+                self.result.cost(),
+                self.elite_set.len(),
+                config.max_elite_size
+            );
+        }
 
-                if reset && CONFIG.ejection_chain_iterations > 0 {
-                    let mut ejection_chain_tabu_list = vec![]; // Still have to maintain a tabu list to avoid cycles
-                    for _ in 0..CONFIG.ejection_chain_iterations {
-                        if let Some(neighbor) = Neighborhood::EjectionChain.search(
-                            &current,
-                            &mut ejection_chain_tabu_list,
-                            CONFIG.ejection_chain_iterations + 1,
-                            result.cost(),
-                        ) {
-                            current = Rc::new(neighbor);
-                            _record_new_solution(
-                                &current,
-                                &mut result,
-                                &mut last_improved_iteration,
-                                &mut adaptive.last_improved_segment,
-                                iteration,
-                                adaptive.segment,
-                                &mut edge_records,
-                                &mut elite_set,
-                            );
-                        }
+        let neighborhood_idx = self.neighborhood_idx;
+        let neighborhood = config.neighborhoods[neighborhood_idx];
+        crash::record_iteration(iteration, neighborhood);
+
+        if iteration >= self.focus_until {
+            self.focus = None;
+        }
 
-                        _update_violation_solution(&current);
-                        logger
-                            .log(&current, Neighborhood::EjectionChain, &ejection_chain_tabu_list)
-                            .unwrap();
+        let searching = matches!(config.algorithm, Algorithm::Tabu | Algorithm::Memetic);
+        let dont_look_focus = (config.dont_look_bits && searching).then(|| self._dont_look_focus(neighborhood_idx));
+        let looked_focus = self.looked[neighborhood_idx].as_ref().map(|looked| {
+            dont_look_focus
+                .clone()
+                .or_else(|| self.focus.clone())
+                .unwrap_or_else(|| FocusList::new(1..=config.customers_count))
+                .with_cold_routes(looked)
+        });
+
+        // Only VNS returns to the solution an iteration started from
+        let old_current = matches!(config.strategy, Strategy::Vns).then(|| self.current.clone());
+        let (side, side_working_time) = _bottleneck(&self.current);
+        let candidate = match config.algorithm {
+            Algorithm::Tabu | Algorithm::Memetic => {
+                let rank = config
+                    .selection_bias
+                    .map_or(0, |bias| _geometric_rank(&mut self.rng, bias));
+                neighborhood.search_ranked(
+                    &self.current,
+                    &mut self.tabu_lists[neighborhood_idx],
+                    self.tabu_size,
+                    self.result.cost(),
+                    looked_focus
+                        .as_ref()
+                        .or(dont_look_focus.as_ref())
+                        .or(self.focus.as_ref()),
+                    rank,
+                )
+            }
+            Algorithm::Sa => neighborhood
+                .sample(&self.current, &mut self.rng, self.focus.as_ref())
+                .filter(|(neighbor, _)| self._accept(neighbor)),
+            Algorithm::Ils => {
+                let local_optimum = self.current.kick(&mut self.rng).post_optimization();
+                (local_optimum.cost() < self.current.cost()).then(|| (local_optimum, TabuAttribute::new()))
+            }
+        };
+        let improving = candidate
+            .as_ref()
+            .is_some_and(|(neighbor, _)| neighbor.cost() < self.current.cost());
+        if config.dont_look_routes && searching {
+            // Skip the moves among the routes searched until they change, or until an improving move is found
+            self.looked[neighborhood_idx] = (!improving).then(|| self.current.clone());
+        }
+        if dont_look_focus.is_some() && !improving {
+            // No improving move from the bottleneck vehicles: do not look at their customers again until their routes
+            // change
+            let dont_look = &mut self.dont_look[neighborhood_idx];
+            for (vehicle, is_truck) in Neighborhood::decisive_vehicles(&self.current) {
+                if is_truck {
+                    for route in &self.current.truck_routes[vehicle] {
+                        route.data().customers.iter().for_each(|&c| dont_look[c] = true);
                     }
                 } else {
-                    _update_violation_solution(&current);
-                    logger
-                        .log(&current, neighborhood, &tabu_lists[neighborhood_idx])
+                    for route in &self.current.drone_routes[vehicle] {
+                        route.data().customers.iter().for_each(|&c| dont_look[c] = true);
+                    }
+                }
+            }
+        }
+        if let Some((neighbor, tabu)) = candidate {
+            let neighbor = Arc::new(neighbor);
+            self.logger
+                .trace(iteration, neighborhood, &tabu, neighbor.cost() - self.current.cost())
+                .unwrap();
+
+            // Update adaptive state
+            if neighbor.feasible {
+                if neighbor.cost() + TOLERANCE < self.result.cost() {
+                    self.adaptive.scores[neighborhood_idx] += 0.3;
+                } else if neighbor.cost() < self.current.cost() {
+                    self.adaptive.scores[neighborhood_idx] += 0.2;
+                } else {
+                    self.adaptive.scores[neighborhood_idx] += 0.1;
+                }
+            }
+
+            // Update bottleneck state: reward offloading the side defining the makespan
+            if _side_working_time(&neighbor, side) + TOLERANCE < side_working_time {
+                self.bottleneck.scores[side][neighborhood_idx] += 0.3;
+            } else if neighbor.cost() < self.current.cost() {
+                self.bottleneck.scores[side][neighborhood_idx] += 0.1;
+            }
+
+            self._record_new_solution(&neighbor);
+            self.current = neighbor;
+        } else if self.focus.is_some() {
+            // No move relocates a perturbed customer: lift the focus early
+            self.focus = None;
+        }
+
+        self.adaptive.occurences[neighborhood_idx] += 1;
+        self.bottleneck.occurences[side][neighborhood_idx] += 1;
+
+        let end_of_segment = if config.adaptive_fixed_iterations {
+            iteration > 0 && iteration.is_multiple_of(adaptive_iterations)
+        } else {
+            iteration != self.last_improved_iteration
+                && (iteration - self.last_improved_iteration).is_multiple_of(adaptive_iterations)
+        };
+        if end_of_segment {
+            self.adaptive.segment += 1;
+        }
+
+        let reset = if let Strategy::Adaptive = config.strategy {
+            let adaptive = &self.adaptive;
+            if config.adaptive_fixed_segments {
+                adaptive.segment >= adaptive.segment_reset + config.adaptive_segments
+            } else {
+                adaptive.segment
+                    >= cmp::max(adaptive.segment_reset, adaptive.last_improved_segment) + config.adaptive_segments
+            }
+        } else {
+            iteration != self.last_improved_iteration
+                && (iteration - self.last_improved_iteration).is_multiple_of(reset_after)
+        };
+
+        if reset {
+            self.adaptive.segment_reset = self.adaptive.segment;
+            self.adaptive.weights = vec![1.0; config.neighborhoods.len()];
+            self.bottleneck.weights = [
+                vec![1.0; config.neighborhoods.len()],
+                vec![1.0; config.neighborhoods.len()],
+            ];
+
+            if self.elite_set.is_empty() {
+                self.termination = Some(Termination::EliteSetExhausted);
+                return false;
+            }
+
+            let i = self.rng.random_range(0..self.elite_set.len());
+            let parent = self.elite_set.swap_remove(i);
+            let mut offspring = if config.algorithm == Algorithm::Memetic && !self.elite_set.is_empty() {
+                let mate = self.elite_set[self.rng.random_range(0..self.elite_set.len())].clone();
+                parent.crossover(&mate, &mut self.rng)
+            } else {
+                None
+            };
+            if offspring.is_none()
+                && config.path_relinking
+                && let Some(relinked) = relink::relink(&self.current, &parent)
+            {
+                let relinked = Arc::new(relinked);
+                self._record_new_solution(&relinked);
+                offspring = Some(Solution::clone(&relinked));
+            }
+            self.current = Arc::new(
+                offspring
+                    .unwrap_or_else(|| parent.destroy_and_repair(&self.edge_records, &self.route_pool, &mut self.rng)),
+            );
+            if config.focus_iterations > 0 {
+                self.focus = Some(FocusList::perturbed(&parent, &self.current)).filter(|focus| !focus.is_empty());
+                self.focus_until = iteration + 1 + config.focus_iterations;
+            }
+            _dampen_penalties();
+            for tabu_list in &mut self.tabu_lists {
+                tabu_list.clear();
+            }
+            self.temperature = config.initial_temperature;
+        }
+
+        let temperature = (config.algorithm == Algorithm::Sa).then_some(self.temperature);
+        if reset && config.ejection_chain_iterations > 0 {
+            let mut ejection_chain_tabu_list = vec![]; // Still have to maintain a tabu list to avoid cycles
+            for _ in 0..config.ejection_chain_iterations {
+                if let Some((neighbor, tabu)) = Neighborhood::EjectionChain.search(
+                    &self.current,
+                    &mut ejection_chain_tabu_list,
+                    config.ejection_chain_iterations + 1,
+                    self.result.cost(),
+                    self.focus.as_ref(),
+                ) {
+                    self.logger
+                        .trace(
+                            iteration,
+                            Neighborhood::EjectionChain,
+                            &tabu,
+                            neighbor.cost() - self.current.cost(),
+                        )
                         .unwrap();
+                    let neighbor = Arc::new(neighbor);
+                    self._record_new_solution(&neighbor);
+                    self.current = neighbor;
                 }
 
-                match CONFIG.strategy {
-                    Strategy::Random => {
-                        neighborhood_idx = rng.random_range(0..NEIGHBORHOODS.len());
-                    }
-                    Strategy::Cyclic => {
-                        neighborhood_idx = (neighborhood_idx + 1) % NEIGHBORHOODS.len();
+                _update_violation_solution(&self.current);
+                self.logger
+                    .log(
+                        &self.current,
+                        &self.result,
+                        temperature,
+                        Neighborhood::EjectionChain,
+                        &ejection_chain_tabu_list,
+                    )
+                    .unwrap();
+            }
+        } else {
+            _update_violation_solution(&self.current);
+            self.logger
+                .log(
+                    &self.current,
+                    &self.result,
+                    temperature,
+                    neighborhood,
+                    &self.tabu_lists[neighborhood_idx],
+                )
+                .unwrap();
+        }
+
+        match config.strategy {
+            Strategy::Random => {
+                self.neighborhood_idx = self.rng.random_range(0..config.neighborhoods.len());
+            }
+            Strategy::Cyclic => {
+                self.neighborhood_idx = (neighborhood_idx + 1) % config.neighborhoods.len();
+            }
+            Strategy::Vns => {
+                if iteration == self.last_improved_iteration {
+                    self.neighborhood_idx = 0;
+                } else {
+                    self.neighborhood_idx = (neighborhood_idx + 1) % config.neighborhoods.len();
+                    if self.neighborhood_idx != 0
+                        && let Some(old_current) = old_current
+                    {
+                        self.current = old_current;
                     }
-                    Strategy::Vns => {
-                        if iteration == last_improved_iteration {
-                            neighborhood_idx = 0;
-                        } else {
-                            neighborhood_idx = (neighborhood_idx + 1) % NEIGHBORHOODS.len();
-                            if neighborhood_idx != 0 {
-                                current = old_current;
-                            }
+                }
+            }
+            Strategy::Adaptive => {
+                let adaptive = &mut self.adaptive;
+                if end_of_segment {
+                    for neighborhood_idx in 0..config.neighborhoods.len() {
+                        if adaptive.occurences[neighborhood_idx] > 0 {
+                            adaptive.weights[neighborhood_idx] = 0.7f64.mul_add(
+                                adaptive.weights[neighborhood_idx],
+                                0.3 * adaptive.scores[neighborhood_idx]
+                                    / f64::from(adaptive.occurences[neighborhood_idx]),
+                            );
                         }
+
+                        adaptive.scores[neighborhood_idx] = 0.0;
+                        adaptive.occurences[neighborhood_idx] = 0;
                     }
-                    Strategy::Adaptive => {
-                        if end_of_segment {
-                            for neighborhood_idx in 0..NEIGHBORHOODS.len() {
-                                if adaptive.occurences[neighborhood_idx] > 0 {
-                                    adaptive.weights[neighborhood_idx] = 0.7f64.mul_add(
-                                        adaptive.weights[neighborhood_idx],
-                                        0.3 * adaptive.scores[neighborhood_idx]
-                                            / f64::from(adaptive.occurences[neighborhood_idx]),
-                                    );
-                                }
+                }
 
-                                adaptive.scores[neighborhood_idx] = 0.0;
-                                adaptive.occurences[neighborhood_idx] = 0;
+                let dist = WeightedIndex::new(&adaptive.weights).unwrap();
+                self.neighborhood_idx = dist.sample(&mut self.rng);
+            }
+            Strategy::Bottleneck => {
+                let bottleneck = &mut self.bottleneck;
+                if end_of_segment {
+                    for side in 0..2 {
+                        for neighborhood_idx in 0..config.neighborhoods.len() {
+                            if bottleneck.occurences[side][neighborhood_idx] > 0 {
+                                bottleneck.weights[side][neighborhood_idx] = 0.7f64.mul_add(
+                                    bottleneck.weights[side][neighborhood_idx],
+                                    0.3 * bottleneck.scores[side][neighborhood_idx]
+                                        / f64::from(bottleneck.occurences[side][neighborhood_idx]),
+                                );
                             }
-                        }
 
-                        let dist = WeightedIndex::new(&adaptive.weights).unwrap();
-                        neighborhood_idx = dist.sample(&mut rng);
+                            bottleneck.scores[side][neighborhood_idx] = 0.0;
+                            bottleneck.occurences[side][neighborhood_idx] = 0;
+                        }
                     }
                 }
+
+                // Weights of neighborhoods that never offload a side decay towards zero, fall back to uniform
+                let (side, _) = _bottleneck(&self.current);
+                self.neighborhood_idx = match WeightedIndex::new(&bottleneck.weights[side]) {
+                    Ok(dist) => dist.sample(&mut self.rng),
+                    Err(_) => self.rng.random_range(0..config.neighborhoods.len()),
+                };
+            }
+        }
+
+        self.temperature *= config.cooling_rate;
+        if config.fix_iteration.is_some_and(|i| iteration >= i) {
+            self.termination = Some(Termination::FixIteration);
+        } else if self._out_of_time() {
+            self.termination = Some(Termination::TimeLimit);
+        } else if self.result.reaches_target() == Some(true) {
+            self.termination = Some(Termination::TargetCost);
+        }
+
+        true
+    }
+
+    /// Stop the search, write the final results to the logger and return the best solution found.
+    pub fn finish(mut self) -> Solution {
+        let config = config::current();
+        if !self.result.feasible {
+            match self.result.project_feasible() {
+                Ok(solution) => {
+                    eprintln!(
+                        "Projected the best solution onto a feasible one of cost {:.2}",
+                        solution.cost()
+                    );
+                    self.result = Arc::new(solution);
+                }
+                Err(reason) => eprintln!("The best solution is infeasible: {reason}"),
             }
+        }
 
-            if CONFIG.verbose {
+        let mut post_optimization = 0.0;
+        let mut post_optimization_elapsed = 0.0;
+        if !config.dry_run {
+            if config.verbose {
                 eprintln!();
             }
 
-            let preresult_cost = result.cost();
+            let preresult_cost = self.result.cost();
             let preresult_time_offset = SystemTime::now();
-            // result = Rc::new(result.post_optimization());
-            post_optimization = preresult_cost - result.cost();
+            // result = Arc::new(result.post_optimization());
+            let steps: [(bool, fn(&Solution) -> Solution); 2] = [
+                (config.refine_drone_schedule, Solution::refine_drone_schedule),
+                (config.match_drone_sorties, Solution::match_drone_sorties),
+            ];
+            for (_, step) in steps.into_iter().filter(|&(enabled, _)| enabled) {
+                if self._out_of_time() {
+                    self.termination = Some(Termination::TimeLimit);
+                    break;
+                }
+
+                self.result = Arc::new(step(&self.result));
+            }
+            post_optimization = preresult_cost - self.result.cost();
             post_optimization_elapsed = SystemTime::now()
                 .duration_since(preresult_time_offset)
                 .unwrap()
                 .as_secs_f64();
         }
 
-        logger
+        if config.verify_final
+            && let Err(e) = self.result.verify_values()
+        {
+            panic!("Final solution does not match its recomputation: {e}");
+        }
+
+        self.logger
             .finalize(
-                &result,
-                tabu_size,
-                reset_after,
-                adaptive_iterations,
-                adaptive.segment,
-                last_improved_iteration,
+                &self.result,
+                self.tabu_size,
+                self.reset_after,
+                self.adaptive_iterations,
+                self.adaptive.segment,
+                self.last_improved_iteration,
                 post_optimization,
                 post_optimization_elapsed,
+                self.termination,
             )
             .unwrap();
 
-        Self::clone(&result)
+        Solution::clone(&self.result)
     }
 }
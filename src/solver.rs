@@ -0,0 +1,98 @@
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::{self, Config};
+use crate::instance::Instance;
+use crate::logger::{FileLogger, Logger};
+use crate::solutions::{self, Solution};
+use crate::{checker, decompose, fleet, inject, pool};
+
+/// Entry point of the search for programs embedding it instead of going through the command line.
+pub struct Solver;
+
+impl Solver {
+    /// Solve `instance` with the search parameters of `config` (e.g. read by [`Config::from_json`] from the config
+    /// saved by a previous run), see [`Config::with_instance`].
+    ///
+    /// The search runs on the calling thread within a [`config::scope`] of its own, so several instances may be solved
+    /// one after another or on different threads. `--decompose` is not supported, since it spawns the command line
+    /// executable for each cluster.
+    pub fn run(config: Config, instance: &Instance) -> Result<Solution, Box<dyn Error>> {
+        if config.decompose.is_some() {
+            return Err("Decomposition is only supported from the command line".into());
+        }
+
+        config::scope(Arc::new(config.with_instance(instance)), || {
+            Self::solve(&mut FileLogger::new()?)
+        })
+    }
+
+    /// Same as [`Self::run`], but reporting the progress and results of the search to `logger` instead of writing them
+    /// to the output directory, e.g. to a [`NullLogger`](crate::logger::NullLogger) to write no file at all.
+    /// `--target-makespan` and the update of `--route-pool` are skipped, as they write files of their own.
+    pub fn run_with_logger(
+        config: Config,
+        instance: &Instance,
+        logger: &mut dyn Logger,
+    ) -> Result<Solution, Box<dyn Error>> {
+        if config.decompose.is_some() {
+            return Err("Decomposition is only supported from the command line".into());
+        }
+
+        config::scope(Arc::new(config.with_instance(instance)), || {
+            let config = config::current();
+            if config.paranoid {
+                checker::check_routes();
+            }
+
+            solutions::reset_penalties();
+            let root = match &config.initial_solution {
+                Some(path) => _load_initial(path)?,
+                None => Solution::initialize(),
+            };
+            Ok(Solution::tabu_search(root, logger))
+        })
+    }
+
+    /// Run the search with the config in scope (see [`config::current`]), as the `run` command does: construct or load
+    /// the initial solution, improve it by tabu search and write the outputs with `logger` (or minimize the fleet with
+    /// `--target-makespan`).
+    pub fn solve(logger: &mut FileLogger) -> Result<Solution, Box<dyn Error>> {
+        let config = config::current();
+        if config.paranoid {
+            checker::check_routes();
+        }
+
+        solutions::reset_penalties();
+        let solution = if let Some(target) = config.target_makespan {
+            let solution = fleet::minimize_fleet(target, logger)?;
+            if config.verify_final
+                && let Err(e) = solution.verify_values()
+            {
+                panic!("Final solution does not match its recomputation: {e}");
+            }
+
+            logger.finalize(&solution, 0, 0, 0, 0, 0, 0.0, 0.0, None)?;
+            solution
+        } else {
+            let root = match (config.decompose, &config.initial_solution) {
+                (Some(_), Some(_)) => return Err("--initial-solution does not support --decompose".into()),
+                (Some(k), None) => decompose::decompose(k, logger)?,
+                (None, Some(path)) => _load_initial(path)?,
+                (None, None) => Solution::initialize(),
+            };
+            Solution::tabu_search(root, logger)
+        };
+        if let Some(ref path) = config.route_pool {
+            pool::save(path, &solution)?;
+        }
+
+        Ok(solution)
+    }
+}
+
+/// Load the `--initial-solution` at `path`.
+fn _load_initial(path: &str) -> Result<Solution, Box<dyn Error>> {
+    inject::load(Path::new(path)).map_err(|e| format!("Invalid initial solution {path}: {e}").into())
+}